@@ -0,0 +1,503 @@
+//! Task artifacts
+//!
+//! When a task runs in a git project a snapshot of its work tree (the
+//! pre-task checkpoint, when one is taken) is recorded as the task's start
+//! commit, so uncommitted edits made before the task aren't counted as the
+//! task's. After the run the project is diffed against that commit,
+//! untracked files included, and the result is stored as artifacts: one
+//! `diff` artifact with the whole unified diff and one `file` artifact per
+//! changed file, holding the file before and after. Each `Bash` call adds a
+//! `transcript` artifact with the command and its output.
+//!
+//! Tasks edit the project in place, so a pending file artifact is already
+//! on disk. Applying one accepts it, writing it back if the file has been
+//! touched since; discarding puts back the start commit's version. Applying
+//! or discarding the `diff` artifact does so for all of the task's pending
+//! files.
+
+use super::manager::AgentManager;
+use crate::projects::ProjectManager;
+use chrono::Utc;
+use git2::{Delta, DiffOptions, Oid, Patch, Repository};
+use rusqlite::params;
+use serde::Serialize;
+use std::path::{Component, Path, PathBuf};
+use uuid::Uuid;
+
+/// Most changed files captured from one run
+const MAX_CHANGED_FILES: usize = 500;
+/// File contents beyond this are not stored; the patch still is
+pub const MAX_ARTIFACT_BYTES: usize = 1024 * 1024;
+/// Transcripts are cut to this many bytes
+const MAX_TRANSCRIPT_BYTES: usize = 256 * 1024;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskArtifact {
+    pub id: String,
+    #[serde(rename = "taskId")]
+    pub task_id: String,
+    /// `diff`, `file` or `transcript`
+    pub kind: String,
+    /// Project-relative path of a file artifact
+    pub path: Option<String>,
+    /// `added`, `modified` or `deleted` for file artifacts
+    pub change: Option<String>,
+    pub size: i64,
+    pub binary: bool,
+    /// `pending`, `applied` or `discarded`
+    pub status: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: i64,
+    #[serde(rename = "decidedAt")]
+    pub decided_at: Option<i64>,
+}
+
+/// An artifact before it is stored, or its stored data
+#[derive(Debug, Clone, Default)]
+pub struct ArtifactData {
+    pub kind: String,
+    pub path: Option<String>,
+    pub change: Option<String>,
+    /// File after the task, the whole diff, or the transcript
+    pub content: Option<Vec<u8>>,
+    /// File at the start commit
+    pub original: Option<Vec<u8>>,
+    pub patch: Option<String>,
+    pub binary: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ArtifactPreview {
+    pub artifact: TaskArtifact,
+    /// Content as text; `None` for binary or unstored content
+    pub text: Option<String>,
+    /// Unified diff of a file artifact
+    pub patch: Option<String>,
+}
+
+/// Diff the work tree at `root` against `start_commit`. Returns the `diff`
+/// artifact followed by one `file` artifact per change, or nothing when
+/// the tree is unchanged.
+pub fn capture_changes(root: &Path, start_commit: &str) -> Result<Vec<ArtifactData>, String> {
+    let repo = Repository::open(root).map_err(|e| e.message().to_string())?;
+    let oid = Oid::from_str(start_commit).map_err(|e| e.message().to_string())?;
+    let tree = repo
+        .find_commit(oid)
+        .and_then(|commit| commit.tree())
+        .map_err(|e| format!("Start commit {}: {}", start_commit, e.message()))?;
+
+    let mut options = DiffOptions::new();
+    options
+        .include_untracked(true)
+        .recurse_untracked_dirs(true)
+        .show_untracked_content(true);
+    let diff = repo
+        .diff_tree_to_workdir_with_index(Some(&tree), Some(&mut options))
+        .map_err(|e| e.message().to_string())?;
+
+    let mut files = Vec::new();
+    let mut full_diff = String::new();
+    for index in 0..diff.deltas().len().min(MAX_CHANGED_FILES) {
+        let Some(delta) = diff.get_delta(index) else {
+            continue;
+        };
+        let change = match delta.status() {
+            Delta::Added | Delta::Untracked => "added",
+            Delta::Deleted => "deleted",
+            Delta::Modified | Delta::Renamed | Delta::Copied | Delta::Typechange => "modified",
+            _ => continue,
+        };
+        let Some(path) = delta.new_file().path().or(delta.old_file().path()) else {
+            continue;
+        };
+        let path = path.to_string_lossy().replace('\\', "/");
+
+        let patch = Patch::from_diff(&diff, index)
+            .ok()
+            .flatten()
+            .and_then(|mut p| p.to_buf().ok())
+            .and_then(|buf| buf.as_str().map(str::to_string));
+        if let Some(patch) = &patch {
+            full_diff.push_str(patch);
+        }
+
+        let original = if delta.old_file().id().is_zero() {
+            None
+        } else {
+            repo.find_blob(delta.old_file().id())
+                .ok()
+                .map(|blob| blob.content().to_vec())
+        };
+        let content = if change == "deleted" {
+            None
+        } else {
+            std::fs::read(root.join(&path)).ok()
+        };
+        let binary = delta.flags().is_binary()
+            || [&content, &original]
+                .into_iter()
+                .flatten()
+                .any(|bytes| is_binary(bytes));
+
+        let fits =
+            |bytes: &Option<Vec<u8>>| bytes.as_ref().map_or(0, Vec::len) <= MAX_ARTIFACT_BYTES;
+        let (content, original) = if fits(&content) && fits(&original) {
+            (content, original)
+        } else {
+            (None, None)
+        };
+
+        files.push(ArtifactData {
+            kind: "file".to_string(),
+            path: Some(path),
+            change: Some(change.to_string()),
+            content,
+            original,
+            patch,
+            binary,
+        });
+    }
+
+    if files.is_empty() {
+        return Ok(files);
+    }
+    let mut artifacts = vec![ArtifactData {
+        kind: "diff".to_string(),
+        content: Some(full_diff.into_bytes()),
+        ..Default::default()
+    }];
+    artifacts.extend(files);
+    Ok(artifacts)
+}
+
+/// Columns read by `map_artifact_row`, in order
+pub const ARTIFACT_COLUMNS: &str =
+    "id, task_id, kind, path, change, size, binary, status, created_at, decided_at";
+
+pub fn map_artifact_row(row: &rusqlite::Row) -> rusqlite::Result<TaskArtifact> {
+    Ok(TaskArtifact {
+        id: row.get(0)?,
+        task_id: row.get(1)?,
+        kind: row.get(2)?,
+        path: row.get(3)?,
+        change: row.get(4)?,
+        size: row.get(5)?,
+        binary: row.get(6)?,
+        status: row.get(7)?,
+        created_at: row.get(8)?,
+        decided_at: row.get(9)?,
+    })
+}
+
+/// Store `data` as a pending artifact of `task_id`
+pub fn insert_artifact(
+    conn: &rusqlite::Connection,
+    task_id: &str,
+    data: &ArtifactData,
+) -> Result<TaskArtifact, String> {
+    let artifact = TaskArtifact {
+        id: Uuid::new_v4().to_string(),
+        task_id: task_id.to_string(),
+        kind: data.kind.clone(),
+        path: data.path.clone(),
+        change: data.change.clone(),
+        size: data.content.as_ref().map_or(0, Vec::len) as i64,
+        binary: data.binary,
+        status: "pending".to_string(),
+        created_at: Utc::now().timestamp(),
+        decided_at: None,
+    };
+    conn.execute(
+        "INSERT INTO task_artifacts (id, task_id, kind, path, change, content, original, patch, size, binary, status, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+        params![
+            artifact.id,
+            artifact.task_id,
+            artifact.kind,
+            artifact.path,
+            artifact.change,
+            data.content,
+            data.original,
+            data.patch,
+            artifact.size,
+            artifact.binary,
+            artifact.status,
+            artifact.created_at,
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(artifact)
+}
+
+/// A transcript of one shell command
+pub fn transcript(command: &str, output: &str) -> ArtifactData {
+    let mut text = format!("$ {}\n{}", command, output);
+    if text.len() > MAX_TRANSCRIPT_BYTES {
+        let mut end = MAX_TRANSCRIPT_BYTES;
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        text.truncate(end);
+        text.push_str("\n[truncated]");
+    }
+    ArtifactData {
+        kind: "transcript".to_string(),
+        content: Some(text.into_bytes()),
+        ..Default::default()
+    }
+}
+
+fn is_binary(bytes: &[u8]) -> bool {
+    bytes.iter().take(8000).any(|b| *b == 0) || std::str::from_utf8(bytes).is_err()
+}
+
+pub fn preview_artifact(
+    manager: &AgentManager,
+    artifact_id: &str,
+) -> Result<ArtifactPreview, String> {
+    let (artifact, data) = manager.get_artifact(artifact_id)?;
+    let text = if artifact.binary {
+        None
+    } else {
+        data.content
+            .map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+    };
+    Ok(ArtifactPreview {
+        artifact,
+        text,
+        patch: data.patch,
+    })
+}
+
+/// Accept an artifact, writing its files to the project
+pub fn apply_artifact(
+    manager: &AgentManager,
+    projects: &ProjectManager,
+    artifact_id: &str,
+) -> Result<Vec<TaskArtifact>, String> {
+    decide(manager, projects, artifact_id, "applied")
+}
+
+/// Reject an artifact, restoring its files to the start commit
+pub fn discard_artifact(
+    manager: &AgentManager,
+    projects: &ProjectManager,
+    artifact_id: &str,
+) -> Result<Vec<TaskArtifact>, String> {
+    decide(manager, projects, artifact_id, "discarded")
+}
+
+/// Apply or discard an artifact; returns the artifacts that changed
+fn decide(
+    manager: &AgentManager,
+    projects: &ProjectManager,
+    artifact_id: &str,
+    status: &str,
+) -> Result<Vec<TaskArtifact>, String> {
+    let (artifact, _) = manager.get_artifact(artifact_id)?;
+    if artifact.status != "pending" {
+        return Err(format!("Artifact is already {}", artifact.status));
+    }
+
+    let targets = match artifact.kind.as_str() {
+        "file" => vec![artifact],
+        "diff" => {
+            let mut targets: Vec<TaskArtifact> = manager
+                .list_artifacts(&artifact.task_id)?
+                .into_iter()
+                .filter(|a| a.kind == "file" && a.status == "pending")
+                .collect();
+            targets.push(artifact);
+            targets
+        }
+        _ if status == "applied" => {
+            return Err(format!("{} artifacts can't be applied", artifact.kind));
+        }
+        _ => vec![artifact],
+    };
+
+    if targets.iter().any(|a| a.kind == "file") {
+        let root = project_root(manager, projects, &targets[0].task_id)?;
+        for target in targets.iter().filter(|a| a.kind == "file") {
+            let (_, data) = manager.get_artifact(&target.id)?;
+            write_file(&root, &data, status == "applied")?;
+        }
+    }
+
+    let mut decided = Vec::with_capacity(targets.len());
+    for target in targets {
+        decided.push(manager.set_artifact_status(&target.id, status)?);
+    }
+    Ok(decided)
+}
+
+fn project_root(
+    manager: &AgentManager,
+    projects: &ProjectManager,
+    task_id: &str,
+) -> Result<PathBuf, String> {
+    let task = manager
+        .get_task(task_id)?
+        .ok_or_else(|| format!("Task not found: {}", task_id))?;
+    let project_id = task
+        .project_id
+        .ok_or_else(|| "Task has no project".to_string())?;
+    let project = projects
+        .get(&project_id)?
+        .ok_or_else(|| format!("Project not found: {}", project_id))?;
+    Ok(PathBuf::from(project.path))
+}
+
+/// Write the task's version of a file (`apply`) or the start commit's
+fn write_file(root: &Path, data: &ArtifactData, apply: bool) -> Result<(), String> {
+    let relative = data.path.as_deref().unwrap_or_default();
+    let path = resolve(root, relative)?;
+    let exists_after = data.change.as_deref() != Some("deleted");
+    let exists_before = data.change.as_deref() != Some("added");
+
+    let (exists, bytes) = if apply {
+        (exists_after, &data.content)
+    } else {
+        (exists_before, &data.original)
+    };
+
+    if !exists {
+        return match std::fs::remove_file(&path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                Err(format!("{}: {}", relative, e))
+            }
+            _ => Ok(()),
+        };
+    }
+
+    let bytes = bytes
+        .as_ref()
+        .ok_or_else(|| format!("{} is too large to have been stored", relative))?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("{}: {}", relative, e))?;
+    }
+    std::fs::write(&path, bytes).map_err(|e| format!("{}: {}", relative, e))
+}
+
+/// `root` joined with a project-relative path that must stay inside it
+fn resolve(root: &Path, relative: &str) -> Result<PathBuf, String> {
+    let path = Path::new(relative);
+    if relative.is_empty() || !path.components().all(|c| matches!(c, Component::Normal(_))) {
+        return Err(format!("Invalid artifact path: {}", relative));
+    }
+    Ok(root.join(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::projects::snapshot_work_tree;
+    use git2::Signature;
+    use tempfile::tempdir;
+
+    fn init_repo(root: &Path, files: &[&str]) -> Repository {
+        let repo = Repository::init(root).unwrap();
+        let mut index = repo.index().unwrap();
+        for file in files {
+            index.add_path(Path::new(file)).unwrap();
+        }
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = Signature::now("Test", "test@example.com").unwrap();
+        repo.commit(Some("HEAD"), &signature, &signature, "init", &tree, &[])
+            .unwrap();
+        drop(tree);
+        repo
+    }
+
+    #[test]
+    fn test_capture_and_revert_changes() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        std::fs::write(root.join("kept.txt"), "one\n").unwrap();
+        std::fs::write(root.join("gone.txt"), "bye\n").unwrap();
+        init_repo(root, &["kept.txt", "gone.txt"]);
+        let start = snapshot_work_tree(root).unwrap();
+
+        std::fs::write(root.join("kept.txt"), "one\ntwo\n").unwrap();
+        std::fs::remove_file(root.join("gone.txt")).unwrap();
+        std::fs::create_dir(root.join("src")).unwrap();
+        std::fs::write(root.join("src/new.rs"), "fn main() {}\n").unwrap();
+
+        let artifacts = capture_changes(root, &start).unwrap();
+        assert_eq!(artifacts[0].kind, "diff");
+        let diff = String::from_utf8(artifacts[0].content.clone().unwrap()).unwrap();
+        assert!(diff.contains("+two"));
+
+        let changes: Vec<(&str, &str)> = artifacts[1..]
+            .iter()
+            .map(|a| (a.path.as_deref().unwrap(), a.change.as_deref().unwrap()))
+            .collect();
+        assert_eq!(
+            changes,
+            [
+                ("gone.txt", "deleted"),
+                ("kept.txt", "modified"),
+                ("src/new.rs", "added")
+            ]
+        );
+
+        for file in &artifacts[1..] {
+            write_file(root, file, false).unwrap();
+        }
+        assert_eq!(
+            std::fs::read_to_string(root.join("kept.txt")).unwrap(),
+            "one\n"
+        );
+        assert!(root.join("gone.txt").exists());
+        assert!(!root.join("src/new.rs").exists());
+        assert!(capture_changes(root, &start).unwrap().is_empty());
+
+        write_file(root, &artifacts[2], true).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(root.join("kept.txt")).unwrap(),
+            "one\ntwo\n"
+        );
+        assert!(resolve(root, "../outside").is_err());
+        assert!(resolve(root, "/etc/passwd").is_err());
+    }
+
+    #[test]
+    fn test_uncommitted_edits_are_not_the_tasks() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        std::fs::write(root.join("lib.rs"), "v1\n").unwrap();
+        std::fs::write(root.join("main.rs"), "fn main() {}\n").unwrap();
+        init_repo(root, &["lib.rs", "main.rs"]);
+
+        // The user has work in progress when the task starts
+        std::fs::write(root.join("lib.rs"), "v2\n").unwrap();
+        std::fs::write(root.join("notes.md"), "draft\n").unwrap();
+        let start = snapshot_work_tree(root).unwrap();
+
+        std::fs::write(root.join("lib.rs"), "v3\n").unwrap();
+        std::fs::write(root.join("main.rs"), "fn main() { run() }\n").unwrap();
+
+        let artifacts = capture_changes(root, &start).unwrap();
+        let paths: Vec<&str> = artifacts[1..]
+            .iter()
+            .map(|a| a.path.as_deref().unwrap())
+            .collect();
+        assert_eq!(paths, ["lib.rs", "main.rs"]);
+        assert_eq!(artifacts[1].original.as_deref(), Some(&b"v2\n"[..]));
+
+        // Discarding puts back the user's version, not HEAD's
+        for file in &artifacts[1..] {
+            write_file(root, file, false).unwrap();
+        }
+        assert_eq!(
+            std::fs::read_to_string(root.join("lib.rs")).unwrap(),
+            "v2\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(root.join("notes.md")).unwrap(),
+            "draft\n"
+        );
+        assert!(capture_changes(root, &start).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,249 @@
+//! Task board
+//!
+//! Columns are configurable and each shows one or more task statuses. The
+//! statuses themselves are fixed, since the runtime and scheduler act on
+//! each of them. A task sits in the column it was last moved to while that
+//! column still shows its status, otherwise in the first column that does.
+//! Moving a task into another column gives it the column's drop status, so
+//! dropping into Ready (`pending`) queues it for the runtime and Backlog
+//! keeps it out of the queue. Columns for statuses the runtime owns
+//! (`assigned`, `running`, `waiting`, `failed`) have no drop status.
+//!
+//! Within a column tasks are ordered by a fractional rank, so a move only
+//! rewrites the moved task's rank.
+
+use super::manager::{Task, TASK_STATUSES};
+use serde::{Deserialize, Serialize};
+
+/// Statuses a task can be given by moving it on the board
+pub const MANUAL_STATUSES: &[&str] = &["backlog", "pending", "completed", "cancelled"];
+
+/// Neighbouring ranks closer than this are renumbered before inserting
+const MIN_RANK_GAP: f64 = 1e-6;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BoardColumn {
+    pub id: String,
+    pub name: String,
+    pub statuses: Vec<String>,
+    /// Status given to tasks moved into the column by hand
+    #[serde(rename = "dropStatus", default)]
+    pub drop_status: Option<String>,
+    /// Most tasks the column takes by hand
+    #[serde(rename = "wipLimit", default)]
+    pub wip_limit: Option<i32>,
+}
+
+impl BoardColumn {
+    pub fn shows(&self, status: &str) -> bool {
+        self.statuses.iter().any(|s| s == status)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BoardLane {
+    pub column: BoardColumn,
+    pub tasks: Vec<Task>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Board {
+    pub lanes: Vec<BoardLane>,
+}
+
+/// Check a column layout before saving it
+pub fn validate_columns(columns: &[BoardColumn]) -> Result<(), String> {
+    if columns.is_empty() {
+        return Err("The board needs at least one column".to_string());
+    }
+
+    for (index, column) in columns.iter().enumerate() {
+        if column.id.trim().is_empty() || column.name.trim().is_empty() {
+            return Err(format!("Column {} needs an id and a name", index + 1));
+        }
+        if columns[..index].iter().any(|c| c.id == column.id) {
+            return Err(format!("Duplicate column id '{}'", column.id));
+        }
+        if column.statuses.is_empty() {
+            return Err(format!("Column '{}' shows no statuses", column.name));
+        }
+        if let Some(status) = column
+            .statuses
+            .iter()
+            .find(|s| !TASK_STATUSES.contains(&s.as_str()))
+        {
+            return Err(format!(
+                "Unknown status '{}' in column '{}'; expected one of {}",
+                status,
+                column.name,
+                TASK_STATUSES.join(", ")
+            ));
+        }
+        if let Some(status) = &column.drop_status {
+            if !column.shows(status) {
+                return Err(format!(
+                    "Column '{}' drops tasks as '{}' but doesn't show that status",
+                    column.name, status
+                ));
+            }
+            if !MANUAL_STATUSES.contains(&status.as_str()) {
+                return Err(format!(
+                    "Tasks can't be set to '{}' by hand; drop status must be one of {}",
+                    status,
+                    MANUAL_STATUSES.join(", ")
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Whether a task can be moved by hand from `from` to `to`. Tasks with a
+/// live run (assigned, running or waiting) can only be cancelled; the
+/// runtime owns their other transitions.
+pub fn can_transition(from: &str, to: &str) -> bool {
+    if from == to {
+        return true;
+    }
+    match from {
+        "backlog" => matches!(to, "pending" | "completed" | "cancelled"),
+        "pending" => matches!(to, "backlog" | "cancelled"),
+        "assigned" | "running" | "waiting" => to == "cancelled",
+        "completed" | "failed" | "cancelled" => matches!(to, "backlog" | "pending"),
+        _ => false,
+    }
+}
+
+/// The column `task` is shown in
+pub fn column_for<'a>(task: &Task, columns: &'a [BoardColumn]) -> Option<&'a BoardColumn> {
+    columns
+        .iter()
+        .find(|c| task.board_column.as_deref() == Some(c.id.as_str()) && c.shows(&task.status))
+        .or_else(|| columns.iter().find(|c| c.shows(&task.status)))
+}
+
+/// Lay `tasks` out in `columns`, each column in rank order
+pub fn build_board(columns: Vec<BoardColumn>, tasks: Vec<Task>) -> Board {
+    let mut lanes: Vec<BoardLane> = columns
+        .iter()
+        .cloned()
+        .map(|column| BoardLane {
+            column,
+            tasks: vec![],
+        })
+        .collect();
+
+    for task in tasks {
+        if let Some(column) = column_for(&task, &columns) {
+            if let Some(lane) = lanes.iter_mut().find(|l| l.column.id == column.id) {
+                lane.tasks.push(task);
+            }
+        }
+    }
+
+    for lane in &mut lanes {
+        lane.tasks.sort_by(rank_order);
+    }
+    Board { lanes }
+}
+
+pub fn rank_order(a: &Task, b: &Task) -> std::cmp::Ordering {
+    let rank = |t: &Task| t.board_rank.unwrap_or(f64::MAX);
+    rank(a)
+        .total_cmp(&rank(b))
+        .then(a.created_at.cmp(&b.created_at))
+}
+
+/// A rank between two neighbours, or `None` when they are too close and
+/// the column needs renumbering first
+pub fn rank_between(before: Option<f64>, after: Option<f64>) -> Option<f64> {
+    match (before, after) {
+        (None, None) => Some(1.0),
+        (Some(before), None) => Some(before + 1.0),
+        (None, Some(after)) => Some(after - 1.0),
+        (Some(before), Some(after)) if after - before > MIN_RANK_GAP => {
+            Some(before + (after - before) / 2.0)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::AgentManager;
+    use crate::db::Database;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_moves_change_status_and_order() {
+        let dir = tempdir().unwrap();
+        let manager = AgentManager::new(Database::new(dir.path().to_path_buf()).unwrap());
+        let tasks = manager
+            .create_tasks(&[
+                Task {
+                    status: "backlog".to_string(),
+                    ..Task::new("a")
+                },
+                Task::new("b"),
+                Task::new("c"),
+            ])
+            .unwrap();
+        let titles = |column: &str| -> Vec<String> {
+            manager
+                .board()
+                .unwrap()
+                .lanes
+                .into_iter()
+                .find(|l| l.column.id == column)
+                .unwrap()
+                .tasks
+                .into_iter()
+                .map(|t| t.title)
+                .collect()
+        };
+        assert_eq!(titles("backlog"), ["a"]);
+        assert_eq!(titles("ready"), ["b", "c"]);
+
+        // Dropping into Ready queues it, placed before c
+        let moved = manager
+            .move_task(&tasks[0].id, "ready", Some(&tasks[2].id))
+            .unwrap();
+        assert_eq!(moved.status, "pending");
+        assert_eq!(titles("ready"), ["b", "a", "c"]);
+
+        // Reordering within a column keeps the status
+        manager.move_task(&tasks[2].id, "ready", None).unwrap();
+        manager
+            .move_task(&tasks[2].id, "ready", Some(&tasks[1].id))
+            .unwrap();
+        assert_eq!(titles("ready"), ["c", "b", "a"]);
+
+        manager.update_task_status(&tasks[1].id, "running").unwrap();
+        assert!(manager.move_task(&tasks[1].id, "backlog", None).is_err());
+        assert!(!can_transition("assigned", "backlog"));
+        assert!(!can_transition("assigned", "pending"));
+        assert!(manager
+            .move_task(&tasks[2].id, "in-progress", None)
+            .is_err());
+        assert!(manager.update_task_status(&tasks[2].id, "doing").is_err());
+
+        let mut columns = manager.list_board_columns().unwrap();
+        columns[0].wip_limit = Some(0);
+        manager.save_board_columns(&columns).unwrap();
+        assert!(manager.move_task(&tasks[2].id, "backlog", None).is_err());
+
+        columns[1].drop_status = Some("running".to_string());
+        assert!(manager.save_board_columns(&columns).is_err());
+    }
+
+    #[test]
+    fn test_rank_between() {
+        assert_eq!(rank_between(None, None), Some(1.0));
+        assert_eq!(rank_between(Some(2.0), None), Some(3.0));
+        assert_eq!(rank_between(None, Some(2.0)), Some(1.0));
+        assert_eq!(rank_between(Some(1.0), Some(2.0)), Some(1.5));
+        assert_eq!(rank_between(Some(1.0), Some(1.0 + 1e-9)), None);
+    }
+}
@@ -0,0 +1,619 @@
+//! Built-in tools for agents working on a project
+//!
+//! Each tool is offered when its name is in `AgentConfig.tools` (the names
+//! the agent wizard uses: `Read`, `Write`, `Edit`, `Glob`, `Grep`, `Bash`).
+//! `GitStatus` comes with `Read`. They only exist for tasks with a project;
+//! relative paths are resolved against the project root and commands run
+//! there in a PTY, inside the agent's sandbox (see `sandbox`).
+
+use super::manager::AgentConfig;
+use super::permissions::PermissionPolicy;
+use super::sandbox::{self, SandboxConfig};
+use crate::mcp::protocol::{CallToolResult, Tool};
+use crate::projects::{Project, ProjectManager};
+use crate::terminal::pty::PtyProcess;
+use globset::Glob;
+use regex::Regex;
+use serde_json::{json, Value};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use walkdir::WalkDir;
+
+const DEFAULT_READ_LINES: usize = 2_000;
+const MAX_MATCHES: usize = 200;
+/// Files larger than this are skipped by Grep
+const MAX_GREP_FILE_BYTES: u64 = 1024 * 1024;
+const MAX_COMMAND_OUTPUT_BYTES: usize = 256 * 1024;
+const DEFAULT_COMMAND_TIMEOUT_SECS: u64 = 120;
+const MAX_COMMAND_TIMEOUT_SECS: u64 = 600;
+
+/// Directories no tool walks into
+const SKIPPED_DIRS: &[&str] = &[".git", "node_modules", "target", "__pycache__"];
+
+pub struct BuiltinTools {
+    root: PathBuf,
+    project_id: String,
+    projects: Arc<ProjectManager>,
+    enabled: Vec<&'static str>,
+    sandbox: SandboxConfig,
+    /// Files its `Read` rules cover are left out of Grep and Glob results
+    permissions: PermissionPolicy,
+}
+
+impl BuiltinTools {
+    /// The built-in tools `config` enables for a task on `project`, or
+    /// `None` if it enables none
+    pub fn for_project(
+        config: &AgentConfig,
+        project: &Project,
+        projects: Arc<ProjectManager>,
+    ) -> Option<Self> {
+        let has = |name: &str| config.tools.iter().any(|t| t == name);
+        let enabled: Vec<&'static str> =
+            ["Read", "Write", "Edit", "Glob", "Grep", "Bash", "GitStatus"]
+                .into_iter()
+                .filter(|name| has(name) || (*name == "GitStatus" && has("Read")))
+                .collect();
+
+        (!enabled.is_empty()).then(|| Self {
+            root: PathBuf::from(&project.path),
+            project_id: project.id.clone(),
+            projects,
+            enabled,
+            sandbox: config.sandbox.clone(),
+            permissions: config.permissions.clone(),
+        })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn handles(&self, name: &str) -> bool {
+        self.enabled.contains(&name)
+    }
+
+    pub fn definitions(&self) -> Vec<Tool> {
+        self.enabled
+            .iter()
+            .map(|name| {
+                let (description, input_schema) = definition(name);
+                Tool {
+                    name: name.to_string(),
+                    description: description.to_string(),
+                    input_schema,
+                }
+            })
+            .collect()
+    }
+
+    pub async fn call(&self, name: &str, arguments: &Value) -> CallToolResult {
+        if !self.handles(name) {
+            return CallToolResult::error(format!("Unknown tool: {}", name));
+        }
+
+        let result = match name {
+            "Read" => self.read(arguments),
+            "Write" => self.write(arguments),
+            "Edit" => self.edit(arguments),
+            "Glob" => self.glob(arguments),
+            "Grep" => self.grep(arguments),
+            "Bash" => self.bash(arguments).await,
+            "GitStatus" => self.git_status(),
+            _ => Err(format!("Unknown tool: {}", name)),
+        };
+
+        match result {
+            Ok(text) => CallToolResult::text(text),
+            Err(e) => CallToolResult::error(e),
+        }
+    }
+
+    /// Resolve `path` against the project root
+    pub fn resolve(&self, path: &str) -> PathBuf {
+        normalize(&self.root.join(path))
+    }
+
+    fn read(&self, args: &Value) -> Result<String, String> {
+        let path = self.resolve(str_arg(args, "path")?);
+        let offset = args
+            .get("offset")
+            .and_then(Value::as_u64)
+            .unwrap_or(1)
+            .max(1) as usize;
+        let limit = args
+            .get("limit")
+            .and_then(Value::as_u64)
+            .map_or(DEFAULT_READ_LINES, |l| l as usize);
+
+        let content = std::fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+
+        let mut out = String::new();
+        for (i, line) in content.lines().enumerate().skip(offset - 1).take(limit) {
+            out.push_str(&format!("{:>6}\t{}\n", i + 1, line));
+        }
+        if out.is_empty() {
+            out.push_str("(no lines in range)");
+        }
+        Ok(out)
+    }
+
+    fn write(&self, args: &Value) -> Result<String, String> {
+        let path = self.resolve(str_arg(args, "path")?);
+        let content = str_arg(args, "content")?;
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        std::fs::write(&path, content)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+
+        Ok(format!(
+            "Wrote {} bytes to {}",
+            content.len(),
+            path.display()
+        ))
+    }
+
+    fn edit(&self, args: &Value) -> Result<String, String> {
+        let path = self.resolve(str_arg(args, "path")?);
+        let old = str_arg(args, "old_string")?;
+        let new = str_arg(args, "new_string")?;
+        let replace_all = args
+            .get("replace_all")
+            .and_then(Value::as_bool)
+            .unwrap_or(false);
+
+        let content = std::fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+
+        let count = content.matches(old).count();
+        if old.is_empty() || count == 0 {
+            return Err(format!("old_string not found in {}", path.display()));
+        }
+        if count > 1 && !replace_all {
+            return Err(format!(
+                "old_string appears {} times in {}; add context to make it unique or set replace_all",
+                count,
+                path.display()
+            ));
+        }
+
+        let updated = if replace_all {
+            content.replace(old, new)
+        } else {
+            content.replacen(old, new, 1)
+        };
+        std::fs::write(&path, updated)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+
+        Ok(format!(
+            "Replaced {} occurrence(s) in {}",
+            count,
+            path.display()
+        ))
+    }
+
+    fn glob(&self, args: &Value) -> Result<String, String> {
+        let matcher = Glob::new(str_arg(args, "pattern")?)
+            .map_err(|e| e.to_string())?
+            .compile_matcher();
+
+        let mut matches: Vec<String> = self
+            .files()
+            .filter_map(|path| {
+                let relative = path.strip_prefix(&self.root).ok()?;
+                (matcher.is_match(relative) && !self.hidden(relative))
+                    .then(|| relative.to_string_lossy().to_string())
+            })
+            .take(MAX_MATCHES + 1)
+            .collect();
+
+        Ok(format_matches(&mut matches, "files"))
+    }
+
+    fn grep(&self, args: &Value) -> Result<String, String> {
+        let regex = Regex::new(str_arg(args, "pattern")?).map_err(|e| e.to_string())?;
+        let filter = match args.get("glob").and_then(Value::as_str) {
+            Some(glob) => Some(
+                Glob::new(glob)
+                    .map_err(|e| e.to_string())?
+                    .compile_matcher(),
+            ),
+            None => None,
+        };
+
+        let mut matches = Vec::new();
+        'files: for path in self.files() {
+            let Ok(relative) = path.strip_prefix(&self.root) else {
+                continue;
+            };
+            if filter.as_ref().is_some_and(|f| !f.is_match(relative)) || self.hidden(relative) {
+                continue;
+            }
+            if std::fs::metadata(&path).map_or(true, |m| m.len() > MAX_GREP_FILE_BYTES) {
+                continue;
+            }
+            // Binary and non-UTF-8 files are skipped
+            let Ok(content) = std::fs::read_to_string(&path) else {
+                continue;
+            };
+
+            for (i, line) in content.lines().enumerate() {
+                if regex.is_match(line) {
+                    matches.push(format!(
+                        "{}:{}: {}",
+                        relative.display(),
+                        i + 1,
+                        line.trim_end()
+                    ));
+                    if matches.len() > MAX_MATCHES {
+                        break 'files;
+                    }
+                }
+            }
+        }
+
+        Ok(format_matches(&mut matches, "matches"))
+    }
+
+    /// Whether a `Read` rule keeps `relative` out of search results
+    fn hidden(&self, relative: &Path) -> bool {
+        self.permissions
+            .hides(&relative.to_string_lossy().replace('\\', "/"))
+    }
+
+    async fn bash(&self, args: &Value) -> Result<String, String> {
+        let command = str_arg(args, "command")?;
+        let timeout = args
+            .get("timeout")
+            .and_then(Value::as_u64)
+            .unwrap_or(DEFAULT_COMMAND_TIMEOUT_SECS)
+            .min(MAX_COMMAND_TIMEOUT_SECS);
+        let timeout = sandbox::time_limit(&self.sandbox, timeout);
+        let wrapped = sandbox::wrap_command(command, &self.root, &self.sandbox, sandbox::backend())?;
+
+        let (mut pty, mut output_rx) = PtyProcess::spawn(200, 50, Some(&wrapped), Some(&self.root))?;
+
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(timeout);
+        let mut output = Vec::new();
+        let timed_out = loop {
+            match tokio::time::timeout_at(deadline, output_rx.recv()).await {
+                Ok(Some(chunk)) => {
+                    if output.len() < MAX_COMMAND_OUTPUT_BYTES {
+                        output.extend_from_slice(&chunk);
+                    }
+                }
+                Ok(None) => break false,
+                Err(_) => break true,
+            }
+        };
+
+        // Kill the whole group so background jobs and pipelines the
+        // command started don't outlive it
+        if timed_out {
+            let _ = pty.kill_group();
+        }
+
+        // The exit status can lag the end of output slightly
+        let mut exit_code = pty.exit_code();
+        for _ in 0..20 {
+            if exit_code.is_some() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            exit_code = pty.exit_code();
+        }
+
+        let mut text = strip_ansi(&String::from_utf8_lossy(&output));
+        if output.len() >= MAX_COMMAND_OUTPUT_BYTES {
+            text.push_str("\n… [output truncated]");
+        }
+
+        if timed_out {
+            return Err(format!(
+                "{}\nCommand timed out after {}s",
+                text.trim_end(),
+                timeout
+            ));
+        }
+        match exit_code {
+            Some(0) => Ok(text),
+            Some(code) => Err(format!("{}\nExit code {}", text.trim_end(), code)),
+            None => Ok(text),
+        }
+    }
+
+    fn git_status(&self) -> Result<String, String> {
+        match self.projects.get_git_status(&self.project_id)? {
+            Some(status) => serde_json::to_string_pretty(&status).map_err(|e| e.to_string()),
+            None => Ok("The project is not a git repository".to_string()),
+        }
+    }
+
+    /// Files under the project root, skipping hidden and build directories
+    fn files(&self) -> impl Iterator<Item = PathBuf> {
+        WalkDir::new(&self.root)
+            .into_iter()
+            .filter_entry(|e| {
+                e.depth() == 0
+                    || !(e.file_type().is_dir()
+                        && SKIPPED_DIRS.contains(&e.file_name().to_string_lossy().as_ref()))
+            })
+            .filter_map(Result::ok)
+            .filter(|e| e.file_type().is_file())
+            .map(|e| e.into_path())
+    }
+}
+
+fn definition(name: &str) -> (&'static str, Value) {
+    match name {
+        "Read" => (
+            "Read a file. Lines are numbered from 1.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "path": { "type": "string", "description": "Path relative to the project root" },
+                    "offset": { "type": "integer", "description": "First line to read" },
+                    "limit": { "type": "integer", "description": "Number of lines to read" }
+                },
+                "required": ["path"]
+            }),
+        ),
+        "Write" => (
+            "Create or overwrite a file.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "path": { "type": "string" },
+                    "content": { "type": "string" }
+                },
+                "required": ["path", "content"]
+            }),
+        ),
+        "Edit" => (
+            "Replace exact text in a file. old_string must be unique unless replace_all is set.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "path": { "type": "string" },
+                    "old_string": { "type": "string" },
+                    "new_string": { "type": "string" },
+                    "replace_all": { "type": "boolean" }
+                },
+                "required": ["path", "old_string", "new_string"]
+            }),
+        ),
+        "Glob" => (
+            "Find files whose path relative to the project root matches a glob, e.g. src/**/*.rs.",
+            json!({
+                "type": "object",
+                "properties": { "pattern": { "type": "string" } },
+                "required": ["pattern"]
+            }),
+        ),
+        "Grep" => (
+            "Search file contents with a regular expression, optionally only in files matching a glob.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "pattern": { "type": "string" },
+                    "glob": { "type": "string" }
+                },
+                "required": ["pattern"]
+            }),
+        ),
+        "Bash" => (
+            "Run a shell command in the project root and return its output.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "command": { "type": "string" },
+                    "timeout": { "type": "integer", "description": "Seconds, at most 600" }
+                },
+                "required": ["command"]
+            }),
+        ),
+        _ => (
+            "Show the branch, ahead/behind counts and changed files of the project's git repository.",
+            json!({ "type": "object", "properties": {} }),
+        ),
+    }
+}
+
+fn str_arg<'a>(args: &'a Value, key: &str) -> Result<&'a str, String> {
+    args.get(key)
+        .and_then(Value::as_str)
+        .ok_or_else(|| format!("Missing string argument '{}'", key))
+}
+
+fn format_matches(matches: &mut Vec<String>, what: &str) -> String {
+    if matches.is_empty() {
+        return format!("No {} found", what);
+    }
+    let truncated = matches.len() > MAX_MATCHES;
+    matches.truncate(MAX_MATCHES);
+
+    let mut out = matches.join("\n");
+    if truncated {
+        out.push_str(&format!(
+            "\n… [only the first {} {} are shown]",
+            MAX_MATCHES, what
+        ));
+    }
+    out
+}
+
+/// Resolve `.` and `..` without touching the filesystem, so paths to files
+/// that don't exist yet can be checked too
+pub fn normalize(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                out.pop();
+            }
+            other => out.push(other),
+        }
+    }
+    out
+}
+
+/// Drop terminal escape sequences and carriage returns from PTY output
+fn strip_ansi(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\x1b' => match chars.next() {
+                // CSI: parameters until a final byte in @..~
+                Some('[') => {
+                    for c in chars.by_ref() {
+                        if ('@'..='~').contains(&c) {
+                            break;
+                        }
+                    }
+                }
+                // OSC: until BEL or ST
+                Some(']') => {
+                    while let Some(c) = chars.next() {
+                        if c == '\x07' || (c == '\x1b' && chars.next_if_eq(&'\\').is_some()) {
+                            break;
+                        }
+                    }
+                }
+                _ => {}
+            },
+            '\r' => {}
+            c => out.push(c),
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_file_tools() {
+        let dir = tempdir().unwrap();
+        let projects = Arc::new(ProjectManager::new(
+            Database::new(dir.path().join("data")).unwrap(),
+        ));
+        let root = dir.path().join("repo");
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        let project = projects.add(root.to_str().unwrap(), "rust").unwrap();
+
+        let config = AgentConfig {
+            tools: vec![
+                "Read".to_string(),
+                "Write".to_string(),
+                "Edit".to_string(),
+                "Grep".to_string(),
+            ],
+            permissions: PermissionPolicy {
+                deny: vec!["Read(*.pem)".to_string()],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let tools = BuiltinTools::for_project(&config, &project, projects).unwrap();
+        assert!(tools.handles("GitStatus"));
+        assert!(!tools.handles("Bash"));
+
+        let call = |name: &'static str, args: Value| {
+            let tools = &tools;
+            async move { tools.call(name, &args).await }
+        };
+
+        let written = call(
+            "Write",
+            json!({ "path": "src/lib.rs", "content": "fn a() {}\nfn b() {}\n" }),
+        )
+        .await;
+        assert!(!written.is_error);
+
+        let edited = call(
+            "Edit",
+            json!({ "path": "src/lib.rs", "old_string": "fn", "new_string": "pub fn" }),
+        )
+        .await;
+        assert!(edited.is_error, "ambiguous edits are refused");
+        let edited = call(
+            "Edit",
+            json!({ "path": "./src/../src/lib.rs", "old_string": "fn b", "new_string": "fn c" }),
+        )
+        .await;
+        assert!(!edited.is_error);
+
+        let read = call("Read", json!({ "path": "src/lib.rs", "offset": 2 })).await;
+        assert_eq!(read.joined_text(), "     2\tfn c() {}\n");
+
+        let found = call(
+            "Grep",
+            json!({ "pattern": "fn [a-z]\\(", "glob": "**/*.rs" }),
+        )
+        .await;
+        assert_eq!(
+            found.joined_text(),
+            "src/lib.rs:1: fn a() {}\nsrc/lib.rs:2: fn c() {}"
+        );
+
+        std::fs::write(root.join("src/key.pem"), "fn z() {}\n").unwrap();
+        let found = call("Grep", json!({ "pattern": "fn z" })).await;
+        assert_eq!(found.joined_text(), "No matches found");
+
+        let refused = call("Bash", json!({ "command": "true" })).await;
+        assert!(refused.is_error);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_bash_timeout_kills_background_jobs() {
+        let dir = tempdir().unwrap();
+        let projects = Arc::new(ProjectManager::new(
+            Database::new(dir.path().join("data")).unwrap(),
+        ));
+        let root = dir.path().join("repo");
+        std::fs::create_dir(&root).unwrap();
+        let project = projects.add(root.to_str().unwrap(), "code").unwrap();
+
+        let config = AgentConfig {
+            tools: vec!["Bash".to_string()],
+            sandbox: SandboxConfig {
+                mode: sandbox::SandboxMode::Off,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let tools = BuiltinTools::for_project(&config, &project, projects).unwrap();
+
+        let result = tools
+            .call(
+                "Bash",
+                &json!({ "command": "(sleep 2; touch late) & sleep 30", "timeout": 1 }),
+            )
+            .await;
+        assert!(result.is_error);
+        assert!(result.joined_text().contains("timed out"));
+
+        tokio::time::sleep(Duration::from_secs(3)).await;
+        assert!(!root.join("late").exists());
+    }
+
+    #[test]
+    fn test_strip_ansi() {
+        assert_eq!(
+            strip_ansi("\x1b[1;32mok\x1b[0m\r\n\x1b]0;title\x07done"),
+            "ok\ndone"
+        );
+    }
+}
@@ -0,0 +1,620 @@
+//! Bulk task import and bulk operations
+//!
+//! Tasks can be imported from CSV (a header row naming the columns), a JSON
+//! array of objects, or a Markdown checklist with one task per `- [ ]`
+//! line. Every row is validated first; if any row has errors, none are
+//! created and the errors are returned by row.
+//!
+//! Recognised fields: `title` (required), `description`, `priority`,
+//! `agent` (name or id), `project` (name, id or path), `type` (agent type),
+//! `skills` (separated by `;` or `,` in CSV), `deadline` and `scheduledFor`
+//! (unix seconds, RFC 3339, or a `YYYY-MM-DD` date meaning the end of that
+//! day in UTC).
+
+use super::manager::{
+    Agent, AgentManager, Task, ACTIVE_STATUSES, FINISHED_STATUSES, TASK_PRIORITIES,
+};
+use crate::projects::{Project, ProjectManager};
+use chrono::{NaiveDate, NaiveTime};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::Path;
+
+const FIELDS: &[&str] = &[
+    "title",
+    "description",
+    "priority",
+    "agent",
+    "project",
+    "type",
+    "skills",
+    "deadline",
+    "scheduledfor",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportFormat {
+    Csv,
+    Json,
+    Markdown,
+}
+
+impl ImportFormat {
+    /// Guess the format from a file extension
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_lowercase().as_str() {
+            "csv" => Some(ImportFormat::Csv),
+            "json" => Some(ImportFormat::Json),
+            "md" | "markdown" => Some(ImportFormat::Markdown),
+            _ => None,
+        }
+    }
+}
+
+/// Why a row could not be imported
+#[derive(Debug, Clone, Serialize)]
+pub struct RowError {
+    /// Line number for CSV and Markdown, 1-based position for JSON
+    pub row: usize,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportReport {
+    #[serde(rename = "dryRun")]
+    pub dry_run: bool,
+    /// Tasks created, or that would be created on a dry run or if there
+    /// were no errors
+    pub tasks: Vec<Task>,
+    pub errors: Vec<RowError>,
+}
+
+/// Validate `content` and, unless `dry_run` or a row has errors, create
+/// its tasks in one transaction
+pub fn import_tasks(
+    manager: &AgentManager,
+    projects: &ProjectManager,
+    format: ImportFormat,
+    content: &str,
+    dry_run: bool,
+) -> Result<ImportReport, String> {
+    let rows = parse_rows(format, content)?;
+    if rows.is_empty() {
+        return Err("No tasks found".to_string());
+    }
+
+    let agents = manager.list_agents()?;
+    let projects = projects.list()?;
+
+    let mut tasks = Vec::new();
+    let mut errors = Vec::new();
+    for (row, fields) in rows {
+        match fields.and_then(|fields| to_task(&fields, &agents, &projects)) {
+            Ok(task) => tasks.push(task),
+            Err(message) => errors.push(RowError { row, message }),
+        }
+    }
+
+    if errors.is_empty() && !dry_run {
+        tasks = manager.create_tasks(&tasks)?;
+    }
+    Ok(ImportReport {
+        dry_run,
+        tasks,
+        errors,
+    })
+}
+
+type Fields = HashMap<String, Value>;
+/// A row's number and its fields, or what is wrong with it
+type Row = (usize, Result<Fields, String>);
+
+/// Split `content` into rows of fields keyed by normalised name. File-level
+/// problems are an `Err`; problems with one row are kept with that row.
+fn parse_rows(format: ImportFormat, content: &str) -> Result<Vec<Row>, String> {
+    match format {
+        ImportFormat::Csv => parse_csv_rows(content),
+        ImportFormat::Json => parse_json_rows(content),
+        ImportFormat::Markdown => Ok(parse_checklist(content)),
+    }
+}
+
+/// `scheduled_for`, `Scheduled For` and `scheduledFor` are the same field
+fn normalize_key(key: &str) -> String {
+    let key: String = key
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect();
+    match key.as_str() {
+        "agentid" => "agent".to_string(),
+        "projectid" => "project".to_string(),
+        "agenttype" | "targetagenttype" => "type".to_string(),
+        _ => key,
+    }
+}
+
+fn parse_csv_rows(content: &str) -> Result<Vec<Row>, String> {
+    let mut records = parse_csv(content)?.into_iter();
+    let Some((_, header)) = records.next() else {
+        return Ok(vec![]);
+    };
+
+    let columns: Vec<String> = header.iter().map(|h| normalize_key(h)).collect();
+    if let Some(unknown) = header
+        .iter()
+        .zip(&columns)
+        .find(|(_, c)| !FIELDS.contains(&c.as_str()))
+    {
+        return Err(format!("Unknown column '{}'", unknown.0.trim()));
+    }
+
+    Ok(records
+        .map(|(line, cells)| {
+            if cells.len() > columns.len() {
+                return (
+                    line,
+                    Err(format!("Expected at most {} fields", columns.len())),
+                );
+            }
+            let fields = columns
+                .iter()
+                .zip(cells)
+                .filter(|(_, cell)| !cell.trim().is_empty())
+                .map(|(column, cell)| (column.clone(), Value::String(cell)))
+                .collect();
+            (line, Ok(fields))
+        })
+        .collect())
+}
+
+/// Parse CSV records with their starting line numbers. Quoted fields may
+/// hold commas, newlines and doubled quotes. Blank lines are skipped.
+fn parse_csv(content: &str) -> Result<Vec<(usize, Vec<String>)>, String> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut line = 1;
+    let mut start = 1;
+    let mut chars = content.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' if in_quotes => in_quotes = false,
+            '"' if field.is_empty() => in_quotes = true,
+            '\n' if in_quotes => {
+                line += 1;
+                field.push(c);
+            }
+            ',' if !in_quotes => record.push(std::mem::take(&mut field)),
+            '\r' if !in_quotes => {}
+            '\n' => {
+                record.push(std::mem::take(&mut field));
+                if record.iter().any(|f| !f.is_empty()) {
+                    records.push((start, std::mem::take(&mut record)));
+                }
+                record.clear();
+                line += 1;
+                start = line;
+            }
+            _ => field.push(c),
+        }
+    }
+
+    if in_quotes {
+        return Err(format!(
+            "Unterminated quoted field starting on line {}",
+            start
+        ));
+    }
+    record.push(field);
+    if record.iter().any(|f| !f.is_empty()) {
+        records.push((start, record));
+    }
+    Ok(records)
+}
+
+fn parse_json_rows(content: &str) -> Result<Vec<Row>, String> {
+    let value: Value = serde_json::from_str(content).map_err(|e| format!("Invalid JSON: {}", e))?;
+    let Value::Array(items) = value else {
+        return Err("Expected a JSON array of tasks".to_string());
+    };
+
+    Ok(items
+        .into_iter()
+        .enumerate()
+        .map(|(index, item)| {
+            let fields = match item {
+                Value::Object(map) => map
+                    .into_iter()
+                    .filter(|(_, v)| !v.is_null())
+                    .map(|(k, v)| {
+                        let key = normalize_key(&k);
+                        if FIELDS.contains(&key.as_str()) {
+                            Ok((key, v))
+                        } else {
+                            Err(format!("Unknown field '{}'", k))
+                        }
+                    })
+                    .collect(),
+                _ => Err("Expected an object".to_string()),
+            };
+            (index + 1, fields)
+        })
+        .collect())
+}
+
+/// One task per unchecked `- [ ]` item. Indented lines that follow an item
+/// become its description; checked items are skipped.
+fn parse_checklist(content: &str) -> Vec<Row> {
+    let mut rows: Vec<(usize, Fields)> = Vec::new();
+    let mut current = false;
+
+    for (index, line) in content.lines().enumerate() {
+        let trimmed = line.trim_start();
+        let item = trimmed
+            .strip_prefix("- ")
+            .or_else(|| trimmed.strip_prefix("* "));
+
+        if let Some(item) = item {
+            if let Some(title) = item.strip_prefix("[ ]") {
+                let mut fields = Fields::new();
+                fields.insert("title".to_string(), Value::String(title.trim().to_string()));
+                rows.push((index + 1, fields));
+                current = true;
+                continue;
+            }
+            if item.starts_with("[x]") || item.starts_with("[X]") {
+                current = false;
+                continue;
+            }
+        }
+
+        let indented = line.starts_with(' ') || line.starts_with('\t');
+        match rows.last_mut() {
+            Some((_, fields)) if current && indented && !trimmed.is_empty() => {
+                let description = fields
+                    .entry("description".to_string())
+                    .or_insert_with(|| Value::String(String::new()));
+                if let Value::String(text) = description {
+                    if !text.is_empty() {
+                        text.push('\n');
+                    }
+                    text.push_str(trimmed);
+                }
+            }
+            _ if !trimmed.is_empty() => current = false,
+            _ => {}
+        }
+    }
+
+    rows.into_iter()
+        .map(|(line, fields)| (line, Ok(fields)))
+        .collect()
+}
+
+/// Validate a row and turn it into a task, reporting every problem at once
+fn to_task(fields: &Fields, agents: &[Agent], projects: &[Project]) -> Result<Task, String> {
+    let mut problems = Vec::new();
+    let text = |key: &str| -> Option<String> {
+        match fields.get(key)? {
+            Value::String(s) => Some(s.trim().to_string()).filter(|s| !s.is_empty()),
+            other => Some(other.to_string()),
+        }
+    };
+
+    let title = text("title").unwrap_or_default();
+    if title.is_empty() {
+        problems.push("title is required".to_string());
+    }
+
+    let priority = text("priority")
+        .map(|p| p.to_lowercase())
+        .unwrap_or_else(|| "normal".to_string());
+    if !TASK_PRIORITIES.contains(&priority.as_str()) {
+        problems.push(format!(
+            "unknown priority '{}'; expected one of {}",
+            priority,
+            TASK_PRIORITIES.join(", ")
+        ));
+    }
+
+    let agent_id = text("agent").and_then(|reference| {
+        let found = agents
+            .iter()
+            .find(|a| a.id == reference || a.name.eq_ignore_ascii_case(&reference));
+        if found.is_none() {
+            problems.push(format!("no agent named '{}'", reference));
+        }
+        found.map(|a| a.id.clone())
+    });
+
+    let project_id = text("project").and_then(|reference| {
+        let found = projects.iter().find(|p| {
+            p.id == reference || p.name.eq_ignore_ascii_case(&reference) || p.path == reference
+        });
+        if found.is_none() {
+            problems.push(format!("no project named '{}'", reference));
+        }
+        found.map(|p| p.id.clone())
+    });
+
+    let mut timestamp = |key: &str, label: &str| {
+        fields.get(key).and_then(|value| {
+            let parsed = parse_time(value);
+            if parsed.is_none() {
+                problems.push(format!("{} {} is not a date or timestamp", label, value));
+            }
+            parsed
+        })
+    };
+    let deadline = timestamp("deadline", "deadline");
+    let scheduled_for = timestamp("scheduledfor", "scheduledFor");
+
+    let skills = match fields.get("skills") {
+        Some(Value::Array(items)) => items
+            .iter()
+            .filter_map(|v| v.as_str())
+            .map(str::to_string)
+            .collect(),
+        Some(Value::String(list)) => list
+            .split([';', ','])
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+            .collect(),
+        Some(other) => {
+            problems.push(format!("skills {} is not a list", other));
+            vec![]
+        }
+        None => vec![],
+    };
+
+    if !problems.is_empty() {
+        return Err(problems.join("; "));
+    }
+
+    Ok(Task {
+        agent_id,
+        project_id,
+        description: text("description").unwrap_or_default(),
+        priority,
+        deadline,
+        scheduled_for,
+        target_agent_type: text("type"),
+        skills,
+        ..Task::new(title)
+    })
+}
+
+/// Unix seconds, RFC 3339, or a date meaning the end of that day in UTC
+fn parse_time(value: &Value) -> Option<i64> {
+    if let Some(secs) = value.as_i64() {
+        return Some(secs);
+    }
+    let text = value.as_str()?.trim();
+    if let Ok(secs) = text.parse::<i64>() {
+        return Some(secs);
+    }
+    if let Ok(time) = chrono::DateTime::parse_from_rfc3339(text) {
+        return Some(time.timestamp());
+    }
+    let date = NaiveDate::parse_from_str(text, "%Y-%m-%d").ok()?;
+    let end_of_day = NaiveTime::from_hms_opt(23, 59, 59)?;
+    Some(date.and_time(end_of_day).and_utc().timestamp())
+}
+
+/// Which tasks a bulk operation applies to. Every field that is set must
+/// match; at least one must be set.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TaskFilter {
+    #[serde(rename = "taskIds", default)]
+    pub task_ids: Vec<String>,
+    #[serde(default)]
+    pub statuses: Vec<String>,
+    #[serde(rename = "agentId", default)]
+    pub agent_id: Option<String>,
+    #[serde(rename = "projectId", default)]
+    pub project_id: Option<String>,
+    #[serde(default)]
+    pub priority: Option<String>,
+}
+
+impl TaskFilter {
+    pub fn is_empty(&self) -> bool {
+        self.task_ids.is_empty()
+            && self.statuses.is_empty()
+            && self.agent_id.is_none()
+            && self.project_id.is_none()
+            && self.priority.is_none()
+    }
+
+    pub fn matches(&self, task: &Task) -> bool {
+        (self.task_ids.is_empty() || self.task_ids.contains(&task.id))
+            && (self.statuses.is_empty() || self.statuses.contains(&task.status))
+            && self
+                .agent_id
+                .as_ref()
+                .map_or(true, |id| task.agent_id.as_ref() == Some(id))
+            && self
+                .project_id
+                .as_ref()
+                .map_or(true, |id| task.project_id.as_ref() == Some(id))
+            && self.priority.as_ref().map_or(true, |p| &task.priority == p)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum BulkAction {
+    Cancel,
+    /// Hand tasks to another agent, or back to routing with `None`
+    Reassign {
+        agent_id: Option<String>,
+    },
+    SetPriority {
+        priority: String,
+    },
+}
+
+impl BulkAction {
+    /// Why `task` is left out, if it is
+    pub(super) fn skip_reason(&self, task: &Task) -> Option<String> {
+        if FINISHED_STATUSES.contains(&task.status.as_str()) {
+            return Some(format!("already {}", task.status));
+        }
+        // Requeueing a task with a live run would start a second one
+        if ACTIVE_STATUSES.contains(&task.status.as_str())
+            && matches!(self, BulkAction::Reassign { .. })
+        {
+            return Some(task.status.clone());
+        }
+        None
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BulkResult {
+    /// Tasks the action was applied to
+    pub updated: Vec<String>,
+    /// Matching tasks that were left alone
+    pub skipped: Vec<BulkSkip>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BulkSkip {
+    #[serde(rename = "taskId")]
+    pub task_id: String,
+    pub reason: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+    use tempfile::tempdir;
+
+    fn setup() -> (tempfile::TempDir, AgentManager, ProjectManager) {
+        let dir = tempdir().unwrap();
+        let db = Database::new(dir.path().to_path_buf()).unwrap();
+        (dir, AgentManager::new(db.clone()), ProjectManager::new(db))
+    }
+
+    #[test]
+    fn test_import_formats_and_row_errors() {
+        let (_dir, manager, projects) = setup();
+        manager
+            .create_agent(Default::default(), "Writer", "content")
+            .unwrap();
+
+        let csv = "Title,Priority,Agent,Skills,Deadline\n\
+                   \"Draft, then edit\",high,writer,seo;copy,2030-01-31\n\
+                   Publish,,,,\n";
+        let report = import_tasks(&manager, &projects, ImportFormat::Csv, csv, false).unwrap();
+        assert!(report.errors.is_empty());
+        assert_eq!(report.tasks[0].title, "Draft, then edit");
+        assert_eq!(report.tasks[0].skills, ["seo", "copy"]);
+        assert_eq!(report.tasks[0].deadline, Some(1896134399));
+        assert!(report.tasks[0].agent_id.is_some());
+        assert_eq!(report.tasks[1].priority, "normal");
+
+        let markdown =
+            "# Launch\n- [ ] Write notes\n  for the release\n- [x] Done already\n* [ ] Tag it\n";
+        let report =
+            import_tasks(&manager, &projects, ImportFormat::Markdown, markdown, true).unwrap();
+        let titles: Vec<_> = report.tasks.iter().map(|t| t.title.as_str()).collect();
+        assert_eq!(titles, ["Write notes", "Tag it"]);
+        assert_eq!(report.tasks[0].description, "for the release");
+
+        // One bad row keeps the whole file out
+        let json = r#"[{"title": "Fine", "scheduledFor": 1700000000},
+                       {"title": "", "priority": "asap", "project": "nope"}]"#;
+        let report = import_tasks(&manager, &projects, ImportFormat::Json, json, false).unwrap();
+        assert_eq!(report.errors.len(), 1);
+        assert_eq!(report.errors[0].row, 2);
+        assert!(report.errors[0].message.contains("title is required"));
+        assert!(report.errors[0].message.contains("no project named 'nope'"));
+        assert_eq!(manager.list_tasks(None).unwrap().len(), 2);
+
+        assert!(import_tasks(&manager, &projects, ImportFormat::Csv, "Name\nx\n", false).is_err());
+    }
+
+    #[test]
+    fn test_bulk_update_is_filtered_and_skips_finished() {
+        let (_dir, manager, _projects) = setup();
+        let agent = manager
+            .create_agent(Default::default(), "Writer", "content")
+            .unwrap();
+        let tasks = manager
+            .create_tasks(&[Task::new("a"), Task::new("b"), Task::new("c")])
+            .unwrap();
+        manager
+            .update_task_status(&tasks[2].id, "completed")
+            .unwrap();
+        let waiting = manager.create_task(&Task::new("d")).unwrap();
+        manager.update_task_status(&waiting.id, "waiting").unwrap();
+
+        assert!(manager
+            .bulk_update(&TaskFilter::default(), &BulkAction::Cancel)
+            .is_err());
+
+        let all = TaskFilter {
+            task_ids: tasks
+                .iter()
+                .chain([&waiting])
+                .map(|t| t.id.clone())
+                .collect(),
+            ..Default::default()
+        };
+        let result = manager
+            .bulk_update(
+                &all,
+                &BulkAction::Reassign {
+                    agent_id: Some(agent.id.clone()),
+                },
+            )
+            .unwrap();
+        assert_eq!(result.updated.len(), 2);
+        let mut reasons: Vec<&str> = result.skipped.iter().map(|s| s.reason.as_str()).collect();
+        reasons.sort();
+        assert_eq!(reasons, ["already completed", "waiting"]);
+
+        let by_agent = TaskFilter {
+            agent_id: Some(agent.id.clone()),
+            ..Default::default()
+        };
+        let result = manager
+            .bulk_update(
+                &by_agent,
+                &BulkAction::SetPriority {
+                    priority: "urgent".to_string(),
+                },
+            )
+            .unwrap();
+        assert_eq!(result.updated.len(), 2);
+        assert!(manager
+            .bulk_update(
+                &by_agent,
+                &BulkAction::SetPriority {
+                    priority: "asap".to_string()
+                }
+            )
+            .is_err());
+
+        manager.bulk_update(&by_agent, &BulkAction::Cancel).unwrap();
+        let statuses: Vec<_> = manager
+            .list_tasks(None)
+            .unwrap()
+            .into_iter()
+            .map(|t| (t.title, t.status, t.priority))
+            .collect();
+        assert!(statuses.contains(&("a".into(), "cancelled".into(), "urgent".into())));
+        assert!(statuses.contains(&("c".into(), "completed".into(), "normal".into())));
+    }
+}
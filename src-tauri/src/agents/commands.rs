@@ -1,6 +1,19 @@
 //! Tauri commands for agents module
 
-use super::manager::{Agent, AgentConfig, AgentManager, Task, TaskLog};
+use super::artifacts::{
+    apply_artifact, discard_artifact, preview_artifact, ArtifactPreview, TaskArtifact,
+};
+use super::board::{Board, BoardColumn};
+use super::bulk::{import_tasks, BulkAction, BulkResult, ImportFormat, ImportReport, TaskFilter};
+use super::comments::{export_task, TaskAttachment, TaskComment};
+use super::deadlines::{deadline_report, SlaReport};
+use super::manager::{
+    Agent, AgentConfig, AgentManager, AgentMemory, Task, TaskLog, TaskMessage, ToolApproval,
+    ToolAuditEntry,
+};
+use super::prompt::{assemble_task_prompt, AssembledPrompt};
+use crate::llm::resolve_model;
+use crate::projects::ProjectManager;
 use std::sync::Arc;
 use tauri::State;
 
@@ -37,6 +50,30 @@ pub fn agent_update(
     manager.update_agent(&agent_id, name, status)
 }
 
+/// Assign an agent to projects; an empty list lets it work on any
+#[tauri::command]
+pub fn agent_set_projects(
+    manager: State<'_, Arc<AgentManager>>,
+    projects: State<'_, Arc<ProjectManager>>,
+    agent_id: String,
+    project_ids: Vec<String>,
+) -> Result<Agent, String> {
+    for project_id in &project_ids {
+        projects
+            .get(project_id)?
+            .ok_or_else(|| format!("Project not found: {}", project_id))?;
+    }
+    manager.set_agent_projects(&agent_id, &project_ids)
+}
+
+#[tauri::command]
+pub fn agent_list_for_project(
+    manager: State<'_, Arc<AgentManager>>,
+    project_id: String,
+) -> Result<Vec<Agent>, String> {
+    manager.list_project_agents(&project_id)
+}
+
 #[tauri::command]
 pub fn agent_delete(manager: State<'_, Arc<AgentManager>>, agent_id: String) -> Result<(), String> {
     manager.delete_agent(&agent_id)
@@ -83,3 +120,296 @@ pub fn task_create(manager: State<'_, Arc<AgentManager>>, task: Task) -> Result<
 pub fn task_cancel(manager: State<'_, Arc<AgentManager>>, task_id: String) -> Result<(), String> {
     manager.cancel_task(&task_id)
 }
+
+/// Import tasks from CSV, JSON or a Markdown checklist. Nothing is created
+/// if any row has errors.
+#[tauri::command]
+pub fn task_import(
+    manager: State<'_, Arc<AgentManager>>,
+    projects: State<'_, Arc<ProjectManager>>,
+    format: ImportFormat,
+    content: String,
+    dry_run: Option<bool>,
+) -> Result<ImportReport, String> {
+    import_tasks(&manager, &projects, format, &content, dry_run.unwrap_or(false))
+}
+
+#[tauri::command]
+pub fn task_bulk_cancel(
+    manager: State<'_, Arc<AgentManager>>,
+    filter: TaskFilter,
+) -> Result<BulkResult, String> {
+    manager.bulk_update(&filter, &BulkAction::Cancel)
+}
+
+/// Hand the selected tasks to `agent_id`, or back to routing when unset
+#[tauri::command]
+pub fn task_bulk_reassign(
+    manager: State<'_, Arc<AgentManager>>,
+    filter: TaskFilter,
+    agent_id: Option<String>,
+) -> Result<BulkResult, String> {
+    manager.bulk_update(&filter, &BulkAction::Reassign { agent_id })
+}
+
+#[tauri::command]
+pub fn task_bulk_set_priority(
+    manager: State<'_, Arc<AgentManager>>,
+    filter: TaskFilter,
+    priority: String,
+) -> Result<BulkResult, String> {
+    manager.bulk_update(&filter, &BulkAction::SetPriority { priority })
+}
+
+#[tauri::command]
+pub fn task_board(manager: State<'_, Arc<AgentManager>>) -> Result<Board, String> {
+    manager.board()
+}
+
+/// Move a task into a board column, before `before_task_id` or at the bottom
+#[tauri::command]
+pub fn task_move(
+    manager: State<'_, Arc<AgentManager>>,
+    task_id: String,
+    column_id: String,
+    before_task_id: Option<String>,
+) -> Result<Task, String> {
+    manager.move_task(&task_id, &column_id, before_task_id.as_deref())
+}
+
+#[tauri::command]
+pub fn board_list_columns(
+    manager: State<'_, Arc<AgentManager>>,
+) -> Result<Vec<BoardColumn>, String> {
+    manager.list_board_columns()
+}
+
+#[tauri::command]
+pub fn board_save_columns(
+    manager: State<'_, Arc<AgentManager>>,
+    columns: Vec<BoardColumn>,
+) -> Result<(), String> {
+    manager.save_board_columns(&columns)
+}
+
+#[tauri::command]
+pub fn task_list_comments(
+    manager: State<'_, Arc<AgentManager>>,
+    task_id: String,
+) -> Result<Vec<TaskComment>, String> {
+    manager.list_comments(&task_id)
+}
+
+#[tauri::command]
+pub fn task_add_comment(
+    manager: State<'_, Arc<AgentManager>>,
+    task_id: String,
+    body: String,
+) -> Result<TaskComment, String> {
+    manager.add_comment(&task_id, None, &body)
+}
+
+#[tauri::command]
+pub fn task_update_comment(
+    manager: State<'_, Arc<AgentManager>>,
+    comment_id: String,
+    body: String,
+) -> Result<(), String> {
+    manager.update_comment(&comment_id, &body)
+}
+
+#[tauri::command]
+pub fn task_delete_comment(
+    manager: State<'_, Arc<AgentManager>>,
+    comment_id: String,
+) -> Result<(), String> {
+    manager.delete_comment(&comment_id)
+}
+
+#[tauri::command]
+pub fn task_list_attachments(
+    manager: State<'_, Arc<AgentManager>>,
+    task_id: String,
+) -> Result<Vec<TaskAttachment>, String> {
+    manager.list_attachments(&task_id)
+}
+
+/// Copy the file at `path` into the app data dir and attach it to the task
+#[tauri::command]
+pub fn task_add_attachment(
+    manager: State<'_, Arc<AgentManager>>,
+    task_id: String,
+    path: String,
+) -> Result<TaskAttachment, String> {
+    let path = std::path::Path::new(&path);
+    let bytes = std::fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let file_name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    manager.add_attachment(&task_id, &file_name, &bytes)
+}
+
+#[tauri::command]
+pub fn task_delete_attachment(
+    manager: State<'_, Arc<AgentManager>>,
+    attachment_id: String,
+) -> Result<(), String> {
+    manager.delete_attachment(&attachment_id)
+}
+
+#[tauri::command]
+pub fn task_list_artifacts(
+    manager: State<'_, Arc<AgentManager>>,
+    task_id: String,
+) -> Result<Vec<TaskArtifact>, String> {
+    manager.list_artifacts(&task_id)
+}
+
+#[tauri::command]
+pub fn task_preview_artifact(
+    manager: State<'_, Arc<AgentManager>>,
+    artifact_id: String,
+) -> Result<ArtifactPreview, String> {
+    preview_artifact(&manager, &artifact_id)
+}
+
+/// Accept an artifact, writing its files to the project; returns the
+/// artifacts that were applied
+#[tauri::command]
+pub fn task_apply_artifact(
+    manager: State<'_, Arc<AgentManager>>,
+    projects: State<'_, Arc<ProjectManager>>,
+    artifact_id: String,
+) -> Result<Vec<TaskArtifact>, String> {
+    apply_artifact(&manager, &projects, &artifact_id)
+}
+
+/// Reject an artifact, restoring its files to the task's start commit;
+/// returns the artifacts that were discarded
+#[tauri::command]
+pub fn task_discard_artifact(
+    manager: State<'_, Arc<AgentManager>>,
+    projects: State<'_, Arc<ProjectManager>>,
+    artifact_id: String,
+) -> Result<Vec<TaskArtifact>, String> {
+    discard_artifact(&manager, &projects, &artifact_id)
+}
+
+/// Write the task with its logs, thread, comments and attachments to a new
+/// folder in `dest_dir`; returns the folder
+#[tauri::command]
+pub fn task_export(
+    manager: State<'_, Arc<AgentManager>>,
+    task_id: String,
+    dest_dir: String,
+) -> Result<String, String> {
+    export_task(&manager, &task_id, std::path::Path::new(&dest_dir))
+        .map(|dir| dir.to_string_lossy().to_string())
+}
+
+#[tauri::command]
+pub fn task_get_messages(
+    manager: State<'_, Arc<AgentManager>>,
+    task_id: String,
+) -> Result<Vec<TaskMessage>, String> {
+    manager.list_task_messages(&task_id)
+}
+
+/// Continue a finished task's conversation with the same agent
+#[tauri::command]
+pub fn task_reply(
+    manager: State<'_, Arc<AgentManager>>,
+    task_id: String,
+    content: String,
+) -> Result<TaskMessage, String> {
+    manager.reply_to_task(&task_id, &content)
+}
+
+#[tauri::command]
+pub fn task_list_children(
+    manager: State<'_, Arc<AgentManager>>,
+    task_id: String,
+) -> Result<Vec<Task>, String> {
+    manager.list_child_tasks(&task_id)
+}
+
+#[tauri::command]
+pub fn task_list_approvals(
+    manager: State<'_, Arc<AgentManager>>,
+    task_id: Option<String>,
+    status: Option<String>,
+) -> Result<Vec<ToolApproval>, String> {
+    manager.list_tool_approvals(task_id.as_deref(), status.as_deref())
+}
+
+/// Approve or deny a tool call a paused task is waiting on
+#[tauri::command]
+pub fn task_decide_approval(
+    manager: State<'_, Arc<AgentManager>>,
+    approval_id: String,
+    approve: bool,
+) -> Result<ToolApproval, String> {
+    manager.decide_tool_approval(&approval_id, approve, "user")
+}
+
+#[tauri::command]
+pub fn task_get_tool_audit(
+    manager: State<'_, Arc<AgentManager>>,
+    task_id: String,
+) -> Result<Vec<ToolAuditEntry>, String> {
+    manager.list_tool_audit(&task_id)
+}
+
+/// Show the prompt a task would be sent with, including what was trimmed
+#[tauri::command]
+pub fn task_preview_prompt(
+    manager: State<'_, Arc<AgentManager>>,
+    projects: State<'_, Arc<ProjectManager>>,
+    task_id: String,
+    agent_id: Option<String>,
+) -> Result<AssembledPrompt, String> {
+    let task = manager
+        .get_task(&task_id)?
+        .ok_or_else(|| format!("Task not found: {}", task_id))?;
+
+    let agent_id = agent_id
+        .or_else(|| task.agent_id.clone())
+        .ok_or("Task has no agent assigned")?;
+    let agent = manager
+        .get_agent(&agent_id)?
+        .ok_or_else(|| format!("Agent not found: {}", agent_id))?;
+
+    let model = resolve_model(&agent.config.model);
+    assemble_task_prompt(&manager, &projects, &task, &agent, &model.model_id)
+}
+
+/// Deadline adherence per agent and project for tasks created since `since`
+#[tauri::command]
+pub fn task_deadline_report(
+    manager: State<'_, Arc<AgentManager>>,
+    projects: State<'_, Arc<ProjectManager>>,
+    since: Option<i64>,
+) -> Result<SlaReport, String> {
+    deadline_report(&manager, &projects, since)
+}
+
+#[tauri::command]
+pub fn agent_list_memories(
+    manager: State<'_, Arc<AgentManager>>,
+    agent_id: String,
+    limit: Option<i32>,
+) -> Result<Vec<AgentMemory>, String> {
+    manager.list_memories(&agent_id, limit.unwrap_or(100))
+}
+
+#[tauri::command]
+pub fn agent_add_memory(
+    manager: State<'_, Arc<AgentManager>>,
+    agent_id: String,
+    memory_type: String,
+    content: String,
+    importance: Option<f64>,
+) -> Result<AgentMemory, String> {
+    manager.add_memory(&agent_id, &memory_type, &content, importance.unwrap_or(0.5))
+}
@@ -0,0 +1,361 @@
+//! Task comments and attachments
+//!
+//! Comments are markdown notes from people or agents. Agents leave one by
+//! putting a `<comment>` block in their output:
+//!
+//! ```text
+//! <comment>The spec doesn't say which locale to use; assuming en-US.</comment>
+//! ```
+//!
+//! Attachments are files copied into the app data dir under `attachments/`,
+//! named by their SHA-256 so identical files are stored once. Both are added
+//! to the prompt when the task runs and written out by `export_task`.
+
+use super::manager::{AgentManager, Task, TaskLog, TaskMessage};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+
+/// Largest file that can be attached
+pub const MAX_ATTACHMENT_BYTES: usize = 25 * 1024 * 1024;
+/// Text attachments beyond this are listed but not put in the prompt
+const MAX_PROMPT_ATTACHMENT_BYTES: u64 = 100 * 1024;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskComment {
+    pub id: String,
+    #[serde(rename = "taskId")]
+    pub task_id: String,
+    /// `human` or `agent`
+    pub author: String,
+    #[serde(rename = "agentId")]
+    pub agent_id: Option<String>,
+    /// Markdown
+    pub body: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: i64,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskAttachment {
+    pub id: String,
+    #[serde(rename = "taskId")]
+    pub task_id: String,
+    #[serde(rename = "fileName")]
+    pub file_name: String,
+    #[serde(rename = "mimeType")]
+    pub mime_type: String,
+    pub size: i64,
+    pub sha256: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: i64,
+}
+
+impl TaskAttachment {
+    /// Where the file is kept, relative to the app data dir
+    pub fn stored_path(&self) -> PathBuf {
+        stored_path(&self.sha256)
+    }
+
+    fn is_text(&self) -> bool {
+        self.mime_type.starts_with("text/")
+            || matches!(
+                self.mime_type.as_str(),
+                "application/json"
+                    | "application/xml"
+                    | "application/yaml"
+                    | "application/toml"
+                    | "application/javascript"
+            )
+    }
+}
+
+pub fn stored_path(sha256: &str) -> PathBuf {
+    Path::new("attachments").join(&sha256[..2]).join(sha256)
+}
+
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+/// MIME type from the file's leading bytes, then its extension. Unknown
+/// files are `text/plain` if they are UTF-8, else `application/octet-stream`.
+pub fn detect_mime_type(file_name: &str, bytes: &[u8]) -> String {
+    const SIGNATURES: &[(&[u8], &str)] = &[
+        (b"\x89PNG\r\n\x1a\n", "image/png"),
+        (b"\xff\xd8\xff", "image/jpeg"),
+        (b"GIF87a", "image/gif"),
+        (b"GIF89a", "image/gif"),
+        (b"%PDF-", "application/pdf"),
+        (b"PK\x03\x04", "application/zip"),
+    ];
+    if let Some((_, mime)) = SIGNATURES.iter().find(|(sig, _)| bytes.starts_with(sig)) {
+        return mime.to_string();
+    }
+    if bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        return "image/webp".to_string();
+    }
+
+    let extension = Path::new(file_name)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_lowercase)
+        .unwrap_or_default();
+    let by_extension = match extension.as_str() {
+        "md" | "markdown" => Some("text/markdown"),
+        "txt" | "log" => Some("text/plain"),
+        "csv" => Some("text/csv"),
+        "html" | "htm" => Some("text/html"),
+        "css" => Some("text/css"),
+        "json" => Some("application/json"),
+        "xml" => Some("application/xml"),
+        "yaml" | "yml" => Some("application/yaml"),
+        "toml" => Some("application/toml"),
+        "js" | "mjs" => Some("application/javascript"),
+        "ts" | "tsx" | "rs" | "py" | "go" | "sh" | "sql" => Some("text/plain"),
+        "svg" => Some("image/svg+xml"),
+        _ => None,
+    };
+    if let Some(mime) = by_extension {
+        return mime.to_string();
+    }
+
+    if std::str::from_utf8(bytes).is_ok() {
+        "text/plain".to_string()
+    } else {
+        "application/octet-stream".to_string()
+    }
+}
+
+/// Extract the bodies of `<comment>` blocks from model output
+pub fn parse_comments(output: &str) -> Vec<String> {
+    let mut comments = Vec::new();
+    let mut rest = output;
+
+    while let Some(start) = rest.find("<comment>") {
+        rest = &rest[start + "<comment>".len()..];
+        let Some(end) = rest.find("</comment>") else {
+            break;
+        };
+        let body = rest[..end].trim();
+        if !body.is_empty() {
+            comments.push(body.to_string());
+        }
+        rest = &rest[end + "</comment>".len()..];
+    }
+
+    comments
+}
+
+/// Prompt section with the task's discussion so far
+pub fn render_comments(comments: &[TaskComment]) -> String {
+    let mut out = String::from(
+        "## Comments\n\
+         Leave a comment for the people on this task with <comment>...</comment>.\n",
+    );
+    for comment in comments {
+        let who = match &comment.agent_id {
+            Some(agent_id) => format!("agent {}", agent_id),
+            None => comment.author.clone(),
+        };
+        let when = chrono::DateTime::from_timestamp(comment.created_at, 0)
+            .map(|t| t.format("%Y-%m-%d %H:%M UTC").to_string())
+            .unwrap_or_default();
+        out.push_str(&format!(
+            "\n### {} ({})\n{}\n",
+            who,
+            when,
+            comment.body.trim()
+        ));
+    }
+    out
+}
+
+/// Prompt section listing attachments, with the contents of small text
+/// files inlined
+pub fn render_attachments(app_dir: &Path, attachments: &[TaskAttachment]) -> String {
+    let mut out = String::from("## Attachments\n");
+    for attachment in attachments {
+        out.push_str(&format!(
+            "\n### {} ({}, {} bytes, sha256 {})\n",
+            attachment.file_name, attachment.mime_type, attachment.size, attachment.sha256
+        ));
+        if !attachment.is_text() || attachment.size as u64 > MAX_PROMPT_ATTACHMENT_BYTES {
+            continue;
+        }
+        if let Ok(content) = std::fs::read_to_string(app_dir.join(attachment.stored_path())) {
+            out.push_str(&format!("```\n{}\n```\n", content.trim_end()));
+        }
+    }
+    out
+}
+
+/// Everything about a task, as written to `task.json` by `export_task`
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskExport {
+    #[serde(rename = "exportedAt")]
+    pub exported_at: i64,
+    pub task: Task,
+    pub logs: Vec<TaskLog>,
+    pub messages: Vec<TaskMessage>,
+    pub comments: Vec<TaskComment>,
+    pub attachments: Vec<ExportedAttachment>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportedAttachment {
+    #[serde(flatten)]
+    pub attachment: TaskAttachment,
+    /// File path relative to `task.json`
+    pub path: String,
+}
+
+/// Write a task with its logs, thread, comments and attachments to a new
+/// folder in `dest`. Returns the folder.
+pub fn export_task(manager: &AgentManager, task_id: &str, dest: &Path) -> Result<PathBuf, String> {
+    let task = manager
+        .get_task(task_id)?
+        .ok_or_else(|| format!("Task not found: {}", task_id))?;
+
+    let slug: String = task
+        .title
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect::<String>()
+        .split('-')
+        .filter(|s| !s.is_empty())
+        .take(8)
+        .collect::<Vec<_>>()
+        .join("-");
+    let short_id = task.id.split('-').next().unwrap_or(&task.id);
+    let dir = dest.join(format!("{}-{}", slug, short_id));
+    let files = dir.join("attachments");
+    std::fs::create_dir_all(&files).map_err(|e| format!("{}: {}", files.display(), e))?;
+
+    let mut attachments = Vec::new();
+    for attachment in manager.list_attachments(task_id)? {
+        // Prefix duplicate names so no file overwrites another
+        let mut name = sanitize_file_name(&attachment.file_name);
+        if attachments
+            .iter()
+            .any(|a: &ExportedAttachment| a.path == format!("attachments/{}", name))
+        {
+            name = format!("{}-{}", attachments.len() + 1, name);
+        }
+
+        std::fs::copy(manager.attachment_file(&attachment), files.join(&name))
+            .map_err(|e| format!("{}: {}", attachment.file_name, e))?;
+        attachments.push(ExportedAttachment {
+            attachment,
+            path: format!("attachments/{}", name),
+        });
+    }
+
+    let export = TaskExport {
+        exported_at: chrono::Utc::now().timestamp(),
+        logs: manager.list_task_logs(task_id)?,
+        messages: manager.list_task_messages(task_id)?,
+        comments: manager.list_comments(task_id)?,
+        attachments,
+        task,
+    };
+    let json = serde_json::to_string_pretty(&export).map_err(|e| e.to_string())?;
+    std::fs::write(dir.join("task.json"), json).map_err(|e| e.to_string())?;
+
+    Ok(dir)
+}
+
+/// Keep the last path component and drop characters that aren't safe in
+/// file names
+pub fn sanitize_file_name(name: &str) -> String {
+    let base = name.rsplit(['/', '\\']).next().unwrap_or(name);
+    let clean: String = base
+        .chars()
+        .map(|c| {
+            if c.is_control() || ":*?\"<>|".contains(c) {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect();
+    match clean.trim().trim_start_matches('.') {
+        "" => "attachment".to_string(),
+        name => name.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_detect_mime_type() {
+        assert_eq!(
+            detect_mime_type("x.bin", b"\x89PNG\r\n\x1a\n...."),
+            "image/png"
+        );
+        assert_eq!(detect_mime_type("spec.md", b"# Spec"), "text/markdown");
+        assert_eq!(detect_mime_type("notes", b"plain words"), "text/plain");
+        assert_eq!(
+            detect_mime_type("blob", &[0xff, 0xfe, 0x00, 0x81]),
+            "application/octet-stream"
+        );
+        assert_eq!(sanitize_file_name("../../etc/passwd"), "passwd");
+        assert_eq!(
+            parse_comments(
+                "ok <comment> one </comment> and <comment></comment><comment>two</comment>"
+            ),
+            ["one", "two"]
+        );
+    }
+
+    #[test]
+    fn test_attachments_are_stored_by_hash_and_exported() {
+        let dir = tempdir().unwrap();
+        let manager = AgentManager::new(Database::new(dir.path().to_path_buf()).unwrap());
+        let task = manager.create_task(&Task::new("Write the spec")).unwrap();
+
+        let spec = manager
+            .add_attachment(&task.id, "spec.md", b"# Spec\nUse en-US.")
+            .unwrap();
+        let copy = manager
+            .add_attachment(&task.id, "spec.md", b"# Spec\nUse en-US.")
+            .unwrap();
+        assert_eq!(spec.mime_type, "text/markdown");
+        assert_eq!(spec.sha256, copy.sha256);
+        assert!(dir.path().join(spec.stored_path()).exists());
+
+        manager
+            .add_comment(&task.id, None, "Which locale?")
+            .unwrap();
+        let prompt = render_attachments(dir.path(), &manager.list_attachments(&task.id).unwrap());
+        assert!(prompt.contains("Use en-US."));
+
+        let out = tempdir().unwrap();
+        let folder = export_task(&manager, &task.id, out.path()).unwrap();
+        assert!(folder.join("attachments/spec.md").exists());
+        assert!(folder.join("attachments/2-spec.md").exists());
+        let json: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(folder.join("task.json")).unwrap())
+                .unwrap();
+        assert_eq!(json["comments"][0]["body"], "Which locale?");
+        assert_eq!(json["attachments"][1]["path"], "attachments/2-spec.md");
+
+        // The file stays while another attachment still uses it
+        manager.delete_attachment(&spec.id).unwrap();
+        assert!(dir.path().join(spec.stored_path()).exists());
+        manager.delete_attachment(&copy.id).unwrap();
+        assert!(!dir.path().join(spec.stored_path()).exists());
+    }
+}
@@ -0,0 +1,343 @@
+//! Deadline monitoring
+//!
+//! The runtime checks unfinished tasks with a deadline on every scheduler
+//! tick. A task is at risk once less than a fifth of its time (and at
+//! least `MIN_WARNING_SECS`) is left: it gets a warning and its priority
+//! goes up one step. A task still unfinished at its deadline is marked
+//! overdue and raised to `urgent`. Each happens once per task.
+//!
+//! `sla_report` sums up how often deadlines were met, per agent and per
+//! project.
+
+use super::manager::{Agent, AgentManager, Task, FINISHED_STATUSES, TASK_PRIORITIES};
+use crate::projects::{Project, ProjectManager};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Tasks are at risk this close to their deadline at the latest
+const MIN_WARNING_SECS: i64 = 15 * 60;
+/// Share of a task's time left when it becomes at risk
+const WARNING_SHARE: f64 = 0.2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DeadlineAlert {
+    AtRisk,
+    Overdue,
+}
+
+impl DeadlineAlert {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeadlineAlert::AtRisk => "atRisk",
+            DeadlineAlert::Overdue => "overdue",
+        }
+    }
+}
+
+/// An alert raised for one task
+#[derive(Debug, Clone, Serialize)]
+pub struct DeadlineNotice {
+    #[serde(rename = "taskId")]
+    pub task_id: String,
+    pub title: String,
+    pub alert: DeadlineAlert,
+    pub deadline: i64,
+    /// Priority after escalation
+    pub priority: String,
+}
+
+/// Raise the alerts that are due at `now`
+pub fn check_deadlines(manager: &AgentManager, now: i64) -> Result<Vec<DeadlineNotice>, String> {
+    let mut notices = Vec::new();
+
+    for task in manager.list_tasks(None)? {
+        let Some(deadline) = task.deadline else {
+            continue;
+        };
+        if FINISHED_STATUSES.contains(&task.status.as_str()) || task.overdue_at.is_some() {
+            continue;
+        }
+
+        let alert = if now >= deadline {
+            DeadlineAlert::Overdue
+        } else if task.deadline_warned_at.is_none() && now >= warning_time(&task, deadline) {
+            DeadlineAlert::AtRisk
+        } else {
+            continue;
+        };
+
+        let priority = match alert {
+            DeadlineAlert::AtRisk => bump(&task.priority),
+            DeadlineAlert::Overdue => "urgent",
+        };
+        if !manager.escalate_deadline(&task.id, alert, priority, now)? {
+            continue;
+        }
+
+        let message = match alert {
+            DeadlineAlert::AtRisk => format!(
+                "Deadline in {}; priority raised to {}",
+                format_duration(deadline - now),
+                priority
+            ),
+            DeadlineAlert::Overdue => format!(
+                "Missed its deadline by {}; marked overdue",
+                format_duration(now - deadline)
+            ),
+        };
+        manager.add_task_log(
+            &task.id,
+            "warn",
+            &message,
+            Some(serde_json::json!({
+                "alert": alert.as_str(),
+                "deadline": deadline,
+                "priority": priority,
+            })),
+        )?;
+
+        notices.push(DeadlineNotice {
+            task_id: task.id,
+            title: task.title,
+            alert,
+            deadline,
+            priority: priority.to_string(),
+        });
+    }
+
+    Ok(notices)
+}
+
+/// When `task` becomes at risk
+fn warning_time(task: &Task, deadline: i64) -> i64 {
+    let span = (deadline - task.created_at).max(0);
+    let window = ((span as f64 * WARNING_SHARE) as i64).max(MIN_WARNING_SECS);
+    deadline - window
+}
+
+/// The next priority up, stopping at `urgent`
+fn bump(priority: &str) -> &'static str {
+    let index = TASK_PRIORITIES
+        .iter()
+        .position(|p| *p == priority)
+        .unwrap_or(1);
+    TASK_PRIORITIES[(index + 1).min(TASK_PRIORITIES.len() - 1)]
+}
+
+fn format_duration(secs: i64) -> String {
+    match secs {
+        s if s < 60 => format!("{}s", s),
+        s if s < 3600 => format!("{}m", s / 60),
+        s if s < 86400 => format!("{}h {}m", s / 3600, s % 3600 / 60),
+        s => format!("{}d {}h", s / 86400, s % 86400 / 3600),
+    }
+}
+
+/// Deadline adherence for one agent or project
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct SlaRow {
+    /// Agent or project id; `None` for tasks without one
+    pub id: Option<String>,
+    pub name: String,
+    /// Tasks whose deadline has passed or that have finished
+    pub due: usize,
+    /// Completed by their deadline
+    pub met: usize,
+    /// Completed late, failed, or still open past the deadline
+    pub missed: usize,
+    /// Unfinished with the deadline still ahead
+    pub open: usize,
+    /// `met / due`; `None` until something is due
+    pub adherence: Option<f64>,
+    /// Mean time past the deadline for missed tasks
+    #[serde(rename = "avgLatenessSecs")]
+    pub avg_lateness_secs: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SlaReport {
+    pub since: Option<i64>,
+    #[serde(rename = "generatedAt")]
+    pub generated_at: i64,
+    #[serde(rename = "byAgent")]
+    pub by_agent: Vec<SlaRow>,
+    #[serde(rename = "byProject")]
+    pub by_project: Vec<SlaRow>,
+}
+
+/// Build the SLA report from the current tasks, agents and projects
+pub fn deadline_report(
+    manager: &AgentManager,
+    projects: &ProjectManager,
+    since: Option<i64>,
+) -> Result<SlaReport, String> {
+    Ok(sla_report(
+        &manager.list_tasks(None)?,
+        &manager.list_agents()?,
+        &projects.list()?,
+        since,
+        chrono::Utc::now().timestamp(),
+    ))
+}
+
+/// Summarise deadline adherence for tasks with a deadline created since
+/// `since`. Cancelled tasks are left out.
+pub fn sla_report(
+    tasks: &[Task],
+    agents: &[Agent],
+    projects: &[Project],
+    since: Option<i64>,
+    now: i64,
+) -> SlaReport {
+    let tracked: Vec<&Task> = tasks
+        .iter()
+        .filter(|t| t.deadline.is_some() && t.status != "cancelled")
+        .filter(|t| since.map_or(true, |since| t.created_at >= since))
+        .collect();
+
+    let agent_names: HashMap<&str, &str> = agents
+        .iter()
+        .map(|a| (a.id.as_str(), a.name.as_str()))
+        .collect();
+    let project_names: HashMap<&str, &str> = projects
+        .iter()
+        .map(|p| (p.id.as_str(), p.name.as_str()))
+        .collect();
+
+    SlaReport {
+        since,
+        generated_at: now,
+        by_agent: group(
+            &tracked,
+            now,
+            |t| t.agent_id.as_deref(),
+            &agent_names,
+            "Unassigned",
+        ),
+        by_project: group(
+            &tracked,
+            now,
+            |t| t.project_id.as_deref(),
+            &project_names,
+            "No project",
+        ),
+    }
+}
+
+fn group<'a>(
+    tasks: &[&'a Task],
+    now: i64,
+    key: impl Fn(&'a Task) -> Option<&'a str>,
+    names: &HashMap<&str, &str>,
+    none_name: &str,
+) -> Vec<SlaRow> {
+    let mut rows: Vec<(SlaRow, i64)> = Vec::new();
+
+    for task in tasks {
+        let id = key(task);
+        let index = match rows.iter().position(|(row, _)| row.id.as_deref() == id) {
+            Some(index) => index,
+            None => {
+                let name = id.map_or(none_name, |id| names.get(id).copied().unwrap_or(id));
+                rows.push((
+                    SlaRow {
+                        id: id.map(str::to_string),
+                        name: name.to_string(),
+                        ..Default::default()
+                    },
+                    0,
+                ));
+                rows.len() - 1
+            }
+        };
+        let (row, lateness) = &mut rows[index];
+
+        let deadline = task.deadline.unwrap_or_default();
+        let finished = FINISHED_STATUSES.contains(&task.status.as_str());
+        if !finished && now < deadline {
+            row.open += 1;
+            continue;
+        }
+
+        row.due += 1;
+        let done_at = task.completed_at.unwrap_or(now);
+        if task.status == "completed" && done_at <= deadline {
+            row.met += 1;
+        } else {
+            row.missed += 1;
+            *lateness += (done_at - deadline).max(0);
+        }
+    }
+
+    let mut rows: Vec<SlaRow> = rows
+        .into_iter()
+        .map(|(mut row, lateness)| {
+            row.adherence = (row.due > 0).then(|| row.met as f64 / row.due as f64);
+            row.avg_lateness_secs = (row.missed > 0).then(|| lateness / row.missed as i64);
+            row
+        })
+        .collect();
+    rows.sort_by_key(|row| row.name.to_lowercase());
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_escalation_and_report() {
+        let dir = tempdir().unwrap();
+        let manager = AgentManager::new(Database::new(dir.path().to_path_buf()).unwrap());
+        let agent = manager
+            .create_agent(Default::default(), "Writer", "content")
+            .unwrap();
+
+        let task = manager
+            .create_task(&Task {
+                agent_id: Some(agent.id.clone()),
+                deadline: Some(chrono::Utc::now().timestamp() + 3600),
+                ..Task::new("Draft the post")
+            })
+            .unwrap();
+        let deadline = task.deadline.unwrap();
+
+        // 20% of an hour is 12 minutes, so the 15 minute floor applies
+        assert!(check_deadlines(&manager, deadline - 16 * 60)
+            .unwrap()
+            .is_empty());
+        let notices = check_deadlines(&manager, deadline - 10 * 60).unwrap();
+        assert_eq!(notices[0].alert, DeadlineAlert::AtRisk);
+        assert_eq!(notices[0].priority, "high");
+        assert!(check_deadlines(&manager, deadline - 5 * 60)
+            .unwrap()
+            .is_empty());
+
+        let notices = check_deadlines(&manager, deadline + 120).unwrap();
+        assert_eq!(notices[0].alert, DeadlineAlert::Overdue);
+        let task = manager.get_task(&task.id).unwrap().unwrap();
+        assert_eq!(task.priority, "urgent");
+        assert!(task.overdue_at.is_some());
+        assert!(check_deadlines(&manager, deadline + 600)
+            .unwrap()
+            .is_empty());
+
+        let on_time = Task {
+            id: "on-time".to_string(),
+            agent_id: Some(agent.id.clone()),
+            status: "completed".to_string(),
+            deadline: Some(deadline),
+            completed_at: Some(deadline - 60),
+            ..Task::new("Edit")
+        };
+        let report = sla_report(&[task, on_time], &[agent], &[], None, deadline + 600);
+        let writer = &report.by_agent[0];
+        assert_eq!((writer.due, writer.met, writer.missed), (2, 1, 1));
+        assert_eq!(writer.adherence, Some(0.5));
+        assert_eq!(writer.avg_lateness_secs, Some(600));
+        assert_eq!(report.by_project[0].name, "No project");
+    }
+}
@@ -0,0 +1,437 @@
+//! Delegation of subtasks between agents
+//!
+//! A running task can hand work to another agent by emitting `<delegate>`
+//! blocks in its output:
+//!
+//! ```text
+//! <delegate agent="Reviewer" title="Review the parser">Check src/parser.rs for ...</delegate>
+//! <delegate type="research" title="Find prior art">...</delegate>
+//! ```
+//!
+//! Each block becomes a child task linked to the parent. The runtime waits
+//! for the children to finish and feeds their results back to the parent
+//! agent in a follow-up turn.
+
+use super::manager::{Agent, AgentManager, Task, FINISHED_STATUSES};
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+/// Deepest allowed delegation chain; tasks at this depth can't delegate
+pub const MAX_DELEGATION_DEPTH: i32 = 3;
+/// Follow-up turns a task may spend delegating before its output is final
+pub const MAX_DELEGATION_ROUNDS: usize = 3;
+const MAX_DELEGATIONS_PER_TURN: usize = 5;
+/// Child output beyond this is cut before being handed back to the parent
+const MAX_RESULT_CHARS: usize = 8_000;
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+const DELEGATION_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DelegationTarget {
+    /// A specific agent, by id or name
+    Agent(String),
+    /// Any idle agent of this type
+    Type(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DelegationRequest {
+    pub target: DelegationTarget,
+    pub title: String,
+    pub description: String,
+}
+
+/// Children created for one delegation turn, plus requests that were refused
+pub struct Delegation {
+    pub children: Vec<Task>,
+    pub rejected: Vec<(DelegationRequest, String)>,
+}
+
+/// Extract `<delegate>` blocks from model output. Blocks without a target
+/// or a body are ignored.
+pub fn parse_delegations(output: &str) -> Vec<DelegationRequest> {
+    let mut requests = Vec::new();
+    let mut rest = output;
+
+    while let Some(start) = rest.find("<delegate") {
+        rest = &rest[start + "<delegate".len()..];
+        let Some(tag_end) = rest.find('>') else { break };
+        let attrs = parse_attributes(&rest[..tag_end]);
+        rest = &rest[tag_end + 1..];
+
+        let Some(close) = rest.find("</delegate>") else {
+            break;
+        };
+        let description = rest[..close].trim().to_string();
+        rest = &rest[close + "</delegate>".len()..];
+
+        let target = match (attrs.get("agent"), attrs.get("type")) {
+            (Some(agent), _) => DelegationTarget::Agent(agent.clone()),
+            (None, Some(agent_type)) => DelegationTarget::Type(agent_type.clone()),
+            (None, None) => continue,
+        };
+        if description.is_empty() {
+            continue;
+        }
+
+        let title = attrs.get("title").cloned().unwrap_or_else(|| {
+            description
+                .lines()
+                .next()
+                .unwrap_or_default()
+                .chars()
+                .take(80)
+                .collect()
+        });
+
+        requests.push(DelegationRequest {
+            target,
+            title,
+            description,
+        });
+    }
+
+    requests
+}
+
+/// Parse `key="value"` pairs from the inside of an opening tag
+fn parse_attributes(tag: &str) -> std::collections::HashMap<String, String> {
+    let mut attrs = std::collections::HashMap::new();
+    let mut rest = tag;
+
+    while let Some(eq) = rest.find("=\"") {
+        let key = rest[..eq].trim().to_lowercase();
+        let value_start = &rest[eq + 2..];
+        let Some(end) = value_start.find('"') else {
+            break;
+        };
+        attrs.insert(key, value_start[..end].to_string());
+        rest = &value_start[end + 1..];
+    }
+
+    attrs
+}
+
+/// Prompt section describing how and to whom the agent can delegate
+pub fn delegation_instructions(agents: &[Agent], current: &Agent) -> Option<String> {
+    let others: Vec<&Agent> = agents.iter().filter(|a| a.id != current.id).collect();
+    if others.is_empty() {
+        return None;
+    }
+
+    let mut out = String::from(
+        "## Delegation\n\
+         You can hand subtasks to other agents. Emit one block per subtask and stop; \
+         their results will be sent back to you before you finish.\n\
+         <delegate agent=\"NAME\" title=\"SHORT TITLE\">What to do and what to return</delegate>\n\
+         Use type=\"TYPE\" instead of agent=\"NAME\" to let any agent of that type take it.\n\nAgents:",
+    );
+    for agent in others {
+        out.push_str(&format!("\n- {} ({})", agent.name, agent.agent_type));
+        let description = agent.description.lines().next().unwrap_or_default().trim();
+        if !description.is_empty() {
+            out.push_str(&format!(": {}", description));
+        }
+    }
+
+    Some(out)
+}
+
+/// Create child tasks for `requests` on behalf of `parent`, which `agent`
+/// is running
+pub fn spawn_children(
+    manager: &AgentManager,
+    parent: &Task,
+    agent: &Agent,
+    requests: Vec<DelegationRequest>,
+) -> Result<Delegation, String> {
+    let mut delegation = Delegation {
+        children: Vec::new(),
+        rejected: Vec::new(),
+    };
+
+    if parent.depth >= MAX_DELEGATION_DEPTH {
+        delegation.rejected = requests
+            .into_iter()
+            .map(|r| {
+                (
+                    r,
+                    format!("delegation depth limit ({}) reached", MAX_DELEGATION_DEPTH),
+                )
+            })
+            .collect();
+        return Ok(delegation);
+    }
+
+    let agents = manager.list_agents()?;
+    // Agents already busy up the chain would never pick the child up
+    let mut busy = ancestor_agents(manager, parent)?;
+    busy.insert(agent.id.clone());
+
+    for (index, request) in requests.into_iter().enumerate() {
+        if index >= MAX_DELEGATIONS_PER_TURN {
+            delegation.rejected.push((
+                request,
+                format!("at most {} delegations per turn", MAX_DELEGATIONS_PER_TURN),
+            ));
+            continue;
+        }
+
+        let resolved = match &request.target {
+            DelegationTarget::Agent(name) => {
+                match agents
+                    .iter()
+                    .find(|a| a.id == *name || a.name.eq_ignore_ascii_case(name))
+                {
+                    Some(a) if busy.contains(&a.id) => {
+                        Err(format!("{} is already working on this task chain", a.name))
+                    }
+                    Some(a) => Ok((Some(a.id.clone()), None)),
+                    None => Err(format!("no agent named {}", name)),
+                }
+            }
+            DelegationTarget::Type(agent_type) => {
+                let available = agents.iter().any(|a| {
+                    a.agent_type.eq_ignore_ascii_case(agent_type)
+                        && a.config.auto_assign
+                        && !busy.contains(&a.id)
+                });
+                if available {
+                    Ok((None, Some(agent_type.to_lowercase())))
+                } else {
+                    Err(format!("no available agent of type {}", agent_type))
+                }
+            }
+        };
+
+        let (agent_id, target_agent_type) = match resolved {
+            Ok(target) => target,
+            Err(reason) => {
+                delegation.rejected.push((request, reason));
+                continue;
+            }
+        };
+
+        let child = manager.create_task(&Task {
+            agent_id,
+            project_id: parent.project_id.clone(),
+            description: request.description.clone(),
+            priority: parent.priority.clone(),
+            deadline: parent.deadline,
+            parent_task_id: Some(parent.id.clone()),
+            depth: parent.depth + 1,
+            target_agent_type,
+            ..Task::new(request.title.clone())
+        })?;
+        delegation.children.push(child);
+    }
+
+    Ok(delegation)
+}
+
+/// Agents running this task or any task above it
+fn ancestor_agents(manager: &AgentManager, task: &Task) -> Result<HashSet<String>, String> {
+    let mut agents = HashSet::new();
+    let mut current = Some(task.clone());
+
+    while let Some(task) = current {
+        if let Some(agent_id) = &task.agent_id {
+            agents.insert(agent_id.clone());
+        }
+        current = match &task.parent_task_id {
+            Some(parent_id) => manager.get_task(parent_id)?,
+            None => None,
+        };
+    }
+
+    Ok(agents)
+}
+
+/// Wait until every child has finished. Fails if the parent is cancelled;
+/// children still running at the timeout are cancelled.
+pub async fn wait_for_children(
+    manager: &AgentManager,
+    parent_id: &str,
+    children: &[Task],
+) -> Result<Vec<Task>, String> {
+    let started = Instant::now();
+
+    loop {
+        let parent = manager.get_task(parent_id)?;
+        if parent.map_or(true, |p| p.status == "cancelled") {
+            return Err("Task was cancelled while waiting for delegated tasks".to_string());
+        }
+
+        let mut finished = Vec::with_capacity(children.len());
+        for child in children {
+            if let Some(task) = manager.get_task(&child.id)? {
+                if FINISHED_STATUSES.contains(&task.status.as_str()) {
+                    finished.push(task);
+                }
+            }
+        }
+
+        if finished.len() == children.len() {
+            return Ok(finished);
+        }
+
+        if started.elapsed() >= DELEGATION_TIMEOUT {
+            for child in children {
+                manager.cancel_task(&child.id)?;
+            }
+            let mut timed_out = Vec::with_capacity(children.len());
+            for child in children {
+                timed_out.extend(manager.get_task(&child.id)?);
+            }
+            return Ok(timed_out);
+        }
+
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+/// Follow-up message handing child results back to the parent agent
+pub fn render_results(children: &[Task], rejected: &[(DelegationRequest, String)]) -> String {
+    let mut out = String::from("Results of the tasks you delegated:");
+
+    for child in children {
+        out.push_str(&format!("\n\n## {} ({})\n", child.title, child.status));
+        let body = match &child.result {
+            Some(result) if result.success => result.output.clone().unwrap_or_default(),
+            Some(result) => format!("Failed: {}", result.error.clone().unwrap_or_default()),
+            None => "No result.".to_string(),
+        };
+        if body.chars().count() > MAX_RESULT_CHARS {
+            out.extend(body.chars().take(MAX_RESULT_CHARS));
+            out.push_str("\n… [truncated]");
+        } else {
+            out.push_str(body.trim());
+        }
+    }
+
+    for (request, reason) in rejected {
+        out.push_str(&format!(
+            "\n\n## {} (not delegated)\n{}",
+            request.title, reason
+        ));
+    }
+
+    out.push_str("\n\nUse these results to complete your original task.");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::manager::AgentConfig;
+    use crate::db::Database;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_parse_delegations() {
+        let output = r#"I'll split this up.
+<delegate agent="Reviewer" title="Review parser">Check src/parser.rs for panics.</delegate>
+<delegate type="research">Find prior art
+on incremental parsing.</delegate>
+<delegate title="No target">ignored</delegate>
+<delegate agent="Writer"></delegate>"#;
+
+        let requests = parse_delegations(output);
+        assert_eq!(requests.len(), 2);
+        assert_eq!(
+            requests[0].target,
+            DelegationTarget::Agent("Reviewer".to_string())
+        );
+        assert_eq!(requests[0].title, "Review parser");
+        assert_eq!(requests[0].description, "Check src/parser.rs for panics.");
+        assert_eq!(
+            requests[1].target,
+            DelegationTarget::Type("research".to_string())
+        );
+        assert_eq!(requests[1].title, "Find prior art");
+    }
+
+    #[test]
+    fn test_spawn_children_and_cascade_cancel() {
+        let dir = tempdir().unwrap();
+        let manager = AgentManager::new(Database::new(dir.path().to_path_buf()).unwrap());
+
+        let config = AgentConfig {
+            auto_assign: true,
+            ..Default::default()
+        };
+        let lead = manager
+            .create_agent(config.clone(), "Lead", "general")
+            .unwrap();
+        manager
+            .create_agent(config, "Reviewer", "code-review")
+            .unwrap();
+
+        let parent = manager
+            .create_task(&Task {
+                agent_id: Some(lead.id.clone()),
+                description: "Ship the feature".to_string(),
+                priority: "high".to_string(),
+                ..Task::new("Ship it")
+            })
+            .unwrap();
+
+        let requests = parse_delegations(
+            r#"<delegate agent="reviewer" title="Review">Review it</delegate>
+<delegate agent="Lead" title="Loop">Do it yourself</delegate>
+<delegate type="design" title="Mockups">Draw it</delegate>"#,
+        );
+        let delegation = spawn_children(&manager, &parent, &lead, requests).unwrap();
+
+        assert_eq!(delegation.children.len(), 1);
+        assert_eq!(delegation.rejected.len(), 2);
+        let child = &delegation.children[0];
+        assert_eq!(child.depth, 1);
+        assert_eq!(child.priority, "high");
+        assert_eq!(manager.list_child_tasks(&parent.id).unwrap().len(), 1);
+
+        let reviewer = manager
+            .get_agent(child.agent_id.as_deref().unwrap())
+            .unwrap()
+            .unwrap();
+        let grandchild = spawn_children(
+            &manager,
+            child,
+            &reviewer,
+            vec![DelegationRequest {
+                target: DelegationTarget::Type("general".to_string()),
+                title: "Nested".to_string(),
+                description: "Nested work".to_string(),
+            }],
+        )
+        .unwrap();
+        // Lead is busy with the parent, so nobody of type "general" is free
+        assert!(grandchild.children.is_empty());
+
+        manager.cancel_task(&parent.id).unwrap();
+        assert_eq!(
+            manager.get_task(&child.id).unwrap().unwrap().status,
+            "cancelled"
+        );
+    }
+
+    #[test]
+    fn test_depth_limit() {
+        let dir = tempdir().unwrap();
+        let manager = AgentManager::new(Database::new(dir.path().to_path_buf()).unwrap());
+
+        let task = Task {
+            id: "deep".to_string(),
+            status: "running".to_string(),
+            depth: MAX_DELEGATION_DEPTH,
+            ..Task::new("Deep")
+        };
+        let agent = manager
+            .create_agent(AgentConfig::default(), "Deep", "general")
+            .unwrap();
+        let requests = parse_delegations(r#"<delegate type="general">More</delegate>"#);
+        let delegation = spawn_children(&manager, &task, &agent, requests).unwrap();
+        assert!(delegation.children.is_empty());
+        assert!(delegation.rejected[0].1.contains("depth limit"));
+    }
+}
@@ -0,0 +1,72 @@
+//! Task events
+//!
+//! `AgentManager` broadcasts task changes to in-process subscribers such as
+//! the local HTTP API. Events from another process (the desktop app or
+//! `claudio-daemon`) are not seen; the database stays the source of truth.
+
+use super::comments::TaskComment;
+use super::manager::{Task, ToolApproval};
+use serde::Serialize;
+
+/// Events are dropped for subscribers that fall this far behind
+pub const CHANNEL_CAPACITY: usize = 1024;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum TaskEvent {
+    Created {
+        task: Box<Task>,
+    },
+    Status {
+        #[serde(rename = "taskId")]
+        task_id: String,
+        status: String,
+    },
+    Log {
+        #[serde(rename = "taskId")]
+        task_id: String,
+        level: String,
+        message: String,
+    },
+    /// Streamed model output
+    Output {
+        #[serde(rename = "taskId")]
+        task_id: String,
+        text: String,
+    },
+    /// A tool call needs approval, or was just approved or denied
+    Approval {
+        #[serde(rename = "taskId")]
+        task_id: String,
+        approval: Box<ToolApproval>,
+    },
+    /// Someone, or the task's agent, commented on the task
+    Comment {
+        #[serde(rename = "taskId")]
+        task_id: String,
+        comment: Box<TaskComment>,
+    },
+    /// A task is at risk of missing its deadline, or has missed it
+    Deadline {
+        #[serde(rename = "taskId")]
+        task_id: String,
+        /// `atRisk` or `overdue`
+        alert: String,
+        deadline: i64,
+        priority: String,
+    },
+}
+
+impl TaskEvent {
+    pub fn task_id(&self) -> &str {
+        match self {
+            TaskEvent::Created { task } => &task.id,
+            TaskEvent::Status { task_id, .. }
+            | TaskEvent::Log { task_id, .. }
+            | TaskEvent::Output { task_id, .. }
+            | TaskEvent::Approval { task_id, .. }
+            | TaskEvent::Comment { task_id, .. }
+            | TaskEvent::Deadline { task_id, .. } => task_id,
+        }
+    }
+}
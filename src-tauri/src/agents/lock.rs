@@ -0,0 +1,102 @@
+//! Cross-process lock on the agent runtime
+//!
+//! The desktop app and `claudio-daemon` share one database. Whichever starts
+//! first holds this lock and processes the task queue; the other leaves the
+//! queue alone and keeps retrying, so it takes over when the holder exits.
+
+use fs2::FileExt;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::time::Duration;
+
+const LOCK_FILE: &str = "runtime.lock";
+/// How often to retry while another process holds the lock
+const LOCK_RETRY: Duration = Duration::from_secs(10);
+
+/// Held for as long as this process runs the agent runtime
+pub struct RuntimeLock {
+    file: File,
+}
+
+impl RuntimeLock {
+    /// Take the lock in `app_dir`, or `None` if another process holds it
+    pub fn try_acquire(app_dir: &Path) -> Result<Option<Self>, String> {
+        std::fs::create_dir_all(app_dir).map_err(|e| e.to_string())?;
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .read(true)
+            .write(true)
+            .open(app_dir.join(LOCK_FILE))
+            .map_err(|e| format!("Failed to open runtime lock: {}", e))?;
+
+        match file.try_lock_exclusive() {
+            Ok(()) => {
+                // Record the holder for anyone inspecting the file
+                file.set_len(0).map_err(|e| e.to_string())?;
+                writeln!(file, "{}", std::process::id()).map_err(|e| e.to_string())?;
+                Ok(Some(Self { file }))
+            }
+            Err(e) if e.kind() == fs2::lock_contended_error().kind() => Ok(None),
+            Err(e) => Err(format!("Failed to lock runtime: {}", e)),
+        }
+    }
+
+    /// Take the lock in `app_dir`, waiting for the process holding it to exit
+    pub async fn acquire(app_dir: &Path) -> Result<Self, String> {
+        let mut logged = false;
+        loop {
+            if let Some(lock) = Self::try_acquire(app_dir)? {
+                return Ok(lock);
+            }
+            if !logged {
+                log::info!(
+                    "Another Claud.io process is running the agent runtime; waiting for it to exit"
+                );
+                logged = true;
+            }
+            tokio::time::sleep(LOCK_RETRY).await;
+        }
+    }
+}
+
+impl Drop for RuntimeLock {
+    fn drop(&mut self) {
+        let _ = FileExt::unlock(&self.file);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_lock_is_exclusive() {
+        let dir = tempdir().unwrap();
+
+        let lock = RuntimeLock::try_acquire(dir.path()).unwrap();
+        assert!(lock.is_some());
+        assert!(RuntimeLock::try_acquire(dir.path()).unwrap().is_none());
+
+        drop(lock);
+        assert!(RuntimeLock::try_acquire(dir.path()).unwrap().is_some());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_acquire_takes_over_when_released() {
+        let dir = tempdir().unwrap();
+        let held = RuntimeLock::try_acquire(dir.path()).unwrap().unwrap();
+
+        let path = dir.path().to_path_buf();
+        let waiting = tokio::spawn(async move { RuntimeLock::acquire(&path).await });
+        tokio::time::sleep(LOCK_RETRY * 2).await;
+        assert!(!waiting.is_finished());
+
+        drop(held);
+        let lock = tokio::time::timeout(LOCK_RETRY * 2, waiting).await;
+        assert!(lock.unwrap().unwrap().is_ok());
+    }
+}
@@ -1,9 +1,29 @@
 //! Agent manager implementation
 
+use super::artifacts::{
+    insert_artifact, map_artifact_row, ArtifactData, TaskArtifact, ARTIFACT_COLUMNS,
+};
+use super::board::{
+    build_board, can_transition, column_for, rank_between, rank_order, validate_columns, Board,
+    BoardColumn,
+};
+use super::bulk::{BulkAction, BulkResult, BulkSkip, TaskFilter};
+use super::comments::{
+    detect_mime_type, sanitize_file_name, sha256_hex, stored_path, TaskAttachment, TaskComment,
+    MAX_ATTACHMENT_BYTES,
+};
+use super::deadlines::DeadlineAlert;
+use super::events::{TaskEvent, CHANNEL_CAPACITY};
+use super::permissions::{AutonomyLevel, PermissionPolicy};
+use super::routing::RoutingDecision;
+use super::sandbox::SandboxConfig;
 use crate::db::Database;
+use crate::sync::agent_parser::AgentDefinition;
 use chrono::Utc;
 use rusqlite::params;
 use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tokio::sync::broadcast;
 use uuid::Uuid;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -36,6 +56,25 @@ pub struct AgentConfig {
     pub token_limit: Option<i32>,
     #[serde(rename = "dailyBudget")]
     pub daily_budget: Option<f64>,
+    /// Most tool rounds per task run
+    #[serde(rename = "maxIterations")]
+    pub max_iterations: Option<i32>,
+    #[serde(default)]
+    pub permissions: PermissionPolicy,
+    /// Containment for the agent's shell commands
+    #[serde(default)]
+    pub sandbox: SandboxConfig,
+    /// Overrides the autonomy level inherited from the definition
+    pub autonomy: Option<AutonomyLevel>,
+    /// Agent definition (from the agents repo) this agent was created from
+    #[serde(rename = "definitionId")]
+    pub definition_id: Option<String>,
+    /// Capability tags used to route tasks, e.g. `rust` or `copywriting`
+    #[serde(default)]
+    pub skills: Vec<String>,
+    /// Projects the agent is assigned to; empty means any
+    #[serde(rename = "allowedProjects", default)]
+    pub allowed_projects: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -74,6 +113,37 @@ pub struct Task {
     pub completed_at: Option<i64>,
     pub result: Option<TaskResult>,
     pub logs: Vec<TaskLog>,
+    /// Task that delegated this one, if any
+    #[serde(rename = "parentTaskId", default)]
+    pub parent_task_id: Option<String>,
+    /// Delegation depth; top-level tasks are 0
+    #[serde(default)]
+    pub depth: i32,
+    /// Agent type that should pick this task up when no agent is set
+    #[serde(rename = "targetAgentType", default)]
+    pub target_agent_type: Option<String>,
+    /// Skills an agent needs to pick this task up; any one will do
+    #[serde(default)]
+    pub skills: Vec<String>,
+    /// How the task was routed to its agent
+    #[serde(default)]
+    pub routing: Option<RoutingDecision>,
+    /// When the task was flagged as at risk of missing its deadline
+    #[serde(rename = "deadlineWarnedAt", default)]
+    pub deadline_warned_at: Option<i64>,
+    /// When the task was marked overdue
+    #[serde(rename = "overdueAt", default)]
+    pub overdue_at: Option<i64>,
+    /// Manual order within a board column, ascending
+    #[serde(rename = "boardRank", default)]
+    pub board_rank: Option<f64>,
+    /// Board column the task was last moved to by hand
+    #[serde(rename = "boardColumn", default)]
+    pub board_column: Option<String>,
+    /// Snapshot of the project's work tree when the task first ran;
+    /// artifacts are diffed against it
+    #[serde(rename = "startCommit", default)]
+    pub start_commit: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -93,13 +163,172 @@ pub struct TaskLog {
     pub metadata: Option<serde_json::Value>,
 }
 
+/// One turn in a task's conversation thread
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskMessage {
+    pub id: i64,
+    #[serde(rename = "taskId")]
+    pub task_id: String,
+    /// `user`, `assistant` or `tool`
+    pub role: String,
+    pub content: String,
+    pub metadata: Option<serde_json::Value>,
+    #[serde(rename = "createdAt")]
+    pub created_at: i64,
+}
+
+/// A tool call waiting for, or given, a person's go-ahead
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolApproval {
+    pub id: String,
+    #[serde(rename = "taskId")]
+    pub task_id: String,
+    #[serde(rename = "agentId")]
+    pub agent_id: String,
+    pub tool: String,
+    pub arguments: serde_json::Value,
+    /// The `ask` rule that required approval
+    pub rule: Option<String>,
+    /// `pending`, `approved` or `denied`
+    pub status: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: i64,
+    #[serde(rename = "decidedAt")]
+    pub decided_at: Option<i64>,
+}
+
+/// One permission decision about a tool call
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolAuditEntry {
+    pub id: i64,
+    #[serde(rename = "taskId")]
+    pub task_id: String,
+    #[serde(rename = "agentId")]
+    pub agent_id: String,
+    pub tool: String,
+    pub arguments: serde_json::Value,
+    /// `allow`, `deny` or `ask`
+    pub decision: String,
+    /// `policy`, `scope`, `autonomy`, `user` or `timeout`
+    pub source: String,
+    pub rule: Option<String>,
+    pub reason: Option<String>,
+    #[serde(rename = "approvalId")]
+    pub approval_id: Option<String>,
+    #[serde(rename = "createdAt")]
+    pub created_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentMemory {
+    pub id: String,
+    #[serde(rename = "agentId")]
+    pub agent_id: String,
+    #[serde(rename = "type")]
+    pub memory_type: String,
+    pub content: String,
+    pub metadata: Option<serde_json::Value>,
+    #[serde(rename = "createdAt")]
+    pub created_at: i64,
+    #[serde(rename = "accessCount")]
+    pub access_count: i64,
+    #[serde(rename = "lastAccessed")]
+    pub last_accessed: Option<i64>,
+    pub importance: f64,
+}
+
+/// Accepted values for `Task::status`. Only `pending` tasks are scheduled;
+/// `backlog` tasks wait until they are moved to the queue.
+pub const TASK_STATUSES: &[&str] = &[
+    "backlog",
+    "pending",
+    "assigned",
+    "running",
+    "waiting",
+    "completed",
+    "failed",
+    "cancelled",
+];
+
+/// Statuses of a task with a live run; the runtime owns its transitions
+pub const ACTIVE_STATUSES: &[&str] = &["assigned", "running", "waiting"];
+
+/// Statuses of a task that is done, whatever the outcome
+pub const FINISHED_STATUSES: &[&str] = &["completed", "failed", "cancelled"];
+
+/// Accepted values for `Task::priority`
+pub const TASK_PRIORITIES: &[&str] = &["low", "normal", "high", "urgent"];
+
+const APPROVAL_COLUMNS: &str =
+    "id, task_id, agent_id, tool, arguments, rule, status, created_at, decided_at";
+
+const MEMORY_COLUMNS: &str =
+    "id, agent_id, type, content, metadata, created_at, access_count, last_accessed, importance";
+
+/// Columns read by `map_task_row`, in order
+const TASK_COLUMNS: &str = "id, agent_id, project_id, title, description, status, priority, created_at, \
+     scheduled_for, deadline, started_at, completed_at, result, parent_task_id, depth, target_agent_type, \
+     skills, routing, deadline_warned_at, overdue_at, board_rank, board_column, \
+     start_commit";
+
+impl Task {
+    /// A pending task with no agent, project or schedule
+    pub fn new(title: impl Into<String>) -> Self {
+        Self {
+            id: String::new(),
+            agent_id: None,
+            project_id: None,
+            title: title.into(),
+            description: String::new(),
+            status: "pending".to_string(),
+            priority: "normal".to_string(),
+            created_at: 0,
+            scheduled_for: None,
+            deadline: None,
+            started_at: None,
+            completed_at: None,
+            result: None,
+            logs: vec![],
+            parent_task_id: None,
+            depth: 0,
+            target_agent_type: None,
+            skills: vec![],
+            routing: None,
+            deadline_warned_at: None,
+            overdue_at: None,
+            board_rank: None,
+            board_column: None,
+            start_commit: None,
+        }
+    }
+}
+
 pub struct AgentManager {
     db: Database,
+    events: broadcast::Sender<TaskEvent>,
 }
 
 impl AgentManager {
     pub fn new(db: Database) -> Self {
-        Self { db }
+        let (events, _) = broadcast::channel(CHANNEL_CAPACITY);
+        Self { db, events }
+    }
+
+    /// Receive task events published by this process
+    pub fn subscribe(&self) -> broadcast::Receiver<TaskEvent> {
+        self.events.subscribe()
+    }
+
+    pub fn publish(&self, event: TaskEvent) {
+        // No subscribers is fine
+        let _ = self.events.send(event);
+    }
+
+    fn publish_status(&self, task_id: &str, status: &str) {
+        self.publish(TaskEvent::Status {
+            task_id: task_id.to_string(),
+            status: status.to_string(),
+        });
     }
 
     /// List all agents
@@ -137,6 +366,11 @@ impl AgentManager {
         })
     }
 
+    /// Get an agent by ID
+    pub fn get_agent(&self, agent_id: &str) -> Result<Option<Agent>, String> {
+        Ok(self.list_agents()?.into_iter().find(|a| a.id == agent_id))
+    }
+
     /// Create a new agent
     pub fn create_agent(&self, config: AgentConfig, name: &str, agent_type: &str) -> Result<Agent, String> {
         let id = Uuid::new_v4().to_string();
@@ -201,6 +435,44 @@ impl AgentManager {
         })
     }
 
+    /// Assign an agent to the given projects; an empty list lets it work on any
+    pub fn set_agent_projects(
+        &self,
+        agent_id: &str,
+        project_ids: &[String],
+    ) -> Result<Agent, String> {
+        let mut agent = self
+            .get_agent(agent_id)?
+            .ok_or_else(|| format!("Agent not found: {}", agent_id))?;
+        agent.config.allowed_projects = project_ids.to_vec();
+
+        self.db.with_conn(|conn| {
+            conn.execute(
+                "UPDATE agents SET config = ?1 WHERE id = ?2",
+                params![
+                    serde_json::to_string(&agent.config).unwrap_or_default(),
+                    agent_id
+                ],
+            )
+            .map_err(|e| e.to_string())
+        })?;
+
+        Ok(agent)
+    }
+
+    /// Agents that may work on a project: those assigned to it and those
+    /// not restricted to any
+    pub fn list_project_agents(&self, project_id: &str) -> Result<Vec<Agent>, String> {
+        Ok(self
+            .list_agents()?
+            .into_iter()
+            .filter(|a| {
+                let projects = &a.config.allowed_projects;
+                projects.is_empty() || projects.iter().any(|p| p == project_id)
+            })
+            .collect())
+    }
+
     /// Delete an agent
     pub fn delete_agent(&self, agent_id: &str) -> Result<(), String> {
         self.db.with_conn(|conn| {
@@ -214,14 +486,18 @@ impl AgentManager {
     pub fn list_tasks(&self, agent_id: Option<&str>) -> Result<Vec<Task>, String> {
         self.db.with_conn(|conn| {
             let query = if agent_id.is_some() {
-                "SELECT id, agent_id, project_id, title, description, status, priority, created_at, scheduled_for, deadline, started_at, completed_at, result
-                 FROM tasks WHERE agent_id = ?1 ORDER BY created_at DESC"
+                format!(
+                    "SELECT {} FROM tasks WHERE agent_id = ?1 ORDER BY created_at DESC",
+                    TASK_COLUMNS
+                )
             } else {
-                "SELECT id, agent_id, project_id, title, description, status, priority, created_at, scheduled_for, deadline, started_at, completed_at, result
-                 FROM tasks ORDER BY created_at DESC"
+                format!(
+                    "SELECT {} FROM tasks ORDER BY created_at DESC",
+                    TASK_COLUMNS
+                )
             };
 
-            let mut stmt = conn.prepare(query).map_err(|e| e.to_string())?;
+            let mut stmt = conn.prepare(&query).map_err(|e| e.to_string())?;
 
             let tasks = if let Some(aid) = agent_id {
                 stmt.query_map(params![aid], Self::map_task_row)
@@ -236,6 +512,38 @@ impl AgentManager {
         })
     }
 
+    /// Get a task by ID
+    pub fn get_task(&self, task_id: &str) -> Result<Option<Task>, String> {
+        self.db.with_conn(|conn| {
+            let result = conn.query_row(
+                &format!("SELECT {} FROM tasks WHERE id = ?1", TASK_COLUMNS),
+                params![task_id],
+                Self::map_task_row,
+            );
+
+            match result {
+                Ok(task) => Ok(Some(task)),
+                Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+                Err(e) => Err(e.to_string()),
+            }
+        })
+    }
+
+    fn map_memory_row(row: &rusqlite::Row) -> rusqlite::Result<AgentMemory> {
+        let metadata_json: Option<String> = row.get(4)?;
+        Ok(AgentMemory {
+            id: row.get(0)?,
+            agent_id: row.get(1)?,
+            memory_type: row.get(2)?,
+            content: row.get(3)?,
+            metadata: metadata_json.and_then(|j| serde_json::from_str(&j).ok()),
+            created_at: row.get(5)?,
+            access_count: row.get::<_, Option<i64>>(6)?.unwrap_or(0),
+            last_accessed: row.get(7)?,
+            importance: row.get::<_, Option<f64>>(8)?.unwrap_or(0.5),
+        })
+    }
+
     fn map_task_row(row: &rusqlite::Row) -> rusqlite::Result<Task> {
         let result_json: Option<String> = row.get(12)?;
         let result = result_json.and_then(|j| serde_json::from_str(&j).ok());
@@ -255,50 +563,146 @@ impl AgentManager {
             completed_at: row.get(11)?,
             result,
             logs: vec![], // TODO: Load logs separately
+            parent_task_id: row.get(13)?,
+            depth: row.get(14)?,
+            target_agent_type: row.get(15)?,
+            skills: row
+                .get::<_, Option<String>>(16)?
+                .and_then(|j| serde_json::from_str(&j).ok())
+                .unwrap_or_default(),
+            routing: row
+                .get::<_, Option<String>>(17)?
+                .and_then(|j| serde_json::from_str(&j).ok()),
+            deadline_warned_at: row.get(18)?,
+            overdue_at: row.get(19)?,
+            board_rank: row.get(20)?,
+            board_column: row.get(21)?,
+            start_commit: row.get(22)?,
+        })
+    }
+
+    /// List tasks delegated by a task, oldest first
+    pub fn list_child_tasks(&self, parent_task_id: &str) -> Result<Vec<Task>, String> {
+        self.db.with_conn(|conn| {
+            let mut stmt = conn
+                .prepare(&format!(
+                    "SELECT {} FROM tasks WHERE parent_task_id = ?1 ORDER BY created_at ASC",
+                    TASK_COLUMNS
+                ))
+                .map_err(|e| e.to_string())?;
+
+            let tasks = stmt
+                .query_map(params![parent_task_id], Self::map_task_row)
+                .map_err(|e| e.to_string())?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| e.to_string())?;
+
+            Ok(tasks)
         })
     }
 
     /// Create a new task
     pub fn create_task(&self, task: &Task) -> Result<Task, String> {
+        let mut new_task = Self::new_task(task);
+        self.db
+            .with_conn(|conn| Self::insert_task(conn, &mut new_task))?;
+
+        self.publish(TaskEvent::Created {
+            task: Box::new(new_task.clone()),
+        });
+        Ok(new_task)
+    }
+
+    /// Create several tasks in one transaction; if one fails, none are kept
+    pub fn create_tasks(&self, tasks: &[Task]) -> Result<Vec<Task>, String> {
+        let mut new_tasks: Vec<Task> = tasks.iter().map(Self::new_task).collect();
+
+        self.db.with_conn(|conn| {
+            let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+            for task in &mut new_tasks {
+                Self::insert_task(&tx, task)?;
+            }
+            tx.commit().map_err(|e| e.to_string())
+        })?;
+
+        for task in &new_tasks {
+            self.publish(TaskEvent::Created {
+                task: Box::new(task.clone()),
+            });
+        }
+        Ok(new_tasks)
+    }
+
+    /// `task` as a freshly queued task, with an id if it has none. Tasks
+    /// can start in the backlog instead of the queue.
+    fn new_task(task: &Task) -> Task {
         let id = if task.id.is_empty() {
             Uuid::new_v4().to_string()
         } else {
             task.id.clone()
         };
-        let now = Utc::now().timestamp();
 
-        let new_task = Task {
-            id: id.clone(),
-            created_at: now,
-            status: "pending".to_string(),
+        Task {
+            id,
+            created_at: Utc::now().timestamp(),
+            status: if task.status == "backlog" {
+                "backlog"
+            } else {
+                "pending"
+            }
+            .to_string(),
+            routing: None,
+            board_rank: None,
+            board_column: None,
+            start_commit: None,
             ..task.clone()
-        };
+        }
+    }
 
-        self.db.with_conn(|conn| {
-            conn.execute(
-                "INSERT INTO tasks (id, agent_id, project_id, title, description, status, priority, created_at, scheduled_for, deadline)
-                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
-                params![
-                    new_task.id,
-                    new_task.agent_id,
-                    new_task.project_id,
-                    new_task.title,
-                    new_task.description,
-                    new_task.status,
-                    new_task.priority,
-                    new_task.created_at,
-                    new_task.scheduled_for,
-                    new_task.deadline,
-                ],
-            )
+    /// Insert `task` at the bottom of the board
+    fn insert_task(conn: &rusqlite::Connection, task: &mut Task) -> Result<(), String> {
+        let last_rank: Option<f64> = conn
+            .query_row("SELECT MAX(board_rank) FROM tasks", [], |row| row.get(0))
             .map_err(|e| e.to_string())?;
+        task.board_rank = Some(last_rank.unwrap_or(0.0) + 1.0);
 
-            Ok(new_task)
-        })
+        conn.execute(
+            "INSERT INTO tasks (id, agent_id, project_id, title, description, status, priority, created_at, scheduled_for, deadline,
+                                parent_task_id, depth, target_agent_type, skills, board_rank)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
+            params![
+                task.id,
+                task.agent_id,
+                task.project_id,
+                task.title,
+                task.description,
+                task.status,
+                task.priority,
+                task.created_at,
+                task.scheduled_for,
+                task.deadline,
+                task.parent_task_id,
+                task.depth,
+                task.target_agent_type,
+                serde_json::to_string(&task.skills).unwrap_or_default(),
+                task.board_rank,
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+
+        Ok(())
     }
 
     /// Update task status
     pub fn update_task_status(&self, task_id: &str, status: &str) -> Result<(), String> {
+        if !TASK_STATUSES.contains(&status) {
+            return Err(format!(
+                "Unknown status '{}'; expected one of {}",
+                status,
+                TASK_STATUSES.join(", ")
+            ));
+        }
+
         self.db.with_conn(|conn| {
             let now = Utc::now().timestamp();
 
@@ -326,44 +730,1497 @@ impl AgentManager {
                 }
             }
 
-            Ok(())
-        })
+            Ok::<_, String>(())
+        })?;
+
+        self.publish_status(task_id, status);
+        Ok(())
+    }
+
+    /// Assign a task to the agent `routing` chose
+    pub fn assign_task(&self, task_id: &str, routing: &RoutingDecision) -> Result<(), String> {
+        self.db.with_conn(|conn| {
+            conn.execute(
+                "UPDATE tasks SET agent_id = ?1, routing = ?2, status = 'assigned' WHERE id = ?3",
+                params![
+                    routing.agent_id,
+                    serde_json::to_string(routing).unwrap_or_default(),
+                    task_id
+                ],
+            )
+            .map_err(|e| e.to_string())?;
+
+            Ok::<_, String>(())
+        })?;
+
+        self.publish_status(task_id, "assigned");
+        Ok(())
+    }
+
+    /// Record a deadline alert and set the task's priority. Returns false
+    /// when the alert was already recorded, so each fires once.
+    pub fn escalate_deadline(
+        &self,
+        task_id: &str,
+        alert: DeadlineAlert,
+        priority: &str,
+        now: i64,
+    ) -> Result<bool, String> {
+        let sql = match alert {
+            DeadlineAlert::AtRisk => {
+                "UPDATE tasks SET deadline_warned_at = ?1, priority = ?2
+                 WHERE id = ?3 AND deadline_warned_at IS NULL AND overdue_at IS NULL"
+            }
+            DeadlineAlert::Overdue => {
+                "UPDATE tasks SET overdue_at = ?1, priority = ?2,
+                        deadline_warned_at = COALESCE(deadline_warned_at, ?1)
+                 WHERE id = ?3 AND overdue_at IS NULL"
+            }
+        };
+        let changed = self.db.with_conn(|conn| {
+            conn.execute(sql, params![now, priority, task_id])
+                .map_err(|e| e.to_string())
+        })?;
+
+        if changed > 0 {
+            if let Some(task) = self.get_task(task_id)? {
+                self.publish(TaskEvent::Deadline {
+                    task_id: task_id.to_string(),
+                    alert: alert.as_str().to_string(),
+                    deadline: task.deadline.unwrap_or_default(),
+                    priority: priority.to_string(),
+                });
+            }
+        }
+        Ok(changed > 0)
     }
 
-    /// Cancel a task
+    /// Cancel a task and every unfinished task it delegated, recursively
     pub fn cancel_task(&self, task_id: &str) -> Result<(), String> {
-        self.update_task_status(task_id, "cancelled")
+        self.db
+            .with_conn(|conn| Self::cancel_subtree(conn, task_id, Utc::now().timestamp()))?;
+
+        self.publish_status(task_id, "cancelled");
+        Ok(())
     }
 
-    /// Get agent logs
-    pub fn get_agent_logs(&self, agent_id: &str, limit: i32) -> Result<Vec<TaskLog>, String> {
+    /// Cancel `task_id` and its unfinished descendants on `conn`, which may
+    /// be a transaction. Returns how many tasks were cancelled.
+    fn cancel_subtree(
+        conn: &rusqlite::Connection,
+        task_id: &str,
+        now: i64,
+    ) -> Result<usize, String> {
+        conn.execute(
+            "WITH RECURSIVE subtree(id) AS (
+                SELECT ?1
+                UNION ALL
+                SELECT t.id FROM tasks t JOIN subtree s ON t.parent_task_id = s.id
+             )
+             UPDATE tasks SET status = 'cancelled', completed_at = ?2
+             WHERE id IN (SELECT id FROM subtree)
+               AND status NOT IN ('completed', 'failed', 'cancelled')",
+            params![task_id, now],
+        )
+        .map_err(|e| e.to_string())
+    }
+
+    /// Apply `action` to every task matching `filter` in one transaction.
+    /// Finished tasks are skipped, as are running ones for anything but
+    /// cancelling; cancelling also cancels the tasks they delegated.
+    pub fn bulk_update(
+        &self,
+        filter: &TaskFilter,
+        action: &BulkAction,
+    ) -> Result<BulkResult, String> {
+        if filter.is_empty() {
+            return Err("Select tasks by id, status, agent, project or priority".to_string());
+        }
+        match action {
+            BulkAction::Reassign {
+                agent_id: Some(agent_id),
+            } => {
+                self.get_agent(agent_id)?
+                    .ok_or_else(|| format!("Agent not found: {}", agent_id))?;
+            }
+            BulkAction::SetPriority { priority }
+                if !TASK_PRIORITIES.contains(&priority.as_str()) =>
+            {
+                return Err(format!(
+                    "Unknown priority '{}'; expected one of {}",
+                    priority,
+                    TASK_PRIORITIES.join(", ")
+                ));
+            }
+            _ => {}
+        }
+
+        let now = Utc::now().timestamp();
+        let mut result = BulkResult::default();
+
+        self.db.with_conn(|conn| {
+            let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+
+            let tasks = {
+                let mut stmt = tx
+                    .prepare(&format!(
+                        "SELECT {} FROM tasks ORDER BY created_at ASC",
+                        TASK_COLUMNS
+                    ))
+                    .map_err(|e| e.to_string())?;
+                let tasks = stmt
+                    .query_map([], Self::map_task_row)
+                    .map_err(|e| e.to_string())?
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|e| e.to_string())?;
+                tasks
+            };
+
+            for task in tasks.iter().filter(|t| filter.matches(t)) {
+                if let Some(reason) = action.skip_reason(task) {
+                    result.skipped.push(BulkSkip {
+                        task_id: task.id.clone(),
+                        reason,
+                    });
+                    continue;
+                }
+
+                let changed = match action {
+                    BulkAction::Cancel => Self::cancel_subtree(&tx, &task.id, now)?,
+                    // Back to the queue so the scheduler routes it again
+                    BulkAction::Reassign { agent_id } => tx
+                        .execute(
+                            "UPDATE tasks SET agent_id = ?1, routing = NULL, status = 'pending'
+                             WHERE id = ?2 AND status NOT IN ('assigned', 'running', 'waiting')",
+                            params![agent_id, task.id],
+                        )
+                        .map_err(|e| e.to_string())?,
+                    BulkAction::SetPriority { priority } => tx
+                        .execute(
+                            "UPDATE tasks SET priority = ?1 WHERE id = ?2",
+                            params![priority, task.id],
+                        )
+                        .map_err(|e| e.to_string())?,
+                };
+
+                // The runtime picked it up since it was listed
+                if changed == 0 {
+                    result.skipped.push(BulkSkip {
+                        task_id: task.id.clone(),
+                        reason: "started".to_string(),
+                    });
+                    continue;
+                }
+                result.updated.push(task.id.clone());
+            }
+
+            tx.commit().map_err(|e| e.to_string())
+        })?;
+
+        let status = match action {
+            BulkAction::Cancel => Some("cancelled"),
+            BulkAction::Reassign { .. } => Some("pending"),
+            BulkAction::SetPriority { .. } => None,
+        };
+        if let Some(status) = status {
+            for id in &result.updated {
+                self.publish_status(id, status);
+            }
+        }
+        Ok(result)
+    }
+
+    /// Board columns in display order
+    pub fn list_board_columns(&self) -> Result<Vec<BoardColumn>, String> {
         self.db.with_conn(|conn| {
             let mut stmt = conn
                 .prepare(
-                    "SELECT tl.timestamp, tl.level, tl.message, tl.metadata
-                     FROM task_logs tl
-                     INNER JOIN tasks t ON tl.task_id = t.id
-                     WHERE t.agent_id = ?1
-                     ORDER BY tl.timestamp DESC
-                     LIMIT ?2",
+                    "SELECT id, name, statuses, drop_status, wip_limit
+                     FROM board_columns ORDER BY position ASC",
                 )
                 .map_err(|e| e.to_string())?;
 
-            let logs = stmt
-                .query_map(params![agent_id, limit], |row| {
-                    let metadata_json: Option<String> = row.get(3)?;
-                    Ok(TaskLog {
-                        timestamp: row.get(0)?,
-                        level: row.get(1)?,
-                        message: row.get(2)?,
-                        metadata: metadata_json.and_then(|j| serde_json::from_str(&j).ok()),
+            let columns = stmt
+                .query_map([], |row| {
+                    let statuses: String = row.get(2)?;
+                    Ok(BoardColumn {
+                        id: row.get(0)?,
+                        name: row.get(1)?,
+                        statuses: serde_json::from_str(&statuses).unwrap_or_default(),
+                        drop_status: row.get(3)?,
+                        wip_limit: row.get(4)?,
                     })
                 })
                 .map_err(|e| e.to_string())?
                 .collect::<Result<Vec<_>, _>>()
                 .map_err(|e| e.to_string())?;
 
-            Ok(logs)
+            Ok(columns)
+        })
+    }
+
+    /// Replace the board layout with `columns`, in order
+    pub fn save_board_columns(&self, columns: &[BoardColumn]) -> Result<(), String> {
+        validate_columns(columns)?;
+
+        self.db.with_conn(|conn| {
+            let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+
+            tx.execute("DELETE FROM board_columns", [])
+                .map_err(|e| e.to_string())?;
+            for (position, column) in columns.iter().enumerate() {
+                tx.execute(
+                    "INSERT INTO board_columns (id, name, statuses, drop_status, wip_limit, position)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                    params![
+                        column.id,
+                        column.name,
+                        serde_json::to_string(&column.statuses).unwrap_or_default(),
+                        column.drop_status,
+                        column.wip_limit,
+                        position as i64,
+                    ],
+                )
+                .map_err(|e| e.to_string())?;
+            }
+
+            tx.commit().map_err(|e| e.to_string())
+        })
+    }
+
+    /// Every task laid out in the board's columns
+    pub fn board(&self) -> Result<Board, String> {
+        Ok(build_board(
+            self.list_board_columns()?,
+            self.list_tasks(None)?,
+        ))
+    }
+
+    /// Move a task into a column, before `before_task_id` or at the bottom.
+    /// A task moved into a column that doesn't show its status takes the
+    /// column's drop status, if the transition is allowed.
+    pub fn move_task(
+        &self,
+        task_id: &str,
+        column_id: &str,
+        before_task_id: Option<&str>,
+    ) -> Result<Task, String> {
+        let columns = self.list_board_columns()?;
+        let column = columns
+            .iter()
+            .find(|c| c.id == column_id)
+            .ok_or_else(|| format!("Board column not found: {}", column_id))?;
+        let tasks = self.list_tasks(None)?;
+        let task = tasks
+            .iter()
+            .find(|t| t.id == task_id)
+            .ok_or_else(|| format!("Task not found: {}", task_id))?;
+
+        let status = if column.shows(&task.status) {
+            task.status.clone()
+        } else {
+            column
+                .drop_status
+                .clone()
+                .ok_or_else(|| format!("Tasks can't be moved into {} by hand", column.name))?
+        };
+        if !can_transition(&task.status, &status) {
+            return Err(format!("Can't move a {} task to {}", task.status, status));
+        }
+
+        let mut lane: Vec<&Task> = tasks
+            .iter()
+            .filter(|t| {
+                t.id != task_id && column_for(t, &columns).map(|c| c.id.as_str()) == Some(column_id)
+            })
+            .collect();
+        lane.sort_by(|a, b| rank_order(a, b));
+
+        let entering = column_for(task, &columns).map(|c| c.id.as_str()) != Some(column_id);
+        if let Some(limit) = column.wip_limit.filter(|_| entering) {
+            if lane.len() >= limit.max(0) as usize {
+                return Err(format!(
+                    "{} is at its limit of {} tasks",
+                    column.name, limit
+                ));
+            }
+        }
+
+        let index = match before_task_id {
+            Some(before) => lane
+                .iter()
+                .position(|t| t.id == before)
+                .ok_or_else(|| format!("Task {} is not in {}", before, column.name))?,
+            None => lane.len(),
+        };
+
+        let now = Utc::now().timestamp();
+        self.db.with_conn(|conn| {
+            let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+
+            // The runtime may have picked the task up since it was listed
+            let current: String = tx
+                .query_row(
+                    "SELECT status FROM tasks WHERE id = ?1",
+                    params![task_id],
+                    |row| row.get(0),
+                )
+                .map_err(|e| e.to_string())?;
+            if current != task.status {
+                return Err(format!("Task is now {}; try again", current));
+            }
+
+            let neighbour =
+                |i: Option<usize>| i.and_then(|i| lane.get(i)).and_then(|t| t.board_rank);
+            let rank = match rank_between(neighbour(index.checked_sub(1)), neighbour(Some(index))) {
+                Some(rank) => rank,
+                None => {
+                    // Too close to split; spread the column out again
+                    for (i, t) in lane.iter().enumerate() {
+                        let rank = if i < index { i + 1 } else { i + 2 };
+                        tx.execute(
+                            "UPDATE tasks SET board_rank = ?1 WHERE id = ?2",
+                            params![rank as f64, t.id],
+                        )
+                        .map_err(|e| e.to_string())?;
+                    }
+                    (index + 1) as f64
+                }
+            };
+
+            tx.execute(
+                "UPDATE tasks SET board_rank = ?1, board_column = ?2 WHERE id = ?3",
+                params![rank, column_id, task_id],
+            )
+            .map_err(|e| e.to_string())?;
+
+            // Each status change only applies to the status checked above
+            let changed = match status.as_str() {
+                _ if status == task.status => 1,
+                "cancelled" => Self::cancel_subtree(&tx, task_id, now)?,
+                // Back to the queue or the backlog; routed tasks are routed again
+                "pending" | "backlog" => tx
+                    .execute(
+                        "UPDATE tasks SET status = ?1, completed_at = NULL, routing = NULL,
+                                agent_id = CASE WHEN routing IS NULL THEN agent_id ELSE NULL END
+                         WHERE id = ?2 AND status = ?3",
+                        params![status, task_id, task.status],
+                    )
+                    .map_err(|e| e.to_string())?,
+                "completed" => tx
+                    .execute(
+                        "UPDATE tasks SET status = 'completed', completed_at = ?1
+                         WHERE id = ?2 AND status = ?3",
+                        params![now, task_id, task.status],
+                    )
+                    .map_err(|e| e.to_string())?,
+                _ => 1,
+            };
+            if changed == 0 {
+                return Err("Task changed while it was being moved; try again".to_string());
+            }
+
+            tx.commit().map_err(|e| e.to_string())
+        })?;
+
+        if status != task.status {
+            self.publish_status(task_id, &status);
+        }
+        self.get_task(task_id)?
+            .ok_or_else(|| format!("Task not found: {}", task_id))
+    }
+
+    /// Store the result of a finished task and mark it completed or failed.
+    /// Tasks cancelled while running keep their cancelled status.
+    pub fn complete_task(&self, task_id: &str, result: &TaskResult) -> Result<(), String> {
+        let status = if result.success {
+            "completed"
+        } else {
+            "failed"
+        };
+
+        self.db
+            .with_conn(|conn| {
+                conn.execute(
+                "UPDATE tasks SET status = CASE WHEN status = 'cancelled' THEN status ELSE ?1 END,
+                                  completed_at = ?2, result = ?3
+                 WHERE id = ?4",
+                params![
+                    status,
+                    Utc::now().timestamp(),
+                    serde_json::to_string(result).unwrap_or_default(),
+                    task_id,
+                ],
+            )
+            .map_err(|e| e.to_string())?;
+
+                conn.query_row(
+                    "SELECT status FROM tasks WHERE id = ?1",
+                    params![task_id],
+                    |row| row.get::<_, String>(0),
+                )
+                .map_err(|e| e.to_string())
+            })
+            .map(|final_status| self.publish_status(task_id, &final_status))
+    }
+
+    /// Fold a finished task into the agent's running stats
+    pub fn record_task_outcome(
+        &self,
+        agent_id: &str,
+        success: bool,
+        tokens_used: i64,
+        duration_secs: f64,
+    ) -> Result<(), String> {
+        self.db.with_conn(|conn| {
+            let stats_json: String = conn
+                .query_row(
+                    "SELECT stats FROM agents WHERE id = ?1",
+                    params![agent_id],
+                    |row| row.get(0),
+                )
+                .map_err(|e| e.to_string())?;
+
+            let mut stats: AgentStats = serde_json::from_str(&stats_json).unwrap_or_default();
+            let finished = (stats.tasks_completed + stats.tasks_failed) as f64;
+            stats.average_task_duration =
+                (stats.average_task_duration * finished + duration_secs) / (finished + 1.0);
+            if success {
+                stats.tasks_completed += 1;
+            } else {
+                stats.tasks_failed += 1;
+            }
+            stats.total_tokens_used += tokens_used;
+
+            conn.execute(
+                "UPDATE agents SET stats = ?1, last_active_at = ?2 WHERE id = ?3",
+                params![
+                    serde_json::to_string(&stats).unwrap_or_default(),
+                    Utc::now().timestamp(),
+                    agent_id,
+                ],
+            )
+            .map_err(|e| e.to_string())?;
+
+            Ok(())
+        })
+    }
+
+    /// Append an entry to a task's log
+    pub fn add_task_log(
+        &self,
+        task_id: &str,
+        level: &str,
+        message: &str,
+        metadata: Option<serde_json::Value>,
+    ) -> Result<(), String> {
+        self.db.with_conn(|conn| {
+            conn.execute(
+                "INSERT INTO task_logs (task_id, timestamp, level, message, metadata)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    task_id,
+                    Utc::now().timestamp(),
+                    level,
+                    message,
+                    metadata.map(|m| m.to_string()),
+                ],
+            )
+            .map_err(|e| e.to_string())?;
+
+            Ok::<_, String>(())
+        })?;
+
+        self.publish(TaskEvent::Log {
+            task_id: task_id.to_string(),
+            level: level.to_string(),
+            message: message.to_string(),
+        });
+        Ok(())
+    }
+
+    /// Append a turn to a task's conversation thread
+    pub fn add_task_message(
+        &self,
+        task_id: &str,
+        role: &str,
+        content: &str,
+        metadata: Option<serde_json::Value>,
+    ) -> Result<TaskMessage, String> {
+        self.db.with_conn(|conn| {
+            let created_at = Utc::now().timestamp();
+            conn.execute(
+                "INSERT INTO task_messages (task_id, role, content, metadata, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    task_id,
+                    role,
+                    content,
+                    metadata.as_ref().map(|m| m.to_string()),
+                    created_at,
+                ],
+            )
+            .map_err(|e| e.to_string())?;
+
+            Ok(TaskMessage {
+                id: conn.last_insert_rowid(),
+                task_id: task_id.to_string(),
+                role: role.to_string(),
+                content: content.to_string(),
+                metadata,
+                created_at,
+            })
+        })
+    }
+
+    /// A task's conversation thread, oldest first
+    pub fn list_task_messages(&self, task_id: &str) -> Result<Vec<TaskMessage>, String> {
+        self.db.with_conn(|conn| {
+            let mut stmt = conn
+                .prepare(
+                    "SELECT id, task_id, role, content, metadata, created_at
+                     FROM task_messages WHERE task_id = ?1 ORDER BY id ASC",
+                )
+                .map_err(|e| e.to_string())?;
+
+            let messages = stmt
+                .query_map(params![task_id], |row| {
+                    let metadata_json: Option<String> = row.get(4)?;
+                    Ok(TaskMessage {
+                        id: row.get(0)?,
+                        task_id: row.get(1)?,
+                        role: row.get(2)?,
+                        content: row.get(3)?,
+                        metadata: metadata_json.and_then(|j| serde_json::from_str(&j).ok()),
+                        created_at: row.get(5)?,
+                    })
+                })
+                .map_err(|e| e.to_string())?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| e.to_string())?;
+
+            Ok(messages)
+        })
+    }
+
+    /// Add a follow-up message to a finished task and queue it again for the
+    /// same agent
+    pub fn reply_to_task(&self, task_id: &str, content: &str) -> Result<TaskMessage, String> {
+        let task = self
+            .get_task(task_id)?
+            .ok_or_else(|| format!("Task not found: {}", task_id))?;
+
+        match task.status.as_str() {
+            "assigned" | "running" | "waiting" => {
+                return Err(
+                    "Task is still running; wait for it to finish before replying".to_string(),
+                )
+            }
+            _ if task.agent_id.is_none() => {
+                return Err("Task has not been picked up by an agent yet".to_string())
+            }
+            _ => {}
+        }
+        // The runtime only assembles the task prompt for an empty thread, so
+        // a reply before it must not become the first message
+        if self.list_task_messages(task_id)?.is_empty() {
+            return Err("Task has no conversation yet; run it before replying".to_string());
+        }
+
+        let message = self.add_task_message(task_id, "user", content, None)?;
+
+        self.db.with_conn(|conn| {
+            conn.execute(
+                "UPDATE tasks SET status = 'pending', completed_at = NULL WHERE id = ?1",
+                params![task_id],
+            )
+            .map_err(|e| e.to_string())?;
+            Ok::<(), String>(())
+        })?;
+
+        self.publish_status(task_id, "pending");
+        Ok(message)
+    }
+
+    /// Requeue tasks that were in flight when the app last exited and free
+    /// their agents. Returns the number of tasks requeued.
+    pub fn recover_interrupted_tasks(&self) -> Result<usize, String> {
+        self.db.with_conn(|conn| {
+            let requeued = conn
+                .execute(
+                    "UPDATE tasks SET status = 'pending' WHERE status IN ('assigned', 'running', 'waiting')",
+                    [],
+                )
+                .map_err(|e| e.to_string())?;
+
+            conn.execute("UPDATE agents SET status = 'idle' WHERE status = 'working'", [])
+                .map_err(|e| e.to_string())?;
+
+            Ok(requeued)
+        })
+    }
+
+    /// Ask for approval to run `tool` with `arguments` in a task. An earlier
+    /// request for the same call in the task is reused, so a resumed task
+    /// doesn't ask twice. Returns the request and whether it is new.
+    pub fn request_tool_approval(
+        &self,
+        task_id: &str,
+        agent_id: &str,
+        tool: &str,
+        arguments: &serde_json::Value,
+        rule: Option<&str>,
+    ) -> Result<(ToolApproval, bool), String> {
+        let arguments_json = arguments.to_string();
+
+        let existing = self.db.with_conn(|conn| {
+            let result = conn.query_row(
+                &format!(
+                    "SELECT {} FROM tool_approvals
+                     WHERE task_id = ?1 AND tool = ?2 AND arguments = ?3
+                     ORDER BY created_at DESC LIMIT 1",
+                    APPROVAL_COLUMNS
+                ),
+                params![task_id, tool, arguments_json],
+                Self::map_approval_row,
+            );
+
+            match result {
+                Ok(approval) => Ok(Some(approval)),
+                Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+                Err(e) => Err(e.to_string()),
+            }
+        })?;
+        if let Some(approval) = existing {
+            return Ok((approval, false));
+        }
+
+        let approval = ToolApproval {
+            id: Uuid::new_v4().to_string(),
+            task_id: task_id.to_string(),
+            agent_id: agent_id.to_string(),
+            tool: tool.to_string(),
+            arguments: arguments.clone(),
+            rule: rule.map(str::to_string),
+            status: "pending".to_string(),
+            created_at: Utc::now().timestamp(),
+            decided_at: None,
+        };
+
+        self.db.with_conn(|conn| {
+            conn.execute(
+                "INSERT INTO tool_approvals (id, task_id, agent_id, tool, arguments, rule, status, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![
+                    approval.id,
+                    approval.task_id,
+                    approval.agent_id,
+                    approval.tool,
+                    arguments_json,
+                    approval.rule,
+                    approval.status,
+                    approval.created_at,
+                ],
+            )
+            .map_err(|e| e.to_string())
+        })?;
+
+        self.record_tool_decision(&ToolAuditEntry {
+            decision: "ask".to_string(),
+            source: "policy".to_string(),
+            reason: Some("Waiting for approval".to_string()),
+            ..Self::audit_entry(&approval)
+        })?;
+        self.publish(TaskEvent::Approval {
+            task_id: task_id.to_string(),
+            approval: Box::new(approval.clone()),
+        });
+        Ok((approval, true))
+    }
+
+    pub fn get_tool_approval(&self, approval_id: &str) -> Result<Option<ToolApproval>, String> {
+        self.db.with_conn(|conn| {
+            let result = conn.query_row(
+                &format!(
+                    "SELECT {} FROM tool_approvals WHERE id = ?1",
+                    APPROVAL_COLUMNS
+                ),
+                params![approval_id],
+                Self::map_approval_row,
+            );
+
+            match result {
+                Ok(approval) => Ok(Some(approval)),
+                Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+                Err(e) => Err(e.to_string()),
+            }
+        })
+    }
+
+    /// Approval requests, newest first, optionally for one task or status
+    pub fn list_tool_approvals(
+        &self,
+        task_id: Option<&str>,
+        status: Option<&str>,
+    ) -> Result<Vec<ToolApproval>, String> {
+        self.db.with_conn(|conn| {
+            let mut stmt = conn
+                .prepare(&format!(
+                    "SELECT {} FROM tool_approvals
+                     WHERE (?1 IS NULL OR task_id = ?1) AND (?2 IS NULL OR status = ?2)
+                     ORDER BY created_at DESC",
+                    APPROVAL_COLUMNS
+                ))
+                .map_err(|e| e.to_string())?;
+
+            let approvals = stmt
+                .query_map(params![task_id, status], Self::map_approval_row)
+                .map_err(|e| e.to_string())?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| e.to_string())?;
+
+            Ok(approvals)
+        })
+    }
+
+    /// Approve or deny a pending request. `source` is recorded in the audit
+    /// trail: `user` for a person, `timeout` when nobody answered.
+    pub fn decide_tool_approval(
+        &self,
+        approval_id: &str,
+        approve: bool,
+        source: &str,
+    ) -> Result<ToolApproval, String> {
+        let status = if approve { "approved" } else { "denied" };
+        let now = Utc::now().timestamp();
+
+        let updated = self.db.with_conn(|conn| {
+            conn.execute(
+                "UPDATE tool_approvals SET status = ?1, decided_at = ?2
+                 WHERE id = ?3 AND status = 'pending'",
+                params![status, now, approval_id],
+            )
+            .map_err(|e| e.to_string())
+        })?;
+
+        let approval = self
+            .get_tool_approval(approval_id)?
+            .ok_or_else(|| format!("Approval request not found: {}", approval_id))?;
+        if updated == 0 {
+            return Err(format!("Approval request was already {}", approval.status));
+        }
+
+        self.record_tool_decision(&ToolAuditEntry {
+            decision: if approve { "allow" } else { "deny" }.to_string(),
+            source: source.to_string(),
+            reason: Some(match source {
+                "timeout" => "Nobody answered the approval request in time".to_string(),
+                _ => format!(
+                    "{} by {}",
+                    if approve { "Approved" } else { "Denied" },
+                    source
+                ),
+            }),
+            ..Self::audit_entry(&approval)
+        })?;
+        self.publish(TaskEvent::Approval {
+            task_id: approval.task_id.clone(),
+            approval: Box::new(approval.clone()),
+        });
+        Ok(approval)
+    }
+
+    /// Add a permission decision to the audit trail
+    pub fn record_tool_decision(&self, entry: &ToolAuditEntry) -> Result<(), String> {
+        self.db.with_conn(|conn| {
+            conn.execute(
+                "INSERT INTO tool_audit
+                    (task_id, agent_id, tool, arguments, decision, source, rule, reason, approval_id, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                params![
+                    entry.task_id,
+                    entry.agent_id,
+                    entry.tool,
+                    entry.arguments.to_string(),
+                    entry.decision,
+                    entry.source,
+                    entry.rule,
+                    entry.reason,
+                    entry.approval_id,
+                    Utc::now().timestamp(),
+                ],
+            )
+            .map_err(|e| e.to_string())?;
+            Ok(())
+        })
+    }
+
+    /// A task's permission decisions, oldest first
+    pub fn list_tool_audit(&self, task_id: &str) -> Result<Vec<ToolAuditEntry>, String> {
+        self.db.with_conn(|conn| {
+            let mut stmt = conn
+                .prepare(
+                    "SELECT id, task_id, agent_id, tool, arguments, decision, source, rule, reason,
+                            approval_id, created_at
+                     FROM tool_audit WHERE task_id = ?1 ORDER BY id ASC",
+                )
+                .map_err(|e| e.to_string())?;
+
+            let entries = stmt
+                .query_map(params![task_id], |row| {
+                    let arguments: String = row.get(4)?;
+                    Ok(ToolAuditEntry {
+                        id: row.get(0)?,
+                        task_id: row.get(1)?,
+                        agent_id: row.get(2)?,
+                        tool: row.get(3)?,
+                        arguments: serde_json::from_str(&arguments).unwrap_or_default(),
+                        decision: row.get(5)?,
+                        source: row.get(6)?,
+                        rule: row.get(7)?,
+                        reason: row.get(8)?,
+                        approval_id: row.get(9)?,
+                        created_at: row.get(10)?,
+                    })
+                })
+                .map_err(|e| e.to_string())?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| e.to_string())?;
+
+            Ok(entries)
+        })
+    }
+
+    /// Audit entry for a call covered by `approval`; fill in the decision
+    fn audit_entry(approval: &ToolApproval) -> ToolAuditEntry {
+        ToolAuditEntry {
+            id: 0,
+            task_id: approval.task_id.clone(),
+            agent_id: approval.agent_id.clone(),
+            tool: approval.tool.clone(),
+            arguments: approval.arguments.clone(),
+            decision: String::new(),
+            source: String::new(),
+            rule: approval.rule.clone(),
+            reason: None,
+            approval_id: Some(approval.id.clone()),
+            created_at: 0,
+        }
+    }
+
+    fn map_approval_row(row: &rusqlite::Row) -> rusqlite::Result<ToolApproval> {
+        let arguments: String = row.get(4)?;
+        Ok(ToolApproval {
+            id: row.get(0)?,
+            task_id: row.get(1)?,
+            agent_id: row.get(2)?,
+            tool: row.get(3)?,
+            arguments: serde_json::from_str(&arguments).unwrap_or_default(),
+            rule: row.get(5)?,
+            status: row.get(6)?,
+            created_at: row.get(7)?,
+            decided_at: row.get(8)?,
+        })
+    }
+
+    /// Store the definitions parsed from the agents repo, replacing any
+    /// that are no longer there
+    pub fn save_agent_definitions(&self, definitions: &[AgentDefinition]) -> Result<(), String> {
+        self.db.with_conn(|conn| {
+            let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+
+            tx.execute("DELETE FROM agent_definitions", [])
+                .map_err(|e| e.to_string())?;
+            for definition in definitions {
+                tx.execute(
+                    "INSERT INTO agent_definitions
+                        (id, filename, name, description, model, mode, system_prompt, parsed_at, repo_commit)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                    params![
+                        definition.id,
+                        definition.filename,
+                        definition.name,
+                        definition.description,
+                        definition.model,
+                        definition.mode,
+                        definition.system_prompt,
+                        definition.parsed_at,
+                        definition.repo_commit,
+                    ],
+                )
+                .map_err(|e| e.to_string())?;
+            }
+
+            tx.commit().map_err(|e| e.to_string())
+        })
+    }
+
+    /// The agent's autonomy level and where it came from: its own config,
+    /// its definition's `mode`, or the default
+    pub fn agent_autonomy(&self, agent: &Agent) -> Result<(AutonomyLevel, String), String> {
+        if let Some(level) = agent.config.autonomy {
+            return Ok((level, "agent config".to_string()));
+        }
+
+        if let Some(definition_id) = &agent.config.definition_id {
+            let mode = self.db.with_conn(|conn| {
+                let result = conn.query_row(
+                    "SELECT mode FROM agent_definitions WHERE id = ?1",
+                    params![definition_id],
+                    |row| row.get::<_, Option<String>>(0),
+                );
+
+                match result {
+                    Ok(mode) => Ok(mode),
+                    Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+                    Err(e) => Err(e.to_string()),
+                }
+            })?;
+            if let Some(mode) = mode {
+                return Ok((
+                    AutonomyLevel::from_mode(&mode),
+                    format!("definition {} (mode: {})", definition_id, mode),
+                ));
+            }
+        }
+
+        Ok((AutonomyLevel::Supervised, "default".to_string()))
+    }
+
+    /// Logs for one task, oldest first
+    pub fn list_task_logs(&self, task_id: &str) -> Result<Vec<TaskLog>, String> {
+        self.db.with_conn(|conn| {
+            let mut stmt = conn
+                .prepare(
+                    "SELECT timestamp, level, message, metadata
+                     FROM task_logs WHERE task_id = ?1 ORDER BY id ASC",
+                )
+                .map_err(|e| e.to_string())?;
+
+            let logs = stmt
+                .query_map(params![task_id], |row| {
+                    let metadata_json: Option<String> = row.get(3)?;
+                    Ok(TaskLog {
+                        timestamp: row.get(0)?,
+                        level: row.get(1)?,
+                        message: row.get(2)?,
+                        metadata: metadata_json.and_then(|j| serde_json::from_str(&j).ok()),
+                    })
+                })
+                .map_err(|e| e.to_string())?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| e.to_string())?;
+
+            Ok(logs)
+        })
+    }
+
+    /// Comment on a task as a person, or as `agent_id`
+    pub fn add_comment(
+        &self,
+        task_id: &str,
+        agent_id: Option<&str>,
+        body: &str,
+    ) -> Result<TaskComment, String> {
+        if body.trim().is_empty() {
+            return Err("Comment is empty".to_string());
+        }
+        self.get_task(task_id)?
+            .ok_or_else(|| format!("Task not found: {}", task_id))?;
+
+        let comment = TaskComment {
+            id: Uuid::new_v4().to_string(),
+            task_id: task_id.to_string(),
+            author: if agent_id.is_some() { "agent" } else { "human" }.to_string(),
+            agent_id: agent_id.map(str::to_string),
+            body: body.trim().to_string(),
+            created_at: Utc::now().timestamp(),
+            updated_at: None,
+        };
+
+        self.db.with_conn(|conn| {
+            conn.execute(
+                "INSERT INTO task_comments (id, task_id, author, agent_id, body, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    comment.id,
+                    comment.task_id,
+                    comment.author,
+                    comment.agent_id,
+                    comment.body,
+                    comment.created_at,
+                ],
+            )
+            .map_err(|e| e.to_string())
+        })?;
+
+        self.publish(TaskEvent::Comment {
+            task_id: task_id.to_string(),
+            comment: Box::new(comment.clone()),
+        });
+        Ok(comment)
+    }
+
+    /// Comments on a task, oldest first
+    pub fn list_comments(&self, task_id: &str) -> Result<Vec<TaskComment>, String> {
+        self.db.with_conn(|conn| {
+            let mut stmt = conn
+                .prepare(
+                    "SELECT id, task_id, author, agent_id, body, created_at, updated_at
+                     FROM task_comments WHERE task_id = ?1 ORDER BY created_at ASC, rowid ASC",
+                )
+                .map_err(|e| e.to_string())?;
+
+            let comments = stmt
+                .query_map(params![task_id], |row| {
+                    Ok(TaskComment {
+                        id: row.get(0)?,
+                        task_id: row.get(1)?,
+                        author: row.get(2)?,
+                        agent_id: row.get(3)?,
+                        body: row.get(4)?,
+                        created_at: row.get(5)?,
+                        updated_at: row.get(6)?,
+                    })
+                })
+                .map_err(|e| e.to_string())?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| e.to_string())?;
+
+            Ok(comments)
+        })
+    }
+
+    /// Replace a comment's body
+    pub fn update_comment(&self, comment_id: &str, body: &str) -> Result<(), String> {
+        if body.trim().is_empty() {
+            return Err("Comment is empty".to_string());
+        }
+
+        let changed = self.db.with_conn(|conn| {
+            conn.execute(
+                "UPDATE task_comments SET body = ?1, updated_at = ?2 WHERE id = ?3",
+                params![body.trim(), Utc::now().timestamp(), comment_id],
+            )
+            .map_err(|e| e.to_string())
+        })?;
+
+        if changed == 0 {
+            return Err(format!("Comment not found: {}", comment_id));
+        }
+        Ok(())
+    }
+
+    pub fn delete_comment(&self, comment_id: &str) -> Result<(), String> {
+        self.db.with_conn(|conn| {
+            conn.execute(
+                "DELETE FROM task_comments WHERE id = ?1",
+                params![comment_id],
+            )
+            .map_err(|e| e.to_string())?;
+            Ok(())
+        })
+    }
+
+    /// Attach a file to a task. The bytes are stored in the app data dir
+    /// under their hash; a file attached twice is stored once.
+    pub fn add_attachment(
+        &self,
+        task_id: &str,
+        file_name: &str,
+        bytes: &[u8],
+    ) -> Result<TaskAttachment, String> {
+        if bytes.len() > MAX_ATTACHMENT_BYTES {
+            return Err(format!(
+                "{} is larger than the {} MB attachment limit",
+                file_name,
+                MAX_ATTACHMENT_BYTES / (1024 * 1024)
+            ));
+        }
+        self.get_task(task_id)?
+            .ok_or_else(|| format!("Task not found: {}", task_id))?;
+
+        let file_name = sanitize_file_name(file_name);
+        let attachment = TaskAttachment {
+            id: Uuid::new_v4().to_string(),
+            task_id: task_id.to_string(),
+            mime_type: detect_mime_type(&file_name, bytes),
+            file_name,
+            size: bytes.len() as i64,
+            sha256: sha256_hex(bytes),
+            created_at: Utc::now().timestamp(),
+        };
+
+        let path = self.attachment_file(&attachment);
+        if !path.exists() {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+            }
+            // Write then rename so a half-written file is never picked up
+            let partial = path.with_extension("partial");
+            std::fs::write(&partial, bytes).map_err(|e| e.to_string())?;
+            std::fs::rename(&partial, &path).map_err(|e| e.to_string())?;
+        }
+
+        self.db.with_conn(|conn| {
+            conn.execute(
+                "INSERT INTO task_attachments (id, task_id, file_name, mime_type, size, sha256, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    attachment.id,
+                    attachment.task_id,
+                    attachment.file_name,
+                    attachment.mime_type,
+                    attachment.size,
+                    attachment.sha256,
+                    attachment.created_at,
+                ],
+            )
+            .map_err(|e| e.to_string())
+        })?;
+
+        Ok(attachment)
+    }
+
+    /// Attachments on a task, oldest first
+    pub fn list_attachments(&self, task_id: &str) -> Result<Vec<TaskAttachment>, String> {
+        self.db.with_conn(|conn| {
+            let mut stmt = conn
+                .prepare(
+                    "SELECT id, task_id, file_name, mime_type, size, sha256, created_at
+                     FROM task_attachments WHERE task_id = ?1 ORDER BY created_at ASC, rowid ASC",
+                )
+                .map_err(|e| e.to_string())?;
+
+            let attachments = stmt
+                .query_map(params![task_id], |row| {
+                    Ok(TaskAttachment {
+                        id: row.get(0)?,
+                        task_id: row.get(1)?,
+                        file_name: row.get(2)?,
+                        mime_type: row.get(3)?,
+                        size: row.get(4)?,
+                        sha256: row.get(5)?,
+                        created_at: row.get(6)?,
+                    })
+                })
+                .map_err(|e| e.to_string())?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| e.to_string())?;
+
+            Ok(attachments)
+        })
+    }
+
+    /// Remove an attachment, and its file once nothing else uses it
+    pub fn delete_attachment(&self, attachment_id: &str) -> Result<(), String> {
+        let orphan: Option<String> = self.db.with_conn(|conn| {
+            let sha256: Option<String> = conn
+                .query_row(
+                    "SELECT sha256 FROM task_attachments WHERE id = ?1",
+                    params![attachment_id],
+                    |row| row.get(0),
+                )
+                .ok();
+            let Some(sha256) = sha256 else {
+                return Ok::<_, String>(None);
+            };
+
+            conn.execute(
+                "DELETE FROM task_attachments WHERE id = ?1",
+                params![attachment_id],
+            )
+            .map_err(|e| e.to_string())?;
+            let users: i64 = conn
+                .query_row(
+                    "SELECT COUNT(*) FROM task_attachments WHERE sha256 = ?1",
+                    params![sha256],
+                    |row| row.get(0),
+                )
+                .map_err(|e| e.to_string())?;
+
+            Ok((users == 0).then_some(sha256))
+        })?;
+
+        if let Some(sha256) = orphan {
+            let _ = std::fs::remove_file(self.app_dir().join(stored_path(&sha256)));
+        }
+        Ok(())
+    }
+
+    /// Absolute path of an attachment's stored file
+    pub fn attachment_file(&self, attachment: &TaskAttachment) -> PathBuf {
+        self.app_dir().join(attachment.stored_path())
+    }
+
+    /// Directory holding the database and stored files
+    pub fn app_dir(&self) -> PathBuf {
+        self.db
+            .path()
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default()
+    }
+
+    /// Record the commit a task starts from, unless it already has one
+    pub fn set_task_start_commit(&self, task_id: &str, commit: &str) -> Result<(), String> {
+        self.db.with_conn(|conn| {
+            conn.execute(
+                "UPDATE tasks SET start_commit = ?1 WHERE id = ?2 AND start_commit IS NULL",
+                params![commit, task_id],
+            )
+            .map_err(|e| e.to_string())?;
+            Ok(())
+        })
+    }
+
+    /// Store a captured diff, dropping the pending diff and file artifacts
+    /// of earlier runs
+    pub fn replace_changes(
+        &self,
+        task_id: &str,
+        artifacts: &[ArtifactData],
+    ) -> Result<Vec<TaskArtifact>, String> {
+        self.db.with_conn(|conn| {
+            let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+            tx.execute(
+                "DELETE FROM task_artifacts
+                 WHERE task_id = ?1 AND status = 'pending' AND kind IN ('diff', 'file')",
+                params![task_id],
+            )
+            .map_err(|e| e.to_string())?;
+            let stored = artifacts
+                .iter()
+                .map(|data| insert_artifact(&tx, task_id, data))
+                .collect::<Result<Vec<_>, _>>()?;
+            tx.commit().map_err(|e| e.to_string())?;
+            Ok(stored)
+        })
+    }
+
+    pub fn add_artifact(&self, task_id: &str, data: &ArtifactData) -> Result<TaskArtifact, String> {
+        self.db
+            .with_conn(|conn| insert_artifact(conn, task_id, data))
+    }
+
+    /// Artifacts of a task, oldest first
+    pub fn list_artifacts(&self, task_id: &str) -> Result<Vec<TaskArtifact>, String> {
+        self.db.with_conn(|conn| {
+            let mut stmt = conn
+                .prepare(&format!(
+                    "SELECT {} FROM task_artifacts WHERE task_id = ?1 ORDER BY created_at ASC, rowid ASC",
+                    ARTIFACT_COLUMNS
+                ))
+                .map_err(|e| e.to_string())?;
+
+            let artifacts = stmt
+                .query_map(params![task_id], map_artifact_row)
+                .map_err(|e| e.to_string())?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| e.to_string())?;
+
+            Ok(artifacts)
+        })
+    }
+
+    /// An artifact with its stored content
+    pub fn get_artifact(&self, artifact_id: &str) -> Result<(TaskArtifact, ArtifactData), String> {
+        self.db.with_conn(|conn| {
+            conn.query_row(
+                &format!(
+                    "SELECT {}, content, original, patch FROM task_artifacts WHERE id = ?1",
+                    ARTIFACT_COLUMNS
+                ),
+                params![artifact_id],
+                |row| {
+                    let artifact = map_artifact_row(row)?;
+                    let data = ArtifactData {
+                        kind: artifact.kind.clone(),
+                        path: artifact.path.clone(),
+                        change: artifact.change.clone(),
+                        content: row.get(10)?,
+                        original: row.get(11)?,
+                        patch: row.get(12)?,
+                        binary: artifact.binary,
+                    };
+                    Ok((artifact, data))
+                },
+            )
+            .map_err(|e| match e {
+                rusqlite::Error::QueryReturnedNoRows => {
+                    format!("Artifact not found: {}", artifact_id)
+                }
+                e => e.to_string(),
+            })
+        })
+    }
+
+    pub fn set_artifact_status(
+        &self,
+        artifact_id: &str,
+        status: &str,
+    ) -> Result<TaskArtifact, String> {
+        self.db.with_conn(|conn| {
+            conn.execute(
+                "UPDATE task_artifacts SET status = ?1, decided_at = ?2 WHERE id = ?3",
+                params![status, Utc::now().timestamp(), artifact_id],
+            )
+            .map_err(|e| e.to_string())?;
+            Ok::<_, String>(())
+        })?;
+        self.get_artifact(artifact_id).map(|(artifact, _)| artifact)
+    }
+
+    /// Get agent logs
+    pub fn get_agent_logs(&self, agent_id: &str, limit: i32) -> Result<Vec<TaskLog>, String> {
+        self.db.with_conn(|conn| {
+            let mut stmt = conn
+                .prepare(
+                    "SELECT tl.timestamp, tl.level, tl.message, tl.metadata
+                     FROM task_logs tl
+                     INNER JOIN tasks t ON tl.task_id = t.id
+                     WHERE t.agent_id = ?1
+                     ORDER BY tl.timestamp DESC
+                     LIMIT ?2",
+                )
+                .map_err(|e| e.to_string())?;
+
+            let logs = stmt
+                .query_map(params![agent_id, limit], |row| {
+                    let metadata_json: Option<String> = row.get(3)?;
+                    Ok(TaskLog {
+                        timestamp: row.get(0)?,
+                        level: row.get(1)?,
+                        message: row.get(2)?,
+                        metadata: metadata_json.and_then(|j| serde_json::from_str(&j).ok()),
+                    })
+                })
+                .map_err(|e| e.to_string())?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| e.to_string())?;
+
+            Ok(logs)
+        })
+    }
+
+    /// List an agent's active memories, most important first
+    pub fn list_memories(&self, agent_id: &str, limit: i32) -> Result<Vec<AgentMemory>, String> {
+        self.db.with_conn(|conn| {
+            let mut stmt = conn
+                .prepare(&format!(
+                    "SELECT {} FROM agent_memories
+                     WHERE agent_id = ?1 AND archived = 0
+                     ORDER BY importance DESC, access_count DESC, created_at DESC
+                     LIMIT ?2",
+                    MEMORY_COLUMNS
+                ))
+                .map_err(|e| e.to_string())?;
+
+            let memories = stmt
+                .query_map(params![agent_id, limit], Self::map_memory_row)
+                .map_err(|e| e.to_string())?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| e.to_string())?;
+
+            Ok(memories)
+        })
+    }
+
+    /// Find active memories whose content contains every word of `query`,
+    /// optionally for one agent
+    pub fn search_memories(
+        &self,
+        query: &str,
+        agent_id: Option<&str>,
+        limit: i32,
+    ) -> Result<Vec<AgentMemory>, String> {
+        let words: Vec<String> = query
+            .split_whitespace()
+            .map(|w| {
+                format!(
+                    "%{}%",
+                    w.replace('\\', "\\\\")
+                        .replace('%', "\\%")
+                        .replace('_', "\\_")
+                )
+            })
+            .collect();
+
+        let mut sql = format!(
+            "SELECT {} FROM agent_memories WHERE archived = 0 AND (?1 IS NULL OR agent_id = ?1)",
+            MEMORY_COLUMNS
+        );
+        for index in 0..words.len() {
+            sql.push_str(&format!(" AND content LIKE ?{} ESCAPE '\\'", index + 3));
+        }
+        sql.push_str(" ORDER BY importance DESC, access_count DESC, created_at DESC LIMIT ?2");
+
+        let mut values: Vec<&dyn rusqlite::ToSql> = vec![&agent_id, &limit];
+        values.extend(words.iter().map(|w| w as &dyn rusqlite::ToSql));
+
+        self.db.with_conn(|conn| {
+            let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+            let memories = stmt
+                .query_map(values.as_slice(), Self::map_memory_row)
+                .map_err(|e| e.to_string())?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| e.to_string())?;
+
+            Ok(memories)
+        })
+    }
+
+    /// Store a new memory for an agent
+    pub fn add_memory(
+        &self,
+        agent_id: &str,
+        memory_type: &str,
+        content: &str,
+        importance: f64,
+    ) -> Result<AgentMemory, String> {
+        let memory = AgentMemory {
+            id: Uuid::new_v4().to_string(),
+            agent_id: agent_id.to_string(),
+            memory_type: memory_type.to_string(),
+            content: content.to_string(),
+            metadata: None,
+            created_at: Utc::now().timestamp(),
+            access_count: 0,
+            last_accessed: None,
+            importance: importance.clamp(0.0, 1.0),
+        };
+
+        self.db.with_conn(|conn| {
+            conn.execute(
+                "INSERT INTO agent_memories (id, agent_id, type, content, created_at, importance)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    memory.id,
+                    memory.agent_id,
+                    memory.memory_type,
+                    memory.content,
+                    memory.created_at,
+                    memory.importance,
+                ],
+            )
+            .map_err(|e| e.to_string())?;
+
+            Ok(memory)
+        })
+    }
+
+    /// Record that memories were used in a prompt
+    pub fn touch_memories(&self, memory_ids: &[String]) -> Result<(), String> {
+        self.db.with_conn(|conn| {
+            let now = Utc::now().timestamp();
+            for id in memory_ids {
+                conn.execute(
+                    "UPDATE agent_memories SET access_count = COALESCE(access_count, 0) + 1, last_accessed = ?1 WHERE id = ?2",
+                    params![now, id],
+                )
+                .map_err(|e| e.to_string())?;
+            }
+            Ok(())
         })
     }
 }
@@ -2,9 +2,30 @@
 //!
 //! Manages autonomous agents, task queue, and execution runtime.
 
+mod artifacts;
+mod board;
+mod builtin_tools;
+mod bulk;
+mod comments;
 pub mod commands;
+mod deadlines;
+mod delegation;
+mod events;
+mod lock;
 mod manager;
+mod permissions;
+mod prompt;
+mod routing;
 mod runtime;
+mod sandbox;
+mod scheduling;
+mod thread;
+mod tools;
 
-pub use manager::AgentManager;
+pub use board::Board;
+pub use bulk::{import_tasks, ImportFormat};
+pub use comments::TaskComment;
+pub use deadlines::{deadline_report, SlaReport};
+pub use lock::RuntimeLock;
+pub use manager::{Agent, AgentConfig, AgentManager, Task, TaskMessage, ToolApproval, TASK_PRIORITIES};
 pub use runtime::AgentRuntime;
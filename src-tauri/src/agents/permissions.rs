@@ -0,0 +1,650 @@
+//! Tool permission policy
+//!
+//! Each agent has `allow`, `deny` and `ask` rules in the same form Claude
+//! Code uses:
+//!
+//! ```text
+//! Read                      every call to Read
+//! Bash(npm run test:*)      commands starting with "npm run test"
+//! Bash(git status)          exactly "git status"
+//! Edit(src/**)              edits under src/ (Edit rules cover Write too)
+//! Read(*.pem)               reading .pem files (Read rules cover Grep and Glob)
+//! mcp__docs                 every tool from the "docs" MCP server
+//! ```
+//!
+//! A Grep or Glob call is matched on its search glob, and files a `Read`
+//! deny or ask rule covers are left out of its results.
+//!
+//! Deny wins over ask, and ask over allow. Chained shell commands are
+//! checked part by part, and are only allowed if every part is. Paths
+//! outside the task's project root are always denied. An `ask` pauses the
+//! task until someone approves or denies the call.
+//!
+//! Calls no rule covers are decided by the agent's autonomy level, set in
+//! `AgentConfig.autonomy` or inherited from its definition's `mode`.
+
+use super::builtin_tools::normalize;
+use super::manager::{AgentManager, ToolApproval};
+use super::tools::ToolCall;
+use globset::Glob;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+/// Unanswered approval requests are denied after this long
+pub const APPROVAL_TIMEOUT: Duration = Duration::from_secs(60 * 60);
+
+/// Tools whose `path` argument must stay inside the project root
+const PATH_TOOLS: &[&str] = &["Read", "Write", "Edit"];
+/// Tools that read the files a glob matches; `Read` rules cover them
+const SEARCH_TOOLS: &[&str] = &["Glob", "Grep"];
+/// Built-in tools that only look, never change anything
+const READ_ONLY_TOOLS: &[&str] = &["Read", "Glob", "Grep", "GitStatus"];
+
+/// System prompt note for suggest-only agents
+pub const SUGGEST_INSTRUCTIONS: &str = "## Suggest-only mode\n\
+     You can't run tools, change files or delegate. Answer with a concrete plan: \
+     the steps you would take, the files and commands involved, and the changes \
+     you would make. Someone else will review and carry it out.";
+
+/// How much an agent may do without asking
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AutonomyLevel {
+    /// Produce a plan; no tool calls or delegation
+    Suggest,
+    /// Looking is fine; anything else needs approval unless a rule allows
+    /// it. `normal`, the definition default, means this level.
+    #[serde(alias = "normal")]
+    Supervised,
+    /// Act freely within the deny and ask rules
+    Autonomous,
+}
+
+impl AutonomyLevel {
+    /// Level for an agent definition's `mode`. `normal`, the frontmatter
+    /// default, and unknown modes get `Supervised`.
+    pub fn from_mode(mode: &str) -> Self {
+        match mode.trim().to_lowercase().as_str() {
+            "suggest" | "suggest-only" | "plan" => AutonomyLevel::Suggest,
+            "autonomous" => AutonomyLevel::Autonomous,
+            _ => AutonomyLevel::Supervised,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AutonomyLevel::Suggest => "suggest",
+            AutonomyLevel::Supervised => "supervised",
+            AutonomyLevel::Autonomous => "autonomous",
+        }
+    }
+
+    /// Verdict for a call to `tool` that no rule covers
+    pub fn default_verdict(&self, tool: &str) -> Verdict {
+        match self {
+            AutonomyLevel::Suggest => Verdict::Deny,
+            AutonomyLevel::Supervised if READ_ONLY_TOOLS.contains(&tool) => Verdict::Allow,
+            AutonomyLevel::Supervised => Verdict::Ask,
+            AutonomyLevel::Autonomous => Verdict::Allow,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PermissionPolicy {
+    #[serde(default)]
+    pub allow: Vec<String>,
+    #[serde(default)]
+    pub deny: Vec<String>,
+    #[serde(default)]
+    pub ask: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Verdict {
+    Allow,
+    Deny,
+    Ask,
+}
+
+impl Verdict {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Verdict::Allow => "allow",
+            Verdict::Deny => "deny",
+            Verdict::Ask => "ask",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Decision {
+    pub verdict: Verdict,
+    /// What decided: `policy`, `scope`, `autonomy`, `user` or `timeout`
+    pub source: &'static str,
+    pub rule: Option<String>,
+    pub reason: String,
+}
+
+impl PermissionPolicy {
+    /// Decide whether `call` may run for an agent at `autonomy`
+    pub fn check(&self, call: &ToolCall, root: Option<&Path>, autonomy: AutonomyLevel) -> Decision {
+        if autonomy == AutonomyLevel::Suggest {
+            return Decision {
+                verdict: Verdict::Deny,
+                source: "autonomy",
+                rule: None,
+                reason: "The agent is in suggest-only mode and can't act".to_string(),
+            };
+        }
+
+        let target = match target(call, root) {
+            Ok(target) => target,
+            Err(reason) => {
+                return Decision {
+                    verdict: Verdict::Deny,
+                    source: "scope",
+                    rule: None,
+                    reason,
+                }
+            }
+        };
+
+        let matching = |rules: &[String], part: Option<&str>| -> Option<String> {
+            rules
+                .iter()
+                .find(|rule| rule_matches(rule, &call.name, part))
+                .cloned()
+        };
+        let decided = |verdict, rule: String, reason: &str| Decision {
+            verdict,
+            source: "policy",
+            reason: format!("{} rule {}", reason, rule),
+            rule: Some(rule),
+        };
+
+        // One part per chained shell command; otherwise the single target
+        let parts: Vec<Option<&str>> = match &target {
+            Target::Command(parts) => parts.iter().map(|p| Some(p.as_str())).collect(),
+            Target::Path(path) => vec![Some(path.as_str())],
+            Target::None => vec![None],
+        };
+
+        for part in &parts {
+            if let Some(rule) = matching(&self.deny, *part) {
+                return decided(Verdict::Deny, rule, "Denied by");
+            }
+        }
+        for part in &parts {
+            if let Some(rule) = matching(&self.ask, *part) {
+                return decided(Verdict::Ask, rule, "Needs approval by");
+            }
+        }
+
+        let allowed: Option<Vec<String>> = parts
+            .iter()
+            .map(|part| matching(&self.allow, *part))
+            .collect();
+        if let Some(rules) = allowed.filter(|r| !r.is_empty()) {
+            return decided(Verdict::Allow, rules.join(", "), "Allowed by");
+        }
+
+        Decision {
+            verdict: autonomy.default_verdict(&call.name),
+            source: "autonomy",
+            rule: None,
+            reason: format!("No rule matched; {} autonomy", autonomy.as_str()),
+        }
+    }
+
+    /// Whether a `Read` deny or ask rule covers `path`, relative to the
+    /// project root. Grep and Glob leave such files out of their results.
+    pub fn hides(&self, path: &str) -> bool {
+        self.deny
+            .iter()
+            .chain(&self.ask)
+            .any(|rule| rule_matches(rule, "Read", Some(path)))
+    }
+
+    /// Whether a Read call for `path` would be allowed by the rules and
+    /// the project scope alone. Files put into prompts are checked with it.
+    pub fn may_read(&self, root: &Path, path: &str) -> bool {
+        let call = ToolCall {
+            name: "Read".to_string(),
+            arguments: serde_json::json!({ "path": path }),
+        };
+        let decision = self.check(&call, Some(root), AutonomyLevel::Autonomous);
+        decision.verdict == Verdict::Allow
+    }
+}
+
+enum Target {
+    /// Parts of a shell command
+    Command(Vec<String>),
+    /// A path relative to the project root
+    Path(String),
+    None,
+}
+
+/// What rule specifiers are matched against for `call`. Errors for paths
+/// outside the project root.
+fn target(call: &ToolCall, root: Option<&Path>) -> Result<Target, String> {
+    let arg = |key: &str| call.arguments.get(key).and_then(|v| v.as_str());
+
+    if call.name == "Bash" {
+        return Ok(Target::Command(split_command(
+            arg("command").unwrap_or_default(),
+        )));
+    }
+    if SEARCH_TOOLS.contains(&call.name.as_str()) {
+        let glob = if call.name == "Glob" {
+            arg("pattern")
+        } else {
+            arg("glob")
+        };
+        return Ok(glob.map_or(Target::None, |glob| {
+            Target::Path(glob.trim_start_matches("./").to_string())
+        }));
+    }
+    if !PATH_TOOLS.contains(&call.name.as_str()) {
+        return Ok(Target::None);
+    }
+
+    let (Some(root), Some(path)) = (root, arg("path")) else {
+        return Err("File tools need a project".to_string());
+    };
+    let root = canonical(root);
+    let resolved = canonical(&normalize(&root.join(path)));
+    match resolved.strip_prefix(&root) {
+        Ok(relative) => Ok(Target::Path(relative.to_string_lossy().replace('\\', "/"))),
+        Err(_) => Err(format!("{} is outside the project root", path)),
+    }
+}
+
+/// Resolve symlinks in the longest existing prefix of `path`, so a link
+/// inside the project can't be used to reach outside it
+fn canonical(path: &Path) -> std::path::PathBuf {
+    let mut existing = path;
+    let mut rest = Vec::new();
+    while !existing.exists() {
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                rest.push(name.to_owned());
+                existing = parent;
+            }
+            _ => return path.to_path_buf(),
+        }
+    }
+
+    let mut out = existing
+        .canonicalize()
+        .unwrap_or_else(|_| existing.to_path_buf());
+    out.extend(rest.into_iter().rev());
+    out
+}
+
+/// Split a shell command on `&&`, `||`, `&`, `;`, `|` and newlines
+fn split_command(command: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut chars = command.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            ';' | '\n' => parts.push(std::mem::take(&mut current)),
+            // `2>&1` and `&>` are redirections, not separators
+            '&' if current.ends_with('>') || chars.peek() == Some(&'>') => current.push(c),
+            '&' => {
+                chars.next_if_eq(&'&');
+                parts.push(std::mem::take(&mut current));
+            }
+            '|' => {
+                chars.next_if_eq(&'|');
+                parts.push(std::mem::take(&mut current));
+            }
+            c => current.push(c),
+        }
+    }
+    parts.push(current);
+
+    parts
+        .into_iter()
+        .map(|p| p.trim().to_string())
+        .filter(|p| !p.is_empty())
+        .collect()
+}
+
+/// Whether `rule` covers a call to `tool` on `part` of its target
+fn rule_matches(rule: &str, tool: &str, part: Option<&str>) -> bool {
+    let (name, specifier) = match rule.split_once('(') {
+        Some((name, rest)) => (name.trim(), rest.strip_suffix(')').map(str::trim)),
+        None => (rule.trim(), None),
+    };
+
+    let name_matches = name == tool
+        || (name == "Edit" && tool == "Write")
+        || (name == "Read" && SEARCH_TOOLS.contains(&tool))
+        || (name.starts_with("mcp__") && tool.starts_with(&format!("{}__", name)))
+        || name
+            .strip_suffix('*')
+            .is_some_and(|prefix| tool.starts_with(prefix));
+    if !name_matches {
+        return false;
+    }
+
+    let Some(specifier) = specifier else {
+        return true;
+    };
+    let Some(part) = part else {
+        return false;
+    };
+
+    if tool == "Bash" {
+        // Substitutions could run anything; only bare rules cover them
+        if part.contains("$(") || part.contains('`') {
+            return false;
+        }
+        return match specifier.strip_suffix(":*") {
+            Some(prefix) => part == prefix || part.starts_with(&format!("{} ", prefix)),
+            None => wildcard_matches(specifier, part),
+        };
+    }
+
+    let specifier = specifier.trim_start_matches("./");
+    let Ok(glob) = Glob::new(specifier) else {
+        return false;
+    };
+    let matcher = glob.compile_matcher();
+    // A pattern without a slash matches the file name anywhere
+    matcher.is_match(part)
+        || (!specifier.contains('/')
+            && Path::new(part)
+                .file_name()
+                .is_some_and(|name| matcher.is_match(name)))
+}
+
+/// Match `text` against a pattern where `*` stands for any characters
+fn wildcard_matches(pattern: &str, text: &str) -> bool {
+    let pieces: Vec<&str> = pattern.split('*').collect();
+    if pieces.len() == 1 {
+        return pattern == text;
+    }
+
+    let mut rest = text;
+    for (i, piece) in pieces.iter().enumerate() {
+        if i == 0 {
+            let Some(after) = rest.strip_prefix(piece) else {
+                return false;
+            };
+            rest = after;
+        } else if i == pieces.len() - 1 {
+            return rest.ends_with(piece);
+        } else {
+            let Some(at) = rest.find(piece) else {
+                return false;
+            };
+            rest = &rest[at + piece.len()..];
+        }
+    }
+    true
+}
+
+/// Wait until `approval` is decided. Denies it if nobody answers in time.
+pub async fn wait_for_approval(
+    manager: &AgentManager,
+    approval: ToolApproval,
+) -> Result<ToolApproval, String> {
+    let started = Instant::now();
+    let mut approval = approval;
+
+    while approval.status == "pending" {
+        let task = manager.get_task(&approval.task_id)?;
+        if task.map_or(true, |t| t.status == "cancelled") {
+            return Err("Task was cancelled while waiting for approval".to_string());
+        }
+
+        if started.elapsed() >= APPROVAL_TIMEOUT {
+            return manager.decide_tool_approval(&approval.id, false, "timeout");
+        }
+
+        tokio::time::sleep(POLL_INTERVAL).await;
+        approval = manager
+            .get_tool_approval(&approval.id)?
+            .ok_or("Approval request was removed")?;
+    }
+
+    Ok(approval)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::manager::{AgentConfig, Task};
+    use crate::db::Database;
+    use crate::sync::agent_parser::AgentDefinition;
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    fn call(name: &str, arguments: serde_json::Value) -> ToolCall {
+        ToolCall {
+            name: name.to_string(),
+            arguments,
+        }
+    }
+
+    #[test]
+    fn test_policy_rules() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        let policy = PermissionPolicy {
+            allow: vec![
+                "Read".to_string(),
+                "Bash(git status)".to_string(),
+                "Bash(cargo test:*)".to_string(),
+                "Edit(src/**)".to_string(),
+            ],
+            deny: vec!["Bash(rm *)".to_string(), "Read(*.pem)".to_string()],
+            ask: vec!["mcp__deploy".to_string()],
+        };
+        let at = |level, c: ToolCall| policy.check(&c, Some(root), level).verdict;
+        let check = |c: ToolCall| at(AutonomyLevel::Supervised, c);
+
+        assert_eq!(
+            check(call("Read", json!({ "path": "README.md" }))),
+            Verdict::Allow
+        );
+        assert_eq!(
+            check(call("Read", json!({ "path": "keys/server.pem" }))),
+            Verdict::Deny
+        );
+        assert_eq!(
+            check(call("Read", json!({ "path": "../other/file" }))),
+            Verdict::Deny
+        );
+        assert_eq!(
+            check(call("Read", json!({ "path": "/etc/passwd" }))),
+            Verdict::Deny
+        );
+        assert_eq!(
+            check(call("Write", json!({ "path": "src/new.rs" }))),
+            Verdict::Allow
+        );
+        assert_eq!(
+            check(call("Write", json!({ "path": "build.rs" }))),
+            Verdict::Ask
+        );
+
+        let bash = |command: &str| check(call("Bash", json!({ "command": command })));
+        assert_eq!(bash("git status"), Verdict::Allow);
+        assert_eq!(bash("cargo test -p app 2>&1 && git status"), Verdict::Allow);
+        assert_eq!(bash("cargo test & curl evil"), Verdict::Ask);
+        assert_eq!(bash("cargo testing"), Verdict::Ask);
+        assert_eq!(bash("git status; rm -rf src"), Verdict::Deny);
+        assert_eq!(bash("cargo test $(curl evil)"), Verdict::Ask);
+
+        // Read rules cover searches: on the search glob, and on the files
+        // searched
+        let grep = |glob: Option<&str>| {
+            let mut args = json!({ "pattern": "." });
+            if let Some(glob) = glob {
+                args["glob"] = json!(glob);
+            }
+            check(call("Grep", args))
+        };
+        assert_eq!(grep(Some("**/*.pem")), Verdict::Deny);
+        assert_eq!(grep(Some("src/**/*.rs")), Verdict::Allow);
+        assert_eq!(grep(None), Verdict::Allow);
+        assert_eq!(
+            check(call("Glob", json!({ "pattern": "keys/*.pem" }))),
+            Verdict::Deny
+        );
+        assert!(policy.hides("keys/server.pem"));
+        assert!(!policy.hides("src/main.rs"));
+
+        // Calls no rule covers fall to the autonomy level
+        let release = || call("mcp__deploy__release", json!({}));
+        let search = || call("mcp__docs__search", json!({}));
+        let glob = || call("Glob", json!({ "pattern": "*.rs" }));
+        assert_eq!(at(AutonomyLevel::Autonomous, release()), Verdict::Ask);
+        assert_eq!(at(AutonomyLevel::Autonomous, search()), Verdict::Allow);
+        assert_eq!(at(AutonomyLevel::Supervised, search()), Verdict::Ask);
+        assert_eq!(at(AutonomyLevel::Supervised, glob()), Verdict::Allow);
+        assert_eq!(
+            at(
+                AutonomyLevel::Suggest,
+                call("Read", json!({ "path": "README.md" }))
+            ),
+            Verdict::Deny
+        );
+        assert_eq!(
+            AutonomyLevel::from_mode("Supervised"),
+            AutonomyLevel::Supervised
+        );
+        assert_eq!(AutonomyLevel::from_mode("plan"), AutonomyLevel::Suggest);
+        assert_eq!(
+            AutonomyLevel::from_mode("normal"),
+            AutonomyLevel::Supervised
+        );
+        assert_eq!(
+            AutonomyLevel::from_mode("whatever"),
+            AutonomyLevel::Supervised
+        );
+        let legacy: AutonomyLevel = serde_json::from_str("\"normal\"").unwrap();
+        assert_eq!(legacy, AutonomyLevel::Supervised);
+    }
+
+    #[test]
+    fn test_autonomy_inherited_from_definition() {
+        let dir = tempdir().unwrap();
+        let manager = AgentManager::new(Database::new(dir.path().to_path_buf()).unwrap());
+        let mut definition = AgentDefinition {
+            id: "reviewer".to_string(),
+            filename: "reviewer.md".to_string(),
+            name: "Reviewer".to_string(),
+            description: String::new(),
+            model: "sonnet".to_string(),
+            mode: "autonomous".to_string(),
+            system_prompt: "Review code.".to_string(),
+            parsed_at: 0,
+            repo_commit: None,
+        };
+        manager
+            .save_agent_definitions(&[definition.clone()])
+            .unwrap();
+
+        let agent = manager
+            .create_agent(
+                AgentConfig {
+                    definition_id: Some("reviewer".to_string()),
+                    ..Default::default()
+                },
+                "Reviewer",
+                "code-review",
+            )
+            .unwrap();
+        assert_eq!(
+            manager.agent_autonomy(&agent).unwrap().0,
+            AutonomyLevel::Autonomous
+        );
+
+        definition.mode = "plan".to_string();
+        manager.save_agent_definitions(&[definition]).unwrap();
+        assert_eq!(
+            manager.agent_autonomy(&agent).unwrap().0,
+            AutonomyLevel::Suggest
+        );
+
+        // The agent's own setting wins, and a removed definition falls back
+        let mut own = agent.clone();
+        own.config.autonomy = Some(AutonomyLevel::Autonomous);
+        assert_eq!(
+            manager.agent_autonomy(&own).unwrap().0,
+            AutonomyLevel::Autonomous
+        );
+        manager.save_agent_definitions(&[]).unwrap();
+        assert_eq!(
+            manager.agent_autonomy(&agent).unwrap().0,
+            AutonomyLevel::Supervised
+        );
+    }
+
+    #[tokio::test]
+    async fn test_approval_is_requested_once_and_decided() {
+        let dir = tempdir().unwrap();
+        let manager = AgentManager::new(Database::new(dir.path().to_path_buf()).unwrap());
+        let agent = manager
+            .create_agent(AgentConfig::default(), "Builder", "general")
+            .unwrap();
+        let task = manager
+            .create_task(&Task {
+                agent_id: Some(agent.id.clone()),
+                ..Task::new("Deploy")
+            })
+            .unwrap();
+
+        let release = call("mcp__deploy__release", json!({ "env": "prod" }));
+        let (approval, created) = manager
+            .request_tool_approval(
+                &task.id,
+                &agent.id,
+                &release.name,
+                &release.arguments,
+                Some("mcp__deploy"),
+            )
+            .unwrap();
+        assert!(created);
+        assert_eq!(approval.status, "pending");
+
+        // Asking again for the same call reuses the request
+        let (again, created) = manager
+            .request_tool_approval(
+                &task.id,
+                &agent.id,
+                &release.name,
+                &release.arguments,
+                Some("mcp__deploy"),
+            )
+            .unwrap();
+        assert!(!created);
+        assert_eq!(again.id, approval.id);
+
+        manager
+            .decide_tool_approval(&approval.id, true, "user")
+            .unwrap();
+        assert!(manager
+            .decide_tool_approval(&approval.id, false, "user")
+            .is_err());
+        let decided = wait_for_approval(&manager, approval).await.unwrap();
+        assert_eq!(decided.status, "approved");
+
+        let audit = manager.list_tool_audit(&task.id).unwrap();
+        let decisions: Vec<(&str, &str)> = audit
+            .iter()
+            .map(|e| (e.decision.as_str(), e.source.as_str()))
+            .collect();
+        assert_eq!(decisions, [("ask", "policy"), ("allow", "user")]);
+    }
+}
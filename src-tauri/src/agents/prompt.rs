@@ -0,0 +1,603 @@
+//! Prompt assembly for task execution
+//!
+//! Combines the agent's system prompt, the task, relevant memories and
+//! project context (git status, file tree, file contents) into one request,
+//! dropping or truncating lower-priority sections to fit the token budget.
+
+use super::comments::{render_attachments, render_comments};
+use super::delegation::{delegation_instructions, MAX_DELEGATION_DEPTH};
+use super::manager::{Agent, AgentManager, AgentMemory, Task};
+use super::permissions::{AutonomyLevel, PermissionPolicy};
+use crate::llm::{context_window, estimate_tokens};
+use crate::projects::{GitStatus, ProjectFile, ProjectManager};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
+
+/// Output token cap used when the agent has no `token_limit`
+pub const DEFAULT_MAX_OUTPUT_TOKENS: u32 = 4096;
+
+/// Sections at this priority are always included in full
+pub const PRIORITY_REQUIRED: u8 = 100;
+pub const PRIORITY_COMMENTS: u8 = 90;
+pub const PRIORITY_ATTACHMENTS: u8 = 85;
+pub const PRIORITY_MEMORIES: u8 = 80;
+pub const PRIORITY_DELEGATION: u8 = 70;
+pub const PRIORITY_GIT_STATUS: u8 = 60;
+pub const PRIORITY_REFERENCED_FILES: u8 = 50;
+pub const PRIORITY_FILE_TREE: u8 = 40;
+pub const PRIORITY_CHANGED_FILES: u8 = 30;
+
+/// Truncating a section below this many tokens isn't worth it
+const MIN_TRUNCATED_TOKENS: u64 = 64;
+const MAX_MEMORIES: usize = 10;
+const MAX_REFERENCED_FILES: usize = 10;
+const MAX_CHANGED_FILES: usize = 5;
+const MAX_FILE_BYTES: u64 = 100 * 1024;
+
+/// Token budget for one request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PromptBudget {
+    pub input_tokens: u64,
+    pub max_output_tokens: u32,
+}
+
+impl PromptBudget {
+    /// Split the model's context window (capped by the agent's `token_limit`)
+    /// between prompt and output
+    pub fn for_model(model_id: &str, token_limit: Option<i32>) -> Self {
+        let window = context_window(model_id);
+        let total = token_limit
+            .filter(|limit| *limit > 0)
+            .map(|limit| (limit as u64).min(window))
+            .unwrap_or(window);
+
+        let max_output_tokens = (DEFAULT_MAX_OUTPUT_TOKENS as u64).min(total / 4).max(1);
+
+        Self {
+            input_tokens: total - max_output_tokens,
+            max_output_tokens: max_output_tokens as u32,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PromptSection {
+    pub name: String,
+    pub priority: u8,
+    pub content: String,
+    pub truncatable: bool,
+}
+
+/// What happened to one section during assembly
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SectionReport {
+    pub name: String,
+    pub priority: u8,
+    #[serde(rename = "originalTokens")]
+    pub original_tokens: u64,
+    pub tokens: u64,
+    pub included: bool,
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptBreakdown {
+    #[serde(rename = "budgetTokens")]
+    pub budget_tokens: u64,
+    #[serde(rename = "usedTokens")]
+    pub used_tokens: u64,
+    #[serde(rename = "maxOutputTokens")]
+    pub max_output_tokens: u32,
+    pub sections: Vec<SectionReport>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AssembledPrompt {
+    pub system: String,
+    pub user: String,
+    pub breakdown: PromptBreakdown,
+}
+
+/// Collects prompt sections and fits them into a budget
+pub struct PromptBuilder {
+    system: String,
+    sections: Vec<PromptSection>,
+}
+
+impl PromptBuilder {
+    pub fn new(system_prompt: &str) -> Self {
+        Self {
+            system: system_prompt.trim().to_string(),
+            sections: Vec::new(),
+        }
+    }
+
+    /// Add a section; empty content is ignored
+    pub fn section(
+        &mut self,
+        name: &str,
+        priority: u8,
+        content: String,
+        truncatable: bool,
+    ) -> &mut Self {
+        if !content.trim().is_empty() {
+            self.sections.push(PromptSection {
+                name: name.to_string(),
+                priority,
+                content,
+                truncatable,
+            });
+        }
+        self
+    }
+
+    /// Fit sections into the budget, highest priority first. Sections keep
+    /// the order they were added in the final prompt.
+    pub fn build(&self, budget: PromptBudget) -> AssembledPrompt {
+        let system_tokens = estimate_tokens(&self.system);
+        let mut used = system_tokens;
+
+        let mut order: Vec<usize> = (0..self.sections.len()).collect();
+        order.sort_by_key(|&i| std::cmp::Reverse(self.sections[i].priority));
+
+        let mut rendered: Vec<Option<String>> = vec![None; self.sections.len()];
+        let mut reports: Vec<Option<SectionReport>> = vec![None; self.sections.len()];
+
+        for i in order {
+            let section = &self.sections[i];
+            let tokens = estimate_tokens(&section.content);
+            let remaining = budget.input_tokens.saturating_sub(used);
+
+            let (content, truncated) =
+                if section.priority >= PRIORITY_REQUIRED || tokens <= remaining {
+                    (Some(section.content.clone()), false)
+                } else if section.truncatable && remaining >= MIN_TRUNCATED_TOKENS {
+                    (Some(truncate_to_tokens(&section.content, remaining)), true)
+                } else {
+                    (None, false)
+                };
+
+            let included_tokens = content.as_deref().map(estimate_tokens).unwrap_or(0);
+            used += included_tokens;
+
+            reports[i] = Some(SectionReport {
+                name: section.name.clone(),
+                priority: section.priority,
+                original_tokens: tokens,
+                tokens: included_tokens,
+                included: content.is_some(),
+                truncated,
+            });
+            rendered[i] = content;
+        }
+
+        let mut sections = vec![SectionReport {
+            name: "system".to_string(),
+            priority: PRIORITY_REQUIRED,
+            original_tokens: system_tokens,
+            tokens: system_tokens,
+            included: true,
+            truncated: false,
+        }];
+        sections.extend(reports.into_iter().flatten());
+
+        AssembledPrompt {
+            system: self.system.clone(),
+            user: rendered
+                .into_iter()
+                .flatten()
+                .collect::<Vec<_>>()
+                .join("\n\n"),
+            breakdown: PromptBreakdown {
+                budget_tokens: budget.input_tokens,
+                used_tokens: used,
+                max_output_tokens: budget.max_output_tokens,
+                sections,
+            },
+        }
+    }
+}
+
+/// Cut content at a line boundary so it fits in roughly `tokens` tokens
+fn truncate_to_tokens(content: &str, tokens: u64) -> String {
+    const MARKER: &str = "\n… [truncated]";
+    let max_chars = (tokens as usize * 4).saturating_sub(MARKER.chars().count());
+
+    let cut = content
+        .char_indices()
+        .nth(max_chars)
+        .map(|(idx, _)| idx)
+        .unwrap_or(content.len());
+    let head = &content[..cut];
+    let head = match head.rfind('\n') {
+        Some(pos) if pos > 0 => &head[..pos],
+        _ => head,
+    };
+
+    format!("{}{}", head, MARKER)
+}
+
+/// Assemble the prompt for running `task` with `agent` on `model_id`
+pub fn assemble_task_prompt(
+    agents: &AgentManager,
+    projects: &ProjectManager,
+    task: &Task,
+    agent: &Agent,
+    model_id: &str,
+) -> Result<AssembledPrompt, String> {
+    let mut builder = PromptBuilder::new(&agent.config.system_prompt);
+
+    builder.section(
+        "task",
+        PRIORITY_REQUIRED,
+        format!("# Task: {}\n\n{}", task.title, task.description.trim()),
+        false,
+    );
+
+    builder.section(
+        "comments",
+        PRIORITY_COMMENTS,
+        render_comments(&agents.list_comments(&task.id)?),
+        true,
+    );
+
+    let attachments = agents.list_attachments(&task.id)?;
+    if !attachments.is_empty() {
+        builder.section(
+            "attachments",
+            PRIORITY_ATTACHMENTS,
+            render_attachments(&agents.app_dir(), &attachments),
+            true,
+        );
+    }
+
+    let memories = select_memories(
+        agents.list_memories(&agent.id, 50)?,
+        &format!("{} {}", task.title, task.description),
+    );
+    if !memories.is_empty() {
+        let ids: Vec<String> = memories.iter().map(|m| m.id.clone()).collect();
+        agents.touch_memories(&ids)?;
+        builder.section(
+            "memories",
+            PRIORITY_MEMORIES,
+            render_memories(&memories),
+            true,
+        );
+    }
+
+    let (autonomy, _) = agents.agent_autonomy(agent)?;
+    if task.depth < MAX_DELEGATION_DEPTH && autonomy != AutonomyLevel::Suggest {
+        if let Some(instructions) = delegation_instructions(&agents.list_agents()?, agent) {
+            builder.section("delegation", PRIORITY_DELEGATION, instructions, false);
+        }
+    }
+
+    if let Some(project_id) = &task.project_id {
+        if let Some(project) = projects.get(project_id)? {
+            let root = Path::new(&project.path);
+
+            builder.section(
+                "project",
+                PRIORITY_REQUIRED,
+                format!("## Project: {}\nRoot: {}", project.name, project.path),
+                false,
+            );
+
+            let git_status = projects.get_git_status(project_id).ok().flatten();
+            if let Some(status) = &git_status {
+                builder.section(
+                    "git_status",
+                    PRIORITY_GIT_STATUS,
+                    render_git_status(status),
+                    false,
+                );
+            }
+
+            let policy = &agent.config.permissions;
+            let referenced = referenced_files(root, &task.description);
+            for path in &referenced {
+                if let Some(section) = render_file(root, path, policy) {
+                    builder.section(
+                        &format!("file:{}", path),
+                        PRIORITY_REFERENCED_FILES,
+                        section,
+                        true,
+                    );
+                }
+            }
+
+            if let Ok(tree) = projects.get_file_tree(project_id) {
+                builder.section(
+                    "file_tree",
+                    PRIORITY_FILE_TREE,
+                    render_file_tree(&tree),
+                    true,
+                );
+            }
+
+            if let Some(status) = &git_status {
+                let changed = status
+                    .modified
+                    .iter()
+                    .chain(status.untracked.iter())
+                    .filter(|p| !referenced.contains(*p))
+                    .take(MAX_CHANGED_FILES);
+                for path in changed {
+                    if let Some(section) = render_file(root, path, policy) {
+                        builder.section(
+                            &format!("file:{}", path),
+                            PRIORITY_CHANGED_FILES,
+                            section,
+                            true,
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    let budget = PromptBudget::for_model(model_id, agent.config.token_limit);
+    Ok(builder.build(budget))
+}
+
+/// Pick the memories most relevant to the task: importance plus the share
+/// of task keywords the memory mentions
+fn select_memories(memories: Vec<AgentMemory>, task_text: &str) -> Vec<AgentMemory> {
+    let task_keywords = keywords(task_text);
+
+    let mut scored: Vec<(f64, AgentMemory)> = memories
+        .into_iter()
+        .map(|memory| {
+            let overlap = if task_keywords.is_empty() {
+                0.0
+            } else {
+                let words = keywords(&memory.content);
+                task_keywords.intersection(&words).count() as f64 / task_keywords.len() as f64
+            };
+            (memory.importance + overlap, memory)
+        })
+        .collect();
+
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+    scored
+        .into_iter()
+        .take(MAX_MEMORIES)
+        .map(|(_, m)| m)
+        .collect()
+}
+
+fn keywords(text: &str) -> HashSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| w.len() > 3)
+        .map(|w| w.to_lowercase())
+        .collect()
+}
+
+fn render_memories(memories: &[AgentMemory]) -> String {
+    let mut out = String::from("## Relevant memories");
+    for memory in memories {
+        out.push_str(&format!(
+            "\n- [{}] {}",
+            memory.memory_type,
+            memory.content.trim()
+        ));
+    }
+    out
+}
+
+fn render_git_status(status: &GitStatus) -> String {
+    let mut out = format!("## Git status\nBranch: {}", status.branch);
+    if status.ahead > 0 || status.behind > 0 {
+        out.push_str(&format!(
+            " (ahead {}, behind {})",
+            status.ahead, status.behind
+        ));
+    }
+
+    for (label, files) in [
+        ("Staged", &status.staged),
+        ("Modified", &status.modified),
+        ("Untracked", &status.untracked),
+    ] {
+        if !files.is_empty() {
+            out.push_str(&format!("\n{}: {}", label, files.join(", ")));
+        }
+    }
+
+    if status.staged.is_empty() && status.modified.is_empty() && status.untracked.is_empty() {
+        out.push_str("\nWorking tree clean");
+    }
+
+    out
+}
+
+fn render_file_tree(tree: &ProjectFile) -> String {
+    fn walk(file: &ProjectFile, depth: usize, out: &mut String) {
+        for child in file.children.iter().flatten() {
+            out.push('\n');
+            out.push_str(&"  ".repeat(depth));
+            out.push_str(&child.name);
+            if child.is_directory {
+                out.push('/');
+                walk(child, depth + 1, out);
+            }
+        }
+    }
+
+    let mut out = String::from("## File tree");
+    walk(tree, 0, &mut out);
+    out
+}
+
+/// Paths mentioned in the task description that exist inside the project
+fn referenced_files(root: &Path, text: &str) -> Vec<String> {
+    let Ok(canonical_root) = root.canonicalize() else {
+        return vec![];
+    };
+
+    let mut found = Vec::new();
+    for token in text.split_whitespace() {
+        let candidate = token.trim_matches(|c: char| "`'\"()[]{}<>,;:".contains(c));
+        let candidate = candidate.trim_end_matches('.');
+        if candidate.is_empty() || !(candidate.contains('/') || candidate.contains('.')) {
+            continue;
+        }
+
+        let inside_project = root
+            .join(candidate)
+            .canonicalize()
+            .map(|p| p.starts_with(&canonical_root) && p.is_file())
+            .unwrap_or(false);
+
+        if inside_project && !found.iter().any(|f| f == candidate) {
+            found.push(candidate.to_string());
+            if found.len() >= MAX_REFERENCED_FILES {
+                break;
+            }
+        }
+    }
+
+    found
+}
+
+/// A file's content as a prompt section, unless it is too large or the
+/// agent's `Read` rules keep it from the file
+fn render_file(root: &Path, relative: &str, policy: &PermissionPolicy) -> Option<String> {
+    if !policy.may_read(root, relative) {
+        return None;
+    }
+    let path = root.join(relative);
+    let metadata = std::fs::metadata(&path).ok()?;
+    if !metadata.is_file() || metadata.len() > MAX_FILE_BYTES {
+        return None;
+    }
+
+    let content = std::fs::read_to_string(&path).ok()?;
+    let lang = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    Some(format!(
+        "### File: {}\n```{}\n{}\n```",
+        relative,
+        lang,
+        content.trim_end()
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn budget(input_tokens: u64) -> PromptBudget {
+        PromptBudget {
+            input_tokens,
+            max_output_tokens: 100,
+        }
+    }
+
+    #[test]
+    fn test_budget_respects_token_limit() {
+        let full = PromptBudget::for_model("claude-sonnet-4-5", None);
+        assert_eq!(full.max_output_tokens, DEFAULT_MAX_OUTPUT_TOKENS);
+        assert_eq!(
+            full.input_tokens,
+            200_000 - DEFAULT_MAX_OUTPUT_TOKENS as u64
+        );
+
+        let limited = PromptBudget::for_model("claude-sonnet-4-5", Some(8_000));
+        assert_eq!(limited.max_output_tokens, 2_000);
+        assert_eq!(limited.input_tokens, 6_000);
+    }
+
+    #[test]
+    fn test_build_drops_low_priority_sections() {
+        let mut builder = PromptBuilder::new("You are helpful.");
+        builder
+            .section("task", PRIORITY_REQUIRED, "Do the thing".to_string(), false)
+            .section("git_status", PRIORITY_GIT_STATUS, "x".repeat(400), false)
+            .section("file_tree", PRIORITY_FILE_TREE, "y".repeat(4000), false);
+
+        let prompt = builder.build(budget(200));
+        let report = |name: &str| {
+            prompt
+                .breakdown
+                .sections
+                .iter()
+                .find(|s| s.name == name)
+                .cloned()
+                .unwrap()
+        };
+
+        assert!(report("task").included);
+        assert!(report("git_status").included);
+        assert!(!report("file_tree").included);
+        assert!(prompt.user.starts_with("Do the thing"));
+        assert!(!prompt.user.contains('y'));
+        assert!(prompt.breakdown.used_tokens <= 200);
+    }
+
+    #[test]
+    fn test_build_truncates_and_keeps_insertion_order() {
+        let tree: String = (0..500).map(|i| format!("file{}.rs\n", i)).collect();
+
+        let mut builder = PromptBuilder::new("");
+        builder
+            .section("file_tree", PRIORITY_FILE_TREE, tree, true)
+            .section("task", PRIORITY_REQUIRED, "Refactor".to_string(), false);
+
+        let prompt = builder.build(budget(300));
+        let tree_report = &prompt.breakdown.sections[1];
+
+        assert_eq!(tree_report.name, "file_tree");
+        assert!(tree_report.included && tree_report.truncated);
+        assert!(tree_report.tokens < tree_report.original_tokens);
+        assert!(prompt.user.starts_with("file0.rs"));
+        assert!(prompt.user.ends_with("Refactor"));
+        assert!(prompt.user.contains("[truncated]"));
+    }
+
+    #[test]
+    fn test_render_file_follows_read_rules() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        std::fs::write(root.join(".env"), "API_KEY=secret\n").unwrap();
+        std::fs::write(root.join("main.rs"), "fn main() {}\n").unwrap();
+
+        // An untracked .env would be inlined as a changed file
+        let policy = PermissionPolicy {
+            deny: vec!["Read(.env)".to_string()],
+            ..Default::default()
+        };
+        assert!(render_file(root, ".env", &policy).is_none());
+        assert!(render_file(root, "main.rs", &policy)
+            .unwrap()
+            .contains("fn main()"));
+        assert!(render_file(root, ".env", &PermissionPolicy::default()).is_some());
+    }
+
+    #[test]
+    fn test_select_memories_prefers_relevant() {
+        let memory = |id: &str, content: &str, importance: f64| AgentMemory {
+            id: id.to_string(),
+            agent_id: "a".to_string(),
+            memory_type: "semantic".to_string(),
+            content: content.to_string(),
+            metadata: None,
+            created_at: 0,
+            access_count: 0,
+            last_accessed: None,
+            importance,
+        };
+
+        let selected = select_memories(
+            vec![
+                memory("1", "The user prefers tabs", 0.6),
+                memory("2", "Deploys happen through the staging pipeline", 0.5),
+            ],
+            "Fix the staging deploy pipeline",
+        );
+
+        assert_eq!(selected[0].id, "2");
+    }
+}
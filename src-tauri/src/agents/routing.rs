@@ -0,0 +1,335 @@
+//! Task routing
+//!
+//! Decides which idle agent picks up a pending task. Agents that can't take
+//! the task at all (not auto-assign, wrong target type, not assigned to the
+//! task's project, none of its required skills) are left out; the rest are
+//! ranked by how well they fit the task, how busy they are and how often
+//! their tasks succeed. The decision and the reasons behind it are stored
+//! on the task.
+
+use super::manager::{Agent, Task, ACTIVE_STATUSES};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Score weights; they add up to 1
+const FIT_WEIGHT: f64 = 0.6;
+const SUCCESS_WEIGHT: f64 = 0.25;
+const LOAD_WEIGHT: f64 = 0.15;
+
+/// Share of the fit score from each signal
+const TYPE_FIT: f64 = 0.3;
+const SKILL_FIT: f64 = 0.5;
+const PROJECT_FIT: f64 = 0.2;
+
+/// Why a task went to the agent it did
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoutingDecision {
+    #[serde(rename = "agentId")]
+    pub agent_id: String,
+    pub score: f64,
+    pub explanation: String,
+    /// Every agent that could have taken the task, best first
+    pub candidates: Vec<Candidate>,
+    #[serde(rename = "routedAt")]
+    pub routed_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Candidate {
+    #[serde(rename = "agentId")]
+    pub agent_id: String,
+    pub name: String,
+    pub score: f64,
+    /// 0–1: type, skill and project match
+    pub fit: f64,
+    /// 0–1: active and queued tasks over `max_concurrent_tasks`
+    pub load: f64,
+    #[serde(rename = "successRate")]
+    pub success_rate: f64,
+    /// What made up `fit`
+    pub reasons: Vec<String>,
+}
+
+/// Pick an agent from `agents` for `task`. `tasks` is the current task list,
+/// used to work out each agent's load. Returns `None` when no agent may
+/// take the task.
+pub fn route_task(task: &Task, agents: &[&Agent], tasks: &[Task]) -> Option<RoutingDecision> {
+    // Tasks pinned to an agent wait for it, but only run in its projects
+    if let Some(agent_id) = &task.agent_id {
+        let agent = agents.iter().find(|a| &a.id == agent_id)?;
+        if !works_on(agent, task) {
+            return None;
+        }
+        // A routed task put back in the queue keeps the original decision
+        if let Some(routing) = task.routing.as_ref().filter(|r| &r.agent_id == agent_id) {
+            return Some(RoutingDecision {
+                routed_at: Utc::now().timestamp(),
+                ..routing.clone()
+            });
+        }
+        let candidate = score(agent, task, tasks);
+        return Some(RoutingDecision {
+            agent_id: agent.id.clone(),
+            score: candidate.score,
+            explanation: format!("Assigned to {} when the task was created", agent.name),
+            candidates: vec![candidate],
+            routed_at: Utc::now().timestamp(),
+        });
+    }
+
+    let mut candidates: Vec<Candidate> = agents
+        .iter()
+        .filter(|agent| eligible(agent, task))
+        .map(|agent| score(agent, task, tasks))
+        .collect();
+    candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+
+    let best = candidates.first()?;
+    Some(RoutingDecision {
+        agent_id: best.agent_id.clone(),
+        score: best.score,
+        explanation: explain(&candidates),
+        candidates,
+        routed_at: Utc::now().timestamp(),
+    })
+}
+
+/// Whether `agent` may take an unpinned task at all
+fn eligible(agent: &Agent, task: &Task) -> bool {
+    if !agent.config.auto_assign {
+        return false;
+    }
+    if let Some(target) = &task.target_agent_type {
+        if !agent.agent_type.eq_ignore_ascii_case(target) {
+            return false;
+        }
+    }
+    if !works_on(agent, task) {
+        return false;
+    }
+    task.skills.is_empty() || !matched_skills(agent, &task.skills).is_empty()
+}
+
+/// Whether `agent` is assigned to the task's project; no list means any
+fn works_on(agent: &Agent, task: &Task) -> bool {
+    let projects = &agent.config.allowed_projects;
+    match &task.project_id {
+        Some(project_id) => projects.is_empty() || projects.contains(project_id),
+        None => true,
+    }
+}
+
+fn score(agent: &Agent, task: &Task, tasks: &[Task]) -> Candidate {
+    let (fit, reasons) = fit(agent, task);
+
+    let stats = &agent.stats;
+    // Smoothed so a new agent starts at 50% rather than 0 or 100
+    let success_rate = (stats.tasks_completed as f64 + 1.0)
+        / ((stats.tasks_completed + stats.tasks_failed) as f64 + 2.0);
+
+    let active = tasks
+        .iter()
+        .filter(|t| t.agent_id.as_deref() == Some(agent.id.as_str()))
+        .filter(|t| ACTIVE_STATUSES.contains(&t.status.as_str()) || t.status == "pending")
+        .count();
+    let load = (active as f64 / agent.config.max_concurrent_tasks.max(1) as f64).min(1.0);
+
+    Candidate {
+        agent_id: agent.id.clone(),
+        name: agent.name.clone(),
+        score: FIT_WEIGHT * fit + SUCCESS_WEIGHT * success_rate + LOAD_WEIGHT * (1.0 - load),
+        fit,
+        load,
+        success_rate,
+        reasons,
+    }
+}
+
+fn fit(agent: &Agent, task: &Task) -> (f64, Vec<String>) {
+    let words = words(&format!("{} {}", task.title, task.description));
+    let mut fit = 0.0;
+    let mut reasons = Vec::new();
+
+    if task.target_agent_type.is_some() {
+        fit += TYPE_FIT;
+        reasons.push(format!("requested type {}", agent.agent_type));
+    } else if mentions(&words, &agent.agent_type) {
+        fit += TYPE_FIT;
+        reasons.push(format!("task mentions {}", agent.agent_type));
+    }
+
+    if !task.skills.is_empty() {
+        let matched = matched_skills(agent, &task.skills);
+        if !matched.is_empty() {
+            fit += SKILL_FIT * matched.len() as f64 / task.skills.len() as f64;
+            reasons.push(format!(
+                "has {} of {} required skills ({})",
+                matched.len(),
+                task.skills.len(),
+                matched.join(", ")
+            ));
+        }
+    } else {
+        let mentioned: Vec<&str> = agent
+            .config
+            .skills
+            .iter()
+            .filter(|skill| mentions(&words, skill))
+            .map(String::as_str)
+            .collect();
+        if !mentioned.is_empty() {
+            fit += (SKILL_FIT / 2.0 * mentioned.len() as f64).min(SKILL_FIT);
+            reasons.push(format!("task mentions skills {}", mentioned.join(", ")));
+        }
+    }
+
+    if let Some(project_id) = &task.project_id {
+        if agent.config.allowed_projects.contains(project_id) {
+            fit += PROJECT_FIT;
+            reasons.push("assigned to the task's project".to_string());
+        }
+    }
+
+    (fit.min(1.0), reasons)
+}
+
+fn explain(candidates: &[Candidate]) -> String {
+    let best = &candidates[0];
+    let why = if best.reasons.is_empty() {
+        "no type, skill or project match; picked on track record and load".to_string()
+    } else {
+        best.reasons.join("; ")
+    };
+
+    let mut out = format!(
+        "Chose {} (score {:.2}): {}. Success rate {:.0}%, load {:.0}%.",
+        best.name,
+        best.score,
+        why,
+        best.success_rate * 100.0,
+        best.load * 100.0
+    );
+    if let Some(runner_up) = candidates.get(1) {
+        out.push_str(&format!(
+            " Runner-up: {} ({:.2}).",
+            runner_up.name, runner_up.score
+        ));
+    }
+    out
+}
+
+/// The agent's skills that `required` asks for, case-insensitively
+fn matched_skills<'a>(agent: &'a Agent, required: &[String]) -> Vec<&'a str> {
+    agent
+        .config
+        .skills
+        .iter()
+        .filter(|skill| required.iter().any(|r| r.eq_ignore_ascii_case(skill)))
+        .map(String::as_str)
+        .collect()
+}
+
+/// Whether every word of `phrase` (e.g. `code-review`) appears in `words`
+fn mentions(words: &HashSet<String>, phrase: &str) -> bool {
+    let phrase = self::words(phrase);
+    !phrase.is_empty() && phrase.iter().all(|w| words.contains(w))
+}
+
+fn words(text: &str) -> HashSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_lowercase())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::manager::{AgentConfig, AgentStats};
+
+    fn agent(id: &str, agent_type: &str, skills: &[&str]) -> Agent {
+        Agent {
+            id: id.to_string(),
+            name: id.to_string(),
+            agent_type: agent_type.to_string(),
+            description: String::new(),
+            status: "idle".to_string(),
+            created_at: 0,
+            last_active_at: 0,
+            config: AgentConfig {
+                auto_assign: true,
+                max_concurrent_tasks: 1,
+                skills: skills.iter().map(|s| s.to_string()).collect(),
+                ..Default::default()
+            },
+            stats: AgentStats::default(),
+        }
+    }
+
+    #[test]
+    fn test_route_by_fit_load_and_constraints() {
+        let reviewer = agent("reviewer", "code-review", &["rust"]);
+        let writer = agent("writer", "content", &["copywriting", "seo"]);
+        let agents = [&reviewer, &writer];
+
+        let mut task = Task::new("Copywriting for the landing page");
+        let decision = route_task(&task, &agents, &[]).unwrap();
+        assert_eq!(decision.agent_id, "writer");
+        assert_eq!(decision.candidates.len(), 2);
+        assert!(decision.explanation.contains("copywriting"));
+        assert!(decision.explanation.contains("Runner-up: reviewer"));
+
+        task.description = "Then do a code review of the Rust parser".to_string();
+        let mut busy = Task::new("Queued");
+        busy.agent_id = Some("writer".to_string());
+        let decision = route_task(&task, &agents, &[busy]).unwrap();
+        assert_eq!(decision.agent_id, "reviewer");
+
+        // Required skills and project assignment rule agents out
+        task.skills = vec!["SEO".to_string()];
+        let decision = route_task(&task, &agents, &[]).unwrap();
+        assert_eq!(decision.candidates.len(), 1);
+        assert_eq!(decision.agent_id, "writer");
+
+        let mut scoped = agent("scoped", "content", &["seo"]);
+        scoped.config.allowed_projects = vec!["site".to_string()];
+        task.project_id = Some("app".to_string());
+        assert!(route_task(&task, &[&scoped], &[]).is_none());
+        task.project_id = Some("site".to_string());
+        let decision = route_task(&task, &[&writer, &scoped], &[]).unwrap();
+        assert_eq!(decision.agent_id, "scoped");
+        assert!(decision.explanation.contains("project"));
+
+        task.target_agent_type = Some("code-review".to_string());
+        assert!(route_task(&task, &agents, &[]).is_none());
+    }
+
+    #[test]
+    fn test_pinned_tasks_stay_in_project_and_keep_routing() {
+        let mut scoped = agent("scoped", "content", &["seo"]);
+        scoped.config.allowed_projects = vec!["site".to_string()];
+
+        let mut task = Task {
+            agent_id: Some("scoped".to_string()),
+            project_id: Some("app".to_string()),
+            ..Task::new("Write the changelog")
+        };
+        assert!(route_task(&task, &[&scoped], &[]).is_none());
+
+        task.project_id = Some("site".to_string());
+        let decision = route_task(&task, &[&scoped], &[]).unwrap();
+        assert!(decision.explanation.contains("when the task was created"));
+
+        // Requeued after a restart, the task keeps why it was routed
+        let writer = agent("writer", "content", &["seo"]);
+        task.agent_id = None;
+        task.routing = route_task(&task, &[&writer, &scoped], &[]);
+        let routed = task.routing.clone().unwrap();
+        task.agent_id = Some(routed.agent_id.clone());
+        let decision = route_task(&task, &[&writer, &scoped], &[]).unwrap();
+        assert_eq!(decision.agent_id, routed.agent_id);
+        assert_eq!(decision.explanation, routed.explanation);
+        assert_eq!(decision.candidates.len(), 2);
+    }
+}
@@ -2,24 +2,94 @@
 //!
 //! This module handles the autonomous execution of tasks by agents.
 
-use super::manager::{AgentManager, Task};
+use super::artifacts::{capture_changes, transcript};
+use super::comments::parse_comments;
+use super::deadlines::check_deadlines;
+use super::delegation::{
+    parse_delegations, render_results, spawn_children, wait_for_children, Delegation,
+    MAX_DELEGATION_ROUNDS,
+};
+use super::events::TaskEvent;
+use super::lock::RuntimeLock;
+use super::manager::{Agent, AgentManager, Task, TaskResult, ToolAuditEntry};
+use super::permissions::{
+    wait_for_approval, AutonomyLevel, Decision, Verdict, SUGGEST_INSTRUCTIONS,
+};
+use super::prompt::{assemble_task_prompt, PromptBudget};
+use super::routing::route_task;
+use super::sandbox::{self, detect_violations};
+use super::scheduling::{fair_order, ProjectSlots};
+use super::thread::{fit_to_budget, to_messages};
+use super::tools::{
+    parse_tool_calls, render_tool_results, tool_instructions, TaskTools, ToolCall,
+    DEFAULT_MAX_ITERATIONS,
+};
+use crate::llm::{
+    estimate_tokens, resolve_model, CompletionRequest, LlmManager, ProviderKind, StreamEvent, Usage,
+};
+use crate::mcp::protocol::CallToolResult;
+use crate::mcp::McpManager;
+use crate::projects::{snapshot_work_tree, ProjectManager};
+use crate::usage::{NewUsageRecord, UsageManager};
+use futures::StreamExt;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, Ordering};
-use tauri::AppHandle;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+
+/// Streamed model output for a running task
+#[derive(Clone, Serialize)]
+pub struct TaskOutput {
+    #[serde(rename = "taskId")]
+    pub task_id: String,
+    pub text: String,
+}
+
+/// Managers a running task needs, cloned into each spawned task
+#[derive(Clone)]
+struct RuntimeContext {
+    manager: Arc<AgentManager>,
+    projects: Arc<ProjectManager>,
+    llm: Arc<LlmManager>,
+    usage: Arc<UsageManager>,
+    mcp: Arc<McpManager>,
+    app_handle: Option<AppHandle>,
+}
 
 /// Agent runtime manages task execution
 pub struct AgentRuntime {
     manager: Arc<AgentManager>,
+    projects: Arc<ProjectManager>,
+    llm: Arc<LlmManager>,
+    usage: Arc<UsageManager>,
+    mcp: Arc<McpManager>,
     app_handle: Option<AppHandle>,
     is_running: Arc<AtomicBool>,
+    /// Tasks currently executing
+    active: Arc<AtomicUsize>,
+    lock: Option<RuntimeLock>,
 }
 
 impl AgentRuntime {
-    pub fn new(manager: Arc<AgentManager>) -> Self {
+    pub fn new(
+        manager: Arc<AgentManager>,
+        projects: Arc<ProjectManager>,
+        llm: Arc<LlmManager>,
+        usage: Arc<UsageManager>,
+        mcp: Arc<McpManager>,
+    ) -> Self {
         Self {
             manager,
+            projects,
+            llm,
+            usage,
+            mcp,
             app_handle: None,
             is_running: Arc::new(AtomicBool::new(false)),
+            active: Arc::new(AtomicUsize::new(0)),
+            lock: None,
         }
     }
 
@@ -28,13 +98,29 @@ impl AgentRuntime {
         self.app_handle = Some(app_handle);
     }
 
-    /// Start the task scheduler
-    pub fn start(&mut self) {
+    /// Start the task scheduler. The lock is held until the runtime is
+    /// dropped so no other process works the same queue.
+    pub fn start(&mut self, lock: RuntimeLock) {
+        self.lock = Some(lock);
         self.is_running.store(true, Ordering::SeqCst);
 
-        let manager = Arc::clone(&self.manager);
-        let app_handle = self.app_handle.clone();
+        // Anything left in flight by the previous run is picked up again
+        match self.manager.recover_interrupted_tasks() {
+            Ok(0) => {}
+            Ok(count) => log::info!("Requeued {} interrupted task(s)", count),
+            Err(e) => log::error!("Failed to recover interrupted tasks: {}", e),
+        }
+
+        let ctx = RuntimeContext {
+            manager: Arc::clone(&self.manager),
+            projects: Arc::clone(&self.projects),
+            llm: Arc::clone(&self.llm),
+            usage: Arc::clone(&self.usage),
+            mcp: Arc::clone(&self.mcp),
+            app_handle: self.app_handle.clone(),
+        };
         let is_running = Arc::clone(&self.is_running);
+        let active = Arc::clone(&self.active);
 
         // Spawn the scheduler task using tauri's async runtime
         tauri::async_runtime::spawn(async move {
@@ -43,8 +129,11 @@ impl AgentRuntime {
             while is_running.load(Ordering::SeqCst) {
                 interval.tick().await;
 
+                // Raise deadline alerts first so bumped priorities count this tick
+                Self::check_deadlines(&ctx);
+
                 // Check for pending tasks and assign to idle agents
-                if let Err(e) = Self::process_queue(&manager, &app_handle).await {
+                if let Err(e) = Self::process_queue(&ctx, &active).await {
                     log::error!("Error processing task queue: {}", e);
                 }
             }
@@ -56,62 +145,830 @@ impl AgentRuntime {
     }
 
     /// Stop the runtime
-    #[allow(dead_code)]
     pub fn stop(&self) {
         self.is_running.store(false, Ordering::SeqCst);
     }
 
+    /// Stop scheduling and wait up to `timeout` for running tasks to finish.
+    /// Returns how many were still running; they are requeued on next start.
+    pub async fn shutdown(&self, timeout: Duration) -> usize {
+        self.stop();
+
+        let deadline = tokio::time::Instant::now() + timeout;
+        while self.active.load(Ordering::SeqCst) > 0 && tokio::time::Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(250)).await;
+        }
+
+        self.active.load(Ordering::SeqCst)
+    }
+
+    /// Warn about tasks nearing their deadline and mark missed ones overdue
+    fn check_deadlines(ctx: &RuntimeContext) {
+        let notices = match check_deadlines(&ctx.manager, chrono::Utc::now().timestamp()) {
+            Ok(notices) => notices,
+            Err(e) => {
+                log::error!("Error checking task deadlines: {}", e);
+                return;
+            }
+        };
+
+        for notice in notices {
+            log::warn!(
+                "Task {} ({}) is {}; priority now {}",
+                notice.task_id,
+                notice.title,
+                notice.alert.as_str(),
+                notice.priority
+            );
+            if let Some(app) = &ctx.app_handle {
+                let _ = app.emit("agent:task-deadline", &notice);
+            }
+        }
+    }
+
     /// Process the task queue
-    async fn process_queue(
-        manager: &Arc<AgentManager>,
-        _app_handle: &Option<AppHandle>,
-    ) -> Result<(), String> {
-        // Get pending tasks
+    async fn process_queue(ctx: &RuntimeContext, active: &Arc<AtomicUsize>) -> Result<(), String> {
+        let manager = &ctx.manager;
+        let now = chrono::Utc::now().timestamp();
+
+        // Get pending tasks that are due
         let tasks = manager.list_tasks(None)?;
         let pending_tasks: Vec<&Task> = tasks
             .iter()
-            .filter(|t| t.status == "pending")
+            .filter(|t| t.status == "pending" && t.scheduled_for.map_or(true, |at| at <= now))
             .collect();
 
         if pending_tasks.is_empty() {
             return Ok(());
         }
 
+        // Give each project with pending work a turn, within its cap
+        let mut slots = ProjectSlots::new(&ctx.projects.list()?, &tasks);
+        let pending_tasks = fair_order(pending_tasks, &slots);
+
         // Get available agents
         let agents = manager.list_agents()?;
-        let idle_agents: Vec<_> = agents
-            .iter()
-            .filter(|a| a.status == "idle" && a.config.auto_assign)
-            .collect();
+        let mut idle_agents: Vec<&Agent> = agents.iter().filter(|a| a.status == "idle").collect();
+
+        // Route each task to the best-fitting idle agent
+        for task in pending_tasks {
+            if !slots.has_room(task) {
+                continue;
+            }
+            let Some(routing) = route_task(task, &idle_agents, &tasks) else {
+                continue;
+            };
+            let Some(index) = idle_agents.iter().position(|a| a.id == routing.agent_id) else {
+                continue;
+            };
 
-        // Assign tasks to agents
-        for (task, agent) in pending_tasks.iter().zip(idle_agents.iter()) {
-            // Update task status
-            manager.update_task_status(&task.id, "assigned")?;
+            // Leave the task queued while the agent's provider is at its
+            // limits rather than starting it only to block
+            let provider = resolve_model(&idle_agents[index].config.model).provider;
+            if ctx.llm.is_saturated(provider) {
+                continue;
+            }
+            let agent = idle_agents.remove(index);
+
+            // Assign the task
+            manager.assign_task(&task.id, &routing)?;
+            slots.take(task);
+            manager.add_task_log(
+                &task.id,
+                "info",
+                &routing.explanation,
+                Some(serde_json::json!({ "agentId": agent.id, "score": routing.score })),
+            )?;
 
             // Update agent status
             manager.update_agent(&agent.id, None, Some("working"))?;
 
             log::info!("Assigned task {} to agent {}", task.id, agent.id);
 
-            // TODO: Actually execute the task with Claude API
-            // For now, just mark it as completed after a delay
-            let task_id = task.id.clone();
-            let agent_id = agent.id.clone();
-            let manager_clone = Arc::clone(manager);
+            let task = task.clone();
+            let agent = agent.clone();
+            let ctx = ctx.clone();
+            let active = Arc::clone(active);
+            active.fetch_add(1, Ordering::SeqCst);
 
             tauri::async_runtime::spawn(async move {
-                // Simulate task execution
-                tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
-
-                // Mark task as completed
-                let _ = manager_clone.update_task_status(&task_id, "completed");
-                let _ = manager_clone.update_agent(&agent_id, None, Some("idle"));
-
-                log::info!("Task {} completed by agent {}", task_id, agent_id);
+                Self::execute_task(&ctx, &task, &agent).await;
+                let _ = ctx.manager.update_agent(&agent.id, None, Some("idle"));
+                active.fetch_sub(1, Ordering::SeqCst);
             });
         }
 
         Ok(())
     }
+
+    /// Run a single task against the agent's model and store the outcome
+    async fn execute_task(ctx: &RuntimeContext, task: &Task, agent: &Agent) {
+        let manager = &ctx.manager;
+        let started = std::time::Instant::now();
+        let _ = manager.update_task_status(&task.id, "running");
+        let checkpoint = Self::checkpoint_project(ctx, task);
+        let root = Self::project_root(ctx, task);
+        // Changes are measured from the tree as the task found it, not from
+        // HEAD, so the user's uncommitted edits aren't taken for the task's
+        if let (Some(root), None) = (&root, &task.start_commit) {
+            let baseline = checkpoint.or_else(|| snapshot_work_tree(root).ok());
+            if let Some(commit) = baseline {
+                let _ = manager.set_task_start_commit(&task.id, &commit);
+            }
+        }
+
+        let result = match Self::run_completion(ctx, task, agent).await {
+            Ok((output, tokens)) => TaskResult {
+                success: true,
+                output: Some(output),
+                error: None,
+                tokens_used: Some(tokens),
+            },
+            Err(e) => {
+                log::error!("Task {} failed: {}", task.id, e);
+                let _ = manager.add_task_log(&task.id, "error", &e, None);
+                TaskResult {
+                    success: false,
+                    output: None,
+                    error: Some(e),
+                    tokens_used: None,
+                }
+            }
+        };
+
+        if let Some(root) = &root {
+            Self::capture_artifacts(ctx, &task.id, root);
+        }
+
+        if let Err(e) = manager.complete_task(&task.id, &result) {
+            log::error!("Failed to store result for task {}: {}", task.id, e);
+        }
+        let _ = manager.record_task_outcome(
+            &agent.id,
+            result.success,
+            result.tokens_used.unwrap_or(0),
+            started.elapsed().as_secs_f64(),
+        );
+
+        log::info!("Task {} finished by agent {}", task.id, agent.id);
+    }
+
+    fn project_root(ctx: &RuntimeContext, task: &Task) -> Option<PathBuf> {
+        let project_id = task.project_id.as_deref()?;
+        let project = ctx.projects.get(project_id).ok()??;
+        Some(PathBuf::from(project.path))
+    }
+
+    /// Snapshot the task's project so its changes can be rolled back.
+    /// Returns the checkpoint's commit.
+    fn checkpoint_project(ctx: &RuntimeContext, task: &Task) -> Option<String> {
+        let project_id = task.project_id.as_deref()?;
+        let project = ctx.projects.get(project_id).ok()??;
+        if project.settings.checkpoint_tasks == Some(false)
+            || !Path::new(&project.path).join(".git").exists()
+        {
+            return None;
+        }
+
+        let label = format!("Before task \"{}\"", task.title);
+        let created = ctx
+            .projects
+            .create_checkpoint(project_id, &label, Some(&task.id));
+        match created {
+            Ok(checkpoint) => {
+                let _ = ctx.manager.add_task_log(
+                    &task.id,
+                    "info",
+                    "Checkpointed the project before running",
+                    Some(serde_json::json!({ "checkpointId": checkpoint.id, "commit": checkpoint.commit })),
+                );
+                Some(checkpoint.commit)
+            }
+            Err(e) => {
+                let _ = ctx.manager.add_task_log(
+                    &task.id,
+                    "warn",
+                    &format!("Failed to checkpoint the project: {}", e),
+                    None,
+                );
+                None
+            }
+        }
+    }
+
+    /// Store what the run changed in the project since the task's start
+    /// snapshot
+    fn capture_artifacts(ctx: &RuntimeContext, task_id: &str, root: &Path) {
+        let manager = &ctx.manager;
+        let start_commit = match manager.get_task(task_id) {
+            Ok(Some(task)) => task.start_commit,
+            _ => None,
+        };
+        let Some(start_commit) = start_commit else {
+            return;
+        };
+
+        let stored = capture_changes(root, &start_commit)
+            .and_then(|artifacts| manager.replace_changes(task_id, &artifacts));
+        let _ = match stored {
+            Ok(artifacts) if artifacts.is_empty() => Ok(()),
+            Ok(artifacts) => manager.add_task_log(
+                task_id,
+                "info",
+                &format!("Captured changes to {} file(s)", artifacts.len() - 1),
+                Some(serde_json::json!({ "startCommit": start_commit })),
+            ),
+            Err(e) => manager.add_task_log(
+                task_id,
+                "warn",
+                &format!("Failed to capture changes: {}", e),
+                None,
+            ),
+        };
+    }
+
+    async fn run_completion(
+        ctx: &RuntimeContext,
+        task: &Task,
+        agent: &Agent,
+    ) -> Result<(String, i64), String> {
+        let manager = &ctx.manager;
+        let model = resolve_model(&agent.config.model);
+        let budget = PromptBudget::for_model(&model.model_id, agent.config.token_limit);
+        let mut system = agent.config.system_prompt.trim().to_string();
+
+        let (autonomy, autonomy_source) = manager.agent_autonomy(agent)?;
+        manager.add_task_log(
+            &task.id,
+            "debug",
+            &format!("Autonomy: {} (from {})", autonomy.as_str(), autonomy_source),
+            None,
+        )?;
+
+        let tools = TaskTools::load(&ctx.projects, &ctx.mcp, task, agent).await?;
+        for warning in &tools.warnings {
+            manager.add_task_log(&task.id, "warn", warning, None)?;
+        }
+        // Suggest-only agents are not offered tools at all
+        let definitions = if autonomy == AutonomyLevel::Suggest {
+            vec![]
+        } else {
+            tools.definitions()
+        };
+        if !definitions.is_empty() {
+            manager.add_task_log(
+                &task.id,
+                "debug",
+                &format!("{} tool(s) available", definitions.len()),
+                Some(serde_json::json!({
+                    "tools": definitions.iter().map(|t| &t.name).collect::<Vec<_>>(),
+                })),
+            )?;
+        }
+        let instructions = if autonomy == AutonomyLevel::Suggest {
+            Some(SUGGEST_INSTRUCTIONS.to_string())
+        } else {
+            tool_instructions(&definitions)
+        };
+        if let Some(instructions) = instructions {
+            if !system.is_empty() {
+                system.push_str("\n\n");
+            }
+            system.push_str(&instructions);
+        }
+
+        let mut thread = manager.list_task_messages(&task.id)?;
+        if thread.is_empty() {
+            let prompt = assemble_task_prompt(manager, &ctx.projects, task, agent, &model.model_id)?;
+
+            manager.add_task_log(
+                &task.id,
+                "debug",
+                &format!(
+                    "Assembled prompt ({} of {} tokens)",
+                    prompt.breakdown.used_tokens, prompt.breakdown.budget_tokens
+                ),
+                serde_json::to_value(&prompt.breakdown).ok(),
+            )?;
+            thread.push(manager.add_task_message(&task.id, "user", &prompt.user, None)?);
+        } else {
+            manager.add_task_log(
+                &task.id,
+                "info",
+                &format!("Continuing conversation ({} messages)", thread.len()),
+                None,
+            )?;
+        }
+
+        let mut total_tokens = 0;
+        let mut round = 0;
+        let mut tool_round = 0;
+        let max_iterations = agent
+            .config
+            .max_iterations
+            .map_or(DEFAULT_MAX_ITERATIONS, |n| n.max(0) as usize);
+
+        loop {
+            ensure_not_cancelled(manager, &task.id)?;
+
+            // A trailing assistant turn means the app stopped before the
+            // task finished; pick up from its output instead of asking again
+            let output = match thread.last() {
+                Some(last) if last.role == "assistant" => last.content.clone(),
+                _ => {
+                    let request = CompletionRequest {
+                        model: model.model_id.clone(),
+                        system: Some(system.clone()).filter(|s| !s.is_empty()),
+                        messages: fit_to_budget(
+                            to_messages(&thread),
+                            budget.input_tokens.saturating_sub(estimate_tokens(&system)),
+                        ),
+                        max_tokens: budget.max_output_tokens,
+                        temperature: None,
+                        stop_sequences: vec![],
+                    };
+
+                    let (output, usage) =
+                        Self::stream_turn(ctx, task, agent, model.provider, &request).await?;
+                    total_tokens += usage.total() as i64;
+
+                    thread.push(manager.add_task_message(&task.id, "assistant", &output, None)?);
+                    for body in parse_comments(&output) {
+                        manager.add_comment(&task.id, Some(&agent.id), &body)?;
+                    }
+                    output
+                }
+            };
+
+            let calls = parse_tool_calls(&output);
+            if !calls.is_empty() {
+                if tool_round >= max_iterations {
+                    manager.add_task_log(
+                        &task.id,
+                        "warn",
+                        &format!("Stopped after {} tool rounds", tool_round),
+                        None,
+                    )?;
+                    return Ok((output, total_tokens));
+                }
+                tool_round += 1;
+
+                let results =
+                    Self::run_tool_calls(ctx, task, agent, &tools, autonomy, calls).await?;
+                thread.push(manager.add_task_message(
+                    &task.id,
+                    "tool",
+                    &render_tool_results(&results),
+                    Some(serde_json::json!({
+                        "tool": "tool_call",
+                        "calls": results.iter().map(|(name, _)| name).collect::<Vec<_>>(),
+                    })),
+                )?);
+                continue;
+            }
+
+            let requests = parse_delegations(&output);
+            if requests.is_empty() || round >= MAX_DELEGATION_ROUNDS {
+                return Ok((output, total_tokens));
+            }
+            if autonomy == AutonomyLevel::Suggest {
+                manager.add_task_log(
+                    &task.id,
+                    "warn",
+                    "Ignored delegation requests from a suggest-only agent",
+                    None,
+                )?;
+                return Ok((output, total_tokens));
+            }
+            round += 1;
+
+            // Children spawned before an interrupted run are waited on, not recreated
+            let turn_started = thread.last().map(|m| m.created_at).unwrap_or_default();
+            let existing: Vec<Task> = manager
+                .list_child_tasks(&task.id)?
+                .into_iter()
+                .filter(|c| c.created_at >= turn_started)
+                .collect();
+
+            let delegation = if existing.is_empty() {
+                let delegation = spawn_children(manager, task, agent, requests)?;
+                manager.add_task_log(
+                    &task.id,
+                    "info",
+                    &format!(
+                        "Delegated {} subtask(s), {} refused",
+                        delegation.children.len(),
+                        delegation.rejected.len()
+                    ),
+                    Some(serde_json::json!({
+                        "children": delegation.children.iter().map(|c| &c.id).collect::<Vec<_>>(),
+                        "rejected": delegation.rejected.iter().map(|(r, reason)| {
+                            serde_json::json!({ "title": r.title, "reason": reason })
+                        }).collect::<Vec<_>>(),
+                    })),
+                )?;
+                delegation
+            } else {
+                Delegation {
+                    children: existing,
+                    rejected: vec![],
+                }
+            };
+
+            let finished = if delegation.children.is_empty() {
+                vec![]
+            } else {
+                manager.update_task_status(&task.id, "waiting")?;
+                let finished =
+                    wait_for_children(manager, &task.id, &delegation.children).await?;
+                manager.update_task_status(&task.id, "running")?;
+                finished
+            };
+
+            thread.push(manager.add_task_message(
+                &task.id,
+                "tool",
+                &render_results(&finished, &delegation.rejected),
+                Some(serde_json::json!({
+                    "tool": "delegate",
+                    "children": finished.iter().map(|c| &c.id).collect::<Vec<_>>(),
+                })),
+            )?);
+        }
+    }
+
+    /// Run one turn's tool calls in order, logging each. Calls the agent's
+    /// policy denies are reported back to it instead of run.
+    async fn run_tool_calls(
+        ctx: &RuntimeContext,
+        task: &Task,
+        agent: &Agent,
+        tools: &TaskTools,
+        autonomy: AutonomyLevel,
+        calls: Vec<Result<ToolCall, String>>,
+    ) -> Result<Vec<(String, Result<CallToolResult, String>)>, String> {
+        let manager = &ctx.manager;
+        let mut results = Vec::with_capacity(calls.len());
+
+        for call in calls {
+            let call = match call {
+                Ok(call) => call,
+                Err(e) => {
+                    manager.add_task_log(&task.id, "warn", &e, None)?;
+                    results.push(("tool_call".to_string(), Err(e)));
+                    continue;
+                }
+            };
+
+            let decision = Self::authorize(ctx, task, agent, tools, autonomy, &call).await?;
+            if decision.verdict != Verdict::Allow {
+                manager.add_task_log(
+                    &task.id,
+                    "warn",
+                    &format!("Tool {} denied: {}", call.name, decision.reason),
+                    Some(serde_json::json!({ "tool": call.name, "rule": decision.rule })),
+                )?;
+                results.push((call.name, Err(format!("Permission denied: {}", decision.reason))));
+                continue;
+            }
+
+            manager.add_task_log(
+                &task.id,
+                "info",
+                &format!("Calling tool {}", call.name),
+                Some(serde_json::json!({ "tool": call.name, "arguments": call.arguments })),
+            )?;
+
+            // Approval can take a while; the task may be gone by now
+            ensure_not_cancelled(manager, &task.id)?;
+            let started = std::time::Instant::now();
+            let result = tools.call(&call).await;
+            let (level, message) = match &result {
+                Ok(r) if !r.is_error => ("info", format!("Tool {} succeeded", call.name)),
+                Ok(r) => ("warn", format!("Tool {} failed: {}", call.name, r.joined_text())),
+                Err(e) => ("warn", format!("Tool {} failed: {}", call.name, e)),
+            };
+            manager.add_task_log(
+                &task.id,
+                level,
+                &message,
+                Some(serde_json::json!({
+                    "tool": call.name,
+                    "durationMs": started.elapsed().as_millis() as u64,
+                    "outputChars": result.as_ref().map(|r| r.joined_text().len()).unwrap_or(0),
+                })),
+            )?;
+            if call.name == "Bash" {
+                if let (Some(command), Ok(output)) = (call.arguments["command"].as_str(), &result) {
+                    let output = output.joined_text();
+                    manager.add_artifact(&task.id, &transcript(command, &output))?;
+                    for violation in detect_violations(&output, &agent.config.sandbox) {
+                        manager.add_task_log(
+                            &task.id,
+                            "warn",
+                            &format!("Sandbox blocked {}: {}", violation.describe(), violation.detail),
+                            Some(serde_json::json!({
+                                "tool": call.name,
+                                "violation": violation.kind,
+                                "backend": sandbox::backend().as_str(),
+                            })),
+                        )?;
+                    }
+                }
+            }
+            results.push((call.name, result));
+        }
+
+        Ok(results)
+    }
+
+    /// Check a call against the agent's permission policy and autonomy
+    /// level, pausing the task for approval when needed. Every decision is
+    /// audited.
+    async fn authorize(
+        ctx: &RuntimeContext,
+        task: &Task,
+        agent: &Agent,
+        tools: &TaskTools,
+        autonomy: AutonomyLevel,
+        call: &ToolCall,
+    ) -> Result<Decision, String> {
+        let manager = &ctx.manager;
+        let audit = |decision: &str, source: &str, rule: Option<String>, reason: &str, approval_id| {
+            manager.record_tool_decision(&ToolAuditEntry {
+                id: 0,
+                task_id: task.id.clone(),
+                agent_id: agent.id.clone(),
+                tool: call.name.clone(),
+                arguments: call.arguments.clone(),
+                decision: decision.to_string(),
+                source: source.to_string(),
+                rule,
+                reason: Some(reason.to_string()),
+                approval_id,
+                created_at: 0,
+            })
+        };
+
+        let decision = agent
+            .config
+            .permissions
+            .check(call, tools.project_root(), autonomy);
+        if decision.verdict != Verdict::Ask {
+            audit(
+                decision.verdict.as_str(),
+                decision.source,
+                decision.rule.clone(),
+                &decision.reason,
+                None,
+            )?;
+            return Ok(decision);
+        }
+
+        let (approval, created) = manager.request_tool_approval(
+            &task.id,
+            &agent.id,
+            &call.name,
+            &call.arguments,
+            decision.rule.as_deref(),
+        )?;
+        if created {
+            if let Some(app) = &ctx.app_handle {
+                let _ = app.emit("agent:approval-request", &approval);
+            }
+            manager.add_task_log(
+                &task.id,
+                "info",
+                &format!("Waiting for approval to run {}", call.name),
+                Some(serde_json::json!({ "approvalId": approval.id, "rule": decision.rule })),
+            )?;
+        }
+
+        let approval = if approval.status == "pending" {
+            manager.update_task_status(&task.id, "waiting")?;
+            let approval = wait_for_approval(manager, approval).await?;
+            manager.update_task_status(&task.id, "running")?;
+            approval
+        } else {
+            // Decided before the task was interrupted
+            let verdict = if approval.status == "approved" { "allow" } else { "deny" };
+            audit(
+                verdict,
+                "user",
+                approval.rule.clone(),
+                "Decided earlier in this task",
+                Some(approval.id.clone()),
+            )?;
+            approval
+        };
+
+        let approved = approval.status == "approved";
+        Ok(Decision {
+            verdict: if approved { Verdict::Allow } else { Verdict::Deny },
+            source: "user",
+            rule: decision.rule,
+            reason: if approved {
+                "Approved".to_string()
+            } else {
+                "The approval request was denied".to_string()
+            },
+        })
+    }
+
+    /// Stream one model turn, forwarding deltas to the UI and recording usage
+    async fn stream_turn(
+        ctx: &RuntimeContext,
+        task: &Task,
+        agent: &Agent,
+        provider: ProviderKind,
+        request: &CompletionRequest,
+    ) -> Result<(String, Usage), String> {
+        let manager = &ctx.manager;
+        manager.add_task_log(
+            &task.id,
+            "info",
+            &format!("Sending request to {}", request.model),
+            Some(serde_json::json!({ "provider": provider, "model": request.model })),
+        )?;
+
+        let mut stream = ctx.llm.stream(provider, request).await?;
+        let mut output = String::new();
+        let mut usage = Usage::default();
+        let mut stop_reason = None;
+
+        while let Some(event) = stream.next().await {
+            match event? {
+                StreamEvent::Delta { text } => {
+                    manager.publish(TaskEvent::Output {
+                        task_id: task.id.clone(),
+                        text: text.clone(),
+                    });
+                    if let Some(app) = &ctx.app_handle {
+                        let _ = app.emit(
+                            "agent:task-output",
+                            TaskOutput {
+                                task_id: task.id.clone(),
+                                text: text.clone(),
+                            },
+                        );
+                    }
+                    output.push_str(&text);
+                }
+                StreamEvent::Done {
+                    stop_reason: reason,
+                    usage: final_usage,
+                } => {
+                    usage = final_usage;
+                    stop_reason = reason;
+                }
+            }
+        }
+
+        let record = ctx.usage.record(&NewUsageRecord {
+            task_id: Some(&task.id),
+            agent_id: Some(&agent.id),
+            project_id: task.project_id.as_deref(),
+            provider: provider.as_str(),
+            model: &request.model,
+            usage,
+        })?;
+
+        manager.add_task_log(
+            &task.id,
+            "info",
+            "Received response",
+            Some(serde_json::json!({
+                "usage": usage,
+                "costUsd": record.cost_usd,
+                "stopReason": stop_reason,
+            })),
+        )?;
+
+        Ok((output, usage))
+    }
+}
+
+/// Stop a run whose task was cancelled (or deleted) while it was working,
+/// so it doesn't go on calling tools
+fn ensure_not_cancelled(manager: &AgentManager, task_id: &str) -> Result<(), String> {
+    let task = manager.get_task(task_id)?;
+    if task.map_or(true, |t| t.status == "cancelled") {
+        return Err("Task was cancelled".to_string());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::manager::AgentConfig;
+    use crate::db::Database;
+    use crate::llm::{
+        CompletionResponse, CompletionStream, LlmError, LlmProvider, MockProvider, MockRule,
+    };
+    use async_trait::async_trait;
+    use tempfile::tempdir;
+
+    /// The mock backend, cancelling the task as it answers turn `cancel_on`
+    /// the way a user would while the model is working
+    struct CancellingProvider {
+        mock: MockProvider,
+        manager: Arc<AgentManager>,
+        task_id: String,
+        cancel_on: usize,
+        turns: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl LlmProvider for CancellingProvider {
+        async fn complete(
+            &self,
+            request: &CompletionRequest,
+        ) -> Result<CompletionResponse, LlmError> {
+            self.mock.complete(request).await
+        }
+
+        async fn stream(&self, request: &CompletionRequest) -> Result<CompletionStream, LlmError> {
+            if self.turns.fetch_add(1, Ordering::SeqCst) + 1 == self.cancel_on {
+                self.manager.cancel_task(&self.task_id).unwrap();
+            }
+            self.mock.stream(request).await
+        }
+
+        async fn count_tokens(&self, request: &CompletionRequest) -> Result<u64, LlmError> {
+            self.mock.count_tokens(request).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cancelling_stops_the_loop() {
+        let dir = tempdir().unwrap();
+        let db = Database::new(dir.path().join("data")).unwrap();
+        let manager = Arc::new(AgentManager::new(db.clone()));
+        let projects = Arc::new(ProjectManager::new(db.clone()));
+        let root = dir.path().join("repo");
+        std::fs::create_dir(&root).unwrap();
+        let project = projects.add(root.to_str().unwrap(), "code").unwrap();
+
+        let agent = manager
+            .create_agent(
+                AgentConfig {
+                    tools: vec!["Write".to_string()],
+                    autonomy: Some(AutonomyLevel::Autonomous),
+                    ..Default::default()
+                },
+                "Writer",
+                "general",
+            )
+            .unwrap();
+        let task = manager
+            .create_task(&Task {
+                agent_id: Some(agent.id.clone()),
+                project_id: Some(project.id.clone()),
+                ..Task::new("Write notes")
+            })
+            .unwrap();
+        manager.update_task_status(&task.id, "running").unwrap();
+
+        // Every turn asks for another tool call; the second is cancelled
+        // while the model answers it
+        let mock = MockProvider::new(&[MockRule {
+            pattern: ".".to_string(),
+            reply: r#"<tool_call name="Write">{"path": "notes.md", "content": "hi"}</tool_call>"#
+                .to_string(),
+        }])
+        .unwrap();
+        let llm = Arc::new(LlmManager::new(db.clone()));
+        llm.set_provider(
+            resolve_model(&agent.config.model).provider,
+            Arc::new(CancellingProvider {
+                mock,
+                manager: Arc::clone(&manager),
+                task_id: task.id.clone(),
+                cancel_on: 2,
+                turns: AtomicUsize::new(0),
+            }),
+        );
+        let ctx = RuntimeContext {
+            manager: Arc::clone(&manager),
+            projects,
+            llm,
+            usage: Arc::new(UsageManager::new(db.clone())),
+            mcp: Arc::new(McpManager::new(db)),
+            app_handle: None,
+        };
+
+        let result = AgentRuntime::run_completion(&ctx, &task, &agent).await;
+        assert_eq!(result.unwrap_err(), "Task was cancelled");
+
+        let ran = manager
+            .list_task_logs(&task.id)
+            .unwrap()
+            .into_iter()
+            .filter(|log| log.message == "Tool Write succeeded")
+            .count();
+        assert_eq!(ran, 1);
+        let task = manager.get_task(&task.id).unwrap().unwrap();
+        assert_eq!(task.status, "cancelled");
+    }
 }
@@ -0,0 +1,464 @@
+//! Sandbox for agent shell commands
+//!
+//! On Linux, `Bash` tool commands run contained: the project directory is
+//! writable, the rest of the filesystem is read-only, `/tmp` is private and
+//! there is no network unless the agent's `SandboxConfig` allows it. The
+//! sandbox is bubblewrap when `bwrap` works, otherwise user, mount, PID and
+//! network namespaces set up with `unshare`; if any part of that setup
+//! fails, the command doesn't run. CPU time and memory are capped with
+//! rlimits and the wall-clock time by the tool's timeout.
+//!
+//! Which backend works is probed once per process. Elsewhere, or where
+//! neither works, commands run unconfined unless the sandbox is `required`.
+//! Denied writes, blocked network access and hit limits are recognised in
+//! the command output and logged on the task.
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// Exit code of a shell whose command was killed by `SIGXCPU`
+const CPU_LIMIT_EXIT: &str = "Exit code 152";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SandboxMode {
+    /// Contain commands where the platform allows it
+    #[default]
+    Auto,
+    /// Refuse to run commands that can't be contained
+    Required,
+    /// Run commands unconfined
+    Off,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SandboxConfig {
+    #[serde(default)]
+    pub mode: SandboxMode,
+    /// Let commands reach the network
+    #[serde(default)]
+    pub network: bool,
+    /// Data segment limit per process
+    #[serde(rename = "memoryMb", default = "default_memory_mb")]
+    pub memory_mb: Option<u64>,
+    /// CPU time limit per process
+    #[serde(rename = "cpuSecs", default = "default_cpu_secs")]
+    pub cpu_secs: Option<u64>,
+    /// Cap on the wall-clock timeout a command may ask for
+    #[serde(rename = "timeLimitSecs", default)]
+    pub time_limit_secs: Option<u64>,
+    /// Directories besides the project that stay writable, e.g. a build cache
+    #[serde(rename = "writablePaths", default)]
+    pub writable_paths: Vec<String>,
+}
+
+fn default_memory_mb() -> Option<u64> {
+    Some(4096)
+}
+
+fn default_cpu_secs() -> Option<u64> {
+    Some(600)
+}
+
+impl Default for SandboxConfig {
+    fn default() -> Self {
+        Self {
+            mode: SandboxMode::Auto,
+            network: false,
+            memory_mb: default_memory_mb(),
+            cpu_secs: default_cpu_secs(),
+            time_limit_secs: None,
+            writable_paths: vec![],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    Bubblewrap,
+    Namespaces,
+    None,
+}
+
+impl Backend {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Backend::Bubblewrap => "bubblewrap",
+            Backend::Namespaces => "namespaces",
+            Backend::None => "none",
+        }
+    }
+}
+
+/// The sandbox backend that works on this machine
+pub fn backend() -> Backend {
+    static BACKEND: OnceLock<Backend> = OnceLock::new();
+    *BACKEND.get_or_init(probe)
+}
+
+#[cfg(target_os = "linux")]
+fn probe() -> Backend {
+    let works = |program: &str, args: &[&str]| {
+        std::process::Command::new(program)
+            .args(args)
+            .stdin(std::process::Stdio::null())
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success())
+    };
+
+    if works(
+        "bwrap",
+        &["--ro-bind", "/", "/", "--unshare-all", "--", "true"],
+    ) {
+        Backend::Bubblewrap
+    } else if works(
+        "unshare",
+        &["--user", "--map-root-user", "--mount", "--net", "true"],
+    ) {
+        Backend::Namespaces
+    } else {
+        Backend::None
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn probe() -> Backend {
+    Backend::None
+}
+
+/// The shell command that runs `command` in `root` under `config` with
+/// `backend`. Errors when the sandbox is required but unavailable.
+pub fn wrap_command(
+    command: &str,
+    root: &Path,
+    config: &SandboxConfig,
+    backend: Backend,
+) -> Result<String, String> {
+    if config.mode == SandboxMode::Off {
+        return Ok(command.to_string());
+    }
+
+    let mut script = String::new();
+    if let Some(secs) = config.cpu_secs {
+        script.push_str(&format!("ulimit -t {} 2>/dev/null; ", secs));
+    }
+    if let Some(mb) = config.memory_mb {
+        script.push_str(&format!("ulimit -d {} 2>/dev/null; ", mb * 1024));
+    }
+
+    let root = root.to_string_lossy();
+    let writable: Vec<&str> = std::iter::once(root.as_ref())
+        .chain(config.writable_paths.iter().map(String::as_str))
+        .collect();
+
+    match backend {
+        Backend::Bubblewrap => {
+            let mut args =
+                vec!["exec bwrap --ro-bind / / --dev /dev --proc /proc --tmpfs /tmp".to_string()];
+            for path in &writable {
+                args.push(format!("--bind-try {} {}", quote(path), quote(path)));
+            }
+            args.push(
+                "--unshare-user --unshare-pid --unshare-ipc --unshare-uts --unshare-cgroup-try"
+                    .to_string(),
+            );
+            if !config.network {
+                args.push("--unshare-net".to_string());
+            }
+            args.push(format!(
+                "--die-with-parent --chdir {} -- sh -c",
+                quote(&root)
+            ));
+            script.push_str(command);
+            Ok(format!("{} {}", args.join(" "), quote(&script)))
+        }
+        Backend::Namespaces => {
+            // Give the writable paths their own mounts, then make every
+            // mount but those read-only. A step that fails stops the
+            // command rather than letting it run less contained.
+            let mut setup = or_fail("mount --make-rprivate /", "can't make mounts private");
+            for path in &writable {
+                setup.push_str(&format!("p={}; ", quote(path)));
+                setup.push_str(&or_fail(
+                    r#"[ ! -d "$p" ] || mount --rbind "$p" "$p""#,
+                    "can't bind-mount $p",
+                ));
+            }
+            setup.push_str(&remount_all("/proc/self/mountinfo", &writable));
+            // A private /tmp would hide writable paths inside it
+            if !writable.iter().any(|p| Path::new(p).starts_with("/tmp")) {
+                setup.push_str(&or_fail(
+                    "mount -t tmpfs tmpfs /tmp",
+                    "can't mount a private /tmp",
+                ));
+            }
+            setup.push_str(&format!("cd {} || exit 1; ", quote(&root)));
+            setup.push_str(&script);
+            setup.push_str(command);
+
+            let net = if config.network { "" } else { " --net" };
+            Ok(format!(
+                "exec unshare --user --map-root-user --mount --pid --fork --kill-child \
+                 --mount-proc{} -- sh -c {}",
+                net,
+                quote(&setup)
+            ))
+        }
+        Backend::None if config.mode == SandboxMode::Required => Err(
+            "The agent's sandbox is required, but neither bubblewrap nor user namespaces \
+             are available"
+                .to_string(),
+        ),
+        Backend::None => {
+            script.push_str(command);
+            Ok(script)
+        }
+    }
+}
+
+/// The wall-clock timeout for a command that asked for `requested` seconds
+pub fn time_limit(config: &SandboxConfig, requested: u64) -> u64 {
+    match (config.mode, config.time_limit_secs) {
+        (SandboxMode::Off, _) | (_, None) => requested,
+        (_, Some(limit)) => requested.min(limit),
+    }
+}
+
+/// Shell that runs `step`, exiting with `message` if it fails
+fn or_fail(step: &str, message: &str) -> String {
+    format!(
+        "{} || {{ echo \"sandbox: {}\" >&2; exit 1; }}; ",
+        step, message
+    )
+}
+
+/// Shell that remounts every mount listed in `mountinfo` read-only, or
+/// read-write for the `writable` paths, keeping the mount's other flags:
+/// a remount that drops `nosuid` or `nodev` is refused in a user namespace.
+/// Mount points are decoded from the `\NNN` octal escapes mountinfo uses
+/// for spaces and the like. Exits if any remount fails.
+fn remount_all(mountinfo: &str, writable: &[&str]) -> String {
+    let writable: Vec<String> = writable
+        .iter()
+        .map(|p| quote(p.trim_end_matches('/')))
+        .collect();
+    format!(
+        r#"cut -d' ' -f5,6 {mountinfo} | sed 's/\\\([0-7][0-7][0-7]\)/\\0\1/g' | sort -ru |
+while read -r m o; do
+  m=$(printf '%b_' "$m"); m=${{m%_}}
+  case "$m" in {writable}) w=rw ;; *) w=ro ;; esac
+  mount -o "remount,bind,$w${{o#r[ow]}}" "$m" ||
+    {{ echo "sandbox: can't remount $m $w" >&2; exit 1; }}
+done || exit 1
+"#,
+        mountinfo = quote(mountinfo),
+        writable = writable.join("|")
+    )
+}
+
+fn quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+/// Something a sandboxed command was stopped from doing
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SandboxViolation {
+    /// `write`, `network`, `memory`, `cpu`, `timeout` or `setup`
+    pub kind: &'static str,
+    /// The output line it was recognised from
+    pub detail: String,
+}
+
+impl SandboxViolation {
+    pub fn describe(&self) -> &'static str {
+        match self.kind {
+            "write" => "a write outside the writable paths",
+            "network" => "network access",
+            "memory" => "memory use over the limit",
+            "cpu" => "CPU time over the limit",
+            "timeout" => "a run over the time limit",
+            _ => "setting up the sandbox",
+        }
+    }
+}
+
+/// Violations recognisable in a command's output
+pub fn detect_violations(output: &str, config: &SandboxConfig) -> Vec<SandboxViolation> {
+    if config.mode == SandboxMode::Off {
+        return vec![];
+    }
+
+    let mut patterns: Vec<(&'static str, &[&str])> = vec![
+        ("write", &["Read-only file system"]),
+        ("timeout", &["Command timed out after"]),
+        ("setup", &["bwrap: ", "unshare: ", "sandbox: "]),
+    ];
+    if !config.network {
+        patterns.push((
+            "network",
+            &[
+                "Network is unreachable",
+                "Temporary failure in name resolution",
+                "Could not resolve host",
+                "getaddrinfo EAI_AGAIN",
+                "getaddrinfo ENOTFOUND",
+            ],
+        ));
+    }
+    if config.memory_mb.is_some() {
+        patterns.push((
+            "memory",
+            &[
+                "Cannot allocate memory",
+                "memory allocation of",
+                "std::bad_alloc",
+                "MemoryError",
+                "JavaScript heap out of memory",
+            ],
+        ));
+    }
+    if config.cpu_secs.is_some() {
+        patterns.push(("cpu", &["CPU time limit exceeded", CPU_LIMIT_EXIT]));
+    }
+
+    let mut violations: Vec<SandboxViolation> = Vec::new();
+    for line in output.lines() {
+        for (kind, needles) in &patterns {
+            if violations.iter().any(|v| v.kind == *kind) {
+                continue;
+            }
+            if needles.iter().any(|needle| line.contains(needle)) {
+                violations.push(SandboxViolation {
+                    kind,
+                    detail: line.trim().chars().take(300).collect(),
+                });
+            }
+        }
+    }
+    violations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wrap_and_detect() {
+        let root = Path::new("/work/it's");
+        let config = SandboxConfig::default();
+
+        let wrapped = wrap_command("cargo test", root, &config, Backend::Bubblewrap).unwrap();
+        assert!(wrapped.starts_with("exec bwrap --ro-bind / /"));
+        assert!(wrapped.contains("--bind-try '/work/it'\\''s' '/work/it'\\''s'"));
+        assert!(wrapped.contains("--unshare-net"));
+        assert!(wrapped.contains("ulimit -t 600"));
+        assert!(wrapped.ends_with("cargo test'"));
+
+        let online = SandboxConfig {
+            network: true,
+            ..Default::default()
+        };
+        let wrapped = wrap_command("ls", root, &online, Backend::Namespaces).unwrap();
+        assert!(wrapped.starts_with("exec unshare --user"));
+        assert!(!wrapped.contains("--net "));
+
+        let required = SandboxConfig {
+            mode: SandboxMode::Required,
+            ..Default::default()
+        };
+        assert!(wrap_command("ls", root, &required, Backend::None).is_err());
+        let off = SandboxConfig {
+            mode: SandboxMode::Off,
+            time_limit_secs: Some(10),
+            ..Default::default()
+        };
+        assert_eq!(wrap_command("ls", root, &off, Backend::None).unwrap(), "ls");
+        assert_eq!(time_limit(&off, 60), 60);
+        assert_eq!(
+            time_limit(
+                &SandboxConfig {
+                    mode: SandboxMode::Auto,
+                    ..off
+                },
+                60
+            ),
+            10
+        );
+
+        let output = "touch: cannot touch '/etc/x': Read-only file system\n\
+                      curl: (6) Could not resolve host: example.com\n\
+                      touch: cannot touch '/usr/y': Read-only file system\n\
+                      Exit code 152";
+        let kinds: Vec<&str> = detect_violations(output, &config)
+            .iter()
+            .map(|v| v.kind)
+            .collect();
+        assert_eq!(kinds, ["write", "network", "cpu"]);
+        assert!(detect_violations(output, &online)
+            .iter()
+            .all(|v| v.kind != "network"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_remount_fails_closed() {
+        use std::os::unix::fs::PermissionsExt;
+        use std::process::Command;
+
+        let dir = tempfile::tempdir().unwrap();
+        let mountinfo = dir.path().join("mountinfo");
+        std::fs::write(
+            &mountinfo,
+            "22 1 8:1 / / rw,relatime shared:1 - ext4 /dev/sda1 rw\n\
+             40 22 8:1 /work /work/my\\040project rw,nosuid,nodev - ext4 /dev/sda1 rw\n",
+        )
+        .unwrap();
+        // Stands in for mount: logs its arguments, fails when asked to
+        let bin = dir.path().join("bin");
+        std::fs::create_dir(&bin).unwrap();
+        std::fs::write(
+            bin.join("mount"),
+            "#!/bin/sh\necho \"$*\" >> \"$LOG\"\n[ -z \"$FAIL\" ]\n",
+        )
+        .unwrap();
+        std::fs::set_permissions(bin.join("mount"), std::fs::Permissions::from_mode(0o755))
+            .unwrap();
+
+        let script = format!(
+            "{}echo ran",
+            remount_all(&mountinfo.to_string_lossy(), &["/work/my project/"])
+        );
+        let run = |fail: &str| {
+            let log = dir.path().join(format!("log{}", fail));
+            let output = Command::new("sh")
+                .arg("-c")
+                .arg(&script)
+                .env("PATH", format!("{}:/usr/bin:/bin", bin.display()))
+                .env("LOG", &log)
+                .env("FAIL", fail)
+                .output()
+                .unwrap();
+            (output, std::fs::read_to_string(log).unwrap_or_default())
+        };
+
+        let (output, log) = run("");
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "ran\n");
+        assert_eq!(
+            log,
+            "-o remount,bind,rw,nosuid,nodev /work/my project\n-o remount,bind,ro,relatime /\n"
+        );
+
+        let (output, _) = run("1");
+        assert!(!output.status.success());
+        assert!(output.stdout.is_empty());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("sandbox: can't remount /work/my project rw"));
+        let violations = detect_violations(&stderr, &SandboxConfig::default());
+        assert_eq!(violations[0].kind, "setup");
+    }
+}
@@ -0,0 +1,198 @@
+//! Queue order and per-project limits
+//!
+//! Pending tasks are taken in turns across projects so one busy repo can't
+//! hold every agent: the project with the fewest running tasks goes first,
+//! then the next, and so on, each contributing its most urgent task per
+//! turn. A project's `maxConcurrentTasks` setting caps how many of its tasks
+//! run at once; further tasks stay queued until one finishes.
+//!
+//! Tasks in a git project run one at a time whatever the setting: each
+//! task's changes are captured as artifacts by diffing the shared work
+//! tree, which would also pick up the edits of a task running alongside.
+
+use super::manager::{Task, ACTIVE_STATUSES, TASK_PRIORITIES};
+use crate::projects::Project;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Running tasks per project against each project's cap
+pub struct ProjectSlots {
+    caps: HashMap<String, usize>,
+    running: HashMap<String, usize>,
+}
+
+impl ProjectSlots {
+    pub fn new(projects: &[Project], tasks: &[Task]) -> Self {
+        let caps = projects
+            .iter()
+            .filter_map(|p| {
+                let cap = p.settings.max_concurrent_tasks.map(|n| n.max(0) as usize);
+                let cap = if Path::new(&p.path).join(".git").exists() {
+                    cap.map_or(1, |n| n.min(1))
+                } else {
+                    cap?
+                };
+                Some((p.id.clone(), cap))
+            })
+            .collect();
+
+        // Every task with a live run holds one of its project's slots
+        let mut running = HashMap::new();
+        for task in tasks {
+            if let Some(project_id) = &task.project_id {
+                if ACTIVE_STATUSES.contains(&task.status.as_str()) {
+                    *running.entry(project_id.clone()).or_insert(0) += 1;
+                }
+            }
+        }
+
+        Self { caps, running }
+    }
+
+    /// Whether `task`'s project can run another task
+    pub fn has_room(&self, task: &Task) -> bool {
+        let Some(project_id) = &task.project_id else {
+            return true;
+        };
+        self.caps
+            .get(project_id)
+            .map_or(true, |cap| self.running(project_id) < *cap)
+    }
+
+    /// Count `task` as running
+    pub fn take(&mut self, task: &Task) {
+        if let Some(project_id) = &task.project_id {
+            *self.running.entry(project_id.clone()).or_insert(0) += 1;
+        }
+    }
+
+    fn running(&self, project_id: &str) -> usize {
+        self.running.get(project_id).copied().unwrap_or(0)
+    }
+}
+
+/// Order `pending` so projects take turns. Within a project, higher
+/// priority goes first, then older tasks. Tasks without a project form
+/// their own group.
+pub fn fair_order<'a>(pending: Vec<&'a Task>, slots: &ProjectSlots) -> Vec<&'a Task> {
+    let mut groups: Vec<(Option<&str>, Vec<&'a Task>)> = Vec::new();
+    for task in pending {
+        let key = task.project_id.as_deref();
+        match groups.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, tasks)) => tasks.push(task),
+            None => groups.push((key, vec![task])),
+        }
+    }
+
+    for (_, tasks) in &mut groups {
+        tasks.sort_by_key(|t| (std::cmp::Reverse(priority_rank(&t.priority)), t.created_at));
+    }
+    // Least busy project first; ties go to the one waiting longest
+    groups.sort_by_key(|(key, tasks)| {
+        (
+            key.map_or(0, |k| slots.running(k)),
+            tasks.first().map_or(0, |t| t.created_at),
+        )
+    });
+
+    let total = groups.iter().map(|(_, tasks)| tasks.len()).sum();
+    let mut ordered = Vec::with_capacity(total);
+    let mut turn = 0;
+    while ordered.len() < total {
+        for (_, tasks) in &groups {
+            if let Some(task) = tasks.get(turn) {
+                ordered.push(*task);
+            }
+        }
+        turn += 1;
+    }
+    ordered
+}
+
+fn priority_rank(priority: &str) -> usize {
+    TASK_PRIORITIES
+        .iter()
+        .position(|p| *p == priority)
+        .unwrap_or(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn task(id: &str, project: Option<&str>, priority: &str, created_at: i64) -> Task {
+        Task {
+            id: id.to_string(),
+            project_id: project.map(str::to_string),
+            priority: priority.to_string(),
+            created_at,
+            ..Task::new(id)
+        }
+    }
+
+    fn project(id: &str, cap: Option<i32>) -> Project {
+        Project {
+            id: id.to_string(),
+            name: id.to_string(),
+            path: format!("/tmp/{}", id),
+            project_type: "code".to_string(),
+            description: None,
+            git_remote: None,
+            last_opened: 0,
+            created_at: 0,
+            tags: vec![],
+            settings: serde_json::from_value(serde_json::json!({ "maxConcurrentTasks": cap }))
+                .unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_projects_take_turns_within_caps() {
+        let running = Task {
+            status: "running".to_string(),
+            ..task("r", Some("busy"), "normal", 0)
+        };
+        let pending = [
+            task("b1", Some("busy"), "normal", 1),
+            task("b2", Some("busy"), "urgent", 2),
+            task("b3", Some("busy"), "normal", 3),
+            task("q1", Some("quiet"), "low", 4),
+            task("n1", None, "normal", 5),
+        ];
+
+        let projects = [project("busy", Some(2)), project("quiet", None)];
+        let mut slots = ProjectSlots::new(&projects, &[running]);
+        let ordered: Vec<&str> = fair_order(pending.iter().collect(), &slots)
+            .iter()
+            .map(|t| t.id.as_str())
+            .collect();
+        assert_eq!(ordered, ["q1", "n1", "b2", "b1", "b3"]);
+
+        assert!(slots.has_room(&pending[1]));
+        slots.take(&pending[1]);
+        assert!(!slots.has_room(&pending[0]));
+        assert!(slots.has_room(&pending[3]));
+        assert!(slots.has_room(&pending[4]));
+    }
+
+    #[test]
+    fn test_git_projects_run_one_task_at_a_time() {
+        let dir = tempdir().unwrap();
+        std::fs::create_dir(dir.path().join(".git")).unwrap();
+        let repo = Project {
+            path: dir.path().to_string_lossy().into_owned(),
+            ..project("repo", Some(4))
+        };
+
+        let mut slots = ProjectSlots::new(&[repo, project("plain", None)], &[]);
+        let first = task("a", Some("repo"), "normal", 0);
+        assert!(slots.has_room(&first));
+        slots.take(&first);
+        assert!(!slots.has_room(&task("b", Some("repo"), "normal", 1)));
+
+        let plain = task("c", Some("plain"), "normal", 2);
+        slots.take(&plain);
+        assert!(slots.has_room(&plain));
+    }
+}
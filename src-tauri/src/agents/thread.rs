@@ -0,0 +1,139 @@
+//! Conversation threads for tasks
+//!
+//! Turns stored in `task_messages` are replayed to the model on every run,
+//! so a task can be continued with a follow-up or resumed after a restart.
+
+use super::manager::TaskMessage;
+use crate::llm::{estimate_tokens, Message};
+
+/// Convert stored turns into provider messages. Tool turns are sent as user
+/// messages, and consecutive turns from the same side are merged since not
+/// every provider accepts them back to back.
+pub fn to_messages(thread: &[TaskMessage]) -> Vec<Message> {
+    let mut messages: Vec<Message> = Vec::with_capacity(thread.len());
+
+    for turn in thread {
+        let message = match turn.role.as_str() {
+            "assistant" => Message::assistant(turn.content.clone()),
+            _ => Message::user(turn.content.clone()),
+        };
+
+        match messages.last_mut() {
+            Some(last) if last.role == message.role => {
+                last.content.push_str("\n\n");
+                last.content.push_str(&message.content);
+            }
+            _ => messages.push(message),
+        }
+    }
+
+    messages
+}
+
+/// Drop the oldest turns after the first (which carries the task and its
+/// context) until the thread fits in `budget` tokens
+pub fn fit_to_budget(mut messages: Vec<Message>, budget: u64) -> Vec<Message> {
+    let tokens = |messages: &[Message]| -> u64 {
+        messages.iter().map(|m| estimate_tokens(&m.content)).sum()
+    };
+
+    let mut dropped = 0;
+    // Remove in pairs so roles keep alternating after the first message
+    while messages.len() > 3 && tokens(&messages) > budget {
+        messages.drain(1..3);
+        dropped += 2;
+    }
+
+    if dropped > 0 {
+        messages[0].content.push_str(&format!(
+            "\n\n[{} earlier messages in this conversation were omitted to fit the context window]",
+            dropped
+        ));
+    }
+
+    messages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::manager::{AgentConfig, AgentManager, Task};
+    use crate::db::Database;
+    use crate::llm::Message;
+    use tempfile::tempdir;
+
+    fn turn(role: &str, content: &str) -> TaskMessage {
+        TaskMessage {
+            id: 0,
+            task_id: "t".to_string(),
+            role: role.to_string(),
+            content: content.to_string(),
+            metadata: None,
+            created_at: 0,
+        }
+    }
+
+    #[test]
+    fn test_to_messages_merges_roles() {
+        let messages = to_messages(&[
+            turn("user", "Do it"),
+            turn("assistant", "<delegate type=\"research\">Look</delegate>"),
+            turn("tool", "Results: found it"),
+            turn("user", "Now add tests"),
+        ]);
+
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[2].content, "Results: found it\n\nNow add tests");
+    }
+
+    #[test]
+    fn test_fit_to_budget_keeps_first_and_latest() {
+        let long = "x".repeat(400);
+        let messages = vec![
+            Message::user("task"),
+            Message::assistant(long.clone()),
+            Message::user(long.clone()),
+            Message::assistant(long),
+            Message::user("latest"),
+        ];
+
+        let fitted = fit_to_budget(messages, 150);
+        assert_eq!(fitted.len(), 3);
+        assert!(fitted[0].content.starts_with("task"));
+        assert!(fitted[0].content.contains("2 earlier messages"));
+        assert_eq!(fitted[2].content, "latest");
+    }
+
+    #[test]
+    fn test_reply_needs_a_conversation() {
+        let dir = tempdir().unwrap();
+        let manager = AgentManager::new(Database::new(dir.path().to_path_buf()).unwrap());
+        let agent = manager
+            .create_agent(AgentConfig::default(), "Writer", "content")
+            .unwrap();
+        let task = manager
+            .create_task(&Task {
+                agent_id: Some(agent.id.clone()),
+                ..Task::new("Draft the post")
+            })
+            .unwrap();
+        manager.update_task_status(&task.id, "failed").unwrap();
+
+        // Pinned but never run: the reply would replace the task prompt
+        assert!(manager.reply_to_task(&task.id, "Shorter please").is_err());
+
+        manager
+            .add_task_message(&task.id, "user", "Draft the post", None)
+            .unwrap();
+        manager
+            .add_task_message(&task.id, "assistant", "Here it is", None)
+            .unwrap();
+        manager.update_task_status(&task.id, "completed").unwrap();
+        manager.reply_to_task(&task.id, "Shorter please").unwrap();
+        assert_eq!(manager.list_task_messages(&task.id).unwrap().len(), 3);
+        assert_eq!(
+            manager.get_task(&task.id).unwrap().unwrap().status,
+            "pending"
+        );
+    }
+}
@@ -0,0 +1,253 @@
+//! Tool calls made by agents
+//!
+//! Agents call tools by emitting `<tool_call>` blocks whose body is the JSON
+//! arguments:
+//!
+//! ```text
+//! <tool_call name="mcp__docs__search">{"query": "rate limits"}</tool_call>
+//! ```
+//!
+//! The runtime runs each call and sends the results back in a follow-up
+//! turn, the same way delegated results are returned. Tools come from the
+//! built-in set (see `builtin_tools`) and from the agent's MCP servers.
+
+use super::builtin_tools::BuiltinTools;
+use super::manager::{Agent, Task};
+use super::sandbox::{self, Backend, SandboxMode};
+use crate::mcp::protocol::{CallToolResult, Tool};
+use crate::mcp::{McpManager, McpToolset};
+use crate::projects::ProjectManager;
+use serde_json::Value;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Tool rounds a task may run when `AgentConfig.max_iterations` is unset
+pub const DEFAULT_MAX_ITERATIONS: usize = 25;
+/// Tool output beyond this is cut before being handed back to the agent
+const MAX_RESULT_CHARS: usize = 16_000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolCall {
+    pub name: String,
+    pub arguments: Value,
+}
+
+/// The tools one task can call
+pub struct TaskTools {
+    builtins: Option<BuiltinTools>,
+    mcp_tools: McpToolset,
+    mcp: Arc<McpManager>,
+    /// Problems loading tools, worth surfacing in the task log
+    pub warnings: Vec<String>,
+}
+
+impl TaskTools {
+    pub async fn load(
+        projects: &Arc<ProjectManager>,
+        mcp: &Arc<McpManager>,
+        task: &Task,
+        agent: &Agent,
+    ) -> Result<Self, String> {
+        let builtins = match &task.project_id {
+            Some(project_id) => projects.get(project_id)?.and_then(|project| {
+                BuiltinTools::for_project(&agent.config, &project, Arc::clone(projects))
+            }),
+            None => None,
+        };
+
+        let mut warnings = Vec::new();
+        let mcp_tools = match mcp.toolset_for_agent(agent).await {
+            Ok(toolset) => toolset,
+            Err(e) => {
+                warnings.push(format!("Failed to load MCP tools: {}", e));
+                McpToolset::default()
+            }
+        };
+        warnings.extend(
+            mcp_tools
+                .errors
+                .iter()
+                .map(|e| format!("MCP server unavailable: {}", e)),
+        );
+        if builtins.as_ref().is_some_and(|b| b.handles("Bash"))
+            && agent.config.sandbox.mode == SandboxMode::Auto
+            && sandbox::backend() == Backend::None
+        {
+            warnings.push(
+                "No sandbox is available here; shell commands run unconfined".to_string(),
+            );
+        }
+
+        Ok(Self {
+            builtins,
+            mcp_tools,
+            mcp: Arc::clone(mcp),
+            warnings,
+        })
+    }
+
+    /// Root of the task's project, when built-in tools work in one
+    pub fn project_root(&self) -> Option<&Path> {
+        self.builtins.as_ref().map(|b| b.root())
+    }
+
+    pub fn definitions(&self) -> Vec<Tool> {
+        let mut tools = self
+            .builtins
+            .as_ref()
+            .map(|b| b.definitions())
+            .unwrap_or_default();
+        tools.extend(self.mcp_tools.tools.iter().cloned());
+        tools
+    }
+
+    pub async fn call(&self, call: &ToolCall) -> Result<CallToolResult, String> {
+        match &self.builtins {
+            Some(builtins) if builtins.handles(&call.name) => {
+                Ok(builtins.call(&call.name, &call.arguments).await)
+            }
+            _ => {
+                self.mcp
+                    .call(&self.mcp_tools, &call.name, call.arguments.clone())
+                    .await
+            }
+        }
+    }
+}
+
+/// Extract `<tool_call>` blocks from model output. An empty body means no
+/// arguments; a body that isn't a JSON object is kept as an error so the
+/// agent hears about it.
+pub fn parse_tool_calls(output: &str) -> Vec<Result<ToolCall, String>> {
+    let mut calls = Vec::new();
+    let mut rest = output;
+
+    while let Some(start) = rest.find("<tool_call") {
+        rest = &rest[start + "<tool_call".len()..];
+        let Some(tag_end) = rest.find('>') else { break };
+        let name = rest[..tag_end]
+            .split_once("name=\"")
+            .and_then(|(_, value)| value.split_once('"'))
+            .map(|(name, _)| name.trim().to_string());
+        rest = &rest[tag_end + 1..];
+
+        let Some(close) = rest.find("</tool_call>") else {
+            break;
+        };
+        let body = rest[..close].trim();
+        rest = &rest[close + "</tool_call>".len()..];
+
+        let Some(name) = name.filter(|n| !n.is_empty()) else {
+            continue;
+        };
+        let arguments = if body.is_empty() {
+            Ok(Value::Object(Default::default()))
+        } else {
+            serde_json::from_str::<Value>(body)
+                .map_err(|e| e.to_string())
+                .and_then(|v| match v {
+                    Value::Object(_) => Ok(v),
+                    _ => Err("arguments must be a JSON object".to_string()),
+                })
+        };
+
+        calls.push(
+            arguments
+                .map(|arguments| ToolCall {
+                    name: name.clone(),
+                    arguments,
+                })
+                .map_err(|e| format!("Invalid arguments for {}: {}", name, e)),
+        );
+    }
+
+    calls
+}
+
+/// System prompt section listing the tools the agent can call
+pub fn tool_instructions(tools: &[Tool]) -> Option<String> {
+    if tools.is_empty() {
+        return None;
+    }
+
+    let mut out = String::from(
+        "## Tools\n\
+         You can call tools. Emit one block per call, with the arguments as a JSON object, \
+         then stop; the results will be sent back to you.\n\
+         <tool_call name=\"TOOL NAME\">{\"argument\": \"value\"}</tool_call>\n\nAvailable tools:",
+    );
+
+    for tool in tools {
+        out.push_str(&format!("\n\n### {}\n", tool.name));
+        if !tool.description.trim().is_empty() {
+            out.push_str(tool.description.trim());
+            out.push('\n');
+        }
+        out.push_str(&format!("Input schema: {}", tool.input_schema));
+    }
+
+    Some(out)
+}
+
+/// Follow-up message handing tool results back to the agent
+pub fn render_tool_results(results: &[(String, Result<CallToolResult, String>)]) -> String {
+    let mut out = String::from("Results of your tool calls:");
+
+    for (name, result) in results {
+        let (body, is_error) = match result {
+            Ok(result) => (result.joined_text(), result.is_error),
+            Err(e) => (e.clone(), true),
+        };
+
+        if is_error {
+            out.push_str(&format!(
+                "\n\n<tool_result name=\"{}\" error=\"true\">\n",
+                name
+            ));
+        } else {
+            out.push_str(&format!("\n\n<tool_result name=\"{}\">\n", name));
+        }
+        if body.chars().count() > MAX_RESULT_CHARS {
+            out.extend(body.chars().take(MAX_RESULT_CHARS));
+            out.push_str("\n… [truncated]");
+        } else {
+            out.push_str(body.trim());
+        }
+        out.push_str("\n</tool_result>");
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tool_calls() {
+        let output = r#"Let me look that up.
+<tool_call name="mcp__docs__search">{"query": "rate limits"}</tool_call>
+<tool_call name="list_files"></tool_call>
+<tool_call name="broken">{not json}</tool_call>
+<tool_call>{"ignored": true}</tool_call>"#;
+
+        let calls = parse_tool_calls(output);
+        assert_eq!(calls.len(), 3);
+        assert_eq!(
+            calls[0],
+            Ok(ToolCall {
+                name: "mcp__docs__search".to_string(),
+                arguments: serde_json::json!({ "query": "rate limits" }),
+            })
+        );
+        assert_eq!(calls[1].as_ref().unwrap().arguments, serde_json::json!({}));
+        assert!(calls[2].as_ref().unwrap_err().contains("broken"));
+
+        let rendered = render_tool_results(&[
+            ("a".to_string(), Ok(CallToolResult::text("done"))),
+            ("b".to_string(), Err("boom".to_string())),
+        ]);
+        assert!(rendered.contains("<tool_result name=\"a\">\ndone\n</tool_result>"));
+        assert!(rendered.contains("<tool_result name=\"b\" error=\"true\">\nboom"));
+    }
+}
@@ -0,0 +1,29 @@
+//! Tauri commands for api module
+
+use super::manager::{ApiConfig, ApiServer, ApiStatus};
+use std::sync::Arc;
+use tauri::State;
+
+#[tauri::command]
+pub fn api_get_config(server: State<'_, Arc<ApiServer>>) -> Result<ApiConfig, String> {
+    server.get_config()
+}
+
+#[tauri::command]
+pub async fn api_update_config(
+    server: State<'_, Arc<ApiServer>>,
+    enabled: bool,
+    port: u16,
+) -> Result<ApiStatus, String> {
+    server.update_config(enabled, port).await
+}
+
+#[tauri::command]
+pub async fn api_regenerate_token(server: State<'_, Arc<ApiServer>>) -> Result<ApiConfig, String> {
+    server.regenerate_token().await
+}
+
+#[tauri::command]
+pub fn api_status(server: State<'_, Arc<ApiServer>>) -> ApiStatus {
+    server.status()
+}
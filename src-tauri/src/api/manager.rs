@@ -0,0 +1,218 @@
+//! API server manager implementation
+//!
+//! Owns the API settings and starts or stops the HTTP listener to match them.
+
+use super::routes::{router, ApiContext};
+use crate::agents::AgentManager;
+use crate::db::Database;
+use crate::mcp::McpServer;
+use crate::projects::ProjectManager;
+use chrono::Utc;
+use parking_lot::Mutex;
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+use tauri::async_runtime::JoinHandle;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiConfig {
+    pub enabled: bool,
+    pub port: u16,
+    pub token: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ApiStatus {
+    pub running: bool,
+    /// Where the server is listening, e.g. `http://127.0.0.1:7311`
+    pub address: Option<String>,
+}
+
+struct Running {
+    addr: SocketAddr,
+    handle: JoinHandle<()>,
+}
+
+pub struct ApiServer {
+    db: Database,
+    agents: Arc<AgentManager>,
+    projects: Arc<ProjectManager>,
+    running: Mutex<Option<Running>>,
+}
+
+impl ApiServer {
+    pub fn new(db: Database, agents: Arc<AgentManager>, projects: Arc<ProjectManager>) -> Self {
+        Self {
+            db,
+            agents,
+            projects,
+            running: Mutex::new(None),
+        }
+    }
+
+    pub fn get_config(&self) -> Result<ApiConfig, String> {
+        self.db.with_conn(|conn| {
+            conn.query_row(
+                "SELECT enabled, port, token FROM api_settings WHERE id = 1",
+                [],
+                |row| {
+                    Ok(ApiConfig {
+                        enabled: row.get(0)?,
+                        port: row.get(1)?,
+                        token: row.get(2)?,
+                    })
+                },
+            )
+            .map_err(|e| e.to_string())
+        })
+    }
+
+    /// Change whether the API is enabled and its port, then restart it
+    pub async fn update_config(&self, enabled: bool, port: u16) -> Result<ApiStatus, String> {
+        self.db.with_conn(|conn| {
+            conn.execute(
+                "UPDATE api_settings SET enabled = ?1, port = ?2, updated_at = ?3 WHERE id = 1",
+                params![enabled, port, Utc::now().timestamp()],
+            )
+            .map_err(|e| e.to_string())
+        })?;
+
+        self.apply().await
+    }
+
+    /// Issue a new token; clients using the old one are rejected
+    pub async fn regenerate_token(&self) -> Result<ApiConfig, String> {
+        self.db.with_conn(|conn| {
+            conn.execute(
+                "UPDATE api_settings SET token = lower(hex(randomblob(24))), updated_at = ?1 WHERE id = 1",
+                params![Utc::now().timestamp()],
+            )
+            .map_err(|e| e.to_string())
+        })?;
+
+        self.apply().await?;
+        self.get_config()
+    }
+
+    /// Start, restart or stop the server to match the stored settings
+    pub async fn apply(&self) -> Result<ApiStatus, String> {
+        self.stop();
+
+        let config = self.get_config()?;
+        if config.enabled {
+            self.start(&config).await?;
+        }
+
+        Ok(self.status())
+    }
+
+    async fn start(&self, config: &ApiConfig) -> Result<(), String> {
+        // Only reachable from this machine
+        let listener = tokio::net::TcpListener::bind((Ipv4Addr::LOCALHOST, config.port))
+            .await
+            .map_err(|e| format!("Failed to bind API to port {}: {}", config.port, e))?;
+        let addr = listener.local_addr().map_err(|e| e.to_string())?;
+
+        let app = router(ApiContext {
+            agents: Arc::clone(&self.agents),
+            projects: Arc::clone(&self.projects),
+            mcp: Arc::new(McpServer::new(
+                Arc::clone(&self.agents),
+                Arc::clone(&self.projects),
+            )),
+            token: Arc::from(config.token.as_str()),
+        });
+
+        let handle = tauri::async_runtime::spawn(async move {
+            if let Err(e) = axum::serve(listener, app).await {
+                log::error!("API server stopped: {}", e);
+            }
+        });
+
+        log::info!("Local API listening on http://{}", addr);
+        *self.running.lock() = Some(Running { addr, handle });
+        Ok(())
+    }
+
+    pub fn stop(&self) {
+        if let Some(running) = self.running.lock().take() {
+            running.handle.abort();
+            log::info!("Local API stopped");
+        }
+    }
+
+    pub fn status(&self) -> ApiStatus {
+        let running = self.running.lock();
+        ApiStatus {
+            running: running.is_some(),
+            address: running.as_ref().map(|r| format!("http://{}", r.addr)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_tasks_over_http() {
+        let dir = tempdir().unwrap();
+        let db = Database::new(dir.path().to_path_buf()).unwrap();
+        let agents = Arc::new(AgentManager::new(db.clone()));
+        let server = ApiServer::new(
+            db.clone(),
+            Arc::clone(&agents),
+            Arc::new(ProjectManager::new(db)),
+        );
+
+        // Port 0 picks a free port
+        let status = server.update_config(true, 0).await.unwrap();
+        let base = format!("{}/api/v1", status.address.unwrap());
+        let token = server.get_config().unwrap().token;
+        let client = reqwest::Client::new();
+
+        let denied = client.get(format!("{}/tasks", base)).send().await.unwrap();
+        assert_eq!(denied.status(), 401);
+
+        let mut events = agents.subscribe();
+        let created: serde_json::Value = client
+            .post(format!("{}/tasks", base))
+            .bearer_auth(&token)
+            .json(&serde_json::json!({ "title": "Review the diff", "priority": "high" }))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(created["status"], "pending");
+        assert_eq!(
+            events.recv().await.unwrap().task_id(),
+            created["id"].as_str().unwrap()
+        );
+
+        let tasks: Vec<serde_json::Value> = client
+            .get(format!("{}/tasks?status=pending&token={}", base, token))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(tasks.len(), 1);
+
+        let invalid = client
+            .post(format!("{}/tasks", base))
+            .bearer_auth(&token)
+            .json(&serde_json::json!({ "title": "x", "priority": "whenever" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(invalid.status(), 400);
+
+        server.stop();
+        assert!(!server.status().running);
+    }
+}
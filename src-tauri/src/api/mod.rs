@@ -0,0 +1,10 @@
+//! Local HTTP API for Claud.io
+//!
+//! Opt-in JSON API on localhost so git hooks, editors and scripts can submit
+//! and follow tasks without going through the desktop window.
+
+pub mod commands;
+mod manager;
+mod routes;
+
+pub use manager::ApiServer;
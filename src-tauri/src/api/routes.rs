@@ -0,0 +1,453 @@
+//! HTTP routes for the local API
+//!
+//! Every route except `/health` needs the token, sent as
+//! `Authorization: Bearer <token>` or, for `EventSource` clients that can't
+//! set headers, as a `token` query parameter.
+
+use crate::agents::{
+    deadline_report, Agent, AgentManager, Board, SlaReport, TaskComment, Task, TaskMessage, ToolApproval,
+    TASK_PRIORITIES,
+};
+use crate::mcp::McpServer;
+use crate::projects::{Project, ProjectManager};
+use axum::extract::{Path, Query, Request, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::Deserialize;
+use std::convert::Infallible;
+use std::sync::Arc;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::{Stream, StreamExt};
+
+#[derive(Clone)]
+pub struct ApiContext {
+    pub agents: Arc<AgentManager>,
+    pub projects: Arc<ProjectManager>,
+    pub mcp: Arc<McpServer>,
+    pub token: Arc<str>,
+}
+
+pub fn router(ctx: ApiContext) -> Router {
+    let api = Router::new()
+        .route("/projects", get(list_projects).post(add_project))
+        .route("/projects/{id}", get(get_project))
+        .route("/agents", get(list_agents))
+        .route("/agents/{id}", get(get_agent))
+        .route("/tasks", get(list_tasks).post(create_task))
+        .route("/tasks/{id}", get(get_task))
+        .route("/tasks/{id}/cancel", post(cancel_task))
+        .route("/tasks/{id}/messages", get(task_messages))
+        .route("/tasks/{id}/reply", post(reply_to_task))
+        .route("/tasks/{id}/move", post(move_task))
+        .route("/tasks/{id}/comments", get(task_comments).post(add_comment))
+        .route("/board", get(board))
+        .route("/reports/deadlines", get(deadlines_report))
+        .route("/approvals", get(list_approvals))
+        .route("/approvals/{id}", post(decide_approval))
+        .route("/events", get(events))
+        .route("/mcp", post(mcp))
+        .route_layer(middleware::from_fn_with_state(ctx.clone(), require_token))
+        .route("/health", get(|| async { "ok" }))
+        .with_state(ctx);
+
+    Router::new().nest("/api/v1", api)
+}
+
+struct ApiError(StatusCode, String);
+
+impl ApiError {
+    fn bad_request(message: impl Into<String>) -> Self {
+        Self(StatusCode::BAD_REQUEST, message.into())
+    }
+
+    fn not_found(what: &str, id: &str) -> Self {
+        Self(StatusCode::NOT_FOUND, format!("{} not found: {}", what, id))
+    }
+}
+
+/// Manager errors are plain strings; report them as server errors
+impl From<String> for ApiError {
+    fn from(message: String) -> Self {
+        Self(StatusCode::INTERNAL_SERVER_ERROR, message)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.0, Json(serde_json::json!({ "error": self.1 }))).into_response()
+    }
+}
+
+type ApiResult<T> = Result<Json<T>, ApiError>;
+
+async fn require_token(State(ctx): State<ApiContext>, request: Request, next: Next) -> Response {
+    let bearer = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    let query = request
+        .uri()
+        .query()
+        .and_then(|q| q.split('&').find_map(|pair| pair.strip_prefix("token=")));
+
+    match bearer.or(query) {
+        Some(token) if tokens_match(token, &ctx.token) => next.run(request).await,
+        _ => ApiError(
+            StatusCode::UNAUTHORIZED,
+            "Missing or invalid token".to_string(),
+        )
+        .into_response(),
+    }
+}
+
+/// Compare without exiting early on the first differing byte
+fn tokens_match(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+// ============================================================================
+// Projects
+// ============================================================================
+
+#[derive(Deserialize)]
+struct AddProject {
+    path: String,
+    #[serde(rename = "type", default = "default_project_type")]
+    project_type: String,
+}
+
+fn default_project_type() -> String {
+    "unknown".to_string()
+}
+
+async fn list_projects(State(ctx): State<ApiContext>) -> ApiResult<Vec<Project>> {
+    Ok(Json(ctx.projects.list()?))
+}
+
+async fn add_project(
+    State(ctx): State<ApiContext>,
+    Json(body): Json<AddProject>,
+) -> ApiResult<Project> {
+    ctx.projects
+        .add(&body.path, &body.project_type)
+        .map(Json)
+        .map_err(ApiError::bad_request)
+}
+
+async fn get_project(State(ctx): State<ApiContext>, Path(id): Path<String>) -> ApiResult<Project> {
+    ctx.projects
+        .get(&id)?
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found("Project", &id))
+}
+
+// ============================================================================
+// Agents
+// ============================================================================
+
+async fn list_agents(State(ctx): State<ApiContext>) -> ApiResult<Vec<Agent>> {
+    Ok(Json(ctx.agents.list_agents()?))
+}
+
+async fn get_agent(State(ctx): State<ApiContext>, Path(id): Path<String>) -> ApiResult<Agent> {
+    ctx.agents
+        .get_agent(&id)?
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found("Agent", &id))
+}
+
+// ============================================================================
+// Tasks
+// ============================================================================
+
+#[derive(Deserialize)]
+struct TaskQuery {
+    status: Option<String>,
+    #[serde(rename = "agentId")]
+    agent_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct CreateTask {
+    title: String,
+    #[serde(default)]
+    description: String,
+    #[serde(rename = "agentId")]
+    agent_id: Option<String>,
+    #[serde(rename = "projectId")]
+    project_id: Option<String>,
+    priority: Option<String>,
+    #[serde(rename = "targetAgentType")]
+    target_agent_type: Option<String>,
+    #[serde(default)]
+    skills: Vec<String>,
+    #[serde(rename = "scheduledFor")]
+    scheduled_for: Option<i64>,
+    deadline: Option<i64>,
+}
+
+#[derive(Deserialize)]
+struct NewComment {
+    body: String,
+}
+
+#[derive(Deserialize)]
+struct Move {
+    #[serde(rename = "columnId")]
+    column_id: String,
+    #[serde(rename = "beforeTaskId")]
+    before_task_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Reply {
+    content: String,
+}
+
+async fn list_tasks(
+    State(ctx): State<ApiContext>,
+    Query(query): Query<TaskQuery>,
+) -> ApiResult<Vec<Task>> {
+    let mut tasks = ctx.agents.list_tasks(query.agent_id.as_deref())?;
+    if let Some(status) = &query.status {
+        tasks.retain(|t| &t.status == status);
+    }
+    Ok(Json(tasks))
+}
+
+async fn create_task(
+    State(ctx): State<ApiContext>,
+    Json(body): Json<CreateTask>,
+) -> ApiResult<Task> {
+    if body.title.trim().is_empty() {
+        return Err(ApiError::bad_request("Task title is required"));
+    }
+    let priority = body.priority.unwrap_or_else(|| "normal".to_string());
+    if !TASK_PRIORITIES.contains(&priority.as_str()) {
+        return Err(ApiError::bad_request(format!(
+            "Unknown priority '{}'; expected one of {}",
+            priority,
+            TASK_PRIORITIES.join(", ")
+        )));
+    }
+    if let Some(agent_id) = &body.agent_id {
+        ctx.agents
+            .get_agent(agent_id)?
+            .ok_or_else(|| ApiError::not_found("Agent", agent_id))?;
+    }
+    if let Some(project_id) = &body.project_id {
+        ctx.projects
+            .get(project_id)?
+            .ok_or_else(|| ApiError::not_found("Project", project_id))?;
+    }
+
+    let task = ctx.agents.create_task(&Task {
+        agent_id: body.agent_id,
+        project_id: body.project_id,
+        description: body.description,
+        priority,
+        scheduled_for: body.scheduled_for,
+        deadline: body.deadline,
+        target_agent_type: body.target_agent_type,
+        skills: body.skills,
+        ..Task::new(body.title)
+    })?;
+    Ok(Json(task))
+}
+
+async fn get_task(State(ctx): State<ApiContext>, Path(id): Path<String>) -> ApiResult<Task> {
+    ctx.agents
+        .get_task(&id)?
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found("Task", &id))
+}
+
+async fn cancel_task(State(ctx): State<ApiContext>, Path(id): Path<String>) -> ApiResult<Task> {
+    ctx.agents
+        .get_task(&id)?
+        .ok_or_else(|| ApiError::not_found("Task", &id))?;
+    ctx.agents.cancel_task(&id)?;
+    get_task(State(ctx), Path(id)).await
+}
+
+async fn move_task(
+    State(ctx): State<ApiContext>,
+    Path(id): Path<String>,
+    Json(body): Json<Move>,
+) -> ApiResult<Task> {
+    ctx.agents
+        .get_task(&id)?
+        .ok_or_else(|| ApiError::not_found("Task", &id))?;
+    ctx.agents
+        .move_task(&id, &body.column_id, body.before_task_id.as_deref())
+        .map(Json)
+        .map_err(ApiError::bad_request)
+}
+
+async fn task_comments(
+    State(ctx): State<ApiContext>,
+    Path(id): Path<String>,
+) -> ApiResult<Vec<TaskComment>> {
+    Ok(Json(ctx.agents.list_comments(&id)?))
+}
+
+async fn add_comment(
+    State(ctx): State<ApiContext>,
+    Path(id): Path<String>,
+    Json(body): Json<NewComment>,
+) -> ApiResult<TaskComment> {
+    ctx.agents
+        .get_task(&id)?
+        .ok_or_else(|| ApiError::not_found("Task", &id))?;
+    ctx.agents
+        .add_comment(&id, None, &body.body)
+        .map(Json)
+        .map_err(ApiError::bad_request)
+}
+
+async fn board(State(ctx): State<ApiContext>) -> ApiResult<Board> {
+    Ok(Json(ctx.agents.board()?))
+}
+
+async fn task_messages(
+    State(ctx): State<ApiContext>,
+    Path(id): Path<String>,
+) -> ApiResult<Vec<TaskMessage>> {
+    Ok(Json(ctx.agents.list_task_messages(&id)?))
+}
+
+async fn reply_to_task(
+    State(ctx): State<ApiContext>,
+    Path(id): Path<String>,
+    Json(body): Json<Reply>,
+) -> ApiResult<TaskMessage> {
+    ctx.agents
+        .reply_to_task(&id, &body.content)
+        .map(Json)
+        .map_err(ApiError::bad_request)
+}
+
+// ============================================================================
+// Reports
+// ============================================================================
+
+#[derive(Deserialize)]
+struct ReportQuery {
+    since: Option<i64>,
+}
+
+async fn deadlines_report(
+    State(ctx): State<ApiContext>,
+    Query(query): Query<ReportQuery>,
+) -> ApiResult<SlaReport> {
+    Ok(Json(deadline_report(&ctx.agents, &ctx.projects, query.since)?))
+}
+
+// ============================================================================
+// Approvals
+// ============================================================================
+
+#[derive(Deserialize)]
+struct ApprovalQuery {
+    #[serde(rename = "taskId")]
+    task_id: Option<String>,
+    status: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Decide {
+    approve: bool,
+}
+
+async fn list_approvals(
+    State(ctx): State<ApiContext>,
+    Query(query): Query<ApprovalQuery>,
+) -> ApiResult<Vec<ToolApproval>> {
+    Ok(Json(ctx.agents.list_tool_approvals(
+        query.task_id.as_deref(),
+        query.status.as_deref(),
+    )?))
+}
+
+async fn decide_approval(
+    State(ctx): State<ApiContext>,
+    Path(id): Path<String>,
+    Json(body): Json<Decide>,
+) -> ApiResult<ToolApproval> {
+    ctx.agents
+        .get_tool_approval(&id)?
+        .ok_or_else(|| ApiError::not_found("Approval request", &id))?;
+    ctx.agents
+        .decide_tool_approval(&id, body.approve, "user")
+        .map(Json)
+        .map_err(ApiError::bad_request)
+}
+
+// ============================================================================
+// Events
+// ============================================================================
+
+#[derive(Deserialize)]
+struct EventQuery {
+    #[serde(rename = "taskId")]
+    task_id: Option<String>,
+}
+
+/// Server-Sent Events stream of task events, optionally for one task
+async fn events(
+    State(ctx): State<ApiContext>,
+    Query(query): Query<EventQuery>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let stream = BroadcastStream::new(ctx.agents.subscribe()).filter_map(move |event| {
+        match event {
+            Ok(event) => {
+                if query
+                    .task_id
+                    .as_deref()
+                    .is_some_and(|id| id != event.task_id())
+                {
+                    return None;
+                }
+                Event::default().json_data(&event).ok().map(Ok)
+            }
+            // The client fell behind and missed events; tell it to refetch
+            Err(_) => Some(Ok(Event::default().event("lagged").data("{}"))),
+        }
+    });
+
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+// ============================================================================
+// MCP
+// ============================================================================
+
+/// MCP over HTTP: one JSON-RPC message per POST, answered with plain JSON
+async fn mcp(State(ctx): State<ApiContext>, Json(message): Json<serde_json::Value>) -> Response {
+    match ctx.mcp.handle(message) {
+        Some(response) => Json(response).into_response(),
+        None => StatusCode::ACCEPTED.into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokens_match() {
+        assert!(tokens_match("abc123", "abc123"));
+        assert!(!tokens_match("abc124", "abc123"));
+        assert!(!tokens_match("abc", "abc123"));
+    }
+}
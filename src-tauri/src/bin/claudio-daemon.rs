@@ -0,0 +1,8 @@
+//! Headless agent runtime. See `app_lib::daemon`.
+
+fn main() {
+    if let Err(e) = app_lib::daemon::run() {
+        eprintln!("claudio-daemon: {}", e);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,8 @@
+//! Command-line client. See `app_lib::cli`.
+
+fn main() {
+    if let Err(e) = app_lib::cli::run() {
+        eprintln!("claudio: {}", e);
+        std::process::exit(1);
+    }
+}
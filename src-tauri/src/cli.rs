@@ -0,0 +1,632 @@
+//! Command-line client
+//!
+//! `claudio` works on the same database as the desktop app, so tasks added
+//! here are picked up by whichever process runs the agent runtime.
+
+use crate::agents::{import_tasks, AgentManager, ImportFormat, Task};
+use crate::db::{default_app_dir, Database};
+use crate::mcp::McpServer;
+use crate::projects::{Project, ProjectManager};
+use crate::sync::commands::{pull_agents_repo, SyncState};
+use crate::sync::project_discovery::detect_project_type;
+use crate::triggers::TriggerManager;
+use clap::{Parser, Subcommand, ValueEnum};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+#[derive(Parser, Debug)]
+#[command(
+    name = "claudio",
+    version,
+    about = "Manage Claud.io projects, agents and tasks"
+)]
+struct Cli {
+    /// Directory holding the database (defaults to the desktop app's data dir)
+    #[arg(long, global = true, env = "CLAUDIO_DATA_DIR")]
+    data_dir: Option<PathBuf>,
+
+    /// Print JSON instead of a table
+    #[arg(long, global = true)]
+    json: bool,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Queue and inspect tasks
+    #[command(subcommand)]
+    Task(TaskCommand),
+    /// List agents
+    #[command(subcommand)]
+    Agent(AgentCommand),
+    /// Register and list projects
+    #[command(subcommand)]
+    Project(ProjectCommand),
+    /// Sync the agents repository
+    #[command(subcommand)]
+    Sync(SyncCommand),
+    /// Serve MCP over stdio, e.g. `claude mcp add claudio -- claudio mcp`
+    Mcp,
+}
+
+#[derive(Subcommand, Debug)]
+enum TaskCommand {
+    /// Queue a new task
+    Add {
+        title: String,
+        /// Agent name or id; otherwise any auto-assign agent may take it
+        #[arg(long)]
+        agent: Option<String>,
+        /// Project name, id or path
+        #[arg(long)]
+        project: Option<String>,
+        #[arg(long, short)]
+        description: Option<String>,
+        #[arg(long, value_enum, default_value_t = Priority::Normal)]
+        priority: Priority,
+        /// Only let agents of this type take the task
+        #[arg(long = "type")]
+        agent_type: Option<String>,
+        /// Only let agents with this skill take the task (repeatable)
+        #[arg(long = "skill")]
+        skills: Vec<String>,
+    },
+    /// List tasks
+    Ls {
+        #[arg(long, value_enum)]
+        status: Option<Status>,
+        /// Agent name or id
+        #[arg(long)]
+        agent: Option<String>,
+    },
+    /// Show a task with its result and logs
+    Show { id: String },
+    /// Cancel a task and its subtasks
+    Cancel { id: String },
+    /// Move a task to a board column, e.g. `ready` to queue it
+    Move {
+        id: String,
+        column: String,
+        /// Place it above this task instead of at the bottom
+        #[arg(long)]
+        before: Option<String>,
+    },
+    /// Queue tasks from a CSV, JSON or Markdown checklist file
+    Import {
+        file: PathBuf,
+        /// File format; guessed from the extension when omitted
+        #[arg(long, value_enum)]
+        format: Option<Format>,
+        /// Validate only; create nothing
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum AgentCommand {
+    Ls,
+}
+
+#[derive(Subcommand, Debug)]
+enum ProjectCommand {
+    /// Register a project folder
+    Add {
+        path: PathBuf,
+        /// Project type; detected from the folder when omitted
+        #[arg(long = "type")]
+        project_type: Option<String>,
+    },
+    Ls,
+}
+
+#[derive(Subcommand, Debug)]
+enum SyncCommand {
+    /// Pull the agents repository
+    Pull {
+        #[arg(long, env = "CLAUDIO_AGENTS_PATH")]
+        agents_path: Option<PathBuf>,
+    },
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Priority {
+    Low,
+    Normal,
+    High,
+    Urgent,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Format {
+    Csv,
+    Json,
+    #[value(alias = "md")]
+    Markdown,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Status {
+    Backlog,
+    Pending,
+    Assigned,
+    Running,
+    Waiting,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl Priority {
+    fn as_str(self) -> &'static str {
+        match self {
+            Priority::Low => "low",
+            Priority::Normal => "normal",
+            Priority::High => "high",
+            Priority::Urgent => "urgent",
+        }
+    }
+}
+
+impl From<Format> for ImportFormat {
+    fn from(format: Format) -> Self {
+        match format {
+            Format::Csv => ImportFormat::Csv,
+            Format::Json => ImportFormat::Json,
+            Format::Markdown => ImportFormat::Markdown,
+        }
+    }
+}
+
+impl Status {
+    fn as_str(self) -> &'static str {
+        match self {
+            Status::Backlog => "backlog",
+            Status::Pending => "pending",
+            Status::Assigned => "assigned",
+            Status::Running => "running",
+            Status::Waiting => "waiting",
+            Status::Completed => "completed",
+            Status::Failed => "failed",
+            Status::Cancelled => "cancelled",
+        }
+    }
+}
+
+/// Entry point for the `claudio` binary
+pub fn run() -> Result<(), String> {
+    let cli = Cli::parse();
+
+    let app_dir = cli
+        .data_dir
+        .clone()
+        .or_else(default_app_dir)
+        .ok_or("Could not determine the app data directory; pass --data-dir")?;
+
+    let database = Database::new(app_dir).map_err(|e| e.to_string())?;
+    let agents = Arc::new(AgentManager::new(database.clone()));
+    let projects = Arc::new(ProjectManager::new(database.clone()));
+
+    match cli.command {
+        Command::Mcp => McpServer::new(agents, projects).serve_stdio(),
+        Command::Task(command) => run_task(command, cli.json, &agents, &projects),
+        Command::Agent(AgentCommand::Ls) => {
+            let list = agents.list_agents()?;
+            print(cli.json, &list, || {
+                table(
+                    &["ID", "NAME", "TYPE", "STATUS", "MODEL"],
+                    list.iter()
+                        .map(|a| {
+                            vec![
+                                short_id(&a.id),
+                                a.name.clone(),
+                                a.agent_type.clone(),
+                                a.status.clone(),
+                                a.config.model.clone(),
+                            ]
+                        })
+                        .collect(),
+                )
+            })
+        }
+        Command::Project(ProjectCommand::Add { path, project_type }) => {
+            let path = path
+                .canonicalize()
+                .map_err(|e| format!("{}: {}", path.display(), e))?;
+            let project_type =
+                project_type.unwrap_or_else(|| detect_project_type(&path).as_str().to_string());
+
+            let project = projects.add(&path.to_string_lossy(), &project_type)?;
+            print(cli.json, &project, || {
+                format!("Added project {} ({})", project.name, project.id)
+            })
+        }
+        Command::Project(ProjectCommand::Ls) => {
+            let list = projects.list()?;
+            print(cli.json, &list, || {
+                table(
+                    &["ID", "NAME", "TYPE", "PATH"],
+                    list.iter()
+                        .map(|p| {
+                            vec![
+                                short_id(&p.id),
+                                p.name.clone(),
+                                p.project_type.clone(),
+                                p.path.clone(),
+                            ]
+                        })
+                        .collect(),
+                )
+            })
+        }
+        Command::Sync(SyncCommand::Pull { agents_path }) => {
+            let path = agents_path.unwrap_or_else(|| SyncState::new().agents_path);
+            let triggers =
+                TriggerManager::new(database, Arc::clone(&agents), Arc::clone(&projects));
+            let result = pull_agents_repo(&agents, &triggers, &path)?;
+            print(cli.json, &result, || match &result.refresh_error {
+                Some(e) => format!(
+                    "{}, but the agent definitions failed to reload: {}",
+                    result.message, e
+                ),
+                None => result.message.clone(),
+            })
+        }
+    }
+}
+
+fn run_task(
+    command: TaskCommand,
+    json: bool,
+    agents: &AgentManager,
+    projects: &ProjectManager,
+) -> Result<(), String> {
+    match command {
+        TaskCommand::Add {
+            title,
+            agent,
+            project,
+            description,
+            priority,
+            agent_type,
+            skills,
+        } => {
+            let agent_id = agent.map(|a| find_agent(agents, &a)).transpose()?;
+            let project_id = project
+                .map(|p| find_project(projects, &p).map(|p| p.id))
+                .transpose()?;
+
+            let task = agents.create_task(&Task {
+                agent_id,
+                project_id,
+                description: description.unwrap_or_default(),
+                priority: priority.as_str().to_string(),
+                target_agent_type: agent_type,
+                skills,
+                ..Task::new(title)
+            })?;
+            print(json, &task, || format!("Queued task {}", task.id))
+        }
+        TaskCommand::Ls { status, agent } => {
+            let agent_id = agent.map(|a| find_agent(agents, &a)).transpose()?;
+            let mut tasks = agents.list_tasks(agent_id.as_deref())?;
+            if let Some(status) = status {
+                tasks.retain(|t| t.status == status.as_str());
+            }
+
+            print(json, &tasks, || {
+                table(
+                    &["ID", "STATUS", "PRIORITY", "AGENT", "TITLE"],
+                    tasks
+                        .iter()
+                        .map(|t| {
+                            vec![
+                                short_id(&t.id),
+                                t.status.clone(),
+                                t.priority.clone(),
+                                t.agent_id
+                                    .as_deref()
+                                    .map(short_id)
+                                    .unwrap_or_else(|| "-".to_string()),
+                                t.title.clone(),
+                            ]
+                        })
+                        .collect(),
+                )
+            })
+        }
+        TaskCommand::Show { id } => {
+            let mut task = find_task(agents, &id)?;
+            // Task rows don't carry their logs
+            task.logs = agents.list_task_logs(&task.id)?;
+            print(json, &task, || {
+                let mut out = format!(
+                    "{}\n{} [{}, {}]\n",
+                    task.id, task.title, task.status, task.priority
+                );
+                if !task.description.is_empty() {
+                    out.push_str(&format!("\n{}\n", task.description));
+                }
+                if let Some(result) = &task.result {
+                    let text = result
+                        .output
+                        .as_deref()
+                        .or(result.error.as_deref())
+                        .unwrap_or("");
+                    out.push_str(&format!("\n--- result ---\n{}\n", text));
+                }
+                if !task.logs.is_empty() {
+                    out.push_str("\n--- logs ---\n");
+                }
+                for log in &task.logs {
+                    out.push_str(&format!("[{}] {}\n", log.level, log.message));
+                }
+                out.trim_end().to_string()
+            })
+        }
+        TaskCommand::Cancel { id } => {
+            let task = find_task(agents, &id)?;
+            agents.cancel_task(&task.id)?;
+            print(json, &task.id, || format!("Cancelled task {}", task.id))
+        }
+        TaskCommand::Move { id, column, before } => {
+            let task = find_task(agents, &id)?;
+            let before = before
+                .map(|b| find_task(agents, &b).map(|t| t.id))
+                .transpose()?;
+            let task = agents.move_task(&task.id, &column, before.as_deref())?;
+            print(json, &task, || {
+                format!("Moved task {} to {} ({})", task.id, column, task.status)
+            })
+        }
+        TaskCommand::Import {
+            file,
+            format,
+            dry_run,
+        } => {
+            let format = format
+                .map(ImportFormat::from)
+                .or_else(|| ImportFormat::from_path(&file))
+                .ok_or("Could not tell the file format from its extension; pass --format")?;
+            let content =
+                std::fs::read_to_string(&file).map_err(|e| format!("{}: {}", file.display(), e))?;
+
+            let report = import_tasks(agents, projects, format, &content, dry_run)?;
+            print(json, &report, || {
+                if !report.errors.is_empty() {
+                    let mut out = format!(
+                        "{} row(s) have errors; nothing was imported\n",
+                        report.errors.len()
+                    );
+                    for error in &report.errors {
+                        out.push_str(&format!("row {}: {}\n", error.row, error.message));
+                    }
+                    out.trim_end().to_string()
+                } else if report.dry_run {
+                    format!("{} task(s) would be queued", report.tasks.len())
+                } else {
+                    format!("Queued {} task(s)", report.tasks.len())
+                }
+            })?;
+
+            if report.errors.is_empty() {
+                Ok(())
+            } else {
+                Err("Import failed".to_string())
+            }
+        }
+    }
+}
+
+/// Print `value` as JSON, or the human-readable rendering
+fn print<T: Serialize>(
+    json: bool,
+    value: &T,
+    human: impl FnOnce() -> String,
+) -> Result<(), String> {
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(value).map_err(|e| e.to_string())?
+        );
+    } else {
+        println!("{}", human());
+    }
+    Ok(())
+}
+
+fn table(headers: &[&str], rows: Vec<Vec<String>>) -> String {
+    let mut widths: Vec<usize> = headers.iter().map(|h| h.len()).collect();
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let line = |cells: Vec<String>| -> String {
+        cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect::<Vec<_>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+
+    std::iter::once(line(headers.iter().map(|h| h.to_string()).collect()))
+        .chain(rows.into_iter().map(line))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// First block of a UUID, enough to tell rows apart in a table
+fn short_id(id: &str) -> String {
+    id.split('-').next().unwrap_or(id).to_string()
+}
+
+/// Shortest id prefix accepted in place of a full id
+const MIN_ID_PREFIX: usize = 4;
+
+/// Whether `reference` is `id` or a long enough prefix of it
+fn id_matches(id: &str, reference: &str) -> bool {
+    reference.len() >= MIN_ID_PREFIX && id.starts_with(reference)
+}
+
+/// Error for a reference that matches more than one of `ids`
+fn ambiguous<'a>(reference: &str, kind: &str, ids: impl Iterator<Item = &'a str>) -> String {
+    format!(
+        "{} matches more than one {}: {}",
+        reference,
+        kind,
+        ids.map(short_id).collect::<Vec<_>>().join(", ")
+    )
+}
+
+fn find_agent(agents: &AgentManager, name_or_id: &str) -> Result<String, String> {
+    let list = agents.list_agents()?;
+    let matches: Vec<_> = list
+        .iter()
+        .filter(|a| id_matches(&a.id, name_or_id) || a.name.eq_ignore_ascii_case(name_or_id))
+        .collect();
+
+    match matches.as_slice() {
+        [agent] => Ok(agent.id.clone()),
+        [] => Err(format!("No agent named {}", name_or_id)),
+        _ => Err(ambiguous(
+            name_or_id,
+            "agent",
+            matches.iter().map(|a| a.id.as_str()),
+        )),
+    }
+}
+
+fn find_project(projects: &ProjectManager, reference: &str) -> Result<Project, String> {
+    let path = Path::new(reference)
+        .canonicalize()
+        .ok()
+        .map(|p| p.to_string_lossy().to_string());
+
+    let list = projects.list()?;
+    let matches: Vec<_> = list
+        .into_iter()
+        .filter(|p| {
+            id_matches(&p.id, reference)
+                || p.name.eq_ignore_ascii_case(reference)
+                || path.as_deref() == Some(p.path.as_str())
+        })
+        .collect();
+
+    match matches.len() {
+        1 => Ok(matches.into_iter().next().unwrap()),
+        0 => Err(format!("No project named {}", reference)),
+        _ => Err(ambiguous(
+            reference,
+            "project",
+            matches.iter().map(|p| p.id.as_str()),
+        )),
+    }
+}
+
+fn find_task(agents: &AgentManager, id: &str) -> Result<Task, String> {
+    if let Some(task) = agents.get_task(id)? {
+        return Ok(task);
+    }
+
+    // Accept the short ids shown by `task ls`
+    if id.len() < MIN_ID_PREFIX {
+        return Err(format!(
+            "Give at least {} characters of the task id",
+            MIN_ID_PREFIX
+        ));
+    }
+    let matches: Vec<_> = agents
+        .list_tasks(None)?
+        .into_iter()
+        .filter(|t| id_matches(&t.id, id))
+        .collect();
+
+    match matches.len() {
+        1 => Ok(matches.into_iter().next().unwrap()),
+        0 => Err(format!("No task {}", id)),
+        _ => Err(ambiguous(id, "task", matches.iter().map(|t| t.id.as_str()))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_parse_task_add() {
+        Cli::command().debug_assert();
+
+        let cli = Cli::try_parse_from([
+            "claudio",
+            "--json",
+            "task",
+            "add",
+            "--agent",
+            "reviewer",
+            "--project",
+            "foo",
+            "Review the diff",
+        ])
+        .unwrap();
+
+        assert!(cli.json);
+        match cli.command {
+            Command::Task(TaskCommand::Add {
+                title,
+                agent,
+                project,
+                priority,
+                ..
+            }) => {
+                assert_eq!(title, "Review the diff");
+                assert_eq!(agent.as_deref(), Some("reviewer"));
+                assert_eq!(project.as_deref(), Some("foo"));
+                assert_eq!(priority.as_str(), "normal");
+            }
+            other => panic!("unexpected command: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_table_aligns_columns() {
+        let out = table(
+            &["ID", "NAME"],
+            vec![
+                vec!["a".to_string(), "x".to_string()],
+                vec!["long-id".to_string(), "y".to_string()],
+            ],
+        );
+        assert_eq!(out, "ID       NAME\na        x\nlong-id  y");
+    }
+
+    #[test]
+    fn test_find_task_by_prefix() {
+        let dir = tempdir().unwrap();
+        let agents = AgentManager::new(Database::new(dir.path().to_path_buf()).unwrap());
+        for id in ["abcd1234-0000", "abcd5678-0000"] {
+            agents
+                .create_task(&Task {
+                    id: id.to_string(),
+                    ..Task::new("Task")
+                })
+                .unwrap();
+        }
+
+        assert_eq!(find_task(&agents, "abcd1").unwrap().id, "abcd1234-0000");
+        assert!(find_task(&agents, "").is_err());
+        assert!(find_task(&agents, "abc").is_err());
+        let ambiguous = find_task(&agents, "abcd").unwrap_err();
+        assert!(ambiguous.starts_with("abcd matches more than one task"));
+        assert!(ambiguous.contains("abcd1234") && ambiguous.contains("abcd5678"));
+    }
+}
@@ -0,0 +1,256 @@
+//! Headless daemon
+//!
+//! Runs the agent runtime, project watcher and agents repo sync without the
+//! desktop window, against the same database the app uses. Only one process
+//! works the task queue at a time (see `RuntimeLock`).
+
+use crate::agents::{AgentManager, AgentRuntime, RuntimeLock};
+use crate::api::ApiServer;
+use crate::db::{default_app_dir, Database};
+use crate::llm::LlmManager;
+use crate::mcp::McpManager;
+use crate::projects::ProjectManager;
+use crate::sync::commands::{
+    fire_agents_pulled, fire_files_changed, refresh_agent_definitions, SyncState,
+};
+use crate::sync::git_sync::pull_repository;
+use crate::sync::project_discovery::discover_projects;
+use crate::sync::watcher::FolderWatcher;
+use crate::triggers::{watch_commits, TriggerManager, COMMIT_POLL_INTERVAL};
+use crate::usage::UsageManager;
+use clap::Parser;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[derive(Parser, Debug)]
+#[command(
+    name = "claudio-daemon",
+    version,
+    about = "Run Claud.io agents without the desktop app"
+)]
+struct Args {
+    /// Directory holding the database (defaults to the desktop app's data dir)
+    #[arg(long, env = "CLAUDIO_DATA_DIR")]
+    data_dir: Option<PathBuf>,
+
+    /// Folder to watch for new projects
+    #[arg(long, env = "CLAUDIO_PROJECTS_PATH")]
+    projects_path: Option<PathBuf>,
+
+    /// Agents repository to pull periodically
+    #[arg(long, env = "CLAUDIO_AGENTS_PATH")]
+    agents_path: Option<PathBuf>,
+
+    /// Minutes between pulls of the agents repository (0 disables)
+    #[arg(long, env = "CLAUDIO_PULL_INTERVAL_MINS", default_value_t = 15)]
+    pull_interval_mins: u64,
+
+    /// Seconds to wait for running tasks on shutdown before leaving them
+    /// to be requeued on the next start
+    #[arg(long, default_value_t = 60)]
+    shutdown_timeout_secs: u64,
+}
+
+/// Entry point for the `claudio-daemon` binary
+pub fn run() -> Result<(), String> {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+
+    let args = Args::parse();
+    tauri::async_runtime::block_on(serve(args))
+}
+
+async fn serve(args: Args) -> Result<(), String> {
+    let app_dir = args
+        .data_dir
+        .clone()
+        .or_else(default_app_dir)
+        .ok_or("Could not determine the app data directory; pass --data-dir")?;
+
+    let lock = tokio::select! {
+        lock = RuntimeLock::acquire(&app_dir) => lock?,
+        _ = shutdown_signal() => {
+            log::info!("Shutdown requested before the runtime started");
+            return Ok(());
+        }
+    };
+
+    let database = Database::new(app_dir.clone()).map_err(|e| e.to_string())?;
+    let project_manager = Arc::new(ProjectManager::new(database.clone()));
+    let agent_manager = Arc::new(AgentManager::new(database.clone()));
+    let llm_manager = Arc::new(LlmManager::new(database.clone()));
+    let usage_manager = Arc::new(UsageManager::new(database.clone()));
+    let mcp_manager = Arc::new(McpManager::new(database.clone()));
+    let trigger_manager = Arc::new(TriggerManager::new(
+        database.clone(),
+        Arc::clone(&agent_manager),
+        Arc::clone(&project_manager),
+    ));
+    let api_server = ApiServer::new(
+        database,
+        Arc::clone(&agent_manager),
+        Arc::clone(&project_manager),
+    );
+
+    let mut runtime = AgentRuntime::new(
+        Arc::clone(&agent_manager),
+        Arc::clone(&project_manager),
+        llm_manager,
+        usage_manager,
+        mcp_manager,
+    );
+    runtime.start(lock);
+    log::info!("Agent runtime started (data dir: {})", app_dir.display());
+
+    // The local API runs here too when enabled, so its event stream sees
+    // this runtime's tasks
+    if let Err(e) = api_server.apply().await {
+        log::error!("Failed to start local API: {}", e);
+    }
+
+    let defaults = SyncState::new();
+    let projects_path = args.projects_path.unwrap_or(defaults.projects_path);
+    let agents_path = args.agents_path.unwrap_or(defaults.agents_path);
+
+    if let Err(e) = refresh_agent_definitions(&agent_manager, &agents_path) {
+        log::warn!("Failed to load agent definitions: {}", e);
+    }
+
+    let mut watcher = FolderWatcher::new();
+    if projects_path.exists() {
+        register_projects(&project_manager, &projects_path);
+
+        let projects = Arc::clone(&project_manager);
+        let triggers = Arc::clone(&trigger_manager);
+        let root = projects_path.clone();
+        watcher.start_watching(projects_path.clone(), move |paths| {
+            register_projects(&projects, &root);
+            fire_files_changed(&triggers, paths);
+        })?;
+    } else {
+        log::warn!(
+            "Projects folder {} does not exist; not watching",
+            projects_path.display()
+        );
+    }
+
+    let commit_watcher = watch_commits(
+        Arc::clone(&trigger_manager),
+        Arc::clone(&project_manager),
+        COMMIT_POLL_INTERVAL,
+    );
+    let puller = (args.pull_interval_mins > 0).then(|| {
+        let interval = Duration::from_secs(args.pull_interval_mins * 60);
+        tauri::async_runtime::spawn(pull_periodically(
+            agent_manager,
+            trigger_manager,
+            agents_path,
+            interval,
+        ))
+    });
+
+    shutdown_signal().await;
+    log::info!("Shutting down");
+
+    watcher.stop_watching();
+    commit_watcher.abort();
+    api_server.stop();
+    if let Some(puller) = puller {
+        puller.abort();
+    }
+
+    let remaining = runtime
+        .shutdown(Duration::from_secs(args.shutdown_timeout_secs))
+        .await;
+    if remaining > 0 {
+        log::warn!(
+            "{} task(s) still running; they will be requeued on next start",
+            remaining
+        );
+    }
+
+    // Dropping the runtime releases the lock
+    drop(runtime);
+    log::info!("Stopped");
+    Ok(())
+}
+
+#[cfg(unix)]
+async fn shutdown_signal() {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    match signal(SignalKind::terminate()) {
+        Ok(mut terminate) => {
+            tokio::select! {
+                _ = terminate.recv() => {}
+                _ = tokio::signal::ctrl_c() => {}
+            }
+        }
+        Err(e) => {
+            log::warn!("Failed to listen for SIGTERM: {}", e);
+            let _ = tokio::signal::ctrl_c().await;
+        }
+    }
+}
+
+#[cfg(not(unix))]
+async fn shutdown_signal() {
+    let _ = tokio::signal::ctrl_c().await;
+}
+
+/// Add projects found under `root` that are not registered yet
+fn register_projects(projects: &ProjectManager, root: &Path) {
+    let discovered = match discover_projects(root) {
+        Ok(discovered) => discovered,
+        Err(e) => {
+            log::warn!("Project discovery failed: {}", e);
+            return;
+        }
+    };
+
+    let known: HashSet<String> = match projects.list() {
+        Ok(list) => list.into_iter().map(|p| p.path).collect(),
+        Err(e) => {
+            log::warn!("Failed to list projects: {}", e);
+            return;
+        }
+    };
+
+    for project in discovered.into_iter().filter(|p| !known.contains(&p.path)) {
+        match projects.add(&project.path, project.project_type.as_str()) {
+            Ok(added) => log::info!("Registered project {} ({})", added.name, added.path),
+            Err(e) => log::warn!("Failed to register {}: {}", project.path, e),
+        }
+    }
+}
+
+async fn pull_periodically(
+    agents: Arc<AgentManager>,
+    triggers: Arc<TriggerManager>,
+    agents_path: PathBuf,
+    interval: Duration,
+) {
+    let mut ticker = tokio::time::interval(interval);
+    loop {
+        ticker.tick().await;
+
+        if !agents_path.join(".git").exists() {
+            continue;
+        }
+
+        let path = agents_path.clone();
+        match tokio::task::spawn_blocking(move || pull_repository(&path)).await {
+            Ok(Ok(result)) if result.files_changed > 0 => {
+                log::info!("Pulled agents repo: {}", result.message);
+                if let Err(e) = refresh_agent_definitions(&agents, &agents_path) {
+                    log::warn!("Failed to load agent definitions: {}", e);
+                }
+                fire_agents_pulled(&triggers, &result);
+            }
+            Ok(Ok(_)) => log::debug!("Agents repo up to date"),
+            Ok(Err(e)) => log::warn!("Agents repo pull failed: {}", e),
+            Err(e) => log::warn!("Agents repo pull panicked: {}", e),
+        }
+    }
+}
@@ -0,0 +1,16 @@
+-- Migration 005: LLM provider configuration
+
+CREATE TABLE IF NOT EXISTS llm_providers (
+    kind TEXT PRIMARY KEY,  -- 'anthropic', 'openai', 'ollama'
+    base_url TEXT NOT NULL,
+    api_key TEXT,           -- Falls back to the provider's env var when NULL
+    enabled INTEGER NOT NULL DEFAULT 1,
+    timeout_secs INTEGER NOT NULL DEFAULT 120,
+    updated_at INTEGER NOT NULL
+);
+
+INSERT OR IGNORE INTO llm_providers (kind, base_url, updated_at)
+VALUES
+    ('anthropic', 'https://api.anthropic.com', strftime('%s', 'now')),
+    ('openai', 'https://api.openai.com/v1', strftime('%s', 'now')),
+    ('ollama', 'http://localhost:11434', strftime('%s', 'now'));
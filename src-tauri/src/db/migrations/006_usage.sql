@@ -0,0 +1,45 @@
+-- Migration 006: Model pricing and per-call usage records
+
+CREATE TABLE IF NOT EXISTS model_pricing (
+    model_id TEXT PRIMARY KEY,           -- Exact id or prefix (e.g. 'claude-sonnet-4-5')
+    provider TEXT NOT NULL,
+    input_per_mtok REAL NOT NULL,        -- USD per million tokens
+    output_per_mtok REAL NOT NULL,
+    cache_write_per_mtok REAL NOT NULL DEFAULT 0,
+    cache_read_per_mtok REAL NOT NULL DEFAULT 0,
+    updated_at INTEGER NOT NULL
+);
+
+INSERT OR IGNORE INTO model_pricing
+    (model_id, provider, input_per_mtok, output_per_mtok, cache_write_per_mtok, cache_read_per_mtok, updated_at)
+VALUES
+    ('claude-opus-4-1', 'anthropic', 15.0, 75.0, 18.75, 1.5, strftime('%s', 'now')),
+    ('claude-sonnet-4-5', 'anthropic', 3.0, 15.0, 3.75, 0.3, strftime('%s', 'now')),
+    ('claude-haiku-4-5', 'anthropic', 1.0, 5.0, 1.25, 0.1, strftime('%s', 'now')),
+    ('gpt-4.1', 'openai', 2.0, 8.0, 0, 0.5, strftime('%s', 'now')),
+    ('gpt-4.1-mini', 'openai', 0.4, 1.6, 0, 0.1, strftime('%s', 'now')),
+    ('gpt-4o', 'openai', 2.5, 10.0, 0, 1.25, strftime('%s', 'now')),
+    ('gpt-4o-mini', 'openai', 0.15, 0.6, 0, 0.075, strftime('%s', 'now'));
+
+-- One row per LLM call
+CREATE TABLE IF NOT EXISTS usage_records (
+    id TEXT PRIMARY KEY,
+    task_id TEXT,
+    agent_id TEXT,
+    project_id TEXT,
+    provider TEXT NOT NULL,
+    model TEXT NOT NULL,
+    input_tokens INTEGER NOT NULL DEFAULT 0,
+    output_tokens INTEGER NOT NULL DEFAULT 0,
+    cache_creation_tokens INTEGER NOT NULL DEFAULT 0,
+    cache_read_tokens INTEGER NOT NULL DEFAULT 0,
+    cost_usd REAL NOT NULL DEFAULT 0,
+    created_at INTEGER NOT NULL,
+    FOREIGN KEY (task_id) REFERENCES tasks(id) ON DELETE SET NULL,
+    FOREIGN KEY (agent_id) REFERENCES agents(id) ON DELETE SET NULL,
+    FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE SET NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_usage_created ON usage_records(created_at);
+CREATE INDEX IF NOT EXISTS idx_usage_agent ON usage_records(agent_id);
+CREATE INDEX IF NOT EXISTS idx_usage_project ON usage_records(project_id);
@@ -0,0 +1,7 @@
+-- Migration 007: Parent/child task links for agent delegation
+
+ALTER TABLE tasks ADD COLUMN parent_task_id TEXT REFERENCES tasks(id) ON DELETE CASCADE;
+ALTER TABLE tasks ADD COLUMN depth INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE tasks ADD COLUMN target_agent_type TEXT;  -- Any idle agent of this type may pick it up
+
+CREATE INDEX IF NOT EXISTS idx_tasks_parent ON tasks(parent_task_id);
@@ -0,0 +1,9 @@
+-- Migration 009: Per-provider rate limits (NULL means unlimited)
+
+ALTER TABLE llm_providers ADD COLUMN requests_per_minute INTEGER;
+ALTER TABLE llm_providers ADD COLUMN tokens_per_minute INTEGER;
+ALTER TABLE llm_providers ADD COLUMN max_concurrent INTEGER;
+
+-- A local model server handles one generation at a time well
+UPDATE llm_providers SET max_concurrent = 1 WHERE kind = 'ollama';
+UPDATE llm_providers SET max_concurrent = 4 WHERE kind IN ('anthropic', 'openai');
@@ -0,0 +1,12 @@
+-- Migration 010: Local HTTP API settings (single row)
+
+CREATE TABLE IF NOT EXISTS api_settings (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    enabled INTEGER NOT NULL DEFAULT 0,
+    port INTEGER NOT NULL DEFAULT 7311,
+    token TEXT NOT NULL,
+    updated_at INTEGER NOT NULL
+);
+
+INSERT OR IGNORE INTO api_settings (id, token, updated_at)
+VALUES (1, lower(hex(randomblob(24))), strftime('%s', 'now'));
@@ -0,0 +1,15 @@
+-- Migration 011: MCP servers agents can use tools from
+
+CREATE TABLE IF NOT EXISTS mcp_servers (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE,  -- Prefix of the server's tool names: mcp__<name>__<tool>
+    command TEXT NOT NULL,
+    args TEXT NOT NULL DEFAULT '[]',
+    env TEXT NOT NULL DEFAULT '{}',
+    agent_id TEXT,              -- NULL for servers any agent can opt into
+    enabled INTEGER NOT NULL DEFAULT 1,
+    created_at INTEGER NOT NULL,
+    FOREIGN KEY (agent_id) REFERENCES agents(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_mcp_servers_agent ON mcp_servers(agent_id);
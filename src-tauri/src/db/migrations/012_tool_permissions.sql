@@ -0,0 +1,34 @@
+-- Migration 012: Tool approvals and the audit trail of permission decisions
+
+CREATE TABLE IF NOT EXISTS tool_approvals (
+    id TEXT PRIMARY KEY,
+    task_id TEXT NOT NULL,
+    agent_id TEXT NOT NULL,
+    tool TEXT NOT NULL,
+    arguments TEXT NOT NULL,
+    rule TEXT,                  -- The "ask" rule that matched
+    status TEXT NOT NULL DEFAULT 'pending',  -- 'pending', 'approved', 'denied'
+    created_at INTEGER NOT NULL,
+    decided_at INTEGER,
+    FOREIGN KEY (task_id) REFERENCES tasks(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_tool_approvals_task ON tool_approvals(task_id);
+CREATE INDEX IF NOT EXISTS idx_tool_approvals_status ON tool_approvals(status);
+
+CREATE TABLE IF NOT EXISTS tool_audit (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    task_id TEXT NOT NULL,
+    agent_id TEXT NOT NULL,
+    tool TEXT NOT NULL,
+    arguments TEXT NOT NULL,
+    decision TEXT NOT NULL,     -- 'allow', 'deny', 'ask'
+    source TEXT NOT NULL,       -- 'policy', 'scope', 'user', 'timeout'
+    rule TEXT,
+    reason TEXT,
+    approval_id TEXT,
+    created_at INTEGER NOT NULL,
+    FOREIGN KEY (task_id) REFERENCES tasks(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_tool_audit_task ON tool_audit(task_id, id);
@@ -0,0 +1,4 @@
+-- Migration 013: Skill-based task routing
+
+ALTER TABLE tasks ADD COLUMN skills TEXT;   -- JSON array of skills the task needs
+ALTER TABLE tasks ADD COLUMN routing TEXT;  -- JSON RoutingDecision: why the agent was chosen
@@ -0,0 +1,16 @@
+-- Migration 014: Rules that create tasks from app events
+
+CREATE TABLE IF NOT EXISTS triggers (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    event TEXT NOT NULL,                   -- files_changed, commit, claude_state, agents_pulled
+    filter TEXT NOT NULL DEFAULT '{}',     -- JSON TriggerFilter
+    template TEXT NOT NULL,                -- JSON TaskTemplate
+    debounce_secs INTEGER NOT NULL DEFAULT 60,
+    enabled INTEGER NOT NULL DEFAULT 1,
+    last_fired_at INTEGER,
+    fire_count INTEGER NOT NULL DEFAULT 0,
+    created_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_triggers_event ON triggers(event);
@@ -0,0 +1,4 @@
+-- Migration 015: Deadline monitoring
+
+ALTER TABLE tasks ADD COLUMN deadline_warned_at INTEGER;  -- When the task was flagged at risk
+ALTER TABLE tasks ADD COLUMN overdue_at INTEGER;          -- When the task was marked overdue
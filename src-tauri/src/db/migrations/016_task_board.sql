@@ -0,0 +1,23 @@
+-- Migration 016: Task board columns and manual ordering
+
+ALTER TABLE tasks ADD COLUMN board_rank REAL;    -- Manual order within a column, ascending
+ALTER TABLE tasks ADD COLUMN board_column TEXT;  -- Column the task was last moved to by hand
+
+UPDATE tasks SET board_rank = rowid;
+
+CREATE TABLE IF NOT EXISTS board_columns (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    statuses TEXT NOT NULL,   -- JSON array of task statuses shown in the column
+    drop_status TEXT,         -- Status a task gets when moved in; NULL if tasks can't be moved in by hand
+    wip_limit INTEGER,
+    position INTEGER NOT NULL
+);
+
+INSERT OR IGNORE INTO board_columns (id, name, statuses, drop_status, wip_limit, position) VALUES
+    ('backlog', 'Backlog', '["backlog"]', 'backlog', NULL, 0),
+    ('ready', 'Ready', '["pending"]', 'pending', NULL, 1),
+    ('in-progress', 'In progress', '["assigned","running","waiting"]', NULL, NULL, 2),
+    ('done', 'Done', '["completed"]', 'completed', NULL, 3),
+    ('failed', 'Failed', '["failed"]', NULL, NULL, 4),
+    ('cancelled', 'Cancelled', '["cancelled"]', 'cancelled', NULL, 5);
@@ -0,0 +1,22 @@
+-- Migration 018: Artifacts produced by tasks
+
+ALTER TABLE tasks ADD COLUMN start_commit TEXT;  -- Project HEAD when the task first ran
+
+CREATE TABLE IF NOT EXISTS task_artifacts (
+    id TEXT PRIMARY KEY,
+    task_id TEXT NOT NULL,
+    kind TEXT NOT NULL,            -- 'diff', 'file', 'transcript'
+    path TEXT,                     -- Project-relative path for file artifacts
+    change TEXT,                   -- 'added', 'modified', 'deleted' for file artifacts
+    content BLOB,                  -- File contents after the task, the diff, or the transcript
+    original BLOB,                 -- File contents at the start commit
+    patch TEXT,                    -- Unified diff for one file
+    size INTEGER NOT NULL DEFAULT 0,
+    binary INTEGER NOT NULL DEFAULT 0,
+    status TEXT NOT NULL DEFAULT 'pending',  -- 'pending', 'applied', 'discarded'
+    created_at INTEGER NOT NULL,
+    decided_at INTEGER,
+    FOREIGN KEY (task_id) REFERENCES tasks(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_task_artifacts_task ON task_artifacts(task_id, created_at);
@@ -0,0 +1,58 @@
+-- Migration 019: Agent evaluation suites and runs
+
+CREATE TABLE IF NOT EXISTS eval_suites (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    description TEXT NOT NULL DEFAULT '',
+    mock_responses TEXT NOT NULL DEFAULT '[]',  -- JSON rules for the mock model
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS eval_cases (
+    id TEXT PRIMARY KEY,
+    suite_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    input TEXT NOT NULL,
+    checks TEXT NOT NULL DEFAULT '[]',  -- JSON array of checks
+    position INTEGER NOT NULL,
+    FOREIGN KEY (suite_id) REFERENCES eval_suites(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_eval_cases_suite ON eval_cases(suite_id, position);
+
+CREATE TABLE IF NOT EXISTS eval_runs (
+    id TEXT PRIMARY KEY,
+    suite_id TEXT NOT NULL,
+    agent_id TEXT,
+    revision TEXT NOT NULL,        -- Hash of the agent config that ran
+    config TEXT NOT NULL,          -- The agent config as JSON
+    model TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'running',  -- 'running', 'completed', 'failed'
+    score REAL,
+    passed INTEGER NOT NULL DEFAULT 0,
+    total INTEGER NOT NULL DEFAULT 0,
+    error TEXT,
+    started_at INTEGER NOT NULL,
+    completed_at INTEGER,
+    FOREIGN KEY (suite_id) REFERENCES eval_suites(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_eval_runs_suite ON eval_runs(suite_id, started_at);
+
+CREATE TABLE IF NOT EXISTS eval_results (
+    id TEXT PRIMARY KEY,
+    run_id TEXT NOT NULL,
+    case_id TEXT NOT NULL,
+    case_name TEXT NOT NULL,
+    output TEXT NOT NULL DEFAULT '',
+    score REAL NOT NULL,
+    passed INTEGER NOT NULL,
+    checks TEXT NOT NULL DEFAULT '[]',  -- JSON array of check outcomes
+    tokens INTEGER NOT NULL DEFAULT 0,
+    duration_ms INTEGER NOT NULL DEFAULT 0,
+    error TEXT,
+    FOREIGN KEY (run_id) REFERENCES eval_runs(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_eval_results_run ON eval_results(run_id);
@@ -9,6 +9,15 @@ use std::path::PathBuf;
 use std::sync::Arc;
 use parking_lot::Mutex;
 
+/// Bundle identifier from `tauri.conf.json`; names the app data directory
+pub const APP_IDENTIFIER: &str = "io.claud.desktop";
+
+/// The app data directory the desktop app uses, for processes that run
+/// without a Tauri `AppHandle`
+pub fn default_app_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join(APP_IDENTIFIER))
+}
+
 /// Database wrapper with thread-safe connection
 pub struct Database {
     conn: Arc<Mutex<Connection>>,
@@ -23,8 +32,9 @@ impl Database {
 
         let conn = Connection::open(&db_path)?;
 
-        // Enable WAL mode for better concurrent access
-        conn.execute_batch("PRAGMA journal_mode=WAL; PRAGMA foreign_keys=ON;")?;
+        // Enable WAL mode for better concurrent access; the desktop app and
+        // claudio-daemon may have the database open at the same time
+        conn.execute_batch("PRAGMA journal_mode=WAL; PRAGMA foreign_keys=ON; PRAGMA busy_timeout=5000;")?;
 
         let db = Self {
             conn: Arc::new(Mutex::new(conn)),
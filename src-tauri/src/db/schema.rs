@@ -10,6 +10,21 @@ pub fn run_migrations(conn: &Connection) -> Result<()> {
         ("002_agents", include_str!("migrations/002_agents.sql")),
         ("003_content", include_str!("migrations/003_content.sql")),
         ("004_sync", include_str!("migrations/004_sync.sql")),
+        ("005_llm", include_str!("migrations/005_llm.sql")),
+        ("006_usage", include_str!("migrations/006_usage.sql")),
+        ("007_delegation", include_str!("migrations/007_delegation.sql")),
+        ("008_task_messages", include_str!("migrations/008_task_messages.sql")),
+        ("009_rate_limits", include_str!("migrations/009_rate_limits.sql")),
+        ("010_api", include_str!("migrations/010_api.sql")),
+        ("011_mcp_servers", include_str!("migrations/011_mcp_servers.sql")),
+        ("012_tool_permissions", include_str!("migrations/012_tool_permissions.sql")),
+        ("013_task_routing", include_str!("migrations/013_task_routing.sql")),
+        ("014_triggers", include_str!("migrations/014_triggers.sql")),
+        ("015_deadlines", include_str!("migrations/015_deadlines.sql")),
+        ("016_task_board", include_str!("migrations/016_task_board.sql")),
+        ("017_task_comments", include_str!("migrations/017_task_comments.sql")),
+        ("018_task_artifacts", include_str!("migrations/018_task_artifacts.sql")),
+        ("019_evals", include_str!("migrations/019_evals.sql")),
     ];
 
     for (name, sql) in migrations {
@@ -0,0 +1,405 @@
+//! Expected-output checks
+//!
+//! Exact, regex and JSON-schema checks are decided here. LLM-graded checks
+//! need a model, so the runner sends `grading_prompt` and scores the reply
+//! with `parse_grade`.
+//!
+//! The JSON-schema check supports the keywords golden outputs need: `type`,
+//! `enum`, `const`, `properties`, `required`, `additionalProperties`,
+//! `items`, `minItems`, `maxItems`, `minLength`, `maxLength`, `pattern`,
+//! `minimum`, `maximum`, `anyOf` and `allOf`. Other keywords are ignored.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// System prompt for LLM-graded checks
+pub const GRADER_SYSTEM: &str = "You grade answers against a rubric. Reply with only a JSON \
+     object: {\"score\": <number from 0 to 1>, \"reason\": \"<one sentence>\"}.";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum EvalCheck {
+    /// Output equals `expected`, ignoring surrounding whitespace
+    Exact {
+        expected: String,
+        #[serde(rename = "ignoreCase", default)]
+        ignore_case: bool,
+    },
+    /// Output matches the regex somewhere
+    Regex { pattern: String },
+    /// Output is JSON valid against the schema
+    JsonSchema { schema: Value },
+    /// A model scores the output against the rubric
+    LlmGraded {
+        rubric: String,
+        /// Grader model; the run's model when unset
+        #[serde(default)]
+        model: Option<String>,
+        #[serde(rename = "passScore", default = "default_pass_score")]
+        pass_score: f64,
+    },
+}
+
+fn default_pass_score() -> f64 {
+    0.7
+}
+
+impl EvalCheck {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            EvalCheck::Exact { .. } => "exact",
+            EvalCheck::Regex { .. } => "regex",
+            EvalCheck::JsonSchema { .. } => "jsonSchema",
+            EvalCheck::LlmGraded { .. } => "llmGraded",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckOutcome {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub passed: bool,
+    /// 0 to 1; pass/fail checks score 0 or 1
+    pub score: f64,
+    /// Why it failed, or the grader's reason
+    pub message: Option<String>,
+}
+
+impl CheckOutcome {
+    pub fn new(check: &EvalCheck, passed: bool, message: Option<String>) -> Self {
+        Self {
+            kind: check.kind().to_string(),
+            passed,
+            score: if passed { 1.0 } else { 0.0 },
+            message,
+        }
+    }
+}
+
+/// Decide a check that needs no model; `None` for LLM-graded checks
+pub fn evaluate(check: &EvalCheck, output: &str) -> Option<CheckOutcome> {
+    let outcome = match check {
+        EvalCheck::Exact {
+            expected,
+            ignore_case,
+        } => {
+            let (actual, expected) = (output.trim(), expected.trim());
+            let passed = if *ignore_case {
+                actual.to_lowercase() == expected.to_lowercase()
+            } else {
+                actual == expected
+            };
+            let message = (!passed).then(|| format!("Expected \"{}\"", expected));
+            CheckOutcome::new(check, passed, message)
+        }
+        EvalCheck::Regex { pattern } => match Regex::new(pattern) {
+            Ok(regex) => {
+                let passed = regex.is_match(output);
+                let message = (!passed).then(|| format!("No match for /{}/", pattern));
+                CheckOutcome::new(check, passed, message)
+            }
+            Err(e) => CheckOutcome::new(check, false, Some(format!("Invalid regex: {}", e))),
+        },
+        EvalCheck::JsonSchema { schema } => match extract_json(output) {
+            Some(value) => {
+                let mut errors = Vec::new();
+                validate(schema, &value, "$", &mut errors);
+                let message = (!errors.is_empty()).then(|| errors.join("; "));
+                CheckOutcome::new(check, errors.is_empty(), message)
+            }
+            None => CheckOutcome::new(check, false, Some("Output is not JSON".to_string())),
+        },
+        EvalCheck::LlmGraded { .. } => return None,
+    };
+    Some(outcome)
+}
+
+/// The user message asking a grader to score `output`
+pub fn grading_prompt(rubric: &str, input: &str, output: &str) -> String {
+    format!(
+        "## Rubric\n{}\n\n## Task\n{}\n\n## Answer\n{}",
+        rubric.trim(),
+        input.trim(),
+        output.trim()
+    )
+}
+
+/// Score a grader's reply; a reply without a score fails the check
+pub fn parse_grade(check: &EvalCheck, reply: &str) -> CheckOutcome {
+    let pass_score = match check {
+        EvalCheck::LlmGraded { pass_score, .. } => *pass_score,
+        _ => default_pass_score(),
+    };
+    let grade = extract_json(reply).and_then(|value| {
+        let score = value.get("score")?.as_f64()?.clamp(0.0, 1.0);
+        let reason = value
+            .get("reason")
+            .and_then(Value::as_str)
+            .map(str::to_string);
+        Some((score, reason))
+    });
+
+    match grade {
+        Some((score, reason)) => CheckOutcome {
+            kind: check.kind().to_string(),
+            passed: score >= pass_score,
+            score,
+            message: reason,
+        },
+        None => CheckOutcome::new(
+            check,
+            false,
+            Some(format!(
+                "Grader reply had no score: {}",
+                reply.chars().take(200).collect::<String>()
+            )),
+        ),
+    }
+}
+
+/// The JSON in `text`: the whole text, a fenced block, or the outermost
+/// object or array
+pub fn extract_json(text: &str) -> Option<Value> {
+    let text = text.trim();
+    if let Ok(value) = serde_json::from_str(text) {
+        return Some(value);
+    }
+    if let Some(start) = text.find("```") {
+        let body = &text[start + 3..];
+        let body = body.split_once('\n').map_or(body, |(_, rest)| rest);
+        if let Some(end) = body.find("```") {
+            if let Ok(value) = serde_json::from_str(body[..end].trim()) {
+                return Some(value);
+            }
+        }
+    }
+    [('{', '}'), ('[', ']')]
+        .into_iter()
+        .find_map(|(open, close)| {
+            let start = text.find(open)?;
+            let end = text.rfind(close)?;
+            (end > start)
+                .then(|| serde_json::from_str(&text[start..=end]).ok())
+                .flatten()
+        })
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_i64() || n.is_u64() => "integer",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn has_type(value: &Value, expected: &str) -> bool {
+    let actual = type_name(value);
+    actual == expected
+        || (expected == "number" && actual == "integer")
+        || (expected == "integer" && value.as_f64().is_some_and(|n| n.fract() == 0.0))
+}
+
+/// Collect the ways `value` breaks `schema`, each prefixed with its path
+pub fn validate(schema: &Value, value: &Value, path: &str, errors: &mut Vec<String>) {
+    let schema = match schema {
+        Value::Bool(true) => return,
+        Value::Bool(false) => {
+            errors.push(format!("{}: not allowed", path));
+            return;
+        }
+        Value::Object(schema) => schema,
+        _ => return,
+    };
+
+    if let Some(expected) = schema.get("type") {
+        let types: Vec<&str> = match expected {
+            Value::String(t) => vec![t.as_str()],
+            Value::Array(ts) => ts.iter().filter_map(Value::as_str).collect(),
+            _ => vec![],
+        };
+        if !types.is_empty() && !types.iter().any(|t| has_type(value, t)) {
+            errors.push(format!(
+                "{}: expected {}, got {}",
+                path,
+                types.join(" or "),
+                type_name(value)
+            ));
+            return;
+        }
+    }
+    if let Some(Value::Array(options)) = schema.get("enum") {
+        if !options.contains(value) {
+            errors.push(format!("{}: not one of the allowed values", path));
+        }
+    }
+    if let Some(expected) = schema.get("const") {
+        if expected != value {
+            errors.push(format!("{}: expected {}", path, expected));
+        }
+    }
+
+    match value {
+        Value::Object(object) => {
+            if let Some(Value::Array(required)) = schema.get("required") {
+                for key in required.iter().filter_map(Value::as_str) {
+                    if !object.contains_key(key) {
+                        errors.push(format!("{}: missing \"{}\"", path, key));
+                    }
+                }
+            }
+            let properties = schema.get("properties").and_then(Value::as_object);
+            for (key, item) in object {
+                let item_path = format!("{}.{}", path, key);
+                match properties.and_then(|p| p.get(key)) {
+                    Some(property) => validate(property, item, &item_path, errors),
+                    None => {
+                        if let Some(additional) = schema.get("additionalProperties") {
+                            validate(additional, item, &item_path, errors);
+                        }
+                    }
+                }
+            }
+        }
+        Value::Array(items) => {
+            let len = items.len() as u64;
+            if let Some(min) = schema.get("minItems").and_then(Value::as_u64) {
+                if len < min {
+                    errors.push(format!("{}: fewer than {} items", path, min));
+                }
+            }
+            if let Some(max) = schema.get("maxItems").and_then(Value::as_u64) {
+                if len > max {
+                    errors.push(format!("{}: more than {} items", path, max));
+                }
+            }
+            if let Some(item_schema) = schema.get("items") {
+                for (index, item) in items.iter().enumerate() {
+                    validate(item_schema, item, &format!("{}[{}]", path, index), errors);
+                }
+            }
+        }
+        Value::String(s) => {
+            let len = s.chars().count() as u64;
+            if let Some(min) = schema.get("minLength").and_then(Value::as_u64) {
+                if len < min {
+                    errors.push(format!("{}: shorter than {}", path, min));
+                }
+            }
+            if let Some(max) = schema.get("maxLength").and_then(Value::as_u64) {
+                if len > max {
+                    errors.push(format!("{}: longer than {}", path, max));
+                }
+            }
+            if let Some(pattern) = schema.get("pattern").and_then(Value::as_str) {
+                if !Regex::new(pattern).is_ok_and(|regex| regex.is_match(s)) {
+                    errors.push(format!("{}: doesn't match /{}/", path, pattern));
+                }
+            }
+        }
+        Value::Number(n) => {
+            let n = n.as_f64().unwrap_or_default();
+            if let Some(min) = schema.get("minimum").and_then(Value::as_f64) {
+                if n < min {
+                    errors.push(format!("{}: below {}", path, min));
+                }
+            }
+            if let Some(max) = schema.get("maximum").and_then(Value::as_f64) {
+                if n > max {
+                    errors.push(format!("{}: above {}", path, max));
+                }
+            }
+        }
+        _ => {}
+    }
+
+    if let Some(Value::Array(all)) = schema.get("allOf") {
+        for sub in all {
+            validate(sub, value, path, errors);
+        }
+    }
+    if let Some(Value::Array(any)) = schema.get("anyOf") {
+        let matches = any.iter().any(|sub| {
+            let mut sub_errors = Vec::new();
+            validate(sub, value, path, &mut sub_errors);
+            sub_errors.is_empty()
+        });
+        if !matches {
+            errors.push(format!("{}: matches none of anyOf", path));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_checks() {
+        let exact = EvalCheck::Exact {
+            expected: "Paris".to_string(),
+            ignore_case: true,
+        };
+        assert!(evaluate(&exact, "  paris\n").unwrap().passed);
+        assert!(!evaluate(&exact, "Lyon").unwrap().passed);
+
+        let regex = EvalCheck::Regex {
+            pattern: r"^\d{4}-\d{2}-\d{2}$".to_string(),
+        };
+        assert!(evaluate(&regex, "2026-10-18").unwrap().passed);
+
+        let schema = EvalCheck::JsonSchema {
+            schema: json!({
+                "type": "object",
+                "required": ["title", "tags"],
+                "additionalProperties": false,
+                "properties": {
+                    "title": { "type": "string", "minLength": 3 },
+                    "tags": { "type": "array", "items": { "enum": ["bug", "feature"] } },
+                    "priority": { "type": "integer", "minimum": 1, "maximum": 4 }
+                }
+            }),
+        };
+        let fenced = "Here you go:\n```json\n{\"title\": \"Crash\", \"tags\": [\"bug\"]}\n```";
+        assert!(evaluate(&schema, fenced).unwrap().passed);
+        let outcome = evaluate(
+            &schema,
+            r#"{"title": "No", "tags": ["chore"], "priority": 9, "extra": 1}"#,
+        )
+        .unwrap();
+        let mut errors: Vec<String> = outcome
+            .message
+            .unwrap()
+            .split("; ")
+            .map(str::to_string)
+            .collect();
+        errors.sort();
+        assert_eq!(
+            errors,
+            [
+                "$.extra: not allowed",
+                "$.priority: above 4",
+                "$.tags[0]: not one of the allowed values",
+                "$.title: shorter than 3",
+            ]
+        );
+        assert!(!evaluate(&schema, "not json").unwrap().passed);
+
+        let graded = EvalCheck::LlmGraded {
+            rubric: "Mentions the capital".to_string(),
+            model: None,
+            pass_score: 0.7,
+        };
+        assert!(evaluate(&graded, "Paris").is_none());
+        let grade = parse_grade(&graded, r#"Sure. {"score": 0.8, "reason": "Names Paris"}"#);
+        assert!(grade.passed);
+        assert_eq!(grade.message.as_deref(), Some("Names Paris"));
+        assert!(!parse_grade(&graded, "Looks good").passed);
+    }
+}
@@ -0,0 +1,66 @@
+//! Tauri commands for evals module
+
+use super::manager::{EvalComparison, EvalManager, EvalRun, EvalSuite, EvalTarget};
+use std::sync::Arc;
+use tauri::State;
+
+#[tauri::command]
+pub fn eval_list_suites(manager: State<'_, Arc<EvalManager>>) -> Result<Vec<EvalSuite>, String> {
+    manager.list_suites()
+}
+
+#[tauri::command]
+pub fn eval_save_suite(
+    manager: State<'_, Arc<EvalManager>>,
+    suite: EvalSuite,
+) -> Result<EvalSuite, String> {
+    manager.save_suite(suite)
+}
+
+#[tauri::command]
+pub fn eval_delete_suite(
+    manager: State<'_, Arc<EvalManager>>,
+    suite_id: String,
+) -> Result<(), String> {
+    manager.delete_suite(&suite_id)
+}
+
+/// Run a suite and return the finished run with its results
+#[tauri::command]
+pub async fn eval_run(
+    manager: State<'_, Arc<EvalManager>>,
+    suite_id: String,
+    target: EvalTarget,
+) -> Result<EvalRun, String> {
+    manager.run_suite(&suite_id, &target).await
+}
+
+#[tauri::command]
+pub fn eval_list_runs(
+    manager: State<'_, Arc<EvalManager>>,
+    suite_id: String,
+) -> Result<Vec<EvalRun>, String> {
+    manager.list_runs(&suite_id)
+}
+
+#[tauri::command]
+pub fn eval_get_run(
+    manager: State<'_, Arc<EvalManager>>,
+    run_id: String,
+) -> Result<EvalRun, String> {
+    manager.get_run(&run_id)
+}
+
+#[tauri::command]
+pub fn eval_delete_run(manager: State<'_, Arc<EvalManager>>, run_id: String) -> Result<(), String> {
+    manager.delete_run(&run_id)
+}
+
+#[tauri::command]
+pub fn eval_compare(
+    manager: State<'_, Arc<EvalManager>>,
+    base_run_id: String,
+    head_run_id: String,
+) -> Result<EvalComparison, String> {
+    manager.compare(&base_run_id, &head_run_id)
+}
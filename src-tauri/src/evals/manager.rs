@@ -0,0 +1,934 @@
+//! Eval manager implementation
+//!
+//! A suite is a list of golden cases, each an input task and the checks its
+//! output must pass. Running a suite sends every case to the model of an
+//! agent config, scores the output and stores the run under the config's
+//! revision, a short hash of the config. Two runs of the same suite can then
+//! be compared case by case.
+//!
+//! Models named `mock` or `mock:<label>` are answered by the suite's mock
+//! rules, so a suite can be run without network access or an API key.
+
+use super::checks::{
+    evaluate, grading_prompt, parse_grade, CheckOutcome, EvalCheck, GRADER_SYSTEM,
+};
+use crate::agents::{AgentConfig, AgentManager};
+use crate::db::Database;
+use crate::llm::{
+    resolve_model, CompletionRequest, CompletionResponse, LlmManager, LlmProvider, Message,
+    MockProvider, MockRule,
+};
+use crate::usage::{NewUsageRecord, UsageManager};
+use chrono::Utc;
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
+use uuid::Uuid;
+
+/// Output budget for a case's answer
+const MAX_OUTPUT_TOKENS: u32 = 4096;
+/// Output budget for a grader's reply
+const MAX_GRADE_TOKENS: u32 = 256;
+/// Score differences smaller than this count as unchanged
+const SCORE_EPSILON: f64 = 1e-6;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvalSuite {
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub cases: Vec<EvalCase>,
+    /// Replies for mock models; see `MockProvider`
+    #[serde(rename = "mockResponses", default)]
+    pub mock_responses: Vec<MockRule>,
+    #[serde(rename = "createdAt", default)]
+    pub created_at: i64,
+    #[serde(rename = "updatedAt", default)]
+    pub updated_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvalCase {
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    /// The task sent to the agent as its user message
+    pub input: String,
+    #[serde(default)]
+    pub checks: Vec<EvalCheck>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvalRun {
+    pub id: String,
+    #[serde(rename = "suiteId")]
+    pub suite_id: String,
+    #[serde(rename = "agentId")]
+    pub agent_id: Option<String>,
+    pub revision: String,
+    pub config: AgentConfig,
+    pub model: String,
+    /// `running`, `completed` or `failed`
+    pub status: String,
+    /// Mean case score, 0 to 1
+    pub score: Option<f64>,
+    pub passed: i64,
+    pub total: i64,
+    pub error: Option<String>,
+    #[serde(rename = "startedAt")]
+    pub started_at: i64,
+    #[serde(rename = "completedAt")]
+    pub completed_at: Option<i64>,
+    /// Filled in by `get_run`
+    pub results: Vec<EvalResult>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvalResult {
+    #[serde(rename = "caseId")]
+    pub case_id: String,
+    #[serde(rename = "caseName")]
+    pub case_name: String,
+    pub output: String,
+    /// Mean check score, 0 to 1
+    pub score: f64,
+    /// Whether every check passed
+    pub passed: bool,
+    pub checks: Vec<CheckOutcome>,
+    pub tokens: i64,
+    #[serde(rename = "durationMs")]
+    pub duration_ms: i64,
+    pub error: Option<String>,
+}
+
+/// How one case fared in two runs
+#[derive(Debug, Clone, Serialize)]
+pub struct CaseComparison {
+    #[serde(rename = "caseId")]
+    pub case_id: String,
+    #[serde(rename = "caseName")]
+    pub case_name: String,
+    #[serde(rename = "baseScore")]
+    pub base_score: Option<f64>,
+    #[serde(rename = "headScore")]
+    pub head_score: Option<f64>,
+    #[serde(rename = "basePassed")]
+    pub base_passed: Option<bool>,
+    #[serde(rename = "headPassed")]
+    pub head_passed: Option<bool>,
+    /// `improved`, `regressed`, `unchanged`, `added` or `removed`
+    pub change: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EvalComparison {
+    #[serde(rename = "suiteId")]
+    pub suite_id: String,
+    #[serde(rename = "baseRunId")]
+    pub base_run_id: String,
+    #[serde(rename = "headRunId")]
+    pub head_run_id: String,
+    #[serde(rename = "baseRevision")]
+    pub base_revision: String,
+    #[serde(rename = "headRevision")]
+    pub head_revision: String,
+    #[serde(rename = "baseScore")]
+    pub base_score: f64,
+    #[serde(rename = "headScore")]
+    pub head_score: f64,
+    #[serde(rename = "scoreDelta")]
+    pub score_delta: f64,
+    #[serde(rename = "basePassed")]
+    pub base_passed: i64,
+    #[serde(rename = "headPassed")]
+    pub head_passed: i64,
+    pub improved: i64,
+    pub regressed: i64,
+    pub cases: Vec<CaseComparison>,
+}
+
+/// What to run a suite with. The agent's saved config is used unless
+/// `config` is given; `model` overrides the config's model.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EvalTarget {
+    #[serde(rename = "agentId")]
+    pub agent_id: Option<String>,
+    pub config: Option<AgentConfig>,
+    pub model: Option<String>,
+}
+
+/// Short hash identifying an agent config
+pub fn config_revision(config: &AgentConfig) -> String {
+    let json = serde_json::to_vec(config).unwrap_or_default();
+    format!("{:x}", Sha256::digest(json))[..12].to_string()
+}
+
+const RUN_COLUMNS: &str = "id, suite_id, agent_id, revision, config, model, status, score, \
+     passed, total, error, started_at, completed_at";
+
+pub struct EvalManager {
+    db: Database,
+    agents: Arc<AgentManager>,
+    llm: Arc<LlmManager>,
+    usage: Arc<UsageManager>,
+}
+
+impl EvalManager {
+    pub fn new(
+        db: Database,
+        agents: Arc<AgentManager>,
+        llm: Arc<LlmManager>,
+        usage: Arc<UsageManager>,
+    ) -> Self {
+        Self {
+            db,
+            agents,
+            llm,
+            usage,
+        }
+    }
+
+    /// List suites with their cases
+    pub fn list_suites(&self) -> Result<Vec<EvalSuite>, String> {
+        let mut suites = self.db.with_conn(|conn| {
+            let mut stmt = conn
+                .prepare(
+                    "SELECT id, name, description, mock_responses, created_at, updated_at
+                     FROM eval_suites ORDER BY name",
+                )
+                .map_err(|e| e.to_string())?;
+
+            let suites = stmt
+                .query_map([], |row| {
+                    let mock_responses: String = row.get(3)?;
+                    Ok(EvalSuite {
+                        id: row.get(0)?,
+                        name: row.get(1)?,
+                        description: row.get(2)?,
+                        cases: vec![],
+                        mock_responses: serde_json::from_str(&mock_responses).unwrap_or_default(),
+                        created_at: row.get(4)?,
+                        updated_at: row.get(5)?,
+                    })
+                })
+                .map_err(|e| e.to_string())?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| e.to_string())?;
+
+            Ok::<_, String>(suites)
+        })?;
+
+        for suite in &mut suites {
+            suite.cases = self.list_cases(&suite.id)?;
+        }
+        Ok(suites)
+    }
+
+    pub fn get_suite(&self, suite_id: &str) -> Result<EvalSuite, String> {
+        self.list_suites()?
+            .into_iter()
+            .find(|s| s.id == suite_id)
+            .ok_or_else(|| format!("Eval suite not found: {}", suite_id))
+    }
+
+    fn list_cases(&self, suite_id: &str) -> Result<Vec<EvalCase>, String> {
+        self.db.with_conn(|conn| {
+            let mut stmt = conn
+                .prepare(
+                    "SELECT id, name, input, checks FROM eval_cases
+                     WHERE suite_id = ?1 ORDER BY position",
+                )
+                .map_err(|e| e.to_string())?;
+
+            let cases = stmt
+                .query_map(params![suite_id], |row| {
+                    let checks: String = row.get(3)?;
+                    Ok(EvalCase {
+                        id: row.get(0)?,
+                        name: row.get(1)?,
+                        input: row.get(2)?,
+                        checks: serde_json::from_str(&checks).unwrap_or_default(),
+                    })
+                })
+                .map_err(|e| e.to_string())?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| e.to_string())?;
+
+            Ok(cases)
+        })
+    }
+
+    /// Create or update a suite. Its cases are replaced by `suite.cases`;
+    /// cases keep their ids so past results still line up.
+    pub fn save_suite(&self, suite: EvalSuite) -> Result<EvalSuite, String> {
+        if suite.name.trim().is_empty() {
+            return Err("Suite name is required".to_string());
+        }
+        MockProvider::new(&suite.mock_responses)?;
+        for (index, case) in suite.cases.iter().enumerate() {
+            if case.name.trim().is_empty() {
+                return Err(format!("Case {} needs a name", index + 1));
+            }
+            if case.input.trim().is_empty() {
+                return Err(format!("Case {} needs an input", case.name));
+            }
+            for check in &case.checks {
+                if let EvalCheck::Regex { pattern } = check {
+                    regex::Regex::new(pattern)
+                        .map_err(|e| format!("Invalid regex in {}: {}", case.name, e))?;
+                }
+            }
+        }
+
+        let now = Utc::now().timestamp();
+        let suite = EvalSuite {
+            id: if suite.id.is_empty() {
+                Uuid::new_v4().to_string()
+            } else {
+                suite.id
+            },
+            cases: suite
+                .cases
+                .into_iter()
+                .map(|case| EvalCase {
+                    id: if case.id.is_empty() {
+                        Uuid::new_v4().to_string()
+                    } else {
+                        case.id
+                    },
+                    ..case
+                })
+                .collect(),
+            created_at: if suite.created_at == 0 {
+                now
+            } else {
+                suite.created_at
+            },
+            updated_at: now,
+            ..suite
+        };
+
+        self.db.with_conn(|conn| {
+            let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+            tx.execute(
+                "INSERT INTO eval_suites (id, name, description, mock_responses, created_at, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                 ON CONFLICT(id) DO UPDATE SET
+                    name = excluded.name, description = excluded.description,
+                    mock_responses = excluded.mock_responses, updated_at = excluded.updated_at",
+                params![
+                    suite.id,
+                    suite.name,
+                    suite.description,
+                    serde_json::to_string(&suite.mock_responses).unwrap_or_default(),
+                    suite.created_at,
+                    suite.updated_at,
+                ],
+            )
+            .map_err(|e| e.to_string())?;
+
+            tx.execute("DELETE FROM eval_cases WHERE suite_id = ?1", params![suite.id])
+                .map_err(|e| e.to_string())?;
+            for (position, case) in suite.cases.iter().enumerate() {
+                tx.execute(
+                    "INSERT INTO eval_cases (id, suite_id, name, input, checks, position)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                    params![
+                        case.id,
+                        suite.id,
+                        case.name,
+                        case.input,
+                        serde_json::to_string(&case.checks).unwrap_or_default(),
+                        position as i64,
+                    ],
+                )
+                .map_err(|e| e.to_string())?;
+            }
+
+            tx.commit().map_err(|e| e.to_string())
+        })?;
+
+        Ok(suite)
+    }
+
+    /// Delete a suite with its cases and runs
+    pub fn delete_suite(&self, suite_id: &str) -> Result<(), String> {
+        self.db.with_conn(|conn| {
+            conn.execute("DELETE FROM eval_suites WHERE id = ?1", params![suite_id])
+                .map_err(|e| e.to_string())?;
+            Ok(())
+        })
+    }
+
+    /// Runs of a suite, newest first, without their results
+    pub fn list_runs(&self, suite_id: &str) -> Result<Vec<EvalRun>, String> {
+        self.db.with_conn(|conn| {
+            let mut stmt = conn
+                .prepare(&format!(
+                    "SELECT {} FROM eval_runs WHERE suite_id = ?1 ORDER BY started_at DESC, rowid DESC",
+                    RUN_COLUMNS
+                ))
+                .map_err(|e| e.to_string())?;
+
+            let runs = stmt
+                .query_map(params![suite_id], Self::map_run_row)
+                .map_err(|e| e.to_string())?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| e.to_string())?;
+
+            Ok(runs)
+        })
+    }
+
+    /// A run with its results
+    pub fn get_run(&self, run_id: &str) -> Result<EvalRun, String> {
+        let run = self.db.with_conn(|conn| {
+            conn.query_row(
+                &format!("SELECT {} FROM eval_runs WHERE id = ?1", RUN_COLUMNS),
+                params![run_id],
+                Self::map_run_row,
+            )
+            .optional()
+            .map_err(|e| e.to_string())
+        })?;
+        let mut run = run.ok_or_else(|| format!("Eval run not found: {}", run_id))?;
+
+        run.results = self.db.with_conn(|conn| {
+            let mut stmt = conn
+                .prepare(
+                    "SELECT case_id, case_name, output, score, passed, checks, tokens, duration_ms, error
+                     FROM eval_results WHERE run_id = ?1 ORDER BY rowid",
+                )
+                .map_err(|e| e.to_string())?;
+
+            let results = stmt
+                .query_map(params![run_id], |row| {
+                    let checks: String = row.get(5)?;
+                    Ok(EvalResult {
+                        case_id: row.get(0)?,
+                        case_name: row.get(1)?,
+                        output: row.get(2)?,
+                        score: row.get(3)?,
+                        passed: row.get(4)?,
+                        checks: serde_json::from_str(&checks).unwrap_or_default(),
+                        tokens: row.get(6)?,
+                        duration_ms: row.get(7)?,
+                        error: row.get(8)?,
+                    })
+                })
+                .map_err(|e| e.to_string())?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| e.to_string())?;
+
+            Ok::<_, String>(results)
+        })?;
+
+        Ok(run)
+    }
+
+    fn map_run_row(row: &rusqlite::Row) -> rusqlite::Result<EvalRun> {
+        let config: String = row.get(4)?;
+        Ok(EvalRun {
+            id: row.get(0)?,
+            suite_id: row.get(1)?,
+            agent_id: row.get(2)?,
+            revision: row.get(3)?,
+            config: serde_json::from_str(&config).unwrap_or_default(),
+            model: row.get(5)?,
+            status: row.get(6)?,
+            score: row.get(7)?,
+            passed: row.get(8)?,
+            total: row.get(9)?,
+            error: row.get(10)?,
+            started_at: row.get(11)?,
+            completed_at: row.get(12)?,
+            results: vec![],
+        })
+    }
+
+    pub fn delete_run(&self, run_id: &str) -> Result<(), String> {
+        self.db.with_conn(|conn| {
+            conn.execute("DELETE FROM eval_runs WHERE id = ?1", params![run_id])
+                .map_err(|e| e.to_string())?;
+            Ok(())
+        })
+    }
+
+    /// Run every case of a suite and store the scores. Cases run one at a
+    /// time; a case whose call fails scores 0 and the run goes on.
+    pub async fn run_suite(&self, suite_id: &str, target: &EvalTarget) -> Result<EvalRun, String> {
+        let suite = self.get_suite(suite_id)?;
+        let mut config = match (&target.config, &target.agent_id) {
+            (Some(config), _) => config.clone(),
+            (None, Some(agent_id)) => {
+                self.agents
+                    .get_agent(agent_id)?
+                    .ok_or_else(|| format!("Agent not found: {}", agent_id))?
+                    .config
+            }
+            (None, None) => return Err("An agent or a config to run is required".to_string()),
+        };
+        if let Some(model) = target.model.as_ref().filter(|m| !m.trim().is_empty()) {
+            config.model = model.trim().to_string();
+        }
+        let mock = MockProvider::new(&suite.mock_responses)?;
+
+        let mut run = EvalRun {
+            id: Uuid::new_v4().to_string(),
+            suite_id: suite.id.clone(),
+            agent_id: target.agent_id.clone(),
+            revision: config_revision(&config),
+            model: config.model.clone(),
+            config,
+            status: "running".to_string(),
+            score: None,
+            passed: 0,
+            total: suite.cases.len() as i64,
+            error: None,
+            started_at: Utc::now().timestamp(),
+            completed_at: None,
+            results: vec![],
+        };
+        self.db.with_conn(|conn| {
+            conn.execute(
+                "INSERT INTO eval_runs (id, suite_id, agent_id, revision, config, model, status, total, started_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                params![
+                    run.id,
+                    run.suite_id,
+                    run.agent_id,
+                    run.revision,
+                    serde_json::to_string(&run.config).unwrap_or_default(),
+                    run.model,
+                    run.status,
+                    run.total,
+                    run.started_at,
+                ],
+            )
+            .map_err(|e| e.to_string())
+        })?;
+
+        for case in &suite.cases {
+            let result = self.run_case(&run, &mock, case).await;
+            if let Err(e) = self.insert_result(&run.id, &result) {
+                self.finish_run(&mut run, Some(e.clone()))?;
+                return Err(e);
+            }
+            run.results.push(result);
+        }
+
+        self.finish_run(&mut run, None)?;
+        Ok(run)
+    }
+
+    async fn run_case(&self, run: &EvalRun, mock: &MockProvider, case: &EvalCase) -> EvalResult {
+        let started = Instant::now();
+        let mut result = EvalResult {
+            case_id: case.id.clone(),
+            case_name: case.name.clone(),
+            output: String::new(),
+            score: 0.0,
+            passed: false,
+            checks: vec![],
+            tokens: 0,
+            duration_ms: 0,
+            error: None,
+        };
+
+        let system = run.config.system_prompt.trim();
+        let request = CompletionRequest {
+            model: run.model.clone(),
+            system: Some(system.to_string()).filter(|s| !s.is_empty()),
+            messages: vec![Message::user(case.input.clone())],
+            max_tokens: MAX_OUTPUT_TOKENS,
+            temperature: Some(0.0),
+            stop_sequences: vec![],
+        };
+
+        match self.complete(mock, run, &request).await {
+            Ok(response) => {
+                result.tokens = response.usage.total() as i64;
+                result.output = response.content;
+            }
+            Err(e) => {
+                result.error = Some(e);
+                result.duration_ms = started.elapsed().as_millis() as i64;
+                return result;
+            }
+        }
+
+        for check in &case.checks {
+            let outcome = match evaluate(check, &result.output) {
+                Some(outcome) => outcome,
+                None => {
+                    let (outcome, tokens) =
+                        self.grade(mock, run, check, case, &result.output).await;
+                    result.tokens += tokens;
+                    outcome
+                }
+            };
+            result.checks.push(outcome);
+        }
+
+        // A case without checks only has to produce an answer
+        result.passed = result.checks.iter().all(|c| c.passed);
+        result.score = if result.checks.is_empty() {
+            1.0
+        } else {
+            result.checks.iter().map(|c| c.score).sum::<f64>() / result.checks.len() as f64
+        };
+        result.duration_ms = started.elapsed().as_millis() as i64;
+        result
+    }
+
+    /// Ask a grader model to score an LLM-graded check. Returns the outcome
+    /// and the tokens the grading took.
+    async fn grade(
+        &self,
+        mock: &MockProvider,
+        run: &EvalRun,
+        check: &EvalCheck,
+        case: &EvalCase,
+        output: &str,
+    ) -> (CheckOutcome, i64) {
+        let EvalCheck::LlmGraded { rubric, model, .. } = check else {
+            return (CheckOutcome::new(check, false, None), 0);
+        };
+        let request = CompletionRequest {
+            model: model.clone().unwrap_or_else(|| run.model.clone()),
+            system: Some(GRADER_SYSTEM.to_string()),
+            messages: vec![Message::user(grading_prompt(rubric, &case.input, output))],
+            max_tokens: MAX_GRADE_TOKENS,
+            temperature: Some(0.0),
+            stop_sequences: vec![],
+        };
+
+        match self.complete(mock, run, &request).await {
+            Ok(response) => (
+                parse_grade(check, &response.content),
+                response.usage.total() as i64,
+            ),
+            Err(e) => (
+                CheckOutcome::new(check, false, Some(format!("Grader failed: {}", e))),
+                0,
+            ),
+        }
+    }
+
+    /// Send a request to the mock backend or, for real models, through the
+    /// LLM manager. `request.model` may be an alias.
+    async fn complete(
+        &self,
+        mock: &MockProvider,
+        run: &EvalRun,
+        request: &CompletionRequest,
+    ) -> Result<CompletionResponse, String> {
+        if MockProvider::handles(&request.model) {
+            return Ok(mock.complete(request).await?);
+        }
+
+        let resolved = resolve_model(&request.model);
+        let request = CompletionRequest {
+            model: resolved.model_id.clone(),
+            ..request.clone()
+        };
+        let response = self.llm.complete(resolved.provider, &request).await?;
+
+        if let Err(e) = self.usage.record(&NewUsageRecord {
+            agent_id: run.agent_id.as_deref(),
+            provider: resolved.provider.as_str(),
+            model: &resolved.model_id,
+            usage: response.usage,
+            ..Default::default()
+        }) {
+            log::warn!("Failed to record eval usage: {}", e);
+        }
+
+        Ok(response)
+    }
+
+    fn insert_result(&self, run_id: &str, result: &EvalResult) -> Result<(), String> {
+        self.db.with_conn(|conn| {
+            conn.execute(
+                "INSERT INTO eval_results (id, run_id, case_id, case_name, output, score, passed,
+                                           checks, tokens, duration_ms, error)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+                params![
+                    Uuid::new_v4().to_string(),
+                    run_id,
+                    result.case_id,
+                    result.case_name,
+                    result.output,
+                    result.score,
+                    result.passed,
+                    serde_json::to_string(&result.checks).unwrap_or_default(),
+                    result.tokens,
+                    result.duration_ms,
+                    result.error,
+                ],
+            )
+            .map_err(|e| e.to_string())?;
+            Ok(())
+        })
+    }
+
+    /// Total up the results and mark the run completed, or failed with `error`
+    fn finish_run(&self, run: &mut EvalRun, error: Option<String>) -> Result<(), String> {
+        run.passed = run.results.iter().filter(|r| r.passed).count() as i64;
+        run.score = Some(if run.results.is_empty() {
+            0.0
+        } else {
+            run.results.iter().map(|r| r.score).sum::<f64>() / run.results.len() as f64
+        });
+        run.status = if error.is_some() {
+            "failed"
+        } else {
+            "completed"
+        }
+        .to_string();
+        run.error = error;
+        run.completed_at = Some(Utc::now().timestamp());
+
+        self.db.with_conn(|conn| {
+            conn.execute(
+                "UPDATE eval_runs SET status = ?1, score = ?2, passed = ?3, error = ?4, completed_at = ?5
+                 WHERE id = ?6",
+                params![
+                    run.status,
+                    run.score,
+                    run.passed,
+                    run.error,
+                    run.completed_at,
+                    run.id
+                ],
+            )
+            .map_err(|e| e.to_string())?;
+            Ok(())
+        })
+    }
+
+    /// Compare two runs of the same suite case by case. A case counts as
+    /// improved or regressed when its score or its pass/fail changed.
+    pub fn compare(&self, base_run_id: &str, head_run_id: &str) -> Result<EvalComparison, String> {
+        let base = self.get_run(base_run_id)?;
+        let head = self.get_run(head_run_id)?;
+        if base.suite_id != head.suite_id {
+            return Err("Runs are of different suites".to_string());
+        }
+
+        let base_results: HashMap<&str, &EvalResult> = base
+            .results
+            .iter()
+            .map(|r| (r.case_id.as_str(), r))
+            .collect();
+        let head_ids: Vec<&str> = head.results.iter().map(|r| r.case_id.as_str()).collect();
+
+        let mut cases: Vec<CaseComparison> = head
+            .results
+            .iter()
+            .map(|h| {
+                let b = base_results.get(h.case_id.as_str());
+                let change = match b {
+                    None => "added",
+                    Some(b) if h.score > b.score + SCORE_EPSILON || (h.passed && !b.passed) => {
+                        "improved"
+                    }
+                    Some(b) if h.score < b.score - SCORE_EPSILON || (!h.passed && b.passed) => {
+                        "regressed"
+                    }
+                    Some(_) => "unchanged",
+                };
+                CaseComparison {
+                    case_id: h.case_id.clone(),
+                    case_name: h.case_name.clone(),
+                    base_score: b.map(|b| b.score),
+                    head_score: Some(h.score),
+                    base_passed: b.map(|b| b.passed),
+                    head_passed: Some(h.passed),
+                    change: change.to_string(),
+                }
+            })
+            .collect();
+        cases.extend(
+            base.results
+                .iter()
+                .filter(|b| !head_ids.contains(&b.case_id.as_str()))
+                .map(|b| CaseComparison {
+                    case_id: b.case_id.clone(),
+                    case_name: b.case_name.clone(),
+                    base_score: Some(b.score),
+                    head_score: None,
+                    base_passed: Some(b.passed),
+                    head_passed: None,
+                    change: "removed".to_string(),
+                }),
+        );
+
+        let count = |change: &str| cases.iter().filter(|c| c.change == change).count() as i64;
+        let (base_score, head_score) = (base.score.unwrap_or(0.0), head.score.unwrap_or(0.0));
+
+        Ok(EvalComparison {
+            suite_id: head.suite_id.clone(),
+            base_run_id: base.id,
+            head_run_id: head.id,
+            base_revision: base.revision,
+            head_revision: head.revision,
+            base_score,
+            head_score,
+            score_delta: head_score - base_score,
+            base_passed: base.passed,
+            head_passed: head.passed,
+            improved: count("improved"),
+            regressed: count("regressed"),
+            cases,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    fn case(name: &str, input: &str, checks: Vec<EvalCheck>) -> EvalCase {
+        EvalCase {
+            id: String::new(),
+            name: name.to_string(),
+            input: input.to_string(),
+            checks,
+        }
+    }
+
+    fn rule(pattern: &str, reply: &str) -> MockRule {
+        MockRule {
+            pattern: pattern.to_string(),
+            reply: reply.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_run_suite_offline_and_compare() {
+        let dir = tempdir().unwrap();
+        let db = Database::new(dir.path().to_path_buf()).unwrap();
+        let agents = Arc::new(AgentManager::new(db.clone()));
+        let evals = EvalManager::new(
+            db.clone(),
+            Arc::clone(&agents),
+            Arc::new(LlmManager::new(db.clone())),
+            Arc::new(UsageManager::new(db)),
+        );
+
+        let suite = evals
+            .save_suite(EvalSuite {
+                id: String::new(),
+                name: "Triage".to_string(),
+                description: String::new(),
+                cases: vec![
+                    case(
+                        "Capital",
+                        "Capital of France?",
+                        vec![EvalCheck::Exact {
+                            expected: "Paris".to_string(),
+                            ignore_case: false,
+                        }],
+                    ),
+                    case(
+                        "Label",
+                        "Label this bug report",
+                        vec![
+                            EvalCheck::JsonSchema {
+                                schema: json!({ "type": "object", "required": ["label"] }),
+                            },
+                            EvalCheck::LlmGraded {
+                                rubric: "Picks a sensible label".to_string(),
+                                model: Some("mock:grader".to_string()),
+                                pass_score: 0.7,
+                            },
+                        ],
+                    ),
+                ],
+                mock_responses: vec![
+                    rule("## Rubric", r#"{"score": 0.9, "reason": "Sensible"}"#),
+                    rule("France", "Paris"),
+                    rule("bug report", r#"{"label": "bug"}"#),
+                ],
+                created_at: 0,
+                updated_at: 0,
+            })
+            .unwrap();
+        assert_eq!(evals.get_suite(&suite.id).unwrap().cases.len(), 2);
+
+        let agent = agents
+            .create_agent(
+                AgentConfig {
+                    model: "mock".to_string(),
+                    system_prompt: "You triage issues.".to_string(),
+                    ..Default::default()
+                },
+                "Triage",
+                "triage",
+            )
+            .unwrap();
+        let base = evals
+            .run_suite(
+                &suite.id,
+                &EvalTarget {
+                    agent_id: Some(agent.id.clone()),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(base.status, "completed");
+        assert_eq!((base.passed, base.total), (2, 2));
+        assert!((base.score.unwrap() - 0.975).abs() < 1e-9);
+
+        // Without its rule the mock echoes the question back, so the next
+        // revision regresses on the capital case
+        let mut suite = suite;
+        suite.mock_responses.retain(|r| r.pattern != "France");
+        let suite = evals.save_suite(suite).unwrap();
+        let head = evals
+            .run_suite(
+                &suite.id,
+                &EvalTarget {
+                    agent_id: Some(agent.id.clone()),
+                    config: Some(AgentConfig {
+                        system_prompt: "You triage issues tersely.".to_string(),
+                        ..agent.config.clone()
+                    }),
+                    model: Some("mock:v2".to_string()),
+                },
+            )
+            .await
+            .unwrap();
+        assert_ne!(base.revision, head.revision);
+        assert_eq!(head.model, "mock:v2");
+        assert_eq!(head.passed, 1);
+
+        let stored = evals.get_run(&head.id).unwrap();
+        assert_eq!(stored.results.len(), 2);
+        assert_eq!(stored.results[0].output, "Capital of France?");
+        assert_eq!(evals.list_runs(&suite.id).unwrap().len(), 2);
+
+        let comparison = evals.compare(&base.id, &head.id).unwrap();
+        assert_eq!((comparison.improved, comparison.regressed), (0, 1));
+        assert_eq!(comparison.cases[0].change, "regressed");
+        assert_eq!(comparison.cases[1].change, "unchanged");
+        assert!(comparison.score_delta < 0.0);
+
+        assert!(evals
+            .run_suite(&suite.id, &EvalTarget::default())
+            .await
+            .is_err());
+        evals.delete_suite(&suite.id).unwrap();
+        assert!(evals.get_run(&base.id).is_err());
+    }
+}
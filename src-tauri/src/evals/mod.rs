@@ -0,0 +1,11 @@
+//! Evals module for Claud.io
+//!
+//! Runs suites of golden tasks against an agent config revision, scores the
+//! outputs with exact, regex, JSON-schema and LLM-graded checks, and
+//! compares the stored scores of two revisions.
+
+mod checks;
+pub mod commands;
+mod manager;
+
+pub use manager::EvalManager;
@@ -1,20 +1,60 @@
 mod agents;
+mod api;
+pub mod cli;
 mod content;
+pub mod daemon;
 mod db;
+mod evals;
+mod llm;
+mod mcp;
 mod projects;
 mod state;
 mod sync;
 mod terminal;
+mod triggers;
+mod usage;
 
+use parking_lot::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use tauri::Manager;
-use agents::{AgentManager, AgentRuntime};
+use agents::{AgentManager, AgentRuntime, RuntimeLock};
+use api::ApiServer;
 use content::ContentManager;
 use db::Database;
+use evals::EvalManager;
+use llm::LlmManager;
+use mcp::McpManager;
 use projects::ProjectManager;
 use state::StateManager;
 use sync::commands::SyncState;
 use terminal::SessionManager;
+use triggers::{TriggerEvent, TriggerManager};
+use usage::UsageManager;
+
+/// Start the agent runtime, with the parts that only run in the process
+/// working the queue so each event fires its triggers once: commit
+/// watching, file change triggers and the local API (whose event stream
+/// follows task progress)
+fn start_runtime(app: &tauri::AppHandle, lock: RuntimeLock) {
+    app.state::<Mutex<AgentRuntime>>().lock().start(lock);
+
+    triggers::watch_commits(
+        Arc::clone(&app.state::<Arc<TriggerManager>>()),
+        Arc::clone(&app.state::<Arc<ProjectManager>>()),
+        triggers::COMMIT_POLL_INTERVAL,
+    );
+    app.state::<SyncState>()
+        .fires_triggers
+        .store(true, Ordering::SeqCst);
+
+    let api_server = Arc::clone(&app.state::<Arc<ApiServer>>());
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = api_server.apply().await {
+            log::error!("Failed to start local API: {}", e);
+        }
+    });
+}
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
@@ -42,14 +82,21 @@ pub fn run() {
             projects::commands::project_list,
             projects::commands::project_add,
             projects::commands::project_remove,
+            projects::commands::project_update_settings,
             projects::commands::project_get_file_tree,
             projects::commands::project_git_status,
+            projects::commands::project_list_checkpoints,
+            projects::commands::project_create_checkpoint,
+            projects::commands::project_restore_checkpoint,
+            projects::commands::project_delete_checkpoint,
             projects::commands::project_read_file,
             projects::commands::project_write_file,
             // Agent commands
             agents::commands::agent_list,
             agents::commands::agent_create,
             agents::commands::agent_update,
+            agents::commands::agent_set_projects,
+            agents::commands::agent_list_for_project,
             agents::commands::agent_delete,
             agents::commands::agent_start,
             agents::commands::agent_stop,
@@ -58,6 +105,74 @@ pub fn run() {
             agents::commands::task_list,
             agents::commands::task_create,
             agents::commands::task_cancel,
+            agents::commands::task_list_children,
+            agents::commands::task_list_approvals,
+            agents::commands::task_decide_approval,
+            agents::commands::task_get_tool_audit,
+            agents::commands::task_get_messages,
+            agents::commands::task_reply,
+            agents::commands::task_preview_prompt,
+            agents::commands::task_deadline_report,
+            agents::commands::task_import,
+            agents::commands::task_bulk_cancel,
+            agents::commands::task_bulk_reassign,
+            agents::commands::task_bulk_set_priority,
+            agents::commands::task_board,
+            agents::commands::task_move,
+            agents::commands::board_list_columns,
+            agents::commands::board_save_columns,
+            agents::commands::task_list_comments,
+            agents::commands::task_add_comment,
+            agents::commands::task_update_comment,
+            agents::commands::task_delete_comment,
+            agents::commands::task_list_attachments,
+            agents::commands::task_add_attachment,
+            agents::commands::task_delete_attachment,
+            agents::commands::task_list_artifacts,
+            agents::commands::task_preview_artifact,
+            agents::commands::task_apply_artifact,
+            agents::commands::task_discard_artifact,
+            agents::commands::task_export,
+            agents::commands::agent_list_memories,
+            agents::commands::agent_add_memory,
+            // Trigger commands
+            triggers::commands::trigger_list,
+            triggers::commands::trigger_save,
+            triggers::commands::trigger_delete,
+            triggers::commands::trigger_set_enabled,
+            // LLM commands
+            llm::commands::llm_list_providers,
+            llm::commands::llm_update_provider,
+            llm::commands::llm_resolve_model,
+            llm::commands::llm_test_provider,
+            llm::commands::llm_count_tokens,
+            llm::commands::llm_rate_limit_status,
+            // Usage commands
+            usage::commands::usage_list_pricing,
+            usage::commands::usage_set_pricing,
+            usage::commands::usage_delete_pricing,
+            usage::commands::usage_task_records,
+            usage::commands::usage_report,
+            usage::commands::usage_export_csv,
+            // Eval commands
+            evals::commands::eval_list_suites,
+            evals::commands::eval_save_suite,
+            evals::commands::eval_delete_suite,
+            evals::commands::eval_run,
+            evals::commands::eval_list_runs,
+            evals::commands::eval_get_run,
+            evals::commands::eval_delete_run,
+            evals::commands::eval_compare,
+            // API commands
+            api::commands::api_get_config,
+            api::commands::api_update_config,
+            api::commands::api_regenerate_token,
+            api::commands::api_status,
+            // MCP commands
+            mcp::commands::mcp_list_servers,
+            mcp::commands::mcp_save_server,
+            mcp::commands::mcp_delete_server,
+            mcp::commands::mcp_list_tools,
             // Content commands
             content::commands::content_list_carousels,
             content::commands::content_create_carousel,
@@ -88,30 +203,96 @@ pub fn run() {
 
             // Initialize database
             let app_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
-            let database = Database::new(app_dir).map_err(|e| e.to_string())?;
+            let database = Database::new(app_dir.clone()).map_err(|e| e.to_string())?;
 
             // Initialize managers
             let project_manager = Arc::new(ProjectManager::new(database.clone()));
             let agent_manager = Arc::new(AgentManager::new(database.clone()));
             let content_manager = Arc::new(ContentManager::new(database.clone()));
+            let llm_manager = Arc::new(LlmManager::new(database.clone()));
+            let usage_manager = Arc::new(UsageManager::new(database.clone()));
+            let mcp_manager = Arc::new(McpManager::new(database.clone()));
+            let eval_manager = Arc::new(EvalManager::new(
+                database.clone(),
+                Arc::clone(&agent_manager),
+                Arc::clone(&llm_manager),
+                Arc::clone(&usage_manager),
+            ));
+            let trigger_manager = Arc::new(TriggerManager::new(
+                database.clone(),
+                Arc::clone(&agent_manager),
+                Arc::clone(&project_manager),
+            ));
+            let api_server = Arc::new(ApiServer::new(
+                database.clone(),
+                Arc::clone(&agent_manager),
+                Arc::clone(&project_manager),
+            ));
 
             // Initialize agent runtime
-            let mut agent_runtime = AgentRuntime::new(Arc::clone(&agent_manager));
+            let mut agent_runtime = AgentRuntime::new(
+                Arc::clone(&agent_manager),
+                Arc::clone(&project_manager),
+                Arc::clone(&llm_manager),
+                Arc::clone(&usage_manager),
+                Arc::clone(&mcp_manager),
+            );
             agent_runtime.init(app.handle().clone());
-            agent_runtime.start();
 
             // Initialize sync state
-            let sync_state = SyncState::new();
+            // File triggers fire where commit triggers do, once the runtime starts
+            let sync_state = SyncState {
+                fires_triggers: Arc::new(AtomicBool::new(false)),
+                ..SyncState::new()
+            };
+            if let Err(e) =
+                sync::commands::refresh_agent_definitions(&agent_manager, &sync_state.agents_path)
+            {
+                log::warn!("Failed to load agent definitions: {}", e);
+            }
 
             // Register managers with app state
             app.manage(project_manager);
             app.manage(agent_manager);
             app.manage(content_manager);
-            app.manage(agent_runtime);
+            app.manage(llm_manager);
+            app.manage(usage_manager);
+            app.manage(mcp_manager);
+            app.manage(eval_manager);
+            app.manage(Arc::clone(&trigger_manager));
+            app.manage(api_server);
+            app.manage(Mutex::new(agent_runtime));
             app.manage(sync_state);
 
+            // Leave the queue to claudio-daemon while it runs, and take it
+            // over once it exits
+            match RuntimeLock::try_acquire(&app_dir)? {
+                Some(lock) => start_runtime(app.handle(), lock),
+                None => {
+                    let app = app.handle().clone();
+                    tauri::async_runtime::spawn(async move {
+                        match RuntimeLock::acquire(&app_dir).await {
+                            Ok(lock) => {
+                                log::info!("Taking over the agent runtime from claudio-daemon");
+                                start_runtime(&app, lock);
+                            }
+                            Err(e) => log::error!("Failed to take over the agent runtime: {}", e),
+                        }
+                    });
+                }
+            }
+
             // Start watching the state file for changes
-            state_manager.start_watching(app.handle().clone());
+            state_manager.start_watching(app.handle().clone(), move |from, to| {
+                let event = TriggerEvent::ClaudeState {
+                    from: from.state.clone(),
+                    to: to.state.clone(),
+                    session_id: to.session_id.clone(),
+                };
+                if let Err(e) = trigger_manager.fire(&event) {
+                    log::warn!("Failed to run state triggers: {}", e);
+                }
+            });
 
             log::info!("Claud.io initialized successfully");
 
@@ -0,0 +1,374 @@
+//! Anthropic Messages API backend
+
+use super::provider::{
+    api_error, CompletionRequest, CompletionResponse, CompletionStream, LlmError, LlmProvider,
+    StreamEvent, Usage,
+};
+use super::stream::{lines, sse_data};
+use async_trait::async_trait;
+use futures::future;
+use futures::stream::StreamExt;
+use serde_json::{json, Value};
+use std::time::Duration;
+
+const API_VERSION: &str = "2023-06-01";
+
+pub struct AnthropicProvider {
+    client: reqwest::Client,
+    base_url: String,
+    api_key: String,
+    timeout: Duration,
+}
+
+impl AnthropicProvider {
+    pub fn new(base_url: &str, api_key: String, timeout: Duration) -> Result<Self, LlmError> {
+        let client = reqwest::Client::builder()
+            .connect_timeout(Duration::from_secs(10))
+            .build()?;
+
+        Ok(Self {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key,
+            timeout,
+        })
+    }
+
+    fn request(&self, path: &str) -> reqwest::RequestBuilder {
+        self.client
+            .post(format!("{}{}", self.base_url, path))
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", API_VERSION)
+    }
+
+    fn body(request: &CompletionRequest, stream: bool) -> Value {
+        let messages: Vec<Value> = request
+            .messages
+            .iter()
+            .map(|m| json!({ "role": m.role.as_str(), "content": m.content }))
+            .collect();
+
+        let mut body = json!({
+            "model": request.model,
+            "max_tokens": request.max_tokens,
+            "messages": messages,
+        });
+
+        if let Some(system) = &request.system {
+            body["system"] = json!(system);
+        }
+        if let Some(temperature) = request.temperature {
+            body["temperature"] = json!(temperature);
+        }
+        if !request.stop_sequences.is_empty() {
+            body["stop_sequences"] = json!(request.stop_sequences);
+        }
+        if stream {
+            body["stream"] = json!(true);
+        }
+
+        body
+    }
+}
+
+fn parse_usage(value: &Value) -> Usage {
+    let field = |name: &str| value.get(name).and_then(|v| v.as_u64()).unwrap_or(0);
+    Usage {
+        input_tokens: field("input_tokens"),
+        output_tokens: field("output_tokens"),
+        cache_creation_input_tokens: field("cache_creation_input_tokens"),
+        cache_read_input_tokens: field("cache_read_input_tokens"),
+    }
+}
+
+#[derive(Default)]
+struct StreamState {
+    usage: Usage,
+    stop_reason: Option<String>,
+}
+
+fn parse_stream_line(state: &mut StreamState, line: &str) -> Option<Result<StreamEvent, LlmError>> {
+    let data = sse_data(line)?;
+    let event: Value = match serde_json::from_str(data) {
+        Ok(v) => v,
+        Err(e) => return Some(Err(LlmError::InvalidResponse(e.to_string()))),
+    };
+
+    match event.get("type").and_then(|t| t.as_str()) {
+        Some("message_start") => {
+            if let Some(usage) = event.pointer("/message/usage") {
+                state.usage = parse_usage(usage);
+            }
+            None
+        }
+        Some("content_block_delta") => {
+            event
+                .pointer("/delta/text")
+                .and_then(|t| t.as_str())
+                .map(|text| {
+                    Ok(StreamEvent::Delta {
+                        text: text.to_string(),
+                    })
+                })
+        }
+        Some("message_delta") => {
+            if let Some(reason) = event.pointer("/delta/stop_reason").and_then(|r| r.as_str()) {
+                state.stop_reason = Some(reason.to_string());
+            }
+            if let Some(output) = event
+                .pointer("/usage/output_tokens")
+                .and_then(|o| o.as_u64())
+            {
+                state.usage.output_tokens = output;
+            }
+            None
+        }
+        Some("message_stop") => Some(Ok(StreamEvent::Done {
+            stop_reason: state.stop_reason.take(),
+            usage: state.usage,
+        })),
+        Some("error") => {
+            let kind = event.pointer("/error/type").and_then(|t| t.as_str());
+            let message = event
+                .pointer("/error/message")
+                .and_then(|m| m.as_str())
+                .unwrap_or("stream error")
+                .to_string();
+            let status = match kind {
+                Some("overloaded_error") => 529,
+                Some("rate_limit_error") => 429,
+                _ => 500,
+            };
+            Some(Err(LlmError::Api {
+                status,
+                message,
+                retry_after: None,
+            }))
+        }
+        _ => None,
+    }
+}
+
+#[async_trait]
+impl LlmProvider for AnthropicProvider {
+    async fn complete(&self, request: &CompletionRequest) -> Result<CompletionResponse, LlmError> {
+        let response = self
+            .request("/v1/messages")
+            .timeout(self.timeout)
+            .json(&Self::body(request, false))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(api_error(response).await);
+        }
+
+        let value: Value = response.json().await?;
+
+        let content = value
+            .get("content")
+            .and_then(|c| c.as_array())
+            .ok_or_else(|| LlmError::InvalidResponse("missing content".to_string()))?
+            .iter()
+            .filter(|block| block.get("type").and_then(|t| t.as_str()) == Some("text"))
+            .filter_map(|block| block.get("text").and_then(|t| t.as_str()))
+            .collect::<Vec<_>>()
+            .join("");
+
+        Ok(CompletionResponse {
+            model: value
+                .get("model")
+                .and_then(|m| m.as_str())
+                .unwrap_or(&request.model)
+                .to_string(),
+            content,
+            stop_reason: value
+                .get("stop_reason")
+                .and_then(|r| r.as_str())
+                .map(|r| r.to_string()),
+            usage: value.get("usage").map(parse_usage).unwrap_or_default(),
+        })
+    }
+
+    async fn stream(&self, request: &CompletionRequest) -> Result<CompletionStream, LlmError> {
+        let response = self
+            .request("/v1/messages")
+            .header("accept", "text/event-stream")
+            .json(&Self::body(request, true))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(api_error(response).await);
+        }
+
+        Ok(lines(response)
+            .scan(StreamState::default(), |state, line| {
+                let event = match line {
+                    Ok(line) => parse_stream_line(state, &line),
+                    Err(e) => Some(Err(e)),
+                };
+                future::ready(Some(event))
+            })
+            .filter_map(future::ready)
+            .boxed())
+    }
+
+    async fn count_tokens(&self, request: &CompletionRequest) -> Result<u64, LlmError> {
+        let mut body = Self::body(request, false);
+        if let Some(obj) = body.as_object_mut() {
+            obj.remove("max_tokens");
+            obj.remove("temperature");
+            obj.remove("stop_sequences");
+        }
+
+        let response = self
+            .request("/v1/messages/count_tokens")
+            .timeout(self.timeout)
+            .json(&body)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(api_error(response).await);
+        }
+
+        let value: Value = response.json().await?;
+        value
+            .get("input_tokens")
+            .and_then(|t| t.as_u64())
+            .ok_or_else(|| LlmError::InvalidResponse("missing input_tokens".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::provider::Message;
+    use crate::llm::test_server::MockServer;
+
+    fn provider(server: &MockServer) -> AnthropicProvider {
+        AnthropicProvider::new(&server.url, "test-key".to_string(), Duration::from_secs(5)).unwrap()
+    }
+
+    fn request() -> CompletionRequest {
+        CompletionRequest {
+            model: "claude-sonnet-4-5".to_string(),
+            system: Some("You are terse.".to_string()),
+            messages: vec![Message::user("Say hi")],
+            max_tokens: 64,
+            temperature: None,
+            stop_sequences: vec![],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_complete() {
+        let server = MockServer::start(
+            200,
+            &[],
+            r#"{"model":"claude-sonnet-4-5","content":[{"type":"text","text":"Hi"},{"type":"text","text":"!"}],
+                "stop_reason":"end_turn","usage":{"input_tokens":12,"output_tokens":3,"cache_read_input_tokens":5}}"#,
+        )
+        .await;
+
+        let response = provider(&server).complete(&request()).await.unwrap();
+        assert_eq!(response.content, "Hi!");
+        assert_eq!(response.stop_reason.as_deref(), Some("end_turn"));
+        assert_eq!(response.usage.input_tokens, 12);
+        assert_eq!(response.usage.output_tokens, 3);
+        assert_eq!(response.usage.cache_read_input_tokens, 5);
+
+        let recorded = server.requests();
+        assert_eq!(recorded[0].method, "POST");
+        assert_eq!(recorded[0].path, "/v1/messages");
+        assert_eq!(recorded[0].header("x-api-key"), Some("test-key"));
+        assert_eq!(recorded[0].header("anthropic-version"), Some(API_VERSION));
+        assert_eq!(recorded[0].body["system"], "You are terse.");
+        assert_eq!(recorded[0].body["messages"][0]["role"], "user");
+        assert_eq!(recorded[0].body["max_tokens"], 64);
+    }
+
+    #[tokio::test]
+    async fn test_stream() {
+        let body = [
+            r#"event: message_start"#,
+            r#"data: {"type":"message_start","message":{"usage":{"input_tokens":10,"output_tokens":1}}}"#,
+            "",
+            r#"data: {"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Hel"}}"#,
+            "",
+            r#"data: {"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"lo"}}"#,
+            "",
+            r#"data: {"type":"message_delta","delta":{"stop_reason":"end_turn"},"usage":{"output_tokens":7}}"#,
+            "",
+            r#"data: {"type":"message_stop"}"#,
+            "",
+        ]
+        .join("\n");
+        let server = MockServer::start(200, &[("content-type", "text/event-stream")], &body).await;
+
+        let events: Vec<StreamEvent> = provider(&server)
+            .stream(&request())
+            .await
+            .unwrap()
+            .map(|e| e.unwrap())
+            .collect()
+            .await;
+
+        assert_eq!(
+            events,
+            vec![
+                StreamEvent::Delta {
+                    text: "Hel".to_string()
+                },
+                StreamEvent::Delta {
+                    text: "lo".to_string()
+                },
+                StreamEvent::Done {
+                    stop_reason: Some("end_turn".to_string()),
+                    usage: Usage {
+                        input_tokens: 10,
+                        output_tokens: 7,
+                        ..Default::default()
+                    },
+                },
+            ]
+        );
+        assert_eq!(server.requests()[0].body["stream"], true);
+    }
+
+    #[tokio::test]
+    async fn test_count_tokens() {
+        let server = MockServer::start(200, &[], r#"{"input_tokens":42}"#).await;
+
+        let tokens = provider(&server).count_tokens(&request()).await.unwrap();
+        assert_eq!(tokens, 42);
+
+        let recorded = server.requests();
+        assert_eq!(recorded[0].path, "/v1/messages/count_tokens");
+        assert!(recorded[0].body.get("max_tokens").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_api_error_with_retry_after() {
+        let server = MockServer::start(
+            429,
+            &[("retry-after", "7")],
+            r#"{"type":"error","error":{"type":"rate_limit_error","message":"Slow down"}}"#,
+        )
+        .await;
+
+        match provider(&server).complete(&request()).await {
+            Err(LlmError::Api {
+                status,
+                message,
+                retry_after,
+            }) => {
+                assert_eq!(status, 429);
+                assert_eq!(message, "Slow down");
+                assert_eq!(retry_after, Some(Duration::from_secs(7)));
+            }
+            other => panic!("expected API error, got {:?}", other),
+        }
+    }
+}
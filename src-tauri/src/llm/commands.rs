@@ -0,0 +1,104 @@
+//! Tauri commands for llm module
+
+use super::limiter::RateLimitStatus;
+use super::manager::{LlmManager, ProviderConfig};
+use super::models::{resolve_model, ResolvedModel};
+use super::provider::{CompletionRequest, Message, ProviderKind, Usage};
+use crate::usage::{NewUsageRecord, UsageManager};
+use serde::Serialize;
+use std::sync::Arc;
+use tauri::State;
+
+#[derive(Serialize)]
+pub struct ProviderTestResult {
+    pub provider: ProviderKind,
+    pub model: String,
+    #[serde(rename = "latencyMs")]
+    pub latency_ms: u64,
+    pub output: String,
+    pub usage: Usage,
+}
+
+#[tauri::command]
+pub fn llm_list_providers(
+    manager: State<'_, Arc<LlmManager>>,
+) -> Result<Vec<ProviderConfig>, String> {
+    manager.list_providers()
+}
+
+#[tauri::command]
+pub fn llm_update_provider(
+    manager: State<'_, Arc<LlmManager>>,
+    config: ProviderConfig,
+) -> Result<(), String> {
+    manager.update_provider(&config)
+}
+
+#[tauri::command]
+pub fn llm_resolve_model(model: String) -> ResolvedModel {
+    resolve_model(&model)
+}
+
+/// Send a tiny prompt to check that a provider is reachable and configured
+#[tauri::command]
+pub async fn llm_test_provider(
+    manager: State<'_, Arc<LlmManager>>,
+    usage: State<'_, Arc<UsageManager>>,
+    model: String,
+) -> Result<ProviderTestResult, String> {
+    let resolved = resolve_model(&model);
+
+    let request = CompletionRequest {
+        model: resolved.model_id.clone(),
+        system: None,
+        messages: vec![Message::user("Reply with the single word: pong")],
+        max_tokens: 16,
+        temperature: Some(0.0),
+        stop_sequences: vec![],
+    };
+
+    let started = std::time::Instant::now();
+    let response = manager.complete(resolved.provider, &request).await?;
+    let latency_ms = started.elapsed().as_millis() as u64;
+
+    usage.record(&NewUsageRecord {
+        provider: resolved.provider.as_str(),
+        model: &resolved.model_id,
+        usage: response.usage,
+        ..Default::default()
+    })?;
+
+    Ok(ProviderTestResult {
+        provider: resolved.provider,
+        model: response.model,
+        latency_ms,
+        output: response.content,
+        usage: response.usage,
+    })
+}
+
+#[tauri::command]
+pub async fn llm_count_tokens(
+    manager: State<'_, Arc<LlmManager>>,
+    model: String,
+    system: Option<String>,
+    text: String,
+) -> Result<u64, String> {
+    let (_, resolved) = manager.resolve(&model)?;
+
+    let request = CompletionRequest {
+        model: resolved.model_id,
+        system,
+        messages: vec![Message::user(text)],
+        max_tokens: 1,
+        temperature: None,
+        stop_sequences: vec![],
+    };
+
+    manager.count_tokens(resolved.provider, &request).await
+}
+
+#[tauri::command]
+pub fn llm_rate_limit_status(manager: State<'_, Arc<LlmManager>>) -> Vec<RateLimitStatus> {
+    manager.rate_limit_status()
+}
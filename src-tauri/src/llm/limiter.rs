@@ -0,0 +1,348 @@
+//! Shared rate limiting for LLM providers
+//!
+//! Every call goes through one limiter per provider that enforces requests
+//! per minute, tokens per minute and a cap on concurrent calls, and pauses
+//! the provider when it answers with a `retry-after`.
+
+use super::provider::ProviderKind;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio::time::Instant;
+
+const WINDOW: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RateLimits {
+    #[serde(rename = "requestsPerMinute")]
+    pub requests_per_minute: Option<u32>,
+    #[serde(rename = "tokensPerMinute")]
+    pub tokens_per_minute: Option<u64>,
+    #[serde(rename = "maxConcurrent")]
+    pub max_concurrent: Option<u32>,
+}
+
+/// Snapshot of a provider's limiter for the UI
+#[derive(Debug, Clone, Serialize)]
+pub struct RateLimitStatus {
+    pub provider: ProviderKind,
+    pub limits: RateLimits,
+    #[serde(rename = "requestsLastMinute")]
+    pub requests_last_minute: usize,
+    #[serde(rename = "tokensLastMinute")]
+    pub tokens_last_minute: u64,
+    #[serde(rename = "inFlight")]
+    pub in_flight: u32,
+    /// Seconds left on a `retry-after` pause
+    #[serde(rename = "pausedForSecs")]
+    pub paused_for_secs: Option<f64>,
+}
+
+struct Reservation {
+    id: u64,
+    at: Instant,
+    tokens: u64,
+}
+
+struct ProviderState {
+    limits: RateLimits,
+    semaphore: Option<Arc<Semaphore>>,
+    window: VecDeque<Reservation>,
+    paused_until: Option<Instant>,
+    next_id: u64,
+}
+
+impl ProviderState {
+    fn new(limits: RateLimits) -> Self {
+        Self {
+            limits,
+            semaphore: limits
+                .max_concurrent
+                .map(|n| Arc::new(Semaphore::new(n.max(1) as usize))),
+            window: VecDeque::new(),
+            paused_until: None,
+            next_id: 0,
+        }
+    }
+
+    fn prune(&mut self, now: Instant) {
+        while self
+            .window
+            .front()
+            .is_some_and(|r| now.duration_since(r.at) >= WINDOW)
+        {
+            self.window.pop_front();
+        }
+    }
+
+    fn tokens_in_window(&self) -> u64 {
+        self.window.iter().map(|r| r.tokens).sum()
+    }
+
+    /// How long a request of `tokens` must wait before it may start
+    fn wait_for(&mut self, tokens: u64, now: Instant) -> Duration {
+        self.prune(now);
+
+        if let Some(until) = self.paused_until {
+            if until > now {
+                return until - now;
+            }
+            self.paused_until = None;
+        }
+
+        if let Some(rpm) = self.limits.requests_per_minute {
+            if self.window.len() >= rpm.max(1) as usize {
+                return self.expires_in(0, now);
+            }
+        }
+
+        if let Some(tpm) = self.limits.tokens_per_minute {
+            let used = self.tokens_in_window();
+            // A single request larger than the whole budget may still run alone
+            if used > 0 && used + tokens > tpm {
+                let mut freed = 0;
+                for (index, reservation) in self.window.iter().enumerate() {
+                    freed += reservation.tokens;
+                    if used - freed + tokens <= tpm {
+                        return self.expires_in(index, now);
+                    }
+                }
+                return self.expires_in(self.window.len() - 1, now);
+            }
+        }
+
+        Duration::ZERO
+    }
+
+    fn expires_in(&self, index: usize, now: Instant) -> Duration {
+        self.window
+            .get(index)
+            .map(|r| (r.at + WINDOW).saturating_duration_since(now))
+            .unwrap_or_default()
+    }
+}
+
+/// Permission to make one call. Dropping it frees the concurrency slot.
+pub struct RatePermit {
+    states: Arc<Mutex<HashMap<ProviderKind, ProviderState>>>,
+    kind: ProviderKind,
+    id: u64,
+    _slot: Option<OwnedSemaphorePermit>,
+}
+
+impl RatePermit {
+    /// Replace the estimated token reservation with what the call actually used
+    pub fn settle(&self, tokens: u64) {
+        if let Some(state) = self.states.lock().get_mut(&self.kind) {
+            if let Some(reservation) = state.window.iter_mut().find(|r| r.id == self.id) {
+                reservation.tokens = tokens;
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct RateLimiter {
+    states: Arc<Mutex<HashMap<ProviderKind, ProviderState>>>,
+}
+
+impl RateLimiter {
+    /// Set a provider's limits. Calls already in flight keep their slots.
+    pub fn configure(&self, kind: ProviderKind, limits: RateLimits) {
+        let mut states = self.states.lock();
+        match states.get_mut(&kind) {
+            Some(state) if state.limits == limits => {}
+            Some(state) => {
+                let window = std::mem::take(&mut state.window);
+                let paused_until = state.paused_until;
+                let next_id = state.next_id;
+                *state = ProviderState::new(limits);
+                state.window = window;
+                state.paused_until = paused_until;
+                state.next_id = next_id;
+            }
+            None => {
+                states.insert(kind, ProviderState::new(limits));
+            }
+        }
+    }
+
+    /// Wait until a call of roughly `tokens` may start
+    pub async fn acquire(&self, kind: ProviderKind, tokens: u64) -> RatePermit {
+        let semaphore = self
+            .states
+            .lock()
+            .entry(kind)
+            .or_insert_with(|| ProviderState::new(RateLimits::default()))
+            .semaphore
+            .clone();
+
+        let slot = match semaphore {
+            Some(semaphore) => semaphore.acquire_owned().await.ok(),
+            None => None,
+        };
+
+        loop {
+            let wait = {
+                let mut states = self.states.lock();
+                let state = states
+                    .entry(kind)
+                    .or_insert_with(|| ProviderState::new(RateLimits::default()));
+                let now = Instant::now();
+                let wait = state.wait_for(tokens, now);
+
+                if wait.is_zero() {
+                    let id = state.next_id;
+                    state.next_id += 1;
+                    state.window.push_back(Reservation { id, at: now, tokens });
+
+                    return RatePermit {
+                        states: Arc::clone(&self.states),
+                        kind,
+                        id,
+                        _slot: slot,
+                    };
+                }
+                wait
+            };
+
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Stop sending to a provider for `duration`, e.g. after a 429
+    pub fn pause(&self, kind: ProviderKind, duration: Duration) {
+        let until = Instant::now() + duration;
+        let mut states = self.states.lock();
+        let state = states
+            .entry(kind)
+            .or_insert_with(|| ProviderState::new(RateLimits::default()));
+        if state.paused_until.map_or(true, |current| current < until) {
+            state.paused_until = Some(until);
+        }
+    }
+
+    /// Whether a new call would have to wait. Used by the task queue to hold
+    /// work back instead of starting tasks that would only block.
+    pub fn is_saturated(&self, kind: ProviderKind) -> bool {
+        let mut states = self.states.lock();
+        let Some(state) = states.get_mut(&kind) else {
+            return false;
+        };
+
+        let slots_full = state
+            .semaphore
+            .as_ref()
+            .is_some_and(|s| s.available_permits() == 0);
+        slots_full || !state.wait_for(0, Instant::now()).is_zero()
+    }
+
+    pub fn status(&self) -> Vec<RateLimitStatus> {
+        let mut states = self.states.lock();
+        let now = Instant::now();
+
+        let mut status: Vec<RateLimitStatus> = states
+            .iter_mut()
+            .map(|(kind, state)| {
+                state.prune(now);
+                RateLimitStatus {
+                    provider: *kind,
+                    limits: state.limits,
+                    requests_last_minute: state.window.len(),
+                    tokens_last_minute: state.tokens_in_window(),
+                    in_flight: match (&state.semaphore, state.limits.max_concurrent) {
+                        (Some(s), Some(max)) => max.max(1) - s.available_permits() as u32,
+                        _ => 0,
+                    },
+                    paused_for_secs: state
+                        .paused_until
+                        .filter(|until| *until > now)
+                        .map(|until| (until - now).as_secs_f64()),
+                }
+            })
+            .collect();
+
+        status.sort_by_key(|s| s.provider.as_str());
+        status
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_requests_per_minute() {
+        let limiter = RateLimiter::default();
+        limiter.configure(
+            ProviderKind::Anthropic,
+            RateLimits {
+                requests_per_minute: Some(2),
+                ..Default::default()
+            },
+        );
+
+        let started = Instant::now();
+        limiter.acquire(ProviderKind::Anthropic, 10).await;
+        limiter.acquire(ProviderKind::Anthropic, 10).await;
+        assert!(limiter.is_saturated(ProviderKind::Anthropic));
+
+        limiter.acquire(ProviderKind::Anthropic, 10).await;
+        assert!(started.elapsed() >= WINDOW);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_tokens_per_minute_and_settle() {
+        let limiter = RateLimiter::default();
+        limiter.configure(
+            ProviderKind::OpenAi,
+            RateLimits {
+                tokens_per_minute: Some(1_000),
+                ..Default::default()
+            },
+        );
+
+        let wait_for = |tokens| {
+            limiter
+                .states
+                .lock()
+                .get_mut(&ProviderKind::OpenAi)
+                .unwrap()
+                .wait_for(tokens, Instant::now())
+        };
+
+        let permit = limiter.acquire(ProviderKind::OpenAi, 900).await;
+        assert!(!wait_for(200).is_zero());
+
+        // The call used far fewer tokens than estimated
+        permit.settle(100);
+        assert!(wait_for(200).is_zero());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_pause_and_concurrency() {
+        let limiter = RateLimiter::default();
+        limiter.configure(
+            ProviderKind::Ollama,
+            RateLimits {
+                max_concurrent: Some(1),
+                ..Default::default()
+            },
+        );
+
+        let first = limiter.acquire(ProviderKind::Ollama, 0).await;
+        assert!(limiter.is_saturated(ProviderKind::Ollama));
+        drop(first);
+        assert!(!limiter.is_saturated(ProviderKind::Ollama));
+
+        limiter.pause(ProviderKind::Ollama, Duration::from_secs(30));
+        assert!(limiter.status()[0].paused_for_secs.is_some());
+
+        let started = Instant::now();
+        limiter.acquire(ProviderKind::Ollama, 0).await;
+        assert!(started.elapsed() >= Duration::from_secs(30));
+    }
+}
@@ -0,0 +1,455 @@
+//! LLM manager implementation
+//!
+//! Loads per-provider configuration from the database and hands out
+//! provider instances for resolved models.
+
+use super::anthropic::AnthropicProvider;
+use super::limiter::{RateLimitStatus, RateLimiter, RateLimits};
+use super::models::{resolve_model, ResolvedModel};
+use super::ollama::OllamaProvider;
+use super::openai::OpenAiProvider;
+use super::provider::{
+    estimate_request_tokens, CompletionRequest, CompletionResponse, CompletionStream, LlmError,
+    LlmProvider, ProviderKind, StreamEvent,
+};
+use crate::db::Database;
+use chrono::Utc;
+use futures::StreamExt;
+use parking_lot::RwLock;
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Attempts per call before a rate-limited or overloaded provider is given up on
+const MAX_ATTEMPTS: u32 = 6;
+/// Backoff when the provider didn't say how long to wait; doubles per attempt
+const BASE_BACKOFF: Duration = Duration::from_secs(2);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderConfig {
+    pub kind: ProviderKind,
+    #[serde(rename = "baseUrl")]
+    pub base_url: String,
+    #[serde(rename = "apiKey")]
+    pub api_key: Option<String>,
+    pub enabled: bool,
+    #[serde(rename = "timeoutSecs")]
+    pub timeout_secs: u64,
+    #[serde(default)]
+    pub limits: RateLimits,
+}
+
+impl ProviderConfig {
+    /// Configured API key, falling back to the provider's environment variable
+    pub fn effective_api_key(&self) -> Option<String> {
+        let env_var = match self.kind {
+            ProviderKind::Anthropic => Some("ANTHROPIC_API_KEY"),
+            ProviderKind::OpenAi => Some("OPENAI_API_KEY"),
+            ProviderKind::Ollama => None,
+        };
+
+        self.api_key
+            .clone()
+            .filter(|k| !k.is_empty())
+            .or_else(|| env_var.and_then(|v| std::env::var(v).ok()))
+            .filter(|k| !k.is_empty())
+    }
+}
+
+pub struct LlmManager {
+    db: Database,
+    providers: RwLock<HashMap<ProviderKind, Arc<dyn LlmProvider>>>,
+    limiter: RateLimiter,
+}
+
+impl LlmManager {
+    pub fn new(db: Database) -> Self {
+        Self {
+            db,
+            providers: RwLock::new(HashMap::new()),
+            limiter: RateLimiter::default(),
+        }
+    }
+
+    /// List all provider configurations
+    pub fn list_providers(&self) -> Result<Vec<ProviderConfig>, String> {
+        self.db.with_conn(|conn| {
+            let mut stmt = conn
+                .prepare(
+                    "SELECT kind, base_url, api_key, enabled, timeout_secs,
+                            requests_per_minute, tokens_per_minute, max_concurrent
+                     FROM llm_providers ORDER BY kind",
+                )
+                .map_err(|e| e.to_string())?;
+
+            let configs = stmt
+                .query_map([], Self::map_config_row)
+                .map_err(|e| e.to_string())?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| e.to_string())?;
+
+            Ok(configs.into_iter().flatten().collect())
+        })
+    }
+
+    /// Get the configuration for one provider
+    pub fn get_provider_config(&self, kind: ProviderKind) -> Result<ProviderConfig, String> {
+        self.db.with_conn(|conn| {
+            let result = conn.query_row(
+                "SELECT kind, base_url, api_key, enabled, timeout_secs,
+                        requests_per_minute, tokens_per_minute, max_concurrent
+                 FROM llm_providers WHERE kind = ?1",
+                params![kind.as_str()],
+                Self::map_config_row,
+            );
+
+            match result {
+                Ok(Some(config)) => Ok(config),
+                Ok(None) | Err(rusqlite::Error::QueryReturnedNoRows) => {
+                    Err(format!("Provider not found: {}", kind.as_str()))
+                }
+                Err(e) => Err(e.to_string()),
+            }
+        })
+    }
+
+    fn map_config_row(row: &rusqlite::Row) -> rusqlite::Result<Option<ProviderConfig>> {
+        let kind: String = row.get(0)?;
+        let Some(kind) = ProviderKind::parse(&kind) else {
+            return Ok(None);
+        };
+
+        Ok(Some(ProviderConfig {
+            kind,
+            base_url: row.get(1)?,
+            api_key: row.get(2)?,
+            enabled: row.get(3)?,
+            timeout_secs: row.get::<_, i64>(4)?.max(1) as u64,
+            limits: RateLimits {
+                requests_per_minute: row.get::<_, Option<i64>>(5)?.map(|v| v.max(1) as u32),
+                tokens_per_minute: row.get::<_, Option<i64>>(6)?.map(|v| v.max(1) as u64),
+                max_concurrent: row.get::<_, Option<i64>>(7)?.map(|v| v.max(1) as u32),
+            },
+        }))
+    }
+
+    /// Create or replace a provider configuration
+    pub fn update_provider(&self, config: &ProviderConfig) -> Result<(), String> {
+        self.db.with_conn(|conn| {
+            conn.execute(
+                "INSERT INTO llm_providers (kind, base_url, api_key, enabled, timeout_secs,
+                                            requests_per_minute, tokens_per_minute, max_concurrent, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+                 ON CONFLICT(kind) DO UPDATE SET
+                    base_url = excluded.base_url,
+                    api_key = excluded.api_key,
+                    enabled = excluded.enabled,
+                    timeout_secs = excluded.timeout_secs,
+                    requests_per_minute = excluded.requests_per_minute,
+                    tokens_per_minute = excluded.tokens_per_minute,
+                    max_concurrent = excluded.max_concurrent,
+                    updated_at = excluded.updated_at",
+                params![
+                    config.kind.as_str(),
+                    config.base_url,
+                    config.api_key,
+                    config.enabled,
+                    config.timeout_secs as i64,
+                    config.limits.requests_per_minute,
+                    config.limits.tokens_per_minute.map(|v| v as i64),
+                    config.limits.max_concurrent,
+                    Utc::now().timestamp(),
+                ],
+            )
+            .map_err(|e| e.to_string())?;
+
+            Ok::<(), String>(())
+        })?;
+
+        // Drop the cached client so the next call picks up the new settings
+        self.providers.write().remove(&config.kind);
+        self.limiter.configure(config.kind, config.limits);
+        Ok(())
+    }
+
+    /// Get a provider instance, building it from its configuration on first use
+    pub fn provider(&self, kind: ProviderKind) -> Result<Arc<dyn LlmProvider>, String> {
+        if let Some(provider) = self.providers.read().get(&kind) {
+            return Ok(Arc::clone(provider));
+        }
+
+        let config = self.get_provider_config(kind)?;
+        let provider = Self::build_provider(&config)?;
+        self.providers.write().insert(kind, Arc::clone(&provider));
+        self.limiter.configure(kind, config.limits);
+
+        Ok(provider)
+    }
+
+    /// Serve `kind` with `provider` instead of one built from its configuration
+    #[cfg(test)]
+    pub fn set_provider(&self, kind: ProviderKind, provider: Arc<dyn LlmProvider>) {
+        self.providers.write().insert(kind, provider);
+    }
+
+    /// Run a request to completion through the provider's rate limiter,
+    /// retrying on 429/529 responses
+    pub async fn complete(
+        &self,
+        kind: ProviderKind,
+        request: &CompletionRequest,
+    ) -> Result<CompletionResponse, String> {
+        let provider = self.provider(kind)?;
+        let estimated = estimate_request_tokens(request);
+        let mut attempt = 0;
+
+        loop {
+            let permit = self.limiter.acquire(kind, estimated).await;
+            match provider.complete(request).await {
+                Ok(response) => {
+                    permit.settle(response.usage.total());
+                    return Ok(response);
+                }
+                Err(e) => {
+                    drop(permit);
+                    attempt += 1;
+                    self.back_off(kind, e, attempt)?;
+                }
+            }
+        }
+    }
+
+    /// Start a streaming request through the provider's rate limiter. The
+    /// concurrency slot is held until the returned stream is dropped.
+    pub async fn stream(
+        &self,
+        kind: ProviderKind,
+        request: &CompletionRequest,
+    ) -> Result<CompletionStream, String> {
+        let provider = self.provider(kind)?;
+        let estimated = estimate_request_tokens(request);
+        let mut attempt = 0;
+
+        loop {
+            let permit = self.limiter.acquire(kind, estimated).await;
+            match provider.stream(request).await {
+                Ok(stream) => {
+                    return Ok(stream
+                        .map(move |event| {
+                            if let Ok(StreamEvent::Done { usage, .. }) = &event {
+                                permit.settle(usage.total());
+                            }
+                            event
+                        })
+                        .boxed());
+                }
+                Err(e) => {
+                    drop(permit);
+                    attempt += 1;
+                    self.back_off(kind, e, attempt)?;
+                }
+            }
+        }
+    }
+
+    /// Count a request's input tokens through the provider's rate limiter.
+    /// The call counts as a request but reserves no tokens.
+    pub async fn count_tokens(
+        &self,
+        kind: ProviderKind,
+        request: &CompletionRequest,
+    ) -> Result<u64, String> {
+        let provider = self.provider(kind)?;
+        let mut attempt = 0;
+
+        loop {
+            let permit = self.limiter.acquire(kind, 0).await;
+            match provider.count_tokens(request).await {
+                Ok(tokens) => return Ok(tokens),
+                Err(e) => {
+                    drop(permit);
+                    attempt += 1;
+                    self.back_off(kind, e, attempt)?;
+                }
+            }
+        }
+    }
+
+    /// Pause the provider after a rate-limit or overload error so the next
+    /// attempt (from any caller) waits. Other errors are returned as-is.
+    fn back_off(&self, kind: ProviderKind, error: LlmError, attempt: u32) -> Result<(), String> {
+        let retry_after = match &error {
+            LlmError::Api {
+                status: 429 | 503 | 529,
+                retry_after,
+                ..
+            } if attempt < MAX_ATTEMPTS => *retry_after,
+            _ => return Err(error.into()),
+        };
+
+        let delay = retry_after
+            .unwrap_or_else(|| BASE_BACKOFF.saturating_mul(1 << (attempt - 1).min(5)))
+            .min(MAX_BACKOFF);
+        log::warn!(
+            "{} rate limited or overloaded ({}); retrying in {:.1}s",
+            kind.as_str(),
+            error,
+            delay.as_secs_f64()
+        );
+        self.limiter.pause(kind, delay);
+
+        Ok(())
+    }
+
+    /// Whether calls to a provider would currently have to wait
+    pub fn is_saturated(&self, kind: ProviderKind) -> bool {
+        self.limiter.is_saturated(kind)
+    }
+
+    pub fn rate_limit_status(&self) -> Vec<RateLimitStatus> {
+        self.limiter.status()
+    }
+
+    /// Resolve a model name or alias and return the provider that serves it
+    pub fn resolve(&self, model: &str) -> Result<(Arc<dyn LlmProvider>, ResolvedModel), String> {
+        let resolved = resolve_model(model);
+        let provider = self.provider(resolved.provider)?;
+        Ok((provider, resolved))
+    }
+
+    fn build_provider(config: &ProviderConfig) -> Result<Arc<dyn LlmProvider>, LlmError> {
+        if !config.enabled {
+            return Err(LlmError::NotConfigured(format!(
+                "{} provider is disabled",
+                config.kind.as_str()
+            )));
+        }
+
+        let timeout = Duration::from_secs(config.timeout_secs);
+
+        let provider: Arc<dyn LlmProvider> = match config.kind {
+            ProviderKind::Anthropic => {
+                let api_key = config.effective_api_key().ok_or_else(|| {
+                    LlmError::NotConfigured("missing Anthropic API key".to_string())
+                })?;
+                Arc::new(AnthropicProvider::new(&config.base_url, api_key, timeout)?)
+            }
+            ProviderKind::OpenAi => Arc::new(OpenAiProvider::new(
+                &config.base_url,
+                config.effective_api_key(),
+                timeout,
+            )?),
+            ProviderKind::Ollama => Arc::new(OllamaProvider::new(&config.base_url, timeout)?),
+        };
+
+        Ok(provider)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::{Message, MockProvider};
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_default_providers_seeded() {
+        let dir = tempdir().unwrap();
+        let manager = LlmManager::new(Database::new(dir.path().to_path_buf()).unwrap());
+
+        let providers = manager.list_providers().unwrap();
+        let kinds: Vec<ProviderKind> = providers.iter().map(|p| p.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ProviderKind::Anthropic,
+                ProviderKind::Ollama,
+                ProviderKind::OpenAi
+            ]
+        );
+    }
+
+    #[test]
+    fn test_update_provider_and_resolve() {
+        let dir = tempdir().unwrap();
+        let manager = LlmManager::new(Database::new(dir.path().to_path_buf()).unwrap());
+
+        let mut config = manager
+            .get_provider_config(ProviderKind::Anthropic)
+            .unwrap();
+        config.api_key = Some("sk-ant-test".to_string());
+        config.base_url = "http://127.0.0.1:1".to_string();
+        manager.update_provider(&config).unwrap();
+
+        let (_, resolved) = manager.resolve("opus").unwrap();
+        assert_eq!(resolved.provider, ProviderKind::Anthropic);
+        assert_eq!(resolved.model_id, "claude-opus-4-1");
+
+        let mut ollama = manager.get_provider_config(ProviderKind::Ollama).unwrap();
+        ollama.enabled = false;
+        manager.update_provider(&ollama).unwrap();
+        assert!(manager.resolve("llama3.1").is_err());
+    }
+
+    #[test]
+    fn test_back_off_only_retries_rate_limits() {
+        let dir = tempdir().unwrap();
+        let manager = LlmManager::new(Database::new(dir.path().to_path_buf()).unwrap());
+
+        let api_error = |status, retry_after| LlmError::Api {
+            status,
+            message: "slow down".to_string(),
+            retry_after,
+        };
+
+        assert!(manager
+            .back_off(ProviderKind::Anthropic, api_error(400, None), 1)
+            .is_err());
+        assert!(manager
+            .back_off(
+                ProviderKind::Anthropic,
+                api_error(429, Some(Duration::from_secs(5))),
+                1
+            )
+            .is_ok());
+        assert!(manager.is_saturated(ProviderKind::Anthropic));
+        assert!(manager
+            .back_off(ProviderKind::Anthropic, api_error(529, None), MAX_ATTEMPTS)
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_count_tokens_goes_through_the_limiter() {
+        let dir = tempdir().unwrap();
+        let manager = LlmManager::new(Database::new(dir.path().to_path_buf()).unwrap());
+        manager.set_provider(
+            ProviderKind::Ollama,
+            Arc::new(MockProvider::new(&[]).unwrap()),
+        );
+
+        let request = CompletionRequest {
+            model: "mock".to_string(),
+            system: None,
+            messages: vec![Message::user("Count these words")],
+            max_tokens: 1,
+            temperature: None,
+            stop_sequences: vec![],
+        };
+        let tokens = manager
+            .count_tokens(ProviderKind::Ollama, &request)
+            .await
+            .unwrap();
+        assert!(tokens > 0);
+
+        let status = manager.rate_limit_status();
+        let ollama = status
+            .iter()
+            .find(|s| s.provider == ProviderKind::Ollama)
+            .unwrap();
+        assert_eq!(ollama.requests_last_minute, 1);
+        assert_eq!(ollama.tokens_last_minute, 0);
+    }
+}
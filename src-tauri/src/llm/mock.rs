@@ -0,0 +1,97 @@
+//! Offline mock backend
+//!
+//! Answers from a list of rules instead of a model, so evaluations can run
+//! without network access or an API key. The first rule whose regex matches
+//! the last user message gives the reply; with no match the message is
+//! echoed back. Token usage is estimated.
+
+use super::provider::{
+    estimate_request_tokens, estimate_tokens, CompletionRequest, CompletionResponse,
+    CompletionStream, LlmError, LlmProvider, StreamEvent, Usage,
+};
+use async_trait::async_trait;
+use futures::stream::{self, StreamExt};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// Model name that selects the mock backend, alone or as `mock:<label>`
+pub const MOCK_MODEL: &str = "mock";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MockRule {
+    /// Regex matched against the last user message
+    pub pattern: String,
+    pub reply: String,
+}
+
+pub struct MockProvider {
+    rules: Vec<(Regex, String)>,
+}
+
+impl MockProvider {
+    pub fn new(rules: &[MockRule]) -> Result<Self, String> {
+        let rules = rules
+            .iter()
+            .map(|rule| {
+                Regex::new(&rule.pattern)
+                    .map(|regex| (regex, rule.reply.clone()))
+                    .map_err(|e| format!("Invalid mock pattern '{}': {}", rule.pattern, e))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self { rules })
+    }
+
+    /// Whether `model` names the mock backend
+    pub fn handles(model: &str) -> bool {
+        let model = model.trim();
+        model == MOCK_MODEL || model.starts_with("mock:")
+    }
+
+    fn reply(&self, request: &CompletionRequest) -> String {
+        let input = request
+            .messages
+            .last()
+            .map(|m| m.content.as_str())
+            .unwrap_or_default();
+        self.rules
+            .iter()
+            .find(|(regex, _)| regex.is_match(input))
+            .map(|(_, reply)| reply.clone())
+            .unwrap_or_else(|| input.to_string())
+    }
+}
+
+#[async_trait]
+impl LlmProvider for MockProvider {
+    async fn complete(&self, request: &CompletionRequest) -> Result<CompletionResponse, LlmError> {
+        let content = self.reply(request);
+        Ok(CompletionResponse {
+            model: request.model.clone(),
+            usage: Usage {
+                input_tokens: estimate_request_tokens(request),
+                output_tokens: estimate_tokens(&content),
+                ..Default::default()
+            },
+            content,
+            stop_reason: Some("end_turn".to_string()),
+        })
+    }
+
+    async fn stream(&self, request: &CompletionRequest) -> Result<CompletionStream, LlmError> {
+        let response = self.complete(request).await?;
+        Ok(stream::iter([
+            Ok(StreamEvent::Delta {
+                text: response.content,
+            }),
+            Ok(StreamEvent::Done {
+                stop_reason: response.stop_reason,
+                usage: response.usage,
+            }),
+        ])
+        .boxed())
+    }
+
+    async fn count_tokens(&self, request: &CompletionRequest) -> Result<u64, LlmError> {
+        Ok(estimate_request_tokens(request))
+    }
+}
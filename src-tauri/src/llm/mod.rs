@@ -0,0 +1,28 @@
+//! LLM module for Claud.io
+//!
+//! Provider abstraction over the Anthropic Messages API, OpenAI-compatible
+//! chat completions and local Ollama, plus model alias resolution and an
+//! offline mock backend for evaluations.
+
+mod anthropic;
+pub mod commands;
+mod limiter;
+mod manager;
+mod mock;
+mod models;
+mod ollama;
+mod openai;
+mod provider;
+mod stream;
+#[cfg(test)]
+mod test_server;
+
+pub use manager::LlmManager;
+pub use mock::{MockProvider, MockRule};
+pub use models::{context_window, resolve_model};
+pub use provider::{
+    estimate_tokens, CompletionRequest, CompletionResponse, LlmProvider, Message, ProviderKind,
+    StreamEvent, Usage,
+};
+#[cfg(test)]
+pub use provider::{CompletionStream, LlmError};
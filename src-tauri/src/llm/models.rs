@@ -0,0 +1,145 @@
+//! Model alias resolution
+//!
+//! Agents and agent definitions name models loosely (`sonnet`, `opus`,
+//! `gpt-4o`, `ollama:llama3.1`). This maps those names to a provider and a
+//! concrete model id.
+
+use super::provider::ProviderKind;
+use serde::{Deserialize, Serialize};
+
+/// Alias used when an agent has no model configured
+pub const DEFAULT_MODEL: &str = "sonnet";
+
+const ANTHROPIC_ALIASES: &[(&str, &str)] = &[
+    ("sonnet", "claude-sonnet-4-5"),
+    ("opus", "claude-opus-4-1"),
+    ("haiku", "claude-haiku-4-5"),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResolvedModel {
+    pub provider: ProviderKind,
+    #[serde(rename = "modelId")]
+    pub model_id: String,
+}
+
+/// Resolve a model name or alias to a provider and model id.
+///
+/// An explicit `provider:model` prefix always wins. Otherwise Anthropic
+/// aliases and `claude-*` ids go to Anthropic, `gpt-*`/`o*` ids to OpenAI,
+/// and anything else is assumed to be a local Ollama model.
+pub fn resolve_model(name: &str) -> ResolvedModel {
+    let name = name.trim();
+    let name = if name.is_empty() { DEFAULT_MODEL } else { name };
+
+    if let Some((prefix, model)) = name.split_once(':') {
+        if let Some(provider) = ProviderKind::parse(prefix) {
+            let model_id = match provider {
+                ProviderKind::Anthropic => anthropic_alias(model).unwrap_or(model),
+                _ => model,
+            };
+            return ResolvedModel {
+                provider,
+                model_id: model_id.to_string(),
+            };
+        }
+    }
+
+    if let Some(model_id) = anthropic_alias(name) {
+        return ResolvedModel {
+            provider: ProviderKind::Anthropic,
+            model_id: model_id.to_string(),
+        };
+    }
+
+    let provider = if name.starts_with("claude-") {
+        ProviderKind::Anthropic
+    } else if is_openai_model(name) {
+        ProviderKind::OpenAi
+    } else {
+        ProviderKind::Ollama
+    };
+
+    ResolvedModel {
+        provider,
+        model_id: name.to_string(),
+    }
+}
+
+/// Context window (in tokens) for a resolved model id
+pub fn context_window(model_id: &str) -> u64 {
+    if model_id.starts_with("claude-") {
+        200_000
+    } else if model_id.starts_with("gpt-4.1") {
+        1_000_000
+    } else if model_id.starts_with("gpt-3.5") {
+        16_385
+    } else if is_openai_model(model_id) {
+        128_000
+    } else {
+        // Ollama's default context length unless the model is configured otherwise
+        8_192
+    }
+}
+
+fn anthropic_alias(name: &str) -> Option<&'static str> {
+    ANTHROPIC_ALIASES
+        .iter()
+        .find(|(alias, _)| alias.eq_ignore_ascii_case(name))
+        .map(|(_, id)| *id)
+}
+
+fn is_openai_model(name: &str) -> bool {
+    name.starts_with("gpt-")
+        || name.starts_with("chatgpt-")
+        || (name.starts_with('o') && name[1..].starts_with(|c: char| c.is_ascii_digit()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_aliases() {
+        let sonnet = resolve_model("sonnet");
+        assert_eq!(sonnet.provider, ProviderKind::Anthropic);
+        assert_eq!(sonnet.model_id, "claude-sonnet-4-5");
+
+        assert_eq!(resolve_model("Opus").model_id, "claude-opus-4-1");
+        assert_eq!(resolve_model("").model_id, "claude-sonnet-4-5");
+    }
+
+    #[test]
+    fn test_resolve_by_prefix() {
+        assert_eq!(resolve_model("claude-3-5-haiku-latest").provider, ProviderKind::Anthropic);
+        assert_eq!(resolve_model("gpt-4o").provider, ProviderKind::OpenAi);
+        assert_eq!(resolve_model("o3-mini").provider, ProviderKind::OpenAi);
+        assert_eq!(resolve_model("llama3.1").provider, ProviderKind::Ollama);
+        assert_eq!(resolve_model("opencoder").provider, ProviderKind::Ollama);
+    }
+
+    #[test]
+    fn test_context_window() {
+        assert_eq!(context_window("claude-sonnet-4-5"), 200_000);
+        assert_eq!(context_window("gpt-4o-mini"), 128_000);
+        assert_eq!(context_window("llama3.1"), 8_192);
+    }
+
+    #[test]
+    fn test_resolve_explicit_provider() {
+        let model = resolve_model("ollama:qwen2.5-coder:7b");
+        assert_eq!(model.provider, ProviderKind::Ollama);
+        assert_eq!(model.model_id, "qwen2.5-coder:7b");
+
+        let model = resolve_model("openai:my-finetune");
+        assert_eq!(model.provider, ProviderKind::OpenAi);
+        assert_eq!(model.model_id, "my-finetune");
+
+        assert_eq!(resolve_model("anthropic:haiku").model_id, "claude-haiku-4-5");
+
+        // Ollama tags are not provider prefixes
+        let model = resolve_model("llama3.1:8b");
+        assert_eq!(model.provider, ProviderKind::Ollama);
+        assert_eq!(model.model_id, "llama3.1:8b");
+    }
+}
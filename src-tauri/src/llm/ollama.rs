@@ -0,0 +1,262 @@
+//! Local Ollama backend (`/api/chat`)
+
+use super::provider::{
+    api_error, estimate_request_tokens, CompletionRequest, CompletionResponse, CompletionStream,
+    LlmError, LlmProvider, StreamEvent, Usage,
+};
+use super::stream::lines;
+use async_trait::async_trait;
+use futures::stream::StreamExt;
+use serde_json::{json, Value};
+use std::time::Duration;
+
+pub struct OllamaProvider {
+    client: reqwest::Client,
+    base_url: String,
+    timeout: Duration,
+}
+
+impl OllamaProvider {
+    pub fn new(base_url: &str, timeout: Duration) -> Result<Self, LlmError> {
+        let client = reqwest::Client::builder()
+            .connect_timeout(Duration::from_secs(5))
+            .build()?;
+
+        Ok(Self {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            timeout,
+        })
+    }
+
+    fn body(request: &CompletionRequest, stream: bool) -> Value {
+        let mut messages = Vec::new();
+        if let Some(system) = &request.system {
+            messages.push(json!({ "role": "system", "content": system }));
+        }
+        for message in &request.messages {
+            messages.push(json!({ "role": message.role.as_str(), "content": message.content }));
+        }
+
+        let mut options = json!({ "num_predict": request.max_tokens });
+        if let Some(temperature) = request.temperature {
+            options["temperature"] = json!(temperature);
+        }
+        if !request.stop_sequences.is_empty() {
+            options["stop"] = json!(request.stop_sequences);
+        }
+
+        json!({
+            "model": request.model,
+            "messages": messages,
+            "stream": stream,
+            "options": options,
+        })
+    }
+}
+
+fn parse_usage(value: &Value) -> Usage {
+    Usage {
+        input_tokens: value
+            .get("prompt_eval_count")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(0),
+        output_tokens: value
+            .get("eval_count")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(0),
+        ..Default::default()
+    }
+}
+
+fn parse_stream_line(line: &str) -> Option<Result<StreamEvent, LlmError>> {
+    if line.trim().is_empty() {
+        return None;
+    }
+
+    let chunk: Value = match serde_json::from_str(line) {
+        Ok(v) => v,
+        Err(e) => return Some(Err(LlmError::InvalidResponse(e.to_string()))),
+    };
+
+    if let Some(error) = chunk.get("error").and_then(|e| e.as_str()) {
+        return Some(Err(LlmError::Api {
+            status: 500,
+            message: error.to_string(),
+            retry_after: None,
+        }));
+    }
+
+    if chunk.get("done").and_then(|d| d.as_bool()).unwrap_or(false) {
+        return Some(Ok(StreamEvent::Done {
+            stop_reason: chunk
+                .get("done_reason")
+                .and_then(|r| r.as_str())
+                .map(|r| r.to_string()),
+            usage: parse_usage(&chunk),
+        }));
+    }
+
+    chunk
+        .pointer("/message/content")
+        .and_then(|c| c.as_str())
+        .filter(|c| !c.is_empty())
+        .map(|text| {
+            Ok(StreamEvent::Delta {
+                text: text.to_string(),
+            })
+        })
+}
+
+#[async_trait]
+impl LlmProvider for OllamaProvider {
+    async fn complete(&self, request: &CompletionRequest) -> Result<CompletionResponse, LlmError> {
+        let response = self
+            .client
+            .post(format!("{}/api/chat", self.base_url))
+            .timeout(self.timeout)
+            .json(&Self::body(request, false))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(api_error(response).await);
+        }
+
+        let value: Value = response.json().await?;
+
+        Ok(CompletionResponse {
+            model: value
+                .get("model")
+                .and_then(|m| m.as_str())
+                .unwrap_or(&request.model)
+                .to_string(),
+            content: value
+                .pointer("/message/content")
+                .and_then(|c| c.as_str())
+                .ok_or_else(|| LlmError::InvalidResponse("missing message".to_string()))?
+                .to_string(),
+            stop_reason: value
+                .get("done_reason")
+                .and_then(|r| r.as_str())
+                .map(|r| r.to_string()),
+            usage: parse_usage(&value),
+        })
+    }
+
+    async fn stream(&self, request: &CompletionRequest) -> Result<CompletionStream, LlmError> {
+        let response = self
+            .client
+            .post(format!("{}/api/chat", self.base_url))
+            .json(&Self::body(request, true))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(api_error(response).await);
+        }
+
+        Ok(lines(response)
+            .filter_map(|line| async move {
+                match line {
+                    Ok(line) => parse_stream_line(&line),
+                    Err(e) => Some(Err(e)),
+                }
+            })
+            .boxed())
+    }
+
+    /// Ollama has no tokenizer endpoint; fall back to an estimate
+    async fn count_tokens(&self, request: &CompletionRequest) -> Result<u64, LlmError> {
+        Ok(estimate_request_tokens(request))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::provider::Message;
+    use crate::llm::test_server::MockServer;
+
+    fn request() -> CompletionRequest {
+        CompletionRequest {
+            model: "llama3.1".to_string(),
+            system: None,
+            messages: vec![Message::user("Why is the sky blue?")],
+            max_tokens: 100,
+            temperature: None,
+            stop_sequences: vec![],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_complete() {
+        let server = MockServer::start(
+            200,
+            &[],
+            r#"{"model":"llama3.1","message":{"role":"assistant","content":"Rayleigh scattering."},
+                "done":true,"done_reason":"stop","prompt_eval_count":15,"eval_count":4}"#,
+        )
+        .await;
+        let provider = OllamaProvider::new(&server.url, Duration::from_secs(5)).unwrap();
+
+        let response = provider.complete(&request()).await.unwrap();
+        assert_eq!(response.content, "Rayleigh scattering.");
+        assert_eq!(response.usage.input_tokens, 15);
+        assert_eq!(response.usage.output_tokens, 4);
+
+        let recorded = server.requests();
+        assert_eq!(recorded[0].path, "/api/chat");
+        assert_eq!(recorded[0].body["stream"], false);
+        assert_eq!(recorded[0].body["options"]["num_predict"], 100);
+    }
+
+    #[tokio::test]
+    async fn test_stream() {
+        let body = [
+            r#"{"message":{"role":"assistant","content":"Ray"},"done":false}"#,
+            r#"{"message":{"role":"assistant","content":"leigh"},"done":false}"#,
+            r#"{"message":{"role":"assistant","content":""},"done":true,"done_reason":"stop","prompt_eval_count":15,"eval_count":2}"#,
+        ]
+        .join("\n");
+        let server =
+            MockServer::start(200, &[("content-type", "application/x-ndjson")], &body).await;
+        let provider = OllamaProvider::new(&server.url, Duration::from_secs(5)).unwrap();
+
+        let events: Vec<StreamEvent> = provider
+            .stream(&request())
+            .await
+            .unwrap()
+            .map(|e| e.unwrap())
+            .collect()
+            .await;
+
+        assert_eq!(
+            events,
+            vec![
+                StreamEvent::Delta {
+                    text: "Ray".to_string()
+                },
+                StreamEvent::Delta {
+                    text: "leigh".to_string()
+                },
+                StreamEvent::Done {
+                    stop_reason: Some("stop".to_string()),
+                    usage: Usage {
+                        input_tokens: 15,
+                        output_tokens: 2,
+                        ..Default::default()
+                    },
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_count_tokens_is_estimated() {
+        let provider = OllamaProvider::new("http://127.0.0.1:9", Duration::from_secs(1)).unwrap();
+        let tokens = provider.count_tokens(&request()).await.unwrap();
+        assert_eq!(tokens, estimate_request_tokens(&request()));
+        assert!(tokens > 0);
+    }
+}
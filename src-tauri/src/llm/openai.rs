@@ -0,0 +1,326 @@
+//! OpenAI-compatible chat completions backend
+//!
+//! Works with api.openai.com and any server exposing the same
+//! `/chat/completions` endpoint (vLLM, LM Studio, OpenRouter, ...).
+
+use super::provider::{
+    api_error, estimate_request_tokens, CompletionRequest, CompletionResponse, CompletionStream,
+    LlmError, LlmProvider, StreamEvent, Usage,
+};
+use super::stream::{lines, sse_data};
+use async_trait::async_trait;
+use futures::future;
+use futures::stream::StreamExt;
+use serde_json::{json, Value};
+use std::time::Duration;
+
+pub struct OpenAiProvider {
+    client: reqwest::Client,
+    base_url: String,
+    api_key: Option<String>,
+    timeout: Duration,
+}
+
+impl OpenAiProvider {
+    pub fn new(
+        base_url: &str,
+        api_key: Option<String>,
+        timeout: Duration,
+    ) -> Result<Self, LlmError> {
+        let client = reqwest::Client::builder()
+            .connect_timeout(Duration::from_secs(10))
+            .build()?;
+
+        Ok(Self {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key,
+            timeout,
+        })
+    }
+
+    fn request(&self) -> reqwest::RequestBuilder {
+        let builder = self
+            .client
+            .post(format!("{}/chat/completions", self.base_url));
+
+        match &self.api_key {
+            Some(key) => builder.bearer_auth(key),
+            None => builder,
+        }
+    }
+
+    fn body(request: &CompletionRequest, stream: bool) -> Value {
+        let mut messages = Vec::new();
+        if let Some(system) = &request.system {
+            messages.push(json!({ "role": "system", "content": system }));
+        }
+        for message in &request.messages {
+            messages.push(json!({ "role": message.role.as_str(), "content": message.content }));
+        }
+
+        let mut body = json!({
+            "model": request.model,
+            "max_tokens": request.max_tokens,
+            "messages": messages,
+        });
+
+        if let Some(temperature) = request.temperature {
+            body["temperature"] = json!(temperature);
+        }
+        if !request.stop_sequences.is_empty() {
+            body["stop"] = json!(request.stop_sequences);
+        }
+        if stream {
+            body["stream"] = json!(true);
+            body["stream_options"] = json!({ "include_usage": true });
+        }
+
+        body
+    }
+}
+
+/// `prompt_tokens` includes cached tokens; split them out so cached input
+/// is not counted twice
+fn parse_usage(value: &Value) -> Usage {
+    let prompt = value
+        .get("prompt_tokens")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(0);
+    let cached = value
+        .pointer("/prompt_tokens_details/cached_tokens")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(0);
+
+    Usage {
+        input_tokens: prompt.saturating_sub(cached),
+        output_tokens: value
+            .get("completion_tokens")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(0),
+        cache_creation_input_tokens: 0,
+        cache_read_input_tokens: cached,
+    }
+}
+
+#[derive(Default)]
+struct StreamState {
+    usage: Usage,
+    stop_reason: Option<String>,
+}
+
+fn parse_stream_line(state: &mut StreamState, line: &str) -> Option<Result<StreamEvent, LlmError>> {
+    let data = sse_data(line)?;
+
+    if data == "[DONE]" {
+        return Some(Ok(StreamEvent::Done {
+            stop_reason: state.stop_reason.take(),
+            usage: state.usage,
+        }));
+    }
+
+    let chunk: Value = match serde_json::from_str(data) {
+        Ok(v) => v,
+        Err(e) => return Some(Err(LlmError::InvalidResponse(e.to_string()))),
+    };
+
+    if let Some(usage) = chunk.get("usage").filter(|u| !u.is_null()) {
+        state.usage = parse_usage(usage);
+    }
+
+    let choice = chunk.pointer("/choices/0")?;
+    if let Some(reason) = choice.get("finish_reason").and_then(|r| r.as_str()) {
+        state.stop_reason = Some(reason.to_string());
+    }
+
+    choice
+        .pointer("/delta/content")
+        .and_then(|c| c.as_str())
+        .filter(|c| !c.is_empty())
+        .map(|text| {
+            Ok(StreamEvent::Delta {
+                text: text.to_string(),
+            })
+        })
+}
+
+#[async_trait]
+impl LlmProvider for OpenAiProvider {
+    async fn complete(&self, request: &CompletionRequest) -> Result<CompletionResponse, LlmError> {
+        let response = self
+            .request()
+            .timeout(self.timeout)
+            .json(&Self::body(request, false))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(api_error(response).await);
+        }
+
+        let value: Value = response.json().await?;
+        let choice = value
+            .pointer("/choices/0")
+            .ok_or_else(|| LlmError::InvalidResponse("missing choices".to_string()))?;
+
+        Ok(CompletionResponse {
+            model: value
+                .get("model")
+                .and_then(|m| m.as_str())
+                .unwrap_or(&request.model)
+                .to_string(),
+            content: choice
+                .pointer("/message/content")
+                .and_then(|c| c.as_str())
+                .unwrap_or_default()
+                .to_string(),
+            stop_reason: choice
+                .get("finish_reason")
+                .and_then(|r| r.as_str())
+                .map(|r| r.to_string()),
+            usage: value.get("usage").map(parse_usage).unwrap_or_default(),
+        })
+    }
+
+    async fn stream(&self, request: &CompletionRequest) -> Result<CompletionStream, LlmError> {
+        let response = self
+            .request()
+            .header("accept", "text/event-stream")
+            .json(&Self::body(request, true))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(api_error(response).await);
+        }
+
+        Ok(lines(response)
+            .scan(StreamState::default(), |state, line| {
+                let event = match line {
+                    Ok(line) => parse_stream_line(state, &line),
+                    Err(e) => Some(Err(e)),
+                };
+                future::ready(Some(event))
+            })
+            .filter_map(future::ready)
+            .boxed())
+    }
+
+    /// The chat completions API has no token counting endpoint
+    async fn count_tokens(&self, request: &CompletionRequest) -> Result<u64, LlmError> {
+        Ok(estimate_request_tokens(request))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::provider::Message;
+    use crate::llm::test_server::MockServer;
+
+    fn request() -> CompletionRequest {
+        CompletionRequest {
+            model: "gpt-4o".to_string(),
+            system: Some("Be brief.".to_string()),
+            messages: vec![Message::user("Hello")],
+            max_tokens: 32,
+            temperature: Some(0.2),
+            stop_sequences: vec!["END".to_string()],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_complete() {
+        let server = MockServer::start(
+            200,
+            &[],
+            r#"{"model":"gpt-4o-2024","choices":[{"message":{"role":"assistant","content":"Hey"},"finish_reason":"stop"}],
+                "usage":{"prompt_tokens":20,"completion_tokens":2,"prompt_tokens_details":{"cached_tokens":8}}}"#,
+        )
+        .await;
+        let provider = OpenAiProvider::new(
+            &server.url,
+            Some("sk-test".to_string()),
+            Duration::from_secs(5),
+        )
+        .unwrap();
+
+        let response = provider.complete(&request()).await.unwrap();
+        assert_eq!(response.content, "Hey");
+        assert_eq!(response.model, "gpt-4o-2024");
+        assert_eq!(response.stop_reason.as_deref(), Some("stop"));
+        assert_eq!(response.usage.input_tokens, 12);
+        assert_eq!(response.usage.cache_read_input_tokens, 8);
+        assert_eq!(response.usage.output_tokens, 2);
+
+        let recorded = server.requests();
+        assert_eq!(recorded[0].path, "/chat/completions");
+        assert_eq!(recorded[0].header("authorization"), Some("Bearer sk-test"));
+        assert_eq!(recorded[0].body["messages"][0]["role"], "system");
+        assert_eq!(recorded[0].body["messages"][1]["content"], "Hello");
+        assert_eq!(recorded[0].body["stop"][0], "END");
+    }
+
+    #[tokio::test]
+    async fn test_stream() {
+        let body = [
+            r#"data: {"choices":[{"delta":{"role":"assistant","content":""}}]}"#,
+            "",
+            r#"data: {"choices":[{"delta":{"content":"Hi "}}]}"#,
+            "",
+            r#"data: {"choices":[{"delta":{"content":"there"},"finish_reason":"stop"}]}"#,
+            "",
+            r#"data: {"choices":[],"usage":{"prompt_tokens":9,"completion_tokens":2}}"#,
+            "",
+            "data: [DONE]",
+            "",
+        ]
+        .join("\n");
+        let server = MockServer::start(200, &[("content-type", "text/event-stream")], &body).await;
+        let provider = OpenAiProvider::new(&server.url, None, Duration::from_secs(5)).unwrap();
+
+        let events: Vec<StreamEvent> = provider
+            .stream(&request())
+            .await
+            .unwrap()
+            .map(|e| e.unwrap())
+            .collect()
+            .await;
+
+        assert_eq!(events.len(), 3);
+        assert_eq!(
+            events[0],
+            StreamEvent::Delta {
+                text: "Hi ".to_string()
+            }
+        );
+        assert_eq!(
+            events[2],
+            StreamEvent::Done {
+                stop_reason: Some("stop".to_string()),
+                usage: Usage {
+                    input_tokens: 9,
+                    output_tokens: 2,
+                    ..Default::default()
+                },
+            }
+        );
+        assert!(server.requests()[0].header("authorization").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_server_error() {
+        let server = MockServer::start(503, &[], r#"{"error":{"message":"overloaded"}}"#).await;
+        let provider = OpenAiProvider::new(&server.url, None, Duration::from_secs(5)).unwrap();
+
+        match provider.complete(&request()).await {
+            Err(LlmError::Api {
+                status, message, ..
+            }) => {
+                assert_eq!(status, 503);
+                assert_eq!(message, "overloaded");
+            }
+            other => panic!("expected API error, got {:?}", other),
+        }
+    }
+}
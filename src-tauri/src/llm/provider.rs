@@ -0,0 +1,243 @@
+//! Provider abstraction shared by all LLM backends
+
+use async_trait::async_trait;
+use futures::stream::BoxStream;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Supported LLM backends
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProviderKind {
+    Anthropic,
+    #[serde(rename = "openai")]
+    OpenAi,
+    Ollama,
+}
+
+impl ProviderKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProviderKind::Anthropic => "anthropic",
+            ProviderKind::OpenAi => "openai",
+            ProviderKind::Ollama => "ollama",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "anthropic" => Some(ProviderKind::Anthropic),
+            "openai" => Some(ProviderKind::OpenAi),
+            "ollama" => Some(ProviderKind::Ollama),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    User,
+    Assistant,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::User => "user",
+            Role::Assistant => "assistant",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Message {
+    pub role: Role,
+    pub content: String,
+}
+
+impl Message {
+    pub fn user(content: impl Into<String>) -> Self {
+        Self {
+            role: Role::User,
+            content: content.into(),
+        }
+    }
+
+    pub fn assistant(content: impl Into<String>) -> Self {
+        Self {
+            role: Role::Assistant,
+            content: content.into(),
+        }
+    }
+}
+
+/// A provider-neutral completion request. `model` must already be a
+/// provider model id (see `models::resolve_model`), not an alias.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompletionRequest {
+    pub model: String,
+    pub system: Option<String>,
+    pub messages: Vec<Message>,
+    #[serde(rename = "maxTokens")]
+    pub max_tokens: u32,
+    pub temperature: Option<f32>,
+    #[serde(rename = "stopSequences", default)]
+    pub stop_sequences: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Usage {
+    #[serde(rename = "inputTokens")]
+    pub input_tokens: u64,
+    #[serde(rename = "outputTokens")]
+    pub output_tokens: u64,
+    #[serde(rename = "cacheCreationInputTokens")]
+    pub cache_creation_input_tokens: u64,
+    #[serde(rename = "cacheReadInputTokens")]
+    pub cache_read_input_tokens: u64,
+}
+
+impl Usage {
+    pub fn total(&self) -> u64 {
+        self.input_tokens
+            + self.output_tokens
+            + self.cache_creation_input_tokens
+            + self.cache_read_input_tokens
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompletionResponse {
+    pub model: String,
+    pub content: String,
+    #[serde(rename = "stopReason")]
+    pub stop_reason: Option<String>,
+    pub usage: Usage,
+}
+
+/// Incremental output from `LlmProvider::stream`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum StreamEvent {
+    Delta {
+        text: String,
+    },
+    Done {
+        #[serde(rename = "stopReason")]
+        stop_reason: Option<String>,
+        usage: Usage,
+    },
+}
+
+pub type CompletionStream = BoxStream<'static, Result<StreamEvent, LlmError>>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum LlmError {
+    #[error("HTTP error: {0}")]
+    Http(String),
+    #[error("API error ({status}): {message}")]
+    Api {
+        status: u16,
+        message: String,
+        retry_after: Option<Duration>,
+    },
+    #[error("Invalid response: {0}")]
+    InvalidResponse(String),
+    #[error("Provider not configured: {0}")]
+    NotConfigured(String),
+}
+
+impl From<reqwest::Error> for LlmError {
+    fn from(e: reqwest::Error) -> Self {
+        LlmError::Http(e.to_string())
+    }
+}
+
+impl From<LlmError> for String {
+    fn from(e: LlmError) -> Self {
+        e.to_string()
+    }
+}
+
+/// Common interface implemented by every LLM backend
+#[async_trait]
+pub trait LlmProvider: Send + Sync {
+    /// Run a request to completion and return the full response
+    async fn complete(&self, request: &CompletionRequest) -> Result<CompletionResponse, LlmError>;
+
+    /// Run a request and stream text deltas, ending with a `Done` event
+    async fn stream(&self, request: &CompletionRequest) -> Result<CompletionStream, LlmError>;
+
+    /// Count the input tokens the request would consume
+    async fn count_tokens(&self, request: &CompletionRequest) -> Result<u64, LlmError>;
+}
+
+/// Rough token estimate (~4 characters per token) for backends without a
+/// tokenizer endpoint
+pub fn estimate_tokens(text: &str) -> u64 {
+    (text.chars().count() as u64).div_ceil(4)
+}
+
+/// Estimate the input tokens of a whole request
+pub fn estimate_request_tokens(request: &CompletionRequest) -> u64 {
+    let system = request.system.as_deref().map(estimate_tokens).unwrap_or(0);
+    let messages: u64 = request
+        .messages
+        .iter()
+        // A few tokens of per-message framing overhead
+        .map(|m| estimate_tokens(&m.content) + 4)
+        .sum();
+    system + messages
+}
+
+/// Convert a non-success HTTP response into an `LlmError::Api`, keeping the
+/// `retry-after` hint when the server sent one
+pub(super) async fn api_error(response: reqwest::Response) -> LlmError {
+    let status = response.status().as_u16();
+    let retry_after = response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<f64>().ok())
+        // The header is server-controlled; ignore negative or absurd values
+        .and_then(|secs| Duration::try_from_secs_f64(secs).ok());
+
+    let body = response.text().await.unwrap_or_default();
+    let message = serde_json::from_str::<serde_json::Value>(&body)
+        .ok()
+        .and_then(|v| {
+            v.pointer("/error/message")
+                .or_else(|| v.get("error"))
+                .and_then(|m| m.as_str())
+                .map(|m| m.to_string())
+        })
+        .unwrap_or(body);
+
+    LlmError::Api {
+        status,
+        message,
+        retry_after,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_tokens() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("abcd"), 1);
+        assert_eq!(estimate_tokens("abcde"), 2);
+    }
+
+    #[test]
+    fn test_provider_kind_roundtrip() {
+        for kind in [ProviderKind::Anthropic, ProviderKind::OpenAi, ProviderKind::Ollama] {
+            assert_eq!(ProviderKind::parse(kind.as_str()), Some(kind));
+            let json = serde_json::to_string(&kind).unwrap();
+            assert_eq!(json, format!("\"{}\"", kind.as_str()));
+        }
+    }
+}
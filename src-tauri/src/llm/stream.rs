@@ -0,0 +1,48 @@
+//! Line splitting for streamed HTTP bodies
+//!
+//! Anthropic and OpenAI stream Server-Sent Events, Ollama streams
+//! newline-delimited JSON; both are consumed line by line.
+
+use super::provider::LlmError;
+use futures::stream::{BoxStream, StreamExt};
+
+/// Split a response body into lines as chunks arrive
+pub fn lines(response: reqwest::Response) -> BoxStream<'static, Result<String, LlmError>> {
+    let body = response.bytes_stream().boxed();
+
+    futures::stream::unfold(
+        (body, Vec::<u8>::new(), false),
+        |(mut body, mut buffer, mut finished)| async move {
+            loop {
+                if let Some(pos) = buffer.iter().position(|b| *b == b'\n') {
+                    let raw: Vec<u8> = buffer.drain(..=pos).collect();
+                    let line = String::from_utf8_lossy(&raw)
+                        .trim_end_matches(['\r', '\n'])
+                        .to_string();
+                    return Some((Ok(line), (body, buffer, finished)));
+                }
+
+                if finished {
+                    if buffer.is_empty() {
+                        return None;
+                    }
+                    let line = String::from_utf8_lossy(&buffer).to_string();
+                    buffer.clear();
+                    return Some((Ok(line), (body, buffer, finished)));
+                }
+
+                match body.next().await {
+                    Some(Ok(chunk)) => buffer.extend_from_slice(&chunk),
+                    Some(Err(e)) => return Some((Err(e.into()), (body, buffer, true))),
+                    None => finished = true,
+                }
+            }
+        },
+    )
+    .boxed()
+}
+
+/// Extract the payload of an SSE `data:` line
+pub fn sse_data(line: &str) -> Option<&str> {
+    line.strip_prefix("data:").map(|d| d.trim_start())
+}
@@ -0,0 +1,141 @@
+//! Minimal HTTP server for provider contract tests
+//!
+//! Serves a canned response to every request and records what was sent.
+
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub path: String,
+    pub headers: HashMap<String, String>,
+    pub body: serde_json::Value,
+}
+
+impl RecordedRequest {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).map(|v| v.as_str())
+    }
+}
+
+#[derive(Clone)]
+struct CannedResponse {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: String,
+}
+
+pub struct MockServer {
+    pub url: String,
+    requests: Arc<Mutex<Vec<RecordedRequest>>>,
+}
+
+impl MockServer {
+    pub async fn start(status: u16, headers: &[(&str, &str)], body: &str) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let canned = CannedResponse {
+            status,
+            headers: headers
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            body: body.to_string(),
+        };
+
+        let recorded = Arc::clone(&requests);
+        tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                let canned = canned.clone();
+                let recorded = Arc::clone(&recorded);
+                tokio::spawn(async move {
+                    Self::handle(socket, &canned, &recorded).await;
+                });
+            }
+        });
+
+        Self { url, requests }
+    }
+
+    /// Requests received so far, in arrival order
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.requests.lock().clone()
+    }
+
+    async fn handle(
+        mut socket: TcpStream,
+        canned: &CannedResponse,
+        recorded: &Mutex<Vec<RecordedRequest>>,
+    ) -> Option<()> {
+        let mut data = Vec::new();
+        let mut buf = [0u8; 4096];
+
+        // Read until the end of the headers
+        let header_end = loop {
+            let n = socket.read(&mut buf).await.ok()?;
+            if n == 0 {
+                return None;
+            }
+            data.extend_from_slice(&buf[..n]);
+            if let Some(pos) = data.windows(4).position(|w| w == b"\r\n\r\n") {
+                break pos + 4;
+            }
+        };
+
+        let head = String::from_utf8_lossy(&data[..header_end]).to_string();
+        let mut head_lines = head.lines();
+        let mut request_line = head_lines.next()?.split_whitespace();
+        let method = request_line.next()?.to_string();
+        let path = request_line.next()?.to_string();
+
+        let headers: HashMap<String, String> = head_lines
+            .filter_map(|l| l.split_once(':'))
+            .map(|(k, v)| (k.trim().to_lowercase(), v.trim().to_string()))
+            .collect();
+
+        let content_length: usize = headers
+            .get("content-length")
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(0);
+
+        while data.len() < header_end + content_length {
+            let n = socket.read(&mut buf).await.ok()?;
+            if n == 0 {
+                break;
+            }
+            data.extend_from_slice(&buf[..n]);
+        }
+
+        let body = serde_json::from_slice(&data[header_end..]).unwrap_or(serde_json::Value::Null);
+        recorded.lock().push(RecordedRequest {
+            method,
+            path,
+            headers,
+            body,
+        });
+
+        let mut response = format!(
+            "HTTP/1.1 {} Mock\r\nContent-Length: {}\r\nConnection: close\r\n",
+            canned.status,
+            canned.body.len()
+        );
+        if !canned.headers.iter().any(|(k, _)| k.eq_ignore_ascii_case("content-type")) {
+            response.push_str("Content-Type: application/json\r\n");
+        }
+        for (name, value) in &canned.headers {
+            response.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        response.push_str("\r\n");
+        response.push_str(&canned.body);
+
+        socket.write_all(response.as_bytes()).await.ok()?;
+        socket.shutdown().await.ok();
+        Some(())
+    }
+}
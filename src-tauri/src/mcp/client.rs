@@ -0,0 +1,334 @@
+//! MCP client
+//!
+//! Talks newline-delimited JSON-RPC to an MCP server, normally a stdio
+//! subprocess. Responses are matched to requests by id, so calls from
+//! several tasks can share one connection.
+
+use super::protocol::{
+    CallToolResult, Request, Response, Tool, METHOD_NOT_FOUND, PROTOCOL_VERSION, SUPPORTED_VERSIONS,
+};
+use parking_lot::Mutex;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::process::{Child, Command};
+use tokio::sync::oneshot;
+
+const INIT_TIMEOUT: Duration = Duration::from_secs(30);
+const CALL_TIMEOUT: Duration = Duration::from_secs(120);
+/// Guards against servers that page forever
+const MAX_TOOL_PAGES: usize = 20;
+
+type Writer = Arc<tokio::sync::Mutex<Box<dyn AsyncWrite + Send + Unpin>>>;
+type Pending = Arc<Mutex<HashMap<u64, oneshot::Sender<Response>>>>;
+
+pub struct McpClient {
+    writer: Writer,
+    pending: Pending,
+    next_id: AtomicU64,
+    closed: Arc<AtomicBool>,
+    server_name: String,
+    // Killed when the client is dropped
+    _child: Option<Child>,
+}
+
+impl McpClient {
+    /// Start `command` and complete the MCP handshake with it
+    pub async fn spawn(
+        command: &str,
+        args: &[String],
+        env: &HashMap<String, String>,
+        cwd: Option<&Path>,
+    ) -> Result<Self, String> {
+        let mut cmd = Command::new(command);
+        cmd.args(args)
+            .envs(env)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        if let Some(cwd) = cwd {
+            cmd.current_dir(cwd);
+        }
+
+        let mut child = cmd
+            .spawn()
+            .map_err(|e| format!("Failed to start MCP server '{}': {}", command, e))?;
+
+        let stdin = child.stdin.take().ok_or("MCP server has no stdin")?;
+        let stdout = child.stdout.take().ok_or("MCP server has no stdout")?;
+        if let Some(stderr) = child.stderr.take() {
+            let name = command.to_string();
+            tokio::spawn(async move {
+                let mut lines = BufReader::new(stderr).lines();
+                while let Ok(Some(line)) = lines.next_line().await {
+                    log::debug!("[mcp {}] {}", name, line);
+                }
+            });
+        }
+
+        let mut client = Self::connect(stdout, stdin).await?;
+        client._child = Some(child);
+        Ok(client)
+    }
+
+    /// Complete the MCP handshake over an existing connection
+    pub async fn connect<R, W>(reader: R, writer: W) -> Result<Self, String>
+    where
+        R: AsyncRead + Send + Unpin + 'static,
+        W: AsyncWrite + Send + Unpin + 'static,
+    {
+        let writer: Writer = Arc::new(tokio::sync::Mutex::new(Box::new(writer)));
+        let pending: Pending = Arc::new(Mutex::new(HashMap::new()));
+        let closed = Arc::new(AtomicBool::new(false));
+
+        tokio::spawn(read_loop(
+            reader,
+            Arc::clone(&writer),
+            Arc::clone(&pending),
+            Arc::clone(&closed),
+        ));
+
+        let mut client = Self {
+            writer,
+            pending,
+            next_id: AtomicU64::new(1),
+            closed,
+            server_name: String::new(),
+            _child: None,
+        };
+
+        let init = client
+            .request(
+                "initialize",
+                json!({
+                    "protocolVersion": PROTOCOL_VERSION,
+                    "capabilities": {},
+                    "clientInfo": { "name": "claudio", "version": env!("CARGO_PKG_VERSION") },
+                }),
+                INIT_TIMEOUT,
+            )
+            .await?;
+
+        let version = init
+            .get("protocolVersion")
+            .and_then(Value::as_str)
+            .unwrap_or("");
+        if !SUPPORTED_VERSIONS.contains(&version) {
+            return Err(format!("Unsupported MCP protocol version '{}'", version));
+        }
+        client.server_name = init
+            .pointer("/serverInfo/name")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string();
+
+        client.notify("notifications/initialized", None).await?;
+        Ok(client)
+    }
+
+    /// Name the server gave in its handshake
+    pub fn server_name(&self) -> &str {
+        &self.server_name
+    }
+
+    /// Whether the server has exited or closed its output
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+
+    pub async fn list_tools(&self) -> Result<Vec<Tool>, String> {
+        let mut tools = Vec::new();
+        let mut cursor: Option<String> = None;
+
+        for _ in 0..MAX_TOOL_PAGES {
+            let params = match &cursor {
+                Some(cursor) => json!({ "cursor": cursor }),
+                None => json!({}),
+            };
+            let page = self.request("tools/list", params, CALL_TIMEOUT).await?;
+
+            let batch: Vec<Tool> =
+                serde_json::from_value(page.get("tools").cloned().unwrap_or_default())
+                    .map_err(|e| format!("Invalid tools/list response: {}", e))?;
+            tools.extend(batch);
+
+            cursor = page
+                .get("nextCursor")
+                .and_then(Value::as_str)
+                .map(str::to_string);
+            if cursor.is_none() {
+                break;
+            }
+        }
+
+        Ok(tools)
+    }
+
+    pub async fn call_tool(&self, name: &str, arguments: Value) -> Result<CallToolResult, String> {
+        let result = self
+            .request(
+                "tools/call",
+                json!({ "name": name, "arguments": arguments }),
+                CALL_TIMEOUT,
+            )
+            .await?;
+
+        serde_json::from_value(result).map_err(|e| format!("Invalid tools/call response: {}", e))
+    }
+
+    async fn request(
+        &self,
+        method: &str,
+        params: Value,
+        timeout: Duration,
+    ) -> Result<Value, String> {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let (tx, rx) = oneshot::channel();
+        self.pending.lock().insert(id, tx);
+
+        // Checked after registering so a concurrent hang-up can't strand us
+        if self.is_closed() {
+            self.pending.lock().remove(&id);
+            return Err("MCP server connection is closed".to_string());
+        }
+
+        if let Err(e) = self
+            .send(&Request::new(Some(json!(id)), method, Some(params)))
+            .await
+        {
+            self.pending.lock().remove(&id);
+            return Err(e);
+        }
+
+        let response = match tokio::time::timeout(timeout, rx).await {
+            Ok(Ok(response)) => response,
+            Ok(Err(_)) => return Err("MCP server exited before responding".to_string()),
+            Err(_) => {
+                self.pending.lock().remove(&id);
+                let _ = self
+                    .notify(
+                        "notifications/cancelled",
+                        Some(json!({ "requestId": id, "reason": "timed out" })),
+                    )
+                    .await;
+                return Err(format!("MCP request '{}' timed out", method));
+            }
+        };
+
+        match (response.result, response.error) {
+            (_, Some(error)) => Err(format!("MCP error {}: {}", error.code, error.message)),
+            (Some(result), None) => Ok(result),
+            (None, None) => Ok(Value::Null),
+        }
+    }
+
+    async fn notify(&self, method: &str, params: Option<Value>) -> Result<(), String> {
+        self.send(&Request::new(None, method, params)).await
+    }
+
+    async fn send<T: serde::Serialize>(&self, message: &T) -> Result<(), String> {
+        write_message(&self.writer, message).await
+    }
+}
+
+async fn write_message<T: serde::Serialize>(writer: &Writer, message: &T) -> Result<(), String> {
+    let mut line = serde_json::to_string(message).map_err(|e| e.to_string())?;
+    line.push('\n');
+
+    let mut writer = writer.lock().await;
+    writer
+        .write_all(line.as_bytes())
+        .await
+        .map_err(|e| format!("Failed to write to MCP server: {}", e))?;
+    writer.flush().await.map_err(|e| e.to_string())
+}
+
+/// Route responses to their callers and answer requests from the server
+async fn read_loop<R>(reader: R, writer: Writer, pending: Pending, closed: Arc<AtomicBool>)
+where
+    R: AsyncRead + Send + Unpin,
+{
+    let mut lines = BufReader::new(reader).lines();
+
+    while let Ok(Some(line)) = lines.next_line().await {
+        let Ok(message) = serde_json::from_str::<Value>(&line) else {
+            log::debug!("Ignoring non-JSON line from MCP server: {}", line);
+            continue;
+        };
+
+        match (message.get("id"), message.get("method")) {
+            // A response to one of our requests
+            (Some(id), None) => {
+                let sender = id.as_u64().and_then(|id| pending.lock().remove(&id));
+                match (sender, serde_json::from_value::<Response>(message)) {
+                    (Some(sender), Ok(response)) => {
+                        let _ = sender.send(response);
+                    }
+                    _ => log::debug!("Ignoring unmatched MCP response"),
+                }
+            }
+            // A request from the server; we only support ping
+            (Some(id), Some(method)) => {
+                let response = if method == "ping" {
+                    Response::success(id.clone(), json!({}))
+                } else {
+                    Response::failure(id.clone(), METHOD_NOT_FOUND, "Not supported by this client")
+                };
+                let _ = write_message(&writer, &response).await;
+            }
+            _ => {}
+        }
+    }
+
+    closed.store(true, Ordering::SeqCst);
+    // Dropping the senders fails every outstanding request
+    pending.lock().clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_server::connect_stub;
+
+    #[tokio::test]
+    async fn test_list_and_call_tools() {
+        let client = connect_stub().await.unwrap();
+        assert_eq!(client.server_name(), "stub");
+
+        // The stub splits its tools over two pages
+        let tools = client.list_tools().await.unwrap();
+        let names: Vec<&str> = tools.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, ["echo", "fail"]);
+
+        let echoed = client
+            .call_tool("echo", serde_json::json!({ "text": "hello" }))
+            .await
+            .unwrap();
+        assert!(!echoed.is_error);
+        assert_eq!(echoed.joined_text(), "hello");
+
+        let failed = client
+            .call_tool("fail", serde_json::json!({}))
+            .await
+            .unwrap();
+        assert!(failed.is_error);
+
+        let unknown = client.call_tool("missing", serde_json::json!({})).await;
+        assert!(unknown.unwrap_err().contains("Unknown tool"));
+    }
+
+    #[tokio::test]
+    async fn test_server_exit_fails_pending_calls() {
+        let client = connect_stub().await.unwrap();
+
+        // The stub hangs up instead of answering
+        let result = client.call_tool("exit", serde_json::json!({})).await;
+        assert!(result.unwrap_err().contains("exited"));
+        assert!(client.is_closed());
+    }
+}
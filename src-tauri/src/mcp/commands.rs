@@ -0,0 +1,38 @@
+//! Tauri commands for mcp module
+
+use super::manager::{McpManager, McpServerConfig};
+use super::protocol::Tool;
+use std::sync::Arc;
+use tauri::State;
+
+#[tauri::command]
+pub fn mcp_list_servers(
+    manager: State<'_, Arc<McpManager>>,
+) -> Result<Vec<McpServerConfig>, String> {
+    manager.list_servers()
+}
+
+#[tauri::command]
+pub async fn mcp_save_server(
+    manager: State<'_, Arc<McpManager>>,
+    server: McpServerConfig,
+) -> Result<McpServerConfig, String> {
+    manager.save_server(server).await
+}
+
+#[tauri::command]
+pub async fn mcp_delete_server(
+    manager: State<'_, Arc<McpManager>>,
+    server_id: String,
+) -> Result<(), String> {
+    manager.delete_server(&server_id).await
+}
+
+/// Connect to a server and list its tools, to test its configuration
+#[tauri::command]
+pub async fn mcp_list_tools(
+    manager: State<'_, Arc<McpManager>>,
+    server_id: String,
+) -> Result<Vec<Tool>, String> {
+    manager.list_server_tools(&server_id).await
+}
@@ -0,0 +1,395 @@
+//! MCP server manager implementation
+//!
+//! Stores the MCP servers agents may use, keeps one connection per server
+//! and routes tool calls to the right one.
+//!
+//! A server with an `agent_id` belongs to that agent. Other servers are
+//! global and an agent opts in through `AgentConfig.tools`:
+//! `mcp:<server>` for all of its tools, `mcp:<server>/<tool>` for one, or
+//! `mcp:*` for every global server.
+
+use super::client::McpClient;
+use super::protocol::{CallToolResult, Tool};
+use crate::agents::Agent;
+use crate::db::Database;
+use chrono::Utc;
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+use uuid::Uuid;
+
+const TOOL_PREFIX: &str = "mcp__";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct McpServerConfig {
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    /// Owning agent; `None` for a global server
+    #[serde(rename = "agentId")]
+    pub agent_id: Option<String>,
+    pub enabled: bool,
+    #[serde(rename = "createdAt", default)]
+    pub created_at: i64,
+}
+
+/// Which of a server's tools an agent gets
+#[derive(Debug, PartialEq, Eq)]
+enum Selection {
+    All,
+    Only(Vec<String>),
+}
+
+/// The MCP tools available to one task, with names qualified as
+/// `mcp__<server>__<tool>`
+#[derive(Default)]
+pub struct McpToolset {
+    pub tools: Vec<Tool>,
+    /// Servers that could not be reached, with the reason
+    pub errors: Vec<String>,
+    routes: HashMap<String, (String, String)>,
+}
+
+pub struct McpManager {
+    db: Database,
+    clients: tokio::sync::Mutex<HashMap<String, Arc<McpClient>>>,
+}
+
+impl McpManager {
+    pub fn new(db: Database) -> Self {
+        Self {
+            db,
+            clients: tokio::sync::Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn list_servers(&self) -> Result<Vec<McpServerConfig>, String> {
+        self.db.with_conn(|conn| {
+            let mut stmt = conn
+                .prepare(
+                    "SELECT id, name, command, args, env, agent_id, enabled, created_at
+                     FROM mcp_servers ORDER BY name",
+                )
+                .map_err(|e| e.to_string())?;
+
+            let servers = stmt
+                .query_map([], |row| {
+                    let args: String = row.get(3)?;
+                    let env: String = row.get(4)?;
+                    Ok(McpServerConfig {
+                        id: row.get(0)?,
+                        name: row.get(1)?,
+                        command: row.get(2)?,
+                        args: serde_json::from_str(&args).unwrap_or_default(),
+                        env: serde_json::from_str(&env).unwrap_or_default(),
+                        agent_id: row.get(5)?,
+                        enabled: row.get(6)?,
+                        created_at: row.get(7)?,
+                    })
+                })
+                .map_err(|e| e.to_string())?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| e.to_string())?;
+
+            Ok(servers)
+        })
+    }
+
+    /// Create or update a server. A changed server is reconnected on next use.
+    pub async fn save_server(&self, server: McpServerConfig) -> Result<McpServerConfig, String> {
+        if server.name.is_empty()
+            || !server
+                .name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err("Server names may only use letters, digits, '-' and '_'".to_string());
+        }
+        if server.command.trim().is_empty() {
+            return Err("Server command is required".to_string());
+        }
+
+        let server = McpServerConfig {
+            id: if server.id.is_empty() {
+                Uuid::new_v4().to_string()
+            } else {
+                server.id
+            },
+            created_at: if server.created_at == 0 {
+                Utc::now().timestamp()
+            } else {
+                server.created_at
+            },
+            ..server
+        };
+
+        self.db.with_conn(|conn| {
+            conn.execute(
+                "INSERT INTO mcp_servers (id, name, command, args, env, agent_id, enabled, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+                 ON CONFLICT(id) DO UPDATE SET
+                    name = excluded.name, command = excluded.command, args = excluded.args,
+                    env = excluded.env, agent_id = excluded.agent_id, enabled = excluded.enabled",
+                params![
+                    server.id,
+                    server.name,
+                    server.command,
+                    serde_json::to_string(&server.args).unwrap_or_default(),
+                    serde_json::to_string(&server.env).unwrap_or_default(),
+                    server.agent_id,
+                    server.enabled,
+                    server.created_at,
+                ],
+            )
+            .map_err(|e| e.to_string())
+        })?;
+
+        self.clients.lock().await.remove(&server.id);
+        Ok(server)
+    }
+
+    pub async fn delete_server(&self, server_id: &str) -> Result<(), String> {
+        self.db.with_conn(|conn| {
+            conn.execute("DELETE FROM mcp_servers WHERE id = ?1", params![server_id])
+                .map_err(|e| e.to_string())
+        })?;
+
+        self.clients.lock().await.remove(server_id);
+        Ok(())
+    }
+
+    /// Connect to a server and list its tools, to check its configuration
+    pub async fn list_server_tools(&self, server_id: &str) -> Result<Vec<Tool>, String> {
+        let server = self
+            .list_servers()?
+            .into_iter()
+            .find(|s| s.id == server_id)
+            .ok_or_else(|| format!("MCP server not found: {}", server_id))?;
+
+        self.client(&server).await?.list_tools().await
+    }
+
+    /// Discover the tools `agent` may use. Unreachable servers are skipped
+    /// and reported in `errors`.
+    pub async fn toolset_for_agent(&self, agent: &Agent) -> Result<McpToolset, String> {
+        let mut toolset = McpToolset::default();
+
+        for server in self.list_servers()?.into_iter().filter(|s| s.enabled) {
+            let Some(selection) = selection(agent, &server) else {
+                continue;
+            };
+
+            let tools = match self.client(&server).await {
+                Ok(client) => client.list_tools().await,
+                Err(e) => Err(e),
+            };
+            let tools = match tools {
+                Ok(tools) => tools,
+                Err(e) => {
+                    toolset.errors.push(format!("{}: {}", server.name, e));
+                    continue;
+                }
+            };
+
+            for tool in tools {
+                if let Selection::Only(names) = &selection {
+                    if !names.contains(&tool.name) {
+                        continue;
+                    }
+                }
+
+                let qualified = format!("{}{}__{}", TOOL_PREFIX, server.name, tool.name);
+                toolset
+                    .routes
+                    .insert(qualified.clone(), (server.id.clone(), tool.name.clone()));
+                toolset.tools.push(Tool {
+                    name: qualified,
+                    ..tool
+                });
+            }
+        }
+
+        Ok(toolset)
+    }
+
+    /// Run a tool from `toolset` by its qualified name
+    pub async fn call(
+        &self,
+        toolset: &McpToolset,
+        name: &str,
+        arguments: Value,
+    ) -> Result<CallToolResult, String> {
+        let (server_id, tool) = toolset
+            .routes
+            .get(name)
+            .ok_or_else(|| format!("Unknown tool: {}", name))?;
+
+        let client = self
+            .clients
+            .lock()
+            .await
+            .get(server_id)
+            .cloned()
+            .filter(|c| !c.is_closed());
+        let client = match client {
+            Some(client) => client,
+            // The server exited since the toolset was built; start it again
+            None => {
+                let server = self
+                    .list_servers()?
+                    .into_iter()
+                    .find(|s| &s.id == server_id)
+                    .ok_or_else(|| format!("MCP server was removed: {}", server_id))?;
+                self.client(&server).await?
+            }
+        };
+
+        client.call_tool(tool, arguments).await
+    }
+
+    /// The live connection to `server`, started on first use
+    async fn client(&self, server: &McpServerConfig) -> Result<Arc<McpClient>, String> {
+        if let Some(client) = self
+            .clients
+            .lock()
+            .await
+            .get(&server.id)
+            .filter(|c| !c.is_closed())
+        {
+            return Ok(Arc::clone(client));
+        }
+
+        // Connect without holding the lock, so a slow server doesn't hold
+        // up calls to the others
+        let client =
+            Arc::new(McpClient::spawn(&server.command, &server.args, &server.env, None).await?);
+
+        let mut clients = self.clients.lock().await;
+        // Another call may have connected meanwhile; keep its connection and
+        // let this one shut down on drop
+        if let Some(existing) = clients.get(&server.id).filter(|c| !c.is_closed()) {
+            return Ok(Arc::clone(existing));
+        }
+        log::info!(
+            "Connected to MCP server {} ({})",
+            server.name,
+            client.server_name()
+        );
+        clients.insert(server.id.clone(), Arc::clone(&client));
+        Ok(client)
+    }
+
+    #[cfg(test)]
+    async fn insert_client(&self, server_id: &str, client: McpClient) {
+        self.clients
+            .lock()
+            .await
+            .insert(server_id.to_string(), Arc::new(client));
+    }
+}
+
+/// Whether `agent` may use `server`, and which of its tools
+fn selection(agent: &Agent, server: &McpServerConfig) -> Option<Selection> {
+    if let Some(owner) = &server.agent_id {
+        return (owner == &agent.id).then_some(Selection::All);
+    }
+
+    let mut only = Vec::new();
+    for entry in &agent.config.tools {
+        let Some(reference) = entry.strip_prefix("mcp:") else {
+            continue;
+        };
+        match reference.split_once('/') {
+            None if reference == "*" || reference == server.name => return Some(Selection::All),
+            Some((name, tool)) if name == server.name => only.push(tool.to_string()),
+            _ => {}
+        }
+    }
+
+    (!only.is_empty()).then_some(Selection::Only(only))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_server::connect_stub;
+    use super::*;
+    use crate::agents::AgentManager;
+    use tempfile::tempdir;
+
+    fn server(name: &str, agent_id: Option<&str>) -> McpServerConfig {
+        McpServerConfig {
+            id: String::new(),
+            name: name.to_string(),
+            command: "stub".to_string(),
+            args: vec![],
+            env: HashMap::new(),
+            agent_id: agent_id.map(str::to_string),
+            enabled: true,
+            created_at: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_agent_toolset_routes_calls() {
+        let dir = tempdir().unwrap();
+        let db = Database::new(dir.path().to_path_buf()).unwrap();
+        let agents = AgentManager::new(db.clone());
+        let manager = McpManager::new(db);
+
+        let mut agent = agents
+            .create_agent(Default::default(), "Researcher", "research")
+            .unwrap();
+        agent.config.tools = vec!["mcp:docs/echo".to_string()];
+
+        let docs = manager.save_server(server("docs", None)).await.unwrap();
+        let other = manager.save_server(server("other", None)).await.unwrap();
+        let own = manager
+            .save_server(server("own", Some(&agent.id)))
+            .await
+            .unwrap();
+        assert!(manager.save_server(server("bad name", None)).await.is_err());
+
+        assert_eq!(
+            selection(&agent, &docs),
+            Some(Selection::Only(vec!["echo".to_string()]))
+        );
+        assert_eq!(selection(&agent, &other), None);
+        assert_eq!(selection(&agent, &own), Some(Selection::All));
+
+        manager
+            .insert_client(&docs.id, connect_stub().await.unwrap())
+            .await;
+        manager
+            .insert_client(&own.id, connect_stub().await.unwrap())
+            .await;
+
+        let toolset = manager.toolset_for_agent(&agent).await.unwrap();
+        let names: Vec<&str> = toolset.tools.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(
+            names,
+            ["mcp__docs__echo", "mcp__own__echo", "mcp__own__fail"]
+        );
+        assert!(toolset.errors.is_empty());
+
+        let result = manager
+            .call(
+                &toolset,
+                "mcp__docs__echo",
+                serde_json::json!({ "text": "hi" }),
+            )
+            .await
+            .unwrap();
+        assert_eq!(result.joined_text(), "hi");
+        assert!(manager
+            .call(&toolset, "mcp__docs__fail", serde_json::json!({}))
+            .await
+            .is_err());
+    }
+}
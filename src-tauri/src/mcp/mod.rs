@@ -0,0 +1,15 @@
+//! Model Context Protocol support
+//!
+//! Exposes Claud.io to MCP clients such as Claude Code, and connects agents
+//! to the MCP servers configured for them.
+
+mod client;
+pub mod commands;
+mod manager;
+pub mod protocol;
+mod server;
+#[cfg(test)]
+mod test_server;
+
+pub use manager::{McpManager, McpToolset};
+pub use server::McpServer;
@@ -0,0 +1,418 @@
+//! MCP server
+//!
+//! Lets MCP clients such as Claude Code see Claud.io's projects, agents and
+//! memories and hand tasks to the background agents. Served over stdio by
+//! `claudio mcp` and over HTTP at `/mcp` on the local API.
+
+use super::protocol::{
+    CallToolResult, Request, Response, Tool, INTERNAL_ERROR, INVALID_PARAMS, INVALID_REQUEST,
+    METHOD_NOT_FOUND, PARSE_ERROR, PROTOCOL_VERSION, SUPPORTED_VERSIONS,
+};
+use crate::agents::{AgentManager, Task, TASK_PRIORITIES};
+use crate::projects::ProjectManager;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::io::{BufRead, Write};
+use std::sync::Arc;
+
+const TREE_URI_PREFIX: &str = "claudio://projects/";
+const TREE_URI_SUFFIX: &str = "/tree";
+
+pub struct McpServer {
+    agents: Arc<AgentManager>,
+    projects: Arc<ProjectManager>,
+}
+
+impl McpServer {
+    pub fn new(agents: Arc<AgentManager>, projects: Arc<ProjectManager>) -> Self {
+        Self { agents, projects }
+    }
+
+    /// Serve newline-delimited JSON-RPC on stdin/stdout until stdin closes
+    pub fn serve_stdio(&self) -> Result<(), String> {
+        let stdin = std::io::stdin();
+        let mut stdout = std::io::stdout();
+
+        for line in stdin.lock().lines() {
+            let line = line.map_err(|e| e.to_string())?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            if let Some(response) = self.handle_text(&line) {
+                let text = serde_json::to_string(&response).map_err(|e| e.to_string())?;
+                writeln!(stdout, "{}", text).map_err(|e| e.to_string())?;
+                stdout.flush().map_err(|e| e.to_string())?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Handle one raw message. Notifications get no response.
+    pub fn handle_text(&self, text: &str) -> Option<Response> {
+        match serde_json::from_str::<Value>(text) {
+            Ok(message) => self.handle(message),
+            Err(e) => Some(Response::failure(Value::Null, PARSE_ERROR, e.to_string())),
+        }
+    }
+
+    pub fn handle(&self, message: Value) -> Option<Response> {
+        let request: Request = match serde_json::from_value(message) {
+            Ok(request) => request,
+            Err(e) => {
+                return Some(Response::failure(
+                    Value::Null,
+                    INVALID_REQUEST,
+                    e.to_string(),
+                ))
+            }
+        };
+
+        // Notifications (initialized, cancelled, ...) need no reply
+        let id = request.id?;
+        let params = request.params.unwrap_or(Value::Null);
+
+        let result = match request.method.as_str() {
+            "initialize" => Ok(self.initialize(&params)),
+            "ping" => Ok(json!({})),
+            "tools/list" => Ok(json!({ "tools": tools() })),
+            "tools/call" => self.call_tool(params),
+            "resources/list" => self.list_resources(),
+            "resources/templates/list" => Ok(json!({
+                "resourceTemplates": [{
+                    "uriTemplate": format!("{}{{projectId}}{}", TREE_URI_PREFIX, TREE_URI_SUFFIX),
+                    "name": "Project file tree",
+                    "mimeType": "application/json",
+                }]
+            })),
+            "resources/read" => self.read_resource(params),
+            other => Err((METHOD_NOT_FOUND, format!("Method not found: {}", other))),
+        };
+
+        Some(match result {
+            Ok(result) => Response::success(id, result),
+            Err((code, message)) => Response::failure(id, code, message),
+        })
+    }
+
+    fn initialize(&self, params: &Value) -> Value {
+        let requested = params.get("protocolVersion").and_then(Value::as_str);
+        let version = requested
+            .filter(|v| SUPPORTED_VERSIONS.contains(v))
+            .unwrap_or(PROTOCOL_VERSION);
+
+        json!({
+            "protocolVersion": version,
+            "capabilities": { "tools": {}, "resources": {} },
+            "serverInfo": { "name": "claudio", "version": env!("CARGO_PKG_VERSION") },
+            "instructions": "Claud.io runs background agents against local projects. \
+                Queue work with create_task and poll get_task_result for the outcome.",
+        })
+    }
+
+    fn call_tool(&self, params: Value) -> Result<Value, (i64, String)> {
+        #[derive(Deserialize)]
+        struct Call {
+            name: String,
+            #[serde(default)]
+            arguments: Value,
+        }
+
+        let call: Call =
+            serde_json::from_value(params).map_err(|e| (INVALID_PARAMS, e.to_string()))?;
+        let arguments = if call.arguments.is_null() {
+            json!({})
+        } else {
+            call.arguments
+        };
+
+        let outcome = match call.name.as_str() {
+            "list_projects" => self.projects.list().and_then(to_json),
+            "list_agents" => self.agents.list_agents().and_then(to_json),
+            "create_task" => self.create_task(arguments),
+            "get_task_result" => self.get_task_result(arguments),
+            "search_memories" => self.search_memories(arguments),
+            other => return Err((INVALID_PARAMS, format!("Unknown tool: {}", other))),
+        };
+
+        // Tool failures are reported to the model, not as protocol errors
+        let result = match outcome {
+            Ok(value) => {
+                CallToolResult::text(serde_json::to_string_pretty(&value).unwrap_or_default())
+            }
+            Err(message) => CallToolResult::error(message),
+        };
+        Ok(serde_json::to_value(result).unwrap_or_default())
+    }
+
+    fn create_task(&self, arguments: Value) -> Result<Value, String> {
+        #[derive(Deserialize)]
+        struct Args {
+            title: String,
+            #[serde(default)]
+            description: String,
+            #[serde(rename = "agentId")]
+            agent_id: Option<String>,
+            #[serde(rename = "projectId")]
+            project_id: Option<String>,
+            priority: Option<String>,
+            #[serde(rename = "targetAgentType")]
+            target_agent_type: Option<String>,
+            #[serde(default)]
+            skills: Vec<String>,
+        }
+
+        let args: Args = serde_json::from_value(arguments).map_err(|e| e.to_string())?;
+        let priority = args.priority.unwrap_or_else(|| "normal".to_string());
+        if !TASK_PRIORITIES.contains(&priority.as_str()) {
+            return Err(format!(
+                "priority must be one of {}",
+                TASK_PRIORITIES.join(", ")
+            ));
+        }
+        if let Some(agent_id) = &args.agent_id {
+            self.agents
+                .get_agent(agent_id)?
+                .ok_or_else(|| format!("Agent not found: {}", agent_id))?;
+        }
+        if let Some(project_id) = &args.project_id {
+            self.projects
+                .get(project_id)?
+                .ok_or_else(|| format!("Project not found: {}", project_id))?;
+        }
+
+        let task = self.agents.create_task(&Task {
+            agent_id: args.agent_id,
+            project_id: args.project_id,
+            description: args.description,
+            priority,
+            target_agent_type: args.target_agent_type,
+            skills: args.skills,
+            ..Task::new(args.title)
+        })?;
+
+        Ok(json!({ "taskId": task.id, "status": task.status }))
+    }
+
+    fn get_task_result(&self, arguments: Value) -> Result<Value, String> {
+        let task_id = string_arg(&arguments, "taskId")?;
+        let task = self
+            .agents
+            .get_task(&task_id)?
+            .ok_or_else(|| format!("Task not found: {}", task_id))?;
+
+        // The latest answer is the useful part of a conversation thread
+        let last_reply = self
+            .agents
+            .list_task_messages(&task_id)?
+            .into_iter()
+            .rev()
+            .find(|m| m.role == "assistant")
+            .map(|m| m.content);
+
+        Ok(json!({
+            "taskId": task.id,
+            "title": task.title,
+            "status": task.status,
+            "agentId": task.agent_id,
+            "output": task.result.as_ref().and_then(|r| r.output.clone()).or(last_reply),
+            "error": task.result.as_ref().and_then(|r| r.error.clone()),
+            "completedAt": task.completed_at,
+        }))
+    }
+
+    fn search_memories(&self, arguments: Value) -> Result<Value, String> {
+        let query = string_arg(&arguments, "query")?;
+        let agent_id = arguments.get("agentId").and_then(Value::as_str);
+        let limit = arguments
+            .get("limit")
+            .and_then(Value::as_i64)
+            .unwrap_or(20)
+            .clamp(1, 100) as i32;
+
+        to_json(self.agents.search_memories(&query, agent_id, limit)?)
+    }
+
+    fn list_resources(&self) -> Result<Value, (i64, String)> {
+        let projects = self.projects.list().map_err(|e| (INTERNAL_ERROR, e))?;
+        let resources: Vec<Value> = projects
+            .iter()
+            .map(|p| {
+                json!({
+                    "uri": format!("{}{}{}", TREE_URI_PREFIX, p.id, TREE_URI_SUFFIX),
+                    "name": format!("{} file tree", p.name),
+                    "description": p.path,
+                    "mimeType": "application/json",
+                })
+            })
+            .collect();
+
+        Ok(json!({ "resources": resources }))
+    }
+
+    fn read_resource(&self, params: Value) -> Result<Value, (i64, String)> {
+        let uri = string_arg(&params, "uri").map_err(|e| (INVALID_PARAMS, e))?;
+        let project_id = uri
+            .strip_prefix(TREE_URI_PREFIX)
+            .and_then(|rest| rest.strip_suffix(TREE_URI_SUFFIX))
+            .ok_or_else(|| (INVALID_PARAMS, format!("Unknown resource: {}", uri)))?;
+
+        let tree = self
+            .projects
+            .get_file_tree(project_id)
+            .map_err(|e| (INVALID_PARAMS, e))?;
+
+        Ok(json!({
+            "contents": [{
+                "uri": uri,
+                "mimeType": "application/json",
+                "text": serde_json::to_string(&tree).unwrap_or_default(),
+            }]
+        }))
+    }
+}
+
+fn tools() -> Vec<Tool> {
+    let tool = |name: &str, description: &str, input_schema: Value| Tool {
+        name: name.to_string(),
+        description: description.to_string(),
+        input_schema,
+    };
+    let no_args = json!({ "type": "object", "properties": {} });
+
+    vec![
+        tool(
+            "list_projects",
+            "List the projects registered in Claud.io.",
+            no_args.clone(),
+        ),
+        tool(
+            "list_agents",
+            "List Claud.io's agents with their type, status and model.",
+            no_args,
+        ),
+        tool(
+            "create_task",
+            "Queue a task for Claud.io's background agents. Returns the task id; \
+             poll get_task_result for the outcome.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "title": { "type": "string", "description": "Short summary of the work" },
+                    "description": { "type": "string", "description": "Full instructions" },
+                    "agentId": { "type": "string", "description": "Run on this agent; otherwise any auto-assign agent may take it" },
+                    "projectId": { "type": "string", "description": "Project the task works on" },
+                    "priority": { "type": "string", "enum": TASK_PRIORITIES },
+                    "targetAgentType": { "type": "string", "description": "Only agents of this type may take the task" },
+                    "skills": { "type": "array", "items": { "type": "string" }, "description": "Only agents with one of these skills may take the task" },
+                },
+                "required": ["title"],
+            }),
+        ),
+        tool(
+            "get_task_result",
+            "Get a task's status and, once finished, its output or error.",
+            json!({
+                "type": "object",
+                "properties": { "taskId": { "type": "string" } },
+                "required": ["taskId"],
+            }),
+        ),
+        tool(
+            "search_memories",
+            "Search what Claud.io's agents have remembered from earlier tasks.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "query": { "type": "string", "description": "Words that must all appear" },
+                    "agentId": { "type": "string" },
+                    "limit": { "type": "integer", "minimum": 1, "maximum": 100 },
+                },
+                "required": ["query"],
+            }),
+        ),
+    ]
+}
+
+fn to_json<T: serde::Serialize>(value: T) -> Result<Value, String> {
+    serde_json::to_value(value).map_err(|e| e.to_string())
+}
+
+fn string_arg(arguments: &Value, name: &str) -> Result<String, String> {
+    arguments
+        .get(name)
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .ok_or_else(|| format!("Missing argument: {}", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+    use tempfile::tempdir;
+
+    fn call(server: &McpServer, id: i64, method: &str, params: Value) -> Value {
+        let response = server
+            .handle(json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }))
+            .unwrap();
+        assert!(response.error.is_none(), "{:?}", response.error);
+        response.result.unwrap()
+    }
+
+    fn tool_text(result: &Value) -> Value {
+        serde_json::from_str(result["content"][0]["text"].as_str().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_session() {
+        let dir = tempdir().unwrap();
+        let db = Database::new(dir.path().to_path_buf()).unwrap();
+        let agents = Arc::new(AgentManager::new(db.clone()));
+        let server = McpServer::new(Arc::clone(&agents), Arc::new(ProjectManager::new(db)));
+
+        let init = call(
+            &server,
+            1,
+            "initialize",
+            json!({ "protocolVersion": "2025-03-26" }),
+        );
+        assert_eq!(init["protocolVersion"], "2025-03-26");
+        assert!(server
+            .handle(json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }))
+            .is_none());
+
+        let tools = call(&server, 2, "tools/list", json!({}));
+        assert_eq!(tools["tools"].as_array().unwrap().len(), 5);
+
+        let created = call(
+            &server,
+            3,
+            "tools/call",
+            json!({ "name": "create_task", "arguments": { "title": "Summarize the README" } }),
+        );
+        let task_id = tool_text(&created)["taskId"].as_str().unwrap().to_string();
+
+        let result = call(
+            &server,
+            4,
+            "tools/call",
+            json!({ "name": "get_task_result", "arguments": { "taskId": task_id } }),
+        );
+        assert_eq!(tool_text(&result)["status"], "pending");
+
+        let missing = call(
+            &server,
+            5,
+            "tools/call",
+            json!({ "name": "get_task_result", "arguments": {} }),
+        );
+        assert_eq!(missing["isError"], true);
+
+        let unknown = server
+            .handle(json!({ "jsonrpc": "2.0", "id": 6, "method": "sampling/createMessage" }))
+            .unwrap();
+        assert_eq!(unknown.error.unwrap().code, METHOD_NOT_FOUND);
+    }
+}
@@ -0,0 +1,81 @@
+//! Stub MCP server for client tests
+//!
+//! Speaks the protocol over in-memory pipes and offers two tools: `echo`
+//! returns its `text` argument and `fail` returns a tool error. Calling
+//! `exit` makes the server hang up without answering.
+
+use super::client::McpClient;
+use serde_json::{json, Value};
+use tokio::io::{duplex, AsyncBufReadExt, AsyncWriteExt, BufReader, DuplexStream};
+
+/// Start a stub server and connect a client to it
+pub async fn connect_stub() -> Result<McpClient, String> {
+    let (client_read, server_write) = duplex(64 * 1024);
+    let (server_read, client_write) = duplex(64 * 1024);
+    tokio::spawn(serve(server_read, server_write));
+
+    McpClient::connect(client_read, client_write).await
+}
+
+async fn serve(reader: DuplexStream, mut writer: DuplexStream) {
+    let mut lines = BufReader::new(reader).lines();
+
+    while let Ok(Some(line)) = lines.next_line().await {
+        let request: Value = serde_json::from_str(&line).unwrap();
+        let Some(id) = request.get("id").cloned() else {
+            continue;
+        };
+        let params = request.get("params").cloned().unwrap_or_default();
+
+        let result = match request["method"].as_str().unwrap_or_default() {
+            "initialize" => Ok(json!({
+                "protocolVersion": params["protocolVersion"],
+                "capabilities": { "tools": {} },
+                "serverInfo": { "name": "stub", "version": "0.0.0" },
+            })),
+            // Two pages to exercise the cursor
+            "tools/list" if params.get("cursor").is_none() => Ok(json!({
+                "tools": [tool("echo")],
+                "nextCursor": "page-2",
+            })),
+            "tools/list" => Ok(json!({ "tools": [tool("fail")] })),
+            "tools/call" => match params["name"].as_str().unwrap_or_default() {
+                "echo" => Ok(json!({
+                    "content": [{ "type": "text", "text": params["arguments"]["text"] }],
+                })),
+                "fail" => Ok(json!({
+                    "content": [{ "type": "text", "text": "it broke" }],
+                    "isError": true,
+                })),
+                "exit" => return,
+                other => Err(format!("Unknown tool: {}", other)),
+            },
+            other => Err(format!("Method not found: {}", other)),
+        };
+
+        let response = match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(message) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": -32602, "message": message },
+            }),
+        };
+        let mut out = response.to_string();
+        out.push('\n');
+        if writer.write_all(out.as_bytes()).await.is_err() {
+            return;
+        }
+    }
+}
+
+fn tool(name: &str) -> Value {
+    json!({
+        "name": name,
+        "description": format!("The {} tool", name),
+        "inputSchema": {
+            "type": "object",
+            "properties": { "text": { "type": "string" } },
+        },
+    })
+}
@@ -0,0 +1,351 @@
+//! Work tree checkpoints
+//!
+//! A checkpoint is a commit of the project's work tree, untracked files
+//! included and ignored files left out, kept under `refs/checkpoints/<id>`.
+//! Taking one doesn't touch HEAD, the index or any branch, and since the
+//! ref keeps the commit alive it can be restored whatever has been
+//! committed since. The commit's parent is the HEAD it was taken on.
+//!
+//! Restoring writes the checkpoint's files over the work tree and removes
+//! files it didn't have, leaving HEAD where it is so the difference shows up
+//! as uncommitted changes. The state being replaced is checkpointed first.
+//!
+//! The same snapshot without a ref serves as the baseline a task's changes
+//! are diffed against when the project isn't checkpointed.
+
+use git2::build::CheckoutBuilder;
+use git2::{Commit, Index, IndexEntry, IndexTime, Oid, Repository, Signature, StatusOptions};
+use serde::Serialize;
+use std::path::Path;
+
+const REF_PREFIX: &str = "refs/checkpoints/";
+/// Checkpoints kept per project; older ones are deleted
+const MAX_CHECKPOINTS: usize = 50;
+const TASK_TRAILER: &str = "Task: ";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Checkpoint {
+    pub id: String,
+    /// The snapshot commit
+    pub commit: String,
+    /// HEAD when the checkpoint was taken; `None` before the first commit
+    pub head: Option<String>,
+    pub label: String,
+    /// Task the checkpoint was taken before
+    #[serde(rename = "taskId")]
+    pub task_id: Option<String>,
+    #[serde(rename = "createdAt")]
+    pub created_at: i64,
+}
+
+/// Snapshot the work tree at `root`
+pub fn create_checkpoint(
+    root: &Path,
+    label: &str,
+    task_id: Option<&str>,
+) -> Result<Checkpoint, String> {
+    let repo = open(root)?;
+    let message = match task_id {
+        Some(task_id) => format!("{}\n\n{}{}\n", label, TASK_TRAILER, task_id),
+        None => format!("{}\n", label),
+    };
+    let commit_id = snapshot(&repo, &message)?;
+
+    let id = uuid::Uuid::new_v4().to_string();
+    repo.reference(
+        &format!("{}{}", REF_PREFIX, id),
+        commit_id,
+        false,
+        &format!("checkpoint: {}", label),
+    )
+    .map_err(git_error)?;
+
+    prune(&repo)?;
+    let commit = repo.find_commit(commit_id).map_err(git_error)?;
+    Ok(to_checkpoint(id, &commit))
+}
+
+/// Commit the work tree at `root` like a checkpoint, but without a ref, so
+/// it isn't listed or restorable. Returns the commit.
+pub fn snapshot_work_tree(root: &Path) -> Result<String, String> {
+    let repo = open(root)?;
+    snapshot(&repo, "Work tree snapshot\n").map(|id| id.to_string())
+}
+
+/// Commit the work tree on top of HEAD without moving anything
+fn snapshot(repo: &Repository, message: &str) -> Result<Oid, String> {
+    let head = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+
+    let mut index = Index::new().map_err(git_error)?;
+    if let Some(head) = &head {
+        index
+            .read_tree(&head.tree().map_err(git_error)?)
+            .map_err(git_error)?;
+    }
+
+    let mut options = StatusOptions::new();
+    options
+        .include_untracked(true)
+        .recurse_untracked_dirs(true)
+        .include_ignored(false)
+        .exclude_submodules(true);
+    let statuses = repo.statuses(Some(&mut options)).map_err(git_error)?;
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| "Repository has no work tree".to_string())?;
+
+    for entry in statuses.iter() {
+        let Some(path) = entry.path() else {
+            continue;
+        };
+        let full = workdir.join(path);
+        match std::fs::symlink_metadata(&full) {
+            Ok(meta) if meta.is_file() || meta.file_type().is_symlink() => {
+                let (id, mode) = if meta.file_type().is_symlink() {
+                    let target = std::fs::read_link(&full).map_err(|e| e.to_string())?;
+                    let bytes = target.to_string_lossy().into_owned().into_bytes();
+                    (repo.blob(&bytes).map_err(git_error)?, 0o120000)
+                } else {
+                    let id = repo.blob_path(&full).map_err(git_error)?;
+                    (id, file_mode(&meta))
+                };
+                index
+                    .add(&index_entry(path, id, mode, meta.len()))
+                    .map_err(git_error)?;
+            }
+            Ok(_) => {}
+            Err(_) => {
+                let _ = index.remove_path(Path::new(path));
+            }
+        }
+    }
+
+    let tree_id = index.write_tree_to(repo).map_err(git_error)?;
+    let tree = repo.find_tree(tree_id).map_err(git_error)?;
+    let signature = Signature::now("Claud.io", "checkpoints@claud.io").map_err(git_error)?;
+    let parents: Vec<&Commit> = head.iter().collect();
+    repo.commit(None, &signature, &signature, message, &tree, &parents)
+        .map_err(git_error)
+}
+
+/// Checkpoints of the repository at `root`, newest first
+pub fn list_checkpoints(root: &Path) -> Result<Vec<Checkpoint>, String> {
+    list(&open(root)?)
+}
+
+/// Put the work tree back as it was at checkpoint `id`. Returns the
+/// checkpoint taken of the state it replaced.
+pub fn restore_checkpoint(root: &Path, id: &str) -> Result<Checkpoint, String> {
+    let repo = open(root)?;
+    let target = find(&repo, id)?;
+    let label = target.summary().unwrap_or("checkpoint").to_string();
+    let backup = create_checkpoint(root, &format!("Before restoring \"{}\"", label), None)?;
+
+    let mut checkout = CheckoutBuilder::new();
+    checkout.force().remove_untracked(true);
+    repo.checkout_tree(
+        target.tree().map_err(git_error)?.as_object(),
+        Some(&mut checkout),
+    )
+    .map_err(git_error)?;
+
+    // The checkout staged the checkpoint's files; put the index back on HEAD
+    // so files untracked then are untracked again
+    let mut index = repo.index().map_err(git_error)?;
+    match repo.head().ok().and_then(|h| h.peel_to_tree().ok()) {
+        Some(tree) => index.read_tree(&tree).map_err(git_error)?,
+        None => index.clear().map_err(git_error)?,
+    }
+    index.write().map_err(git_error)?;
+
+    Ok(backup)
+}
+
+pub fn delete_checkpoint(root: &Path, id: &str) -> Result<(), String> {
+    let repo = open(root)?;
+    let mut reference = repo
+        .find_reference(&format!("{}{}", REF_PREFIX, id))
+        .map_err(|_| format!("Checkpoint not found: {}", id))?;
+    reference.delete().map_err(git_error)
+}
+
+fn open(root: &Path) -> Result<Repository, String> {
+    Repository::open(root).map_err(|_| format!("{} is not a git repository", root.display()))
+}
+
+fn git_error(e: git2::Error) -> String {
+    e.message().to_string()
+}
+
+fn find<'r>(repo: &'r Repository, id: &str) -> Result<Commit<'r>, String> {
+    repo.find_reference(&format!("{}{}", REF_PREFIX, id))
+        .and_then(|r| r.peel_to_commit())
+        .map_err(|_| format!("Checkpoint not found: {}", id))
+}
+
+fn list(repo: &Repository) -> Result<Vec<Checkpoint>, String> {
+    let mut checkpoints = Vec::new();
+    for reference in repo
+        .references_glob(&format!("{}*", REF_PREFIX))
+        .map_err(git_error)?
+        .flatten()
+    {
+        let Some(id) = reference.name().and_then(|n| n.strip_prefix(REF_PREFIX)) else {
+            continue;
+        };
+        if let Ok(commit) = reference.peel_to_commit() {
+            checkpoints.push(to_checkpoint(id.to_string(), &commit));
+        }
+    }
+    checkpoints.sort_by_key(|c| std::cmp::Reverse(c.created_at));
+    Ok(checkpoints)
+}
+
+fn prune(repo: &Repository) -> Result<(), String> {
+    for checkpoint in list(repo)?.into_iter().skip(MAX_CHECKPOINTS) {
+        if let Ok(mut reference) = repo.find_reference(&format!("{}{}", REF_PREFIX, checkpoint.id))
+        {
+            reference.delete().map_err(git_error)?;
+        }
+    }
+    Ok(())
+}
+
+fn to_checkpoint(id: String, commit: &Commit) -> Checkpoint {
+    let message = commit.message().unwrap_or_default();
+    Checkpoint {
+        id,
+        commit: commit.id().to_string(),
+        head: commit.parent_id(0).ok().map(|id| id.to_string()),
+        label: commit.summary().unwrap_or_default().to_string(),
+        task_id: message
+            .lines()
+            .find_map(|line| line.strip_prefix(TASK_TRAILER))
+            .map(str::to_string),
+        created_at: commit.time().seconds(),
+    }
+}
+
+#[cfg(unix)]
+fn file_mode(meta: &std::fs::Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    if meta.permissions().mode() & 0o111 != 0 {
+        0o100755
+    } else {
+        0o100644
+    }
+}
+
+#[cfg(not(unix))]
+fn file_mode(_meta: &std::fs::Metadata) -> u32 {
+    0o100644
+}
+
+fn index_entry(path: &str, id: Oid, mode: u32, size: u64) -> IndexEntry {
+    IndexEntry {
+        ctime: IndexTime::new(0, 0),
+        mtime: IndexTime::new(0, 0),
+        dev: 0,
+        ino: 0,
+        mode,
+        uid: 0,
+        gid: 0,
+        file_size: size as u32,
+        id,
+        flags: path.len().min(0xfff) as u16,
+        flags_extended: 0,
+        path: path.as_bytes().to_vec(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn commit_all(repo: &Repository, message: &str) {
+        let mut index = repo.index().unwrap();
+        index
+            .add_all(["*"], git2::IndexAddOption::DEFAULT, None)
+            .unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = Signature::now("Test", "test@example.com").unwrap();
+        let parent = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+        let parents: Vec<&Commit> = parent.iter().collect();
+        repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            message,
+            &tree,
+            &parents,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_restore_after_commit() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        let repo = Repository::init(root).unwrap();
+        std::fs::write(root.join(".gitignore"), "target/\n").unwrap();
+        std::fs::write(root.join("lib.rs"), "v1\n").unwrap();
+        commit_all(&repo, "init");
+
+        // Uncommitted edit, a new file and an ignored one
+        std::fs::write(root.join("lib.rs"), "v2\n").unwrap();
+        std::fs::write(root.join("notes.md"), "draft\n").unwrap();
+        std::fs::create_dir(root.join("target")).unwrap();
+        std::fs::write(root.join("target/out"), "build\n").unwrap();
+        let checkpoint = create_checkpoint(root, "Before task", Some("task-1")).unwrap();
+        assert_eq!(checkpoint.task_id.as_deref(), Some("task-1"));
+        assert_eq!(
+            repo.head().unwrap().peel_to_commit().unwrap().summary(),
+            Some("init")
+        );
+        assert!(repo
+            .statuses(None)
+            .unwrap()
+            .iter()
+            .all(|s| !s.status().is_index_new()));
+
+        // The agent rewrites things and the user commits on top
+        std::fs::write(root.join("lib.rs"), "v3\n").unwrap();
+        std::fs::remove_file(root.join("notes.md")).unwrap();
+        std::fs::write(root.join("extra.rs"), "added\n").unwrap();
+        commit_all(&repo, "agent work");
+
+        let backup = restore_checkpoint(root, &checkpoint.id).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(root.join("lib.rs")).unwrap(),
+            "v2\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(root.join("notes.md")).unwrap(),
+            "draft\n"
+        );
+        assert!(!root.join("extra.rs").exists());
+        assert!(root.join("target/out").exists());
+        let notes = repo.status_file(Path::new("notes.md")).unwrap();
+        assert!(notes.is_wt_new());
+
+        let ids: Vec<String> = list_checkpoints(root)
+            .unwrap()
+            .into_iter()
+            .map(|c| c.id)
+            .collect();
+        assert!(ids.contains(&checkpoint.id) && ids.contains(&backup.id));
+
+        restore_checkpoint(root, &backup.id).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(root.join("lib.rs")).unwrap(),
+            "v3\n"
+        );
+        assert!(root.join("extra.rs").exists());
+        assert!(!root.join("notes.md").exists());
+
+        delete_checkpoint(root, &checkpoint.id).unwrap();
+        assert!(restore_checkpoint(root, &checkpoint.id).is_err());
+    }
+}
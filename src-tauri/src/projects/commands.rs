@@ -1,6 +1,7 @@
 //! Tauri commands for projects module
 
-use super::manager::{GitStatus, Project, ProjectFile, ProjectManager};
+use super::checkpoints::Checkpoint;
+use super::manager::{GitStatus, Project, ProjectFile, ProjectManager, ProjectSettings};
 use std::sync::Arc;
 use tauri::State;
 
@@ -26,6 +27,15 @@ pub fn project_remove(
     manager.remove(&project_id)
 }
 
+#[tauri::command]
+pub fn project_update_settings(
+    manager: State<'_, Arc<ProjectManager>>,
+    project_id: String,
+    settings: ProjectSettings,
+) -> Result<(), String> {
+    manager.update_settings(&project_id, &settings)
+}
+
 #[tauri::command]
 pub fn project_get_file_tree(
     manager: State<'_, Arc<ProjectManager>>,
@@ -42,6 +52,44 @@ pub fn project_git_status(
     manager.get_git_status(&project_id)
 }
 
+#[tauri::command]
+pub fn project_list_checkpoints(
+    manager: State<'_, Arc<ProjectManager>>,
+    project_id: String,
+) -> Result<Vec<Checkpoint>, String> {
+    manager.list_checkpoints(&project_id)
+}
+
+#[tauri::command]
+pub fn project_create_checkpoint(
+    manager: State<'_, Arc<ProjectManager>>,
+    project_id: String,
+    label: Option<String>,
+) -> Result<Checkpoint, String> {
+    let label = label.unwrap_or_else(|| "Manual checkpoint".to_string());
+    manager.create_checkpoint(&project_id, &label, None)
+}
+
+/// Put the work tree back to a checkpoint; returns the checkpoint taken of
+/// the state it replaced, so the restore can be undone
+#[tauri::command]
+pub fn project_restore_checkpoint(
+    manager: State<'_, Arc<ProjectManager>>,
+    project_id: String,
+    checkpoint_id: String,
+) -> Result<Checkpoint, String> {
+    manager.restore_checkpoint(&project_id, &checkpoint_id)
+}
+
+#[tauri::command]
+pub fn project_delete_checkpoint(
+    manager: State<'_, Arc<ProjectManager>>,
+    project_id: String,
+    checkpoint_id: String,
+) -> Result<(), String> {
+    manager.delete_checkpoint(&project_id, &checkpoint_id)
+}
+
 #[tauri::command]
 pub fn project_read_file(path: String) -> Result<String, String> {
     std::fs::read_to_string(&path).map_err(|e| e.to_string())
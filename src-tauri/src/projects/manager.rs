@@ -1,5 +1,6 @@
 //! Project manager implementation
 
+use super::checkpoints::{self, Checkpoint};
 use crate::db::Database;
 use chrono::Utc;
 use rusqlite::params;
@@ -38,6 +39,12 @@ pub struct ProjectSettings {
     pub watch_patterns: Option<Vec<String>>,
     #[serde(rename = "ignorePatterns")]
     pub ignore_patterns: Option<Vec<String>>,
+    /// Most of the project's tasks that may run at once; unset means no cap
+    #[serde(rename = "maxConcurrentTasks")]
+    pub max_concurrent_tasks: Option<i32>,
+    /// Checkpoint the work tree before each task; on unless `false`
+    #[serde(rename = "checkpointTasks")]
+    pub checkpoint_tasks: Option<bool>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -172,6 +179,26 @@ impl ProjectManager {
         })
     }
 
+    /// Replace a project's settings
+    pub fn update_settings(&self, project_id: &str, settings: &ProjectSettings) -> Result<(), String> {
+        if settings.max_concurrent_tasks.is_some_and(|n| n < 1) {
+            return Err("maxConcurrentTasks must be at least 1".to_string());
+        }
+
+        let updated = self.db.with_conn(|conn| {
+            conn.execute(
+                "UPDATE projects SET settings = ?1 WHERE id = ?2",
+                params![serde_json::to_string(settings).unwrap_or_default(), project_id],
+            )
+            .map_err(|e| e.to_string())
+        })?;
+
+        if updated == 0 {
+            return Err(format!("Project not found: {}", project_id));
+        }
+        Ok(())
+    }
+
     /// Get a project by ID
     pub fn get(&self, project_id: &str) -> Result<Option<Project>, String> {
         self.db.with_conn(|conn| {
@@ -207,6 +234,35 @@ impl ProjectManager {
         })
     }
 
+    fn project_path(&self, project_id: &str) -> Result<PathBuf, String> {
+        self.get(project_id)?
+            .map(|p| PathBuf::from(p.path))
+            .ok_or_else(|| format!("Project not found: {}", project_id))
+    }
+
+    /// Checkpoints of a git project, newest first
+    pub fn list_checkpoints(&self, project_id: &str) -> Result<Vec<Checkpoint>, String> {
+        checkpoints::list_checkpoints(&self.project_path(project_id)?)
+    }
+
+    pub fn create_checkpoint(
+        &self,
+        project_id: &str,
+        label: &str,
+        task_id: Option<&str>,
+    ) -> Result<Checkpoint, String> {
+        checkpoints::create_checkpoint(&self.project_path(project_id)?, label, task_id)
+    }
+
+    /// Restore a checkpoint; returns the checkpoint of the replaced state
+    pub fn restore_checkpoint(&self, project_id: &str, checkpoint_id: &str) -> Result<Checkpoint, String> {
+        checkpoints::restore_checkpoint(&self.project_path(project_id)?, checkpoint_id)
+    }
+
+    pub fn delete_checkpoint(&self, project_id: &str, checkpoint_id: &str) -> Result<(), String> {
+        checkpoints::delete_checkpoint(&self.project_path(project_id)?, checkpoint_id)
+    }
+
     /// Get file tree for a project
     pub fn get_file_tree(&self, project_id: &str) -> Result<ProjectFile, String> {
         let project = self
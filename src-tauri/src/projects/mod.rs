@@ -1,8 +1,11 @@
 //! Projects module for Claud.io
 //!
-//! Manages code and content projects, file trees, and git operations.
+//! Manages code and content projects, file trees, git operations and
+//! work tree checkpoints.
 
+mod checkpoints;
 pub mod commands;
 mod manager;
 
-pub use manager::ProjectManager;
+pub use checkpoints::snapshot_work_tree;
+pub use manager::{GitStatus, Project, ProjectFile, ProjectManager};
@@ -67,7 +67,12 @@ impl StateManager {
         *current = Some(state);
     }
 
-    pub fn start_watching(&self, app_handle: AppHandle) {
+    /// Poll the state file, emitting each new state to the frontend and
+    /// calling `on_transition(from, to)` when the state name changes
+    pub fn start_watching<F>(&self, app_handle: AppHandle, on_transition: F)
+    where
+        F: Fn(&ClaudeState, &ClaudeState) + Send + 'static,
+    {
         let state_manager = Arc::new(self.clone());
 
         tauri::async_runtime::spawn(async move {
@@ -89,8 +94,15 @@ impl StateManager {
 
                             // Read and emit new state
                             if let Ok(new_state) = state_manager.read_state().await {
+                                let previous = state_manager.get_current_state().await;
                                 state_manager.update_state(new_state.clone()).await;
 
+                                if let Some(previous) = previous {
+                                    if previous.state != new_state.state {
+                                        on_transition(&previous, &new_state);
+                                    }
+                                }
+
                                 // Emit event to frontend
                                 let _ = app_handle.emit("claude-state-changed", &new_state);
                             }
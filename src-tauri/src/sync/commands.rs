@@ -4,16 +4,23 @@ use super::agent_parser::{parse_agents_directory, AgentDefinition};
 use super::git_sync::{get_repo_status, pull_repository, GitSyncResult, RepoStatus};
 use super::project_discovery::{discover_projects, DiscoveredProject};
 use super::watcher::FolderWatcher;
+use crate::agents::AgentManager;
+use crate::triggers::{TriggerEvent, TriggerManager};
 use parking_lot::Mutex;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use tauri::{AppHandle, State};
+use tauri::{AppHandle, Emitter, State};
 
 /// State for the folder watcher
 pub struct SyncState {
     pub watcher: Arc<Mutex<FolderWatcher>>,
     pub projects_path: PathBuf,
     pub agents_path: PathBuf,
+    /// Whether file changes run `files_changed` triggers. Only the process
+    /// running the agent runtime fires them, so each change fires once; the
+    /// app turns this on when it takes the runtime over.
+    pub fires_triggers: Arc<AtomicBool>,
 }
 
 impl SyncState {
@@ -22,6 +29,7 @@ impl SyncState {
             watcher: Arc::new(Mutex::new(FolderWatcher::new())),
             projects_path: PathBuf::from("/Users/mikel/Desktop/PROYECTOS"),
             agents_path: PathBuf::from("/Users/mikel/Claude/MR-AGENTS"),
+            fires_triggers: Arc::new(AtomicBool::new(true)),
         }
     }
 }
@@ -48,10 +56,30 @@ pub fn sync_discover_projects(
 #[tauri::command]
 pub fn sync_start_project_watch(
     state: State<'_, SyncState>,
+    triggers: State<'_, Arc<TriggerManager>>,
     app_handle: AppHandle,
 ) -> Result<(), String> {
+    let fires_triggers = Arc::clone(&state.fires_triggers);
+    let triggers = Arc::clone(&triggers);
     let mut watcher = state.watcher.lock();
-    watcher.start_watching(state.projects_path.clone(), app_handle)
+    watcher.start_watching(state.projects_path.clone(), move |paths| {
+        let _ = app_handle.emit("sync:projects-changed", ());
+        log::debug!("Emitted sync:projects-changed event");
+        if fires_triggers.load(Ordering::SeqCst) {
+            fire_files_changed(&triggers, paths);
+        }
+    })
+}
+
+/// Run the `files_changed` triggers for paths the watcher reported
+pub fn fire_files_changed(triggers: &TriggerManager, paths: Vec<PathBuf>) {
+    let paths = paths
+        .iter()
+        .map(|p| p.to_string_lossy().to_string())
+        .collect();
+    if let Err(e) = triggers.fire(&TriggerEvent::FilesChanged { paths }) {
+        log::warn!("Failed to run file change triggers: {}", e);
+    }
 }
 
 /// Stop watching the PROYECTOS folder
@@ -72,10 +100,24 @@ pub fn sync_get_projects_path(state: State<'_, SyncState>) -> String {
 // Agent Commands
 // ============================================================================
 
+/// Parse the agent definitions in `agents_path` and store them, so agents
+/// created from a definition inherit its current `mode`
+pub fn refresh_agent_definitions(
+    agents: &AgentManager,
+    agents_path: &Path,
+) -> Result<Vec<AgentDefinition>, String> {
+    let definitions = parse_agents_directory(agents_path)?;
+    agents.save_agent_definitions(&definitions)?;
+    Ok(definitions)
+}
+
 /// Parse all agent definitions from MR-AGENTS folder
 #[tauri::command]
-pub fn sync_parse_agents(state: State<'_, SyncState>) -> Result<Vec<AgentDefinition>, String> {
-    parse_agents_directory(&state.agents_path)
+pub fn sync_parse_agents(
+    state: State<'_, SyncState>,
+    agents: State<'_, Arc<AgentManager>>,
+) -> Result<Vec<AgentDefinition>, String> {
+    refresh_agent_definitions(&agents, &state.agents_path)
 }
 
 /// Get a single agent definition by ID
@@ -93,8 +135,44 @@ pub fn sync_get_agent_definition(
 
 /// Pull latest changes from MR-AGENTS repo
 #[tauri::command]
-pub fn sync_pull_agents_repo(state: State<'_, SyncState>) -> Result<GitSyncResult, String> {
-    pull_repository(&state.agents_path)
+pub fn sync_pull_agents_repo(
+    state: State<'_, SyncState>,
+    agents: State<'_, Arc<AgentManager>>,
+    triggers: State<'_, Arc<TriggerManager>>,
+) -> Result<GitSyncResult, String> {
+    pull_agents_repo(&agents, &triggers, &state.agents_path)
+}
+
+/// Pull the agents repo, reload the definitions and run the
+/// `agents_pulled` triggers. The triggers run even when the definitions
+/// fail to reload, since the pull happened either way; the failure is
+/// reported in `refresh_error`.
+pub fn pull_agents_repo(
+    agents: &AgentManager,
+    triggers: &TriggerManager,
+    agents_path: &Path,
+) -> Result<GitSyncResult, String> {
+    let mut result = pull_repository(agents_path)?;
+    if let Err(e) = refresh_agent_definitions(agents, agents_path) {
+        log::warn!("Failed to reload agent definitions: {}", e);
+        result.refresh_error = Some(e);
+    }
+    fire_agents_pulled(triggers, &result);
+    Ok(result)
+}
+
+/// Run the `agents_pulled` triggers after a pull that brought changes
+pub fn fire_agents_pulled(triggers: &TriggerManager, result: &GitSyncResult) {
+    if !result.success || result.files_changed == 0 {
+        return;
+    }
+    let event = TriggerEvent::AgentsPulled {
+        message: result.message.clone(),
+        files_changed: result.files_changed,
+    };
+    if let Err(e) = triggers.fire(&event) {
+        log::warn!("Failed to run agents pull triggers: {}", e);
+    }
 }
 
 /// Get the status of MR-AGENTS repo
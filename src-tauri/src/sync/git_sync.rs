@@ -15,6 +15,10 @@ pub struct GitSyncResult {
     pub previous_commit: Option<String>,
     pub current_commit: Option<String>,
     pub files_changed: u32,
+    /// Why the pulled agent definitions couldn't be reloaded, if they
+    /// couldn't. The pull itself still went through.
+    #[serde(default)]
+    pub refresh_error: Option<String>,
 }
 
 /// Get the current HEAD commit hash
@@ -84,6 +88,7 @@ pub fn pull_repository(repo_path: &Path) -> Result<GitSyncResult, String> {
             previous_commit: previous_commit.clone(),
             current_commit: previous_commit,
             files_changed: 0,
+            refresh_error: None,
         });
     }
 
@@ -112,6 +117,7 @@ pub fn pull_repository(repo_path: &Path) -> Result<GitSyncResult, String> {
             previous_commit,
             current_commit,
             files_changed: 0, // Would need diff to count
+            refresh_error: None,
         })
     } else {
         Err("Cannot fast-forward, manual merge required".to_string())
@@ -17,6 +17,19 @@ pub enum ProjectType {
     Unknown,
 }
 
+impl ProjectType {
+    /// Name stored in the `projects.type` column
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProjectType::Node => "node",
+            ProjectType::Rust => "rust",
+            ProjectType::Python => "python",
+            ProjectType::Go => "go",
+            ProjectType::Unknown => "unknown",
+        }
+    }
+}
+
 /// A discovered project from the PROYECTOS folder
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -101,7 +114,7 @@ fn detect_project(path: &Path) -> Option<DiscoveredProject> {
 }
 
 /// Detect the type of project based on config files
-fn detect_project_type(path: &Path) -> ProjectType {
+pub fn detect_project_type(path: &Path) -> ProjectType {
     // Check for Node.js
     if path.join("package.json").exists() {
         return ProjectType::Node;
@@ -1,6 +1,7 @@
 //! File system watcher module
 //!
-//! Watches the PROYECTOS folder for changes and emits events.
+//! Watches the PROYECTOS folder for changes and reports them (debounced) to
+//! a callback, with the paths that changed.
 
 use notify::{
     Config, Event, RecommendedWatcher, RecursiveMode, Watcher,
@@ -11,7 +12,6 @@ use std::sync::mpsc::{channel, Receiver};
 use std::sync::Arc;
 use std::time::Duration;
 use parking_lot::Mutex;
-use tauri::{AppHandle, Emitter};
 
 /// File system watcher for project folder
 pub struct FolderWatcher {
@@ -29,12 +29,12 @@ impl FolderWatcher {
         }
     }
 
-    /// Start watching a folder for changes
-    pub fn start_watching(
-        &mut self,
-        path: PathBuf,
-        app_handle: AppHandle,
-    ) -> Result<(), String> {
+    /// Start watching a folder for changes, calling `on_change` once per
+    /// burst of changes with the paths touched in it
+    pub fn start_watching<F>(&mut self, path: PathBuf, on_change: F) -> Result<(), String>
+    where
+        F: Fn(Vec<PathBuf>) + Send + 'static,
+    {
         if *self.is_running.lock() {
             return Err("Watcher is already running".to_string());
         }
@@ -67,7 +67,7 @@ impl FolderWatcher {
         // Spawn event handler
         let is_running = Arc::clone(&self.is_running);
         tauri::async_runtime::spawn(async move {
-            Self::handle_events(rx, app_handle, is_running).await;
+            Self::handle_events(rx, on_change, is_running).await;
         });
 
         log::info!("Started watching folder: {:?}", path);
@@ -83,12 +83,12 @@ impl FolderWatcher {
     }
 
     /// Handle file system events
-    async fn handle_events(
-        rx: Receiver<Event>,
-        app_handle: AppHandle,
-        is_running: Arc<Mutex<bool>>,
-    ) {
+    async fn handle_events<F>(rx: Receiver<Event>, on_change: F, is_running: Arc<Mutex<bool>>)
+    where
+        F: Fn(Vec<PathBuf>) + Send + 'static,
+    {
         let mut debounce_timer: Option<tokio::time::Instant> = None;
+        let mut changed: Vec<PathBuf> = Vec::new();
         let debounce_duration = Duration::from_millis(500);
 
         loop {
@@ -107,15 +107,18 @@ impl FolderWatcher {
 
                     if is_relevant {
                         debounce_timer = Some(tokio::time::Instant::now());
+                        for path in event.paths {
+                            if !changed.contains(&path) {
+                                changed.push(path);
+                            }
+                        }
                     }
                 }
                 Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {
                     // Check if debounce timer has expired
                     if let Some(timer) = debounce_timer {
                         if timer.elapsed() >= debounce_duration {
-                            // Emit change event
-                            let _ = app_handle.emit("sync:projects-changed", ());
-                            log::debug!("Emitted sync:projects-changed event");
+                            on_change(std::mem::take(&mut changed));
                             debounce_timer = None;
                         }
                     }
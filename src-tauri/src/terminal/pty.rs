@@ -1,10 +1,12 @@
-use portable_pty::{native_pty_system, CommandBuilder, MasterPty, PtySize};
+use portable_pty::{native_pty_system, Child, CommandBuilder, MasterPty, PtySize};
 use std::io::{Read, Write};
+use std::path::Path;
 use tokio::sync::mpsc;
 
 pub struct PtyProcess {
     master: Box<dyn MasterPty + Send>,
     writer: Box<dyn Write + Send>,
+    child: Box<dyn Child + Send + Sync>,
 }
 
 impl PtyProcess {
@@ -12,6 +14,7 @@ impl PtyProcess {
         cols: u16,
         rows: u16,
         command: Option<&str>,
+        cwd: Option<&Path>,
     ) -> Result<(Self, mpsc::Receiver<Vec<u8>>), String> {
         let pty_system = native_pty_system();
 
@@ -41,12 +44,14 @@ impl PtyProcess {
         cmd.env("TERM", "xterm-256color");
         cmd.env("COLORTERM", "truecolor");
 
-        // Set current directory to home
-        if let Ok(home) = std::env::var("HOME") {
+        // Set current directory, defaulting to home
+        if let Some(cwd) = cwd {
+            cmd.cwd(cwd);
+        } else if let Ok(home) = std::env::var("HOME") {
             cmd.cwd(&home);
         }
 
-        let _child = pair
+        let child = pair
             .slave
             .spawn_command(cmd)
             .map_err(|e| format!("Failed to spawn command: {}", e))?;
@@ -84,6 +89,7 @@ impl PtyProcess {
             Self {
                 master: pair.master,
                 writer,
+                child,
             },
             rx,
         ))
@@ -99,6 +105,29 @@ impl PtyProcess {
         Ok(())
     }
 
+    /// Exit code of the process, once it has exited
+    pub fn exit_code(&mut self) -> Option<u32> {
+        self.child.try_wait().ok().flatten().map(|status| status.exit_code())
+    }
+
+    pub fn kill(&mut self) -> Result<(), String> {
+        self.child.kill().map_err(|e| format!("Kill error: {}", e))
+    }
+
+    /// Kill the process along with everything it started. The child is
+    /// spawned as the leader of a new session, and so of its own process
+    /// group, which on unix is signalled as a whole.
+    pub fn kill_group(&mut self) -> Result<(), String> {
+        #[cfg(unix)]
+        if let Some(pid) = self.child.process_id() {
+            // SAFETY: kill(2) only reads its arguments
+            if unsafe { libc::kill(-(pid as libc::pid_t), libc::SIGKILL) } == 0 {
+                return Ok(());
+            }
+        }
+        self.kill()
+    }
+
     pub fn resize(&self, cols: u16, rows: u16) -> Result<(), String> {
         self.master
             .resize(PtySize {
@@ -22,7 +22,7 @@ pub struct Session {
 
 impl Session {
     pub fn new(id: String, cols: u16, rows: u16, command: Option<&str>) -> Result<Self, String> {
-        let (pty, output_rx) = PtyProcess::spawn(cols, rows, command)?;
+        let (pty, output_rx) = PtyProcess::spawn(cols, rows, command, None)?;
 
         let created_at = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
@@ -0,0 +1,35 @@
+//! Tauri commands for triggers module
+
+use super::manager::{TriggerManager, TriggerRule};
+use std::sync::Arc;
+use tauri::State;
+
+#[tauri::command]
+pub fn trigger_list(manager: State<'_, Arc<TriggerManager>>) -> Result<Vec<TriggerRule>, String> {
+    manager.list()
+}
+
+#[tauri::command]
+pub fn trigger_save(
+    manager: State<'_, Arc<TriggerManager>>,
+    rule: TriggerRule,
+) -> Result<TriggerRule, String> {
+    manager.save(rule)
+}
+
+#[tauri::command]
+pub fn trigger_delete(
+    manager: State<'_, Arc<TriggerManager>>,
+    trigger_id: String,
+) -> Result<(), String> {
+    manager.delete(&trigger_id)
+}
+
+#[tauri::command]
+pub fn trigger_set_enabled(
+    manager: State<'_, Arc<TriggerManager>>,
+    trigger_id: String,
+    enabled: bool,
+) -> Result<(), String> {
+    manager.set_enabled(&trigger_id, enabled)
+}
@@ -0,0 +1,173 @@
+//! New-commit detection
+//!
+//! Polls the HEAD of every registered git project and reports commits made
+//! since the last look, as one event for all of them. The first look at a
+//! project only records its HEAD, so existing history never fires
+//! triggers, and neither does HEAD moving anywhere but forward.
+
+use super::manager::{TriggerEvent, TriggerManager};
+use crate::projects::ProjectManager;
+use git2::{Commit, Oid, Repository, Sort};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How often projects are checked for new commits
+pub const COMMIT_POLL_INTERVAL: Duration = Duration::from_secs(30);
+/// Most commits one event lists; the newest are kept
+const MAX_REPORTED_COMMITS: usize = 50;
+
+/// Start polling projects for new commits every `interval`
+pub fn watch_commits(
+    triggers: Arc<TriggerManager>,
+    projects: Arc<ProjectManager>,
+    interval: Duration,
+) -> tauri::async_runtime::JoinHandle<()> {
+    tauri::async_runtime::spawn(async move {
+        let mut heads: HashMap<String, String> = HashMap::new();
+        let mut ticker = tokio::time::interval(interval);
+
+        loop {
+            ticker.tick().await;
+
+            let list = match projects.list() {
+                Ok(list) => list,
+                Err(e) => {
+                    log::warn!("Failed to list projects: {}", e);
+                    continue;
+                }
+            };
+
+            for project in list {
+                let previous = heads.get(&project.id).map(String::as_str);
+                let Some((head, commits)) = commits_since(Path::new(&project.path), previous)
+                else {
+                    continue;
+                };
+                heads.insert(project.id.clone(), head);
+                let Some(latest) = commits.last() else {
+                    continue;
+                };
+
+                let event = TriggerEvent::Commit {
+                    project_id: project.id,
+                    sha: latest.sha.clone(),
+                    summary: latest.summary.clone(),
+                    author: latest.author.clone(),
+                    commits: commits.iter().map(NewCommit::describe).collect(),
+                };
+                if let Err(e) = triggers.fire(&event) {
+                    log::warn!("Failed to run commit triggers: {}", e);
+                }
+            }
+        }
+    })
+}
+
+struct NewCommit {
+    sha: String,
+    summary: String,
+    author: String,
+}
+
+impl NewCommit {
+    fn new(commit: &Commit) -> Self {
+        Self {
+            sha: commit.id().to_string(),
+            summary: commit.summary().unwrap_or_default().to_string(),
+            author: commit.author().name().unwrap_or_default().to_string(),
+        }
+    }
+
+    /// One line for the `{{commits}}` placeholder
+    fn describe(&self) -> String {
+        let short: String = self.sha.chars().take(7).collect();
+        format!("{} {} ({})", short, self.summary, self.author)
+    }
+}
+
+/// HEAD of the repository at `path` and the commits it gained on top of
+/// `previous`, oldest first. A HEAD that isn't a descendant of `previous`
+/// (a checkout, reset or rebase) gained nothing.
+fn commits_since(path: &Path, previous: Option<&str>) -> Option<(String, Vec<NewCommit>)> {
+    if !path.join(".git").exists() {
+        return None;
+    }
+
+    let repo = Repository::open(path).ok()?;
+    let head = repo.head().ok()?.peel_to_commit().ok()?.id();
+    let Some(previous) = previous.and_then(|sha| Oid::from_str(sha).ok()) else {
+        return Some((head.to_string(), vec![]));
+    };
+    if head == previous || !repo.graph_descendant_of(head, previous).unwrap_or(false) {
+        return Some((head.to_string(), vec![]));
+    }
+
+    let mut walk = repo.revwalk().ok()?;
+    walk.set_sorting(Sort::TOPOLOGICAL).ok()?;
+    walk.push(head).ok()?;
+    walk.hide(previous).ok()?;
+    let mut commits: Vec<NewCommit> = walk
+        .filter_map(Result::ok)
+        .take(MAX_REPORTED_COMMITS)
+        .filter_map(|oid| repo.find_commit(oid).ok())
+        .map(|commit| NewCommit::new(&commit))
+        .collect();
+    commits.reverse();
+    Some((head.to_string(), commits))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::{ResetType, Signature};
+    use tempfile::tempdir;
+
+    fn commit(repo: &Repository, message: &str) -> String {
+        let tree = repo
+            .find_tree(repo.index().unwrap().write_tree().unwrap())
+            .unwrap();
+        let signature = Signature::now("Ana", "ana@example.com").unwrap();
+        let parent = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+        let parents: Vec<&Commit> = parent.iter().collect();
+        repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            message,
+            &tree,
+            &parents,
+        )
+        .unwrap()
+        .to_string()
+    }
+
+    #[test]
+    fn test_commits_since_only_counts_new_history() {
+        let dir = tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let first = commit(&repo, "First");
+
+        let (head, commits) = commits_since(dir.path(), None).unwrap();
+        assert_eq!(head, first);
+        assert!(commits.is_empty());
+
+        let second = commit(&repo, "Second");
+        let third = commit(&repo, "Third");
+        let (head, commits) = commits_since(dir.path(), Some(&first)).unwrap();
+        assert_eq!(head, third);
+        let summaries: Vec<&str> = commits.iter().map(|c| c.summary.as_str()).collect();
+        assert_eq!(summaries, ["Second", "Third"]);
+        assert!(commits[1].describe().ends_with("Third (Ana)"));
+
+        // Moving HEAD back adds no commits
+        let target = repo
+            .find_object(Oid::from_str(&second).unwrap(), None)
+            .unwrap();
+        repo.reset(&target, ResetType::Hard, None).unwrap();
+        let (head, commits) = commits_since(dir.path(), Some(&third)).unwrap();
+        assert_eq!(head, second);
+        assert!(commits.is_empty());
+    }
+}
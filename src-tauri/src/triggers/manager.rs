@@ -0,0 +1,645 @@
+//! Trigger manager implementation
+//!
+//! A trigger rule names an event, optional conditions on it and a task
+//! template. When a matching event arrives the template is filled in from
+//! the event (`{{project}}`, `{{sha}}`, `{{paths}}`, ...) and queued as a
+//! task. A rule fires at most once per `debounce_secs`; events in between
+//! are dropped. The check is a single conditional update, so the desktop
+//! app and the daemon can both report the same event without creating two
+//! tasks.
+
+use crate::agents::{AgentManager, Task, TASK_PRIORITIES};
+use crate::db::Database;
+use crate::projects::{Project, ProjectManager};
+use chrono::Utc;
+use globset::Glob;
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Event kinds a rule can listen for
+pub const TRIGGER_EVENTS: &[&str] = &["files_changed", "commit", "claude_state", "agents_pulled"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TriggerRule {
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    /// One of `TRIGGER_EVENTS`
+    pub event: String,
+    #[serde(default)]
+    pub filter: TriggerFilter,
+    pub template: TaskTemplate,
+    /// Least time between two tasks from this rule
+    #[serde(rename = "debounceSecs", default = "default_debounce_secs")]
+    pub debounce_secs: i64,
+    pub enabled: bool,
+    #[serde(rename = "lastFiredAt", default)]
+    pub last_fired_at: Option<i64>,
+    #[serde(rename = "fireCount", default)]
+    pub fire_count: i64,
+    #[serde(rename = "createdAt", default)]
+    pub created_at: i64,
+}
+
+/// Matches the column default, so rules saved without one are debounced
+fn default_debounce_secs() -> i64 {
+    60
+}
+
+/// Conditions an event must meet; unset fields match anything
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct TriggerFilter {
+    /// Only events from this project
+    #[serde(rename = "projectId")]
+    pub project_id: Option<String>,
+    /// Glob one of the changed paths must match (`files_changed`)
+    pub pattern: Option<String>,
+    /// State Claude entered, e.g. `idle` (`claude_state`)
+    pub state: Option<String>,
+    /// State Claude left (`claude_state`)
+    #[serde(rename = "fromState")]
+    pub from_state: Option<String>,
+}
+
+/// The task a rule creates. Text fields may use `{{placeholders}}`.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct TaskTemplate {
+    pub title: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(rename = "agentId")]
+    pub agent_id: Option<String>,
+    /// Defaults to the event's project
+    #[serde(rename = "projectId")]
+    pub project_id: Option<String>,
+    #[serde(rename = "targetAgentType")]
+    pub target_agent_type: Option<String>,
+    pub priority: Option<String>,
+    #[serde(default)]
+    pub skills: Vec<String>,
+}
+
+/// Something that happened which rules may react to
+#[derive(Debug, Clone)]
+pub enum TriggerEvent {
+    FilesChanged {
+        paths: Vec<String>,
+    },
+    /// `sha`, `summary` and `author` are the newest of `commits`
+    Commit {
+        project_id: String,
+        sha: String,
+        summary: String,
+        author: String,
+        /// One line per new commit, oldest first
+        commits: Vec<String>,
+    },
+    ClaudeState {
+        from: String,
+        to: String,
+        session_id: String,
+    },
+    AgentsPulled {
+        message: String,
+        files_changed: u32,
+    },
+}
+
+impl TriggerEvent {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            TriggerEvent::FilesChanged { .. } => "files_changed",
+            TriggerEvent::Commit { .. } => "commit",
+            TriggerEvent::ClaudeState { .. } => "claude_state",
+            TriggerEvent::AgentsPulled { .. } => "agents_pulled",
+        }
+    }
+
+    /// Values for the template placeholders
+    fn vars(&self, project: Option<&Project>) -> HashMap<&'static str, String> {
+        let mut vars = HashMap::from([("event", self.kind().to_string())]);
+        if let Some(project) = project {
+            vars.insert("project", project.name.clone());
+            vars.insert("projectId", project.id.clone());
+            vars.insert("projectPath", project.path.clone());
+        }
+
+        match self {
+            TriggerEvent::FilesChanged { paths } => {
+                vars.insert("paths", paths.join("\n"));
+            }
+            TriggerEvent::Commit {
+                sha,
+                summary,
+                author,
+                commits,
+                ..
+            } => {
+                vars.insert("sha", sha.clone());
+                vars.insert("shortSha", sha.chars().take(7).collect());
+                vars.insert("summary", summary.clone());
+                vars.insert("author", author.clone());
+                vars.insert("commits", commits.join("\n"));
+                vars.insert("commitCount", commits.len().to_string());
+            }
+            TriggerEvent::ClaudeState {
+                from,
+                to,
+                session_id,
+            } => {
+                vars.insert("from", from.clone());
+                vars.insert("to", to.clone());
+                vars.insert("sessionId", session_id.clone());
+            }
+            TriggerEvent::AgentsPulled {
+                message,
+                files_changed,
+            } => {
+                vars.insert("message", message.clone());
+                vars.insert("filesChanged", files_changed.to_string());
+            }
+        }
+        vars
+    }
+}
+
+impl TriggerFilter {
+    fn matches(&self, event: &TriggerEvent, project: Option<&Project>) -> bool {
+        if let Some(project_id) = &self.project_id {
+            if project.map(|p| &p.id) != Some(project_id) {
+                return false;
+            }
+        }
+
+        match event {
+            TriggerEvent::FilesChanged { paths } => {
+                let Some(pattern) = &self.pattern else {
+                    return true;
+                };
+                let Ok(glob) = Glob::new(pattern) else {
+                    return false;
+                };
+                let matcher = glob.compile_matcher();
+                paths.iter().any(|path| {
+                    let path = Path::new(path);
+                    matcher.is_match(path)
+                        || path.file_name().is_some_and(|name| matcher.is_match(name))
+                })
+            }
+            TriggerEvent::ClaudeState { from, to, .. } => {
+                self.state
+                    .as_ref()
+                    .map_or(true, |s| s.eq_ignore_ascii_case(to))
+                    && self
+                        .from_state
+                        .as_ref()
+                        .map_or(true, |s| s.eq_ignore_ascii_case(from))
+            }
+            TriggerEvent::Commit { .. } | TriggerEvent::AgentsPulled { .. } => true,
+        }
+    }
+}
+
+/// Replace `{{name}}` placeholders; unknown ones are left as they are.
+/// Substituted values are never scanned again, so a commit summary that
+/// contains `{{sha}}` stays as written.
+fn render(template: &str, vars: &HashMap<&'static str, String>) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else {
+            rest = &rest[start..];
+            break;
+        };
+        match vars.get(&after[..end]) {
+            Some(value) => out.push_str(value),
+            None => out.push_str(&rest[start..start + 2 + end + 2]),
+        }
+        rest = &after[end + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+const TRIGGER_COLUMNS: &str = "id, name, event, filter, template, debounce_secs, enabled, \
+     last_fired_at, fire_count, created_at";
+
+pub struct TriggerManager {
+    db: Database,
+    agents: Arc<AgentManager>,
+    projects: Arc<ProjectManager>,
+}
+
+impl TriggerManager {
+    pub fn new(db: Database, agents: Arc<AgentManager>, projects: Arc<ProjectManager>) -> Self {
+        Self {
+            db,
+            agents,
+            projects,
+        }
+    }
+
+    pub fn list(&self) -> Result<Vec<TriggerRule>, String> {
+        self.db.with_conn(|conn| {
+            let mut stmt = conn
+                .prepare(&format!(
+                    "SELECT {} FROM triggers ORDER BY created_at",
+                    TRIGGER_COLUMNS
+                ))
+                .map_err(|e| e.to_string())?;
+
+            let rules = stmt
+                .query_map([], |row| {
+                    let filter: String = row.get(3)?;
+                    let template: String = row.get(4)?;
+                    Ok(TriggerRule {
+                        id: row.get(0)?,
+                        name: row.get(1)?,
+                        event: row.get(2)?,
+                        filter: serde_json::from_str(&filter).unwrap_or_default(),
+                        template: serde_json::from_str(&template).unwrap_or_default(),
+                        debounce_secs: row.get(5)?,
+                        enabled: row.get(6)?,
+                        last_fired_at: row.get(7)?,
+                        fire_count: row.get(8)?,
+                        created_at: row.get(9)?,
+                    })
+                })
+                .map_err(|e| e.to_string())?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| e.to_string())?;
+
+            Ok(rules)
+        })
+    }
+
+    /// Create or update a rule. Firing history is kept on update.
+    pub fn save(&self, rule: TriggerRule) -> Result<TriggerRule, String> {
+        if rule.name.trim().is_empty() {
+            return Err("Trigger name is required".to_string());
+        }
+        if !TRIGGER_EVENTS.contains(&rule.event.as_str()) {
+            return Err(format!(
+                "event must be one of {}",
+                TRIGGER_EVENTS.join(", ")
+            ));
+        }
+        if rule.template.title.trim().is_empty() {
+            return Err("Task template needs a title".to_string());
+        }
+        if let Some(priority) = &rule.template.priority {
+            if !TASK_PRIORITIES.contains(&priority.as_str()) {
+                return Err(format!(
+                    "priority must be one of {}",
+                    TASK_PRIORITIES.join(", ")
+                ));
+            }
+        }
+        if let Some(pattern) = &rule.filter.pattern {
+            Glob::new(pattern).map_err(|e| format!("Invalid pattern: {}", e))?;
+        }
+        if rule.debounce_secs < 0 {
+            return Err("debounceSecs can't be negative".to_string());
+        }
+
+        let rule = TriggerRule {
+            id: if rule.id.is_empty() {
+                Uuid::new_v4().to_string()
+            } else {
+                rule.id
+            },
+            created_at: if rule.created_at == 0 {
+                Utc::now().timestamp()
+            } else {
+                rule.created_at
+            },
+            ..rule
+        };
+
+        self.db.with_conn(|conn| {
+            conn.execute(
+                "INSERT INTO triggers (id, name, event, filter, template, debounce_secs, enabled, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+                 ON CONFLICT(id) DO UPDATE SET
+                    name = excluded.name, event = excluded.event, filter = excluded.filter,
+                    template = excluded.template, debounce_secs = excluded.debounce_secs,
+                    enabled = excluded.enabled",
+                params![
+                    rule.id,
+                    rule.name,
+                    rule.event,
+                    serde_json::to_string(&rule.filter).unwrap_or_default(),
+                    serde_json::to_string(&rule.template).unwrap_or_default(),
+                    rule.debounce_secs,
+                    rule.enabled,
+                    rule.created_at,
+                ],
+            )
+            .map_err(|e| e.to_string())
+        })?;
+
+        Ok(rule)
+    }
+
+    pub fn delete(&self, trigger_id: &str) -> Result<(), String> {
+        self.db.with_conn(|conn| {
+            conn.execute("DELETE FROM triggers WHERE id = ?1", params![trigger_id])
+                .map_err(|e| e.to_string())?;
+            Ok(())
+        })
+    }
+
+    pub fn set_enabled(&self, trigger_id: &str, enabled: bool) -> Result<(), String> {
+        let updated = self.db.with_conn(|conn| {
+            conn.execute(
+                "UPDATE triggers SET enabled = ?1 WHERE id = ?2",
+                params![enabled, trigger_id],
+            )
+            .map_err(|e| e.to_string())
+        })?;
+
+        if updated == 0 {
+            return Err(format!("Trigger not found: {}", trigger_id));
+        }
+        Ok(())
+    }
+
+    /// Create a task for every enabled rule `event` matches. Errors from
+    /// one rule are logged and don't stop the others.
+    pub fn fire(&self, event: &TriggerEvent) -> Result<Vec<Task>, String> {
+        let rules: Vec<TriggerRule> = self
+            .list()?
+            .into_iter()
+            .filter(|r| r.enabled && r.event == event.kind())
+            .collect();
+        if rules.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let project = self.event_project(event)?;
+        let mut created = Vec::new();
+
+        for rule in rules {
+            if !rule.filter.matches(event, project.as_ref()) {
+                continue;
+            }
+            let now = Utc::now().timestamp();
+            if !self.claim(&rule, now)? {
+                log::debug!("Trigger {} skipped: fired too recently", rule.name);
+                continue;
+            }
+
+            match self.create_task(&rule, event, project.as_ref()) {
+                Ok(task) => {
+                    log::info!("Trigger {} created task {}", rule.name, task.id);
+                    created.push(task);
+                }
+                Err(e) => {
+                    log::warn!("Trigger {} failed to create a task: {}", rule.name, e);
+                    if let Err(e) = self.release(&rule, now) {
+                        log::warn!("Failed to release trigger {}: {}", rule.name, e);
+                    }
+                }
+            }
+        }
+
+        Ok(created)
+    }
+
+    /// Record that `rule` fires at `now`, unless it fired within its
+    /// debounce window. Returns whether it may fire.
+    fn claim(&self, rule: &TriggerRule, now: i64) -> Result<bool, String> {
+        let updated = self.db.with_conn(|conn| {
+            conn.execute(
+                "UPDATE triggers SET last_fired_at = ?1, fire_count = fire_count + 1
+                 WHERE id = ?2 AND enabled = 1
+                   AND (last_fired_at IS NULL OR last_fired_at + debounce_secs <= ?1)",
+                params![now, rule.id],
+            )
+            .map_err(|e| e.to_string())
+        })?;
+
+        Ok(updated == 1)
+    }
+
+    /// Undo the claim made at `now` when no task came of it, so the next
+    /// event isn't debounced by a firing that never happened
+    fn release(&self, rule: &TriggerRule, now: i64) -> Result<(), String> {
+        self.db.with_conn(|conn| {
+            conn.execute(
+                "UPDATE triggers SET last_fired_at = ?1, fire_count = fire_count - 1
+                 WHERE id = ?2 AND last_fired_at = ?3",
+                params![rule.last_fired_at, rule.id, now],
+            )
+            .map_err(|e| e.to_string())?;
+            Ok(())
+        })
+    }
+
+    fn create_task(
+        &self,
+        rule: &TriggerRule,
+        event: &TriggerEvent,
+        project: Option<&Project>,
+    ) -> Result<Task, String> {
+        let vars = event.vars(project);
+        let template = &rule.template;
+
+        let task = self.agents.create_task(&Task {
+            agent_id: template.agent_id.clone(),
+            project_id: template
+                .project_id
+                .clone()
+                .or_else(|| project.map(|p| p.id.clone())),
+            description: render(&template.description, &vars),
+            priority: template
+                .priority
+                .clone()
+                .unwrap_or_else(|| "normal".to_string()),
+            target_agent_type: template.target_agent_type.clone(),
+            skills: template.skills.clone(),
+            ..Task::new(render(&template.title, &vars))
+        })?;
+
+        self.agents.add_task_log(
+            &task.id,
+            "info",
+            &format!("Created by trigger {} ({})", rule.name, event.kind()),
+            Some(serde_json::json!({ "triggerId": rule.id, "event": event.kind() })),
+        )?;
+        Ok(task)
+    }
+
+    /// The registered project an event is about, if any. Changed files
+    /// belong to a project when they all sit inside its folder.
+    fn event_project(&self, event: &TriggerEvent) -> Result<Option<Project>, String> {
+        match event {
+            TriggerEvent::Commit { project_id, .. } => self.projects.get(project_id),
+            TriggerEvent::FilesChanged { paths } if !paths.is_empty() => {
+                Ok(self.projects.list()?.into_iter().find(|project| {
+                    paths
+                        .iter()
+                        .all(|path| Path::new(path).starts_with(&project.path))
+                }))
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_fire_matches_renders_and_debounces() {
+        let dir = tempdir().unwrap();
+        let db = Database::new(dir.path().to_path_buf()).unwrap();
+        let agents = Arc::new(AgentManager::new(db.clone()));
+        let projects = Arc::new(ProjectManager::new(db.clone()));
+        let triggers = TriggerManager::new(db, Arc::clone(&agents), Arc::clone(&projects));
+
+        let repo = dir.path().join("site");
+        std::fs::create_dir(&repo).unwrap();
+        let project = projects.add(repo.to_str().unwrap(), "code").unwrap();
+
+        let rule = triggers
+            .save(TriggerRule {
+                id: String::new(),
+                name: "Review commits".to_string(),
+                event: "commit".to_string(),
+                filter: TriggerFilter::default(),
+                template: TaskTemplate {
+                    title: "Review {{shortSha}} in {{project}}".to_string(),
+                    description: "{{author}}: {{summary}}".to_string(),
+                    target_agent_type: Some("code-review".to_string()),
+                    ..Default::default()
+                },
+                debounce_secs: 3600,
+                enabled: true,
+                last_fired_at: None,
+                fire_count: 0,
+                created_at: 0,
+            })
+            .unwrap();
+        triggers
+            .save(TriggerRule {
+                id: String::new(),
+                name: "Idle".to_string(),
+                event: "claude_state".to_string(),
+                filter: TriggerFilter {
+                    state: Some("idle".to_string()),
+                    ..Default::default()
+                },
+                template: TaskTemplate {
+                    title: "Summarise session {{sessionId}}".to_string(),
+                    ..Default::default()
+                },
+                debounce_secs: 0,
+                enabled: true,
+                last_fired_at: None,
+                fire_count: 0,
+                created_at: 0,
+            })
+            .unwrap();
+
+        let commit = TriggerEvent::Commit {
+            project_id: project.id.clone(),
+            sha: "0123456789abcdef".to_string(),
+            summary: "Fix login".to_string(),
+            author: "Ana".to_string(),
+            commits: vec!["0123456 Fix login (Ana)".to_string()],
+        };
+        let created = triggers.fire(&commit).unwrap();
+        assert_eq!(created.len(), 1);
+        assert_eq!(created[0].title, "Review 0123456 in site");
+        assert_eq!(created[0].description, "Ana: Fix login");
+        assert_eq!(created[0].project_id.as_deref(), Some(project.id.as_str()));
+
+        // Within the debounce window, and after being disabled
+        assert!(triggers.fire(&commit).unwrap().is_empty());
+        triggers.set_enabled(&rule.id, false).unwrap();
+        assert!(triggers.fire(&commit).unwrap().is_empty());
+
+        let state = |to: &str| TriggerEvent::ClaudeState {
+            from: "working".to_string(),
+            to: to.to_string(),
+            session_id: "s1".to_string(),
+        };
+        assert!(triggers.fire(&state("thinking")).unwrap().is_empty());
+        let created = triggers.fire(&state("idle")).unwrap();
+        assert_eq!(created[0].title, "Summarise session s1");
+
+        let mut invalid = rule.clone();
+        invalid.event = "deploy".to_string();
+        assert!(triggers.save(invalid).is_err());
+
+        // Rules saved without a debounce get the column default
+        let parsed: TriggerRule = serde_json::from_value(serde_json::json!({
+            "name": "Changes",
+            "event": "files_changed",
+            "template": { "title": "Look at {{paths}}" },
+            "enabled": true
+        }))
+        .unwrap();
+        assert_eq!(parsed.debounce_secs, 60);
+    }
+
+    #[test]
+    fn test_failed_fire_releases_the_claim() {
+        let dir = tempdir().unwrap();
+        let db = Database::new(dir.path().to_path_buf()).unwrap();
+        let agents = Arc::new(AgentManager::new(db.clone()));
+        let projects = Arc::new(ProjectManager::new(db.clone()));
+        let triggers = TriggerManager::new(db, agents, projects);
+
+        // The agent doesn't exist, so the task insert fails
+        triggers
+            .save(TriggerRule {
+                id: String::new(),
+                name: "Pulled".to_string(),
+                event: "agents_pulled".to_string(),
+                filter: TriggerFilter::default(),
+                template: TaskTemplate {
+                    title: "Check {{message}}".to_string(),
+                    agent_id: Some("missing".to_string()),
+                    ..Default::default()
+                },
+                debounce_secs: 3600,
+                enabled: true,
+                last_fired_at: None,
+                fire_count: 0,
+                created_at: 0,
+            })
+            .unwrap();
+
+        let pulled = TriggerEvent::AgentsPulled {
+            message: "Update agents".to_string(),
+            files_changed: 2,
+        };
+        assert!(triggers.fire(&pulled).unwrap().is_empty());
+
+        let rule = &triggers.list().unwrap()[0];
+        assert_eq!(rule.last_fired_at, None);
+        assert_eq!(rule.fire_count, 0);
+    }
+
+    #[test]
+    fn test_render_substitutes_once() {
+        let vars = HashMap::from([
+            ("sha", "0123456".to_string()),
+            ("summary", "Mention {{sha}} in {{docs}}".to_string()),
+        ]);
+        assert_eq!(
+            render("{{summary}} at {{sha}}, {{missing}} {{", &vars),
+            "Mention {{sha}} in {{docs}} at 0123456, {{missing}} {{"
+        );
+    }
+}
@@ -0,0 +1,11 @@
+//! Triggers module for Claud.io
+//!
+//! Creates tasks from events the app already sees: file changes, new
+//! commits, Claude state transitions and agents repo pulls.
+
+pub mod commands;
+mod commits;
+mod manager;
+
+pub use commits::{watch_commits, COMMIT_POLL_INTERVAL};
+pub use manager::{TriggerEvent, TriggerManager};
@@ -0,0 +1,61 @@
+//! Tauri commands for usage module
+
+use super::manager::{ModelPricing, UsageManager, UsageQuery, UsageRecord, UsageSummary};
+use std::sync::Arc;
+use tauri::State;
+
+#[tauri::command]
+pub fn usage_list_pricing(
+    manager: State<'_, Arc<UsageManager>>,
+) -> Result<Vec<ModelPricing>, String> {
+    manager.list_pricing()
+}
+
+#[tauri::command]
+pub fn usage_set_pricing(
+    manager: State<'_, Arc<UsageManager>>,
+    pricing: ModelPricing,
+) -> Result<(), String> {
+    manager.set_pricing(&pricing)
+}
+
+#[tauri::command]
+pub fn usage_delete_pricing(
+    manager: State<'_, Arc<UsageManager>>,
+    model_id: String,
+) -> Result<(), String> {
+    manager.delete_pricing(&model_id)
+}
+
+#[tauri::command]
+pub fn usage_task_records(
+    manager: State<'_, Arc<UsageManager>>,
+    task_id: String,
+) -> Result<Vec<UsageRecord>, String> {
+    manager.list_task_usage(&task_id)
+}
+
+#[tauri::command]
+pub fn usage_report(
+    manager: State<'_, Arc<UsageManager>>,
+    query: UsageQuery,
+) -> Result<Vec<UsageSummary>, String> {
+    manager.report(&query)
+}
+
+/// Export a report as CSV. Writes to `path` when given and returns the CSV
+/// either way.
+#[tauri::command]
+pub fn usage_export_csv(
+    manager: State<'_, Arc<UsageManager>>,
+    query: UsageQuery,
+    path: Option<String>,
+) -> Result<String, String> {
+    let csv = manager.export_csv(&query)?;
+
+    if let Some(path) = path {
+        std::fs::write(&path, &csv).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+    }
+
+    Ok(csv)
+}
@@ -0,0 +1,483 @@
+//! Usage manager implementation
+//!
+//! Prices each LLM call from the `model_pricing` table, stores it as a usage
+//! record and aggregates records into cost reports.
+
+use crate::db::Database;
+use crate::llm::Usage;
+use chrono::Utc;
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Token prices for a model, in USD per million tokens
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelPricing {
+    /// Exact model id, or a prefix that also matches dated variants
+    #[serde(rename = "modelId")]
+    pub model_id: String,
+    pub provider: String,
+    #[serde(rename = "inputPerMtok")]
+    pub input_per_mtok: f64,
+    #[serde(rename = "outputPerMtok")]
+    pub output_per_mtok: f64,
+    #[serde(rename = "cacheWritePerMtok", default)]
+    pub cache_write_per_mtok: f64,
+    #[serde(rename = "cacheReadPerMtok", default)]
+    pub cache_read_per_mtok: f64,
+}
+
+impl ModelPricing {
+    pub fn cost(&self, usage: &Usage) -> f64 {
+        (usage.input_tokens as f64 * self.input_per_mtok
+            + usage.output_tokens as f64 * self.output_per_mtok
+            + usage.cache_creation_input_tokens as f64 * self.cache_write_per_mtok
+            + usage.cache_read_input_tokens as f64 * self.cache_read_per_mtok)
+            / 1_000_000.0
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageRecord {
+    pub id: String,
+    #[serde(rename = "taskId")]
+    pub task_id: Option<String>,
+    #[serde(rename = "agentId")]
+    pub agent_id: Option<String>,
+    #[serde(rename = "projectId")]
+    pub project_id: Option<String>,
+    pub provider: String,
+    pub model: String,
+    pub usage: Usage,
+    #[serde(rename = "costUsd")]
+    pub cost_usd: f64,
+    #[serde(rename = "createdAt")]
+    pub created_at: i64,
+}
+
+/// One LLM call to record
+#[derive(Debug, Clone, Default)]
+pub struct NewUsageRecord<'a> {
+    pub task_id: Option<&'a str>,
+    pub agent_id: Option<&'a str>,
+    pub project_id: Option<&'a str>,
+    pub provider: &'a str,
+    pub model: &'a str,
+    pub usage: Usage,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportGroup {
+    Day,
+    Week,
+    Agent,
+    Project,
+    Model,
+}
+
+impl ReportGroup {
+    /// SQL for the group key, its display label and any join it needs
+    fn sql(&self) -> (&'static str, &'static str, &'static str) {
+        match self {
+            ReportGroup::Day => (
+                "date(u.created_at, 'unixepoch')",
+                "date(u.created_at, 'unixepoch')",
+                "",
+            ),
+            // Keyed by the Monday the week starts on
+            ReportGroup::Week => (
+                "date(u.created_at, 'unixepoch', 'weekday 0', '-6 days')",
+                "date(u.created_at, 'unixepoch', 'weekday 0', '-6 days')",
+                "",
+            ),
+            ReportGroup::Agent => (
+                "COALESCE(u.agent_id, '')",
+                "COALESCE(a.name, u.agent_id, '(none)')",
+                "LEFT JOIN agents a ON a.id = u.agent_id",
+            ),
+            ReportGroup::Project => (
+                "COALESCE(u.project_id, '')",
+                "COALESCE(p.name, u.project_id, '(none)')",
+                "LEFT JOIN projects p ON p.id = u.project_id",
+            ),
+            ReportGroup::Model => ("u.model", "u.model", ""),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageQuery {
+    #[serde(rename = "groupBy")]
+    pub group_by: ReportGroup,
+    /// Inclusive lower bound (unix seconds)
+    pub from: Option<i64>,
+    /// Exclusive upper bound (unix seconds)
+    pub to: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageSummary {
+    pub key: String,
+    pub label: String,
+    pub calls: i64,
+    #[serde(rename = "inputTokens")]
+    pub input_tokens: i64,
+    #[serde(rename = "outputTokens")]
+    pub output_tokens: i64,
+    #[serde(rename = "cacheCreationTokens")]
+    pub cache_creation_tokens: i64,
+    #[serde(rename = "cacheReadTokens")]
+    pub cache_read_tokens: i64,
+    #[serde(rename = "costUsd")]
+    pub cost_usd: f64,
+}
+
+pub struct UsageManager {
+    db: Database,
+}
+
+impl UsageManager {
+    pub fn new(db: Database) -> Self {
+        Self { db }
+    }
+
+    /// List the pricing table
+    pub fn list_pricing(&self) -> Result<Vec<ModelPricing>, String> {
+        self.db.with_conn(|conn| {
+            let mut stmt = conn
+                .prepare(
+                    "SELECT model_id, provider, input_per_mtok, output_per_mtok,
+                            cache_write_per_mtok, cache_read_per_mtok
+                     FROM model_pricing ORDER BY provider, model_id",
+                )
+                .map_err(|e| e.to_string())?;
+
+            let pricing = stmt
+                .query_map([], Self::map_pricing_row)
+                .map_err(|e| e.to_string())?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| e.to_string())?;
+
+            Ok(pricing)
+        })
+    }
+
+    /// Create or replace the prices for a model
+    pub fn set_pricing(&self, pricing: &ModelPricing) -> Result<(), String> {
+        self.db.with_conn(|conn| {
+            conn.execute(
+                "INSERT INTO model_pricing
+                    (model_id, provider, input_per_mtok, output_per_mtok,
+                     cache_write_per_mtok, cache_read_per_mtok, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+                 ON CONFLICT(model_id) DO UPDATE SET
+                    provider = excluded.provider,
+                    input_per_mtok = excluded.input_per_mtok,
+                    output_per_mtok = excluded.output_per_mtok,
+                    cache_write_per_mtok = excluded.cache_write_per_mtok,
+                    cache_read_per_mtok = excluded.cache_read_per_mtok,
+                    updated_at = excluded.updated_at",
+                params![
+                    pricing.model_id,
+                    pricing.provider,
+                    pricing.input_per_mtok,
+                    pricing.output_per_mtok,
+                    pricing.cache_write_per_mtok,
+                    pricing.cache_read_per_mtok,
+                    Utc::now().timestamp(),
+                ],
+            )
+            .map_err(|e| e.to_string())?;
+
+            Ok(())
+        })
+    }
+
+    pub fn delete_pricing(&self, model_id: &str) -> Result<(), String> {
+        self.db.with_conn(|conn| {
+            conn.execute("DELETE FROM model_pricing WHERE model_id = ?1", params![model_id])
+                .map_err(|e| e.to_string())?;
+            Ok(())
+        })
+    }
+
+    /// Prices for a model id: an exact match, otherwise the longest entry
+    /// that is a prefix of it (so `claude-sonnet-4-5-20250929` uses
+    /// `claude-sonnet-4-5`)
+    pub fn pricing_for(&self, model_id: &str) -> Result<Option<ModelPricing>, String> {
+        self.db.with_conn(|conn| {
+            conn.query_row(
+                "SELECT model_id, provider, input_per_mtok, output_per_mtok,
+                        cache_write_per_mtok, cache_read_per_mtok
+                 FROM model_pricing
+                 WHERE substr(?1, 1, length(model_id)) = model_id
+                 ORDER BY length(model_id) DESC LIMIT 1",
+                params![model_id],
+                Self::map_pricing_row,
+            )
+            .optional()
+            .map_err(|e| e.to_string())
+        })
+    }
+
+    fn map_pricing_row(row: &rusqlite::Row) -> rusqlite::Result<ModelPricing> {
+        Ok(ModelPricing {
+            model_id: row.get(0)?,
+            provider: row.get(1)?,
+            input_per_mtok: row.get(2)?,
+            output_per_mtok: row.get(3)?,
+            cache_write_per_mtok: row.get(4)?,
+            cache_read_per_mtok: row.get(5)?,
+        })
+    }
+
+    /// Price and store one LLM call. Models without pricing (e.g. local
+    /// Ollama models) are recorded at zero cost.
+    pub fn record(&self, record: &NewUsageRecord) -> Result<UsageRecord, String> {
+        let cost_usd = self
+            .pricing_for(record.model)?
+            .map(|pricing| pricing.cost(&record.usage))
+            .unwrap_or(0.0);
+
+        let stored = UsageRecord {
+            id: Uuid::new_v4().to_string(),
+            task_id: record.task_id.map(String::from),
+            agent_id: record.agent_id.map(String::from),
+            project_id: record.project_id.map(String::from),
+            provider: record.provider.to_string(),
+            model: record.model.to_string(),
+            usage: record.usage,
+            cost_usd,
+            created_at: Utc::now().timestamp(),
+        };
+
+        self.db.with_conn(|conn| {
+            conn.execute(
+                "INSERT INTO usage_records
+                    (id, task_id, agent_id, project_id, provider, model, input_tokens, output_tokens,
+                     cache_creation_tokens, cache_read_tokens, cost_usd, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+                params![
+                    stored.id,
+                    stored.task_id,
+                    stored.agent_id,
+                    stored.project_id,
+                    stored.provider,
+                    stored.model,
+                    stored.usage.input_tokens as i64,
+                    stored.usage.output_tokens as i64,
+                    stored.usage.cache_creation_input_tokens as i64,
+                    stored.usage.cache_read_input_tokens as i64,
+                    stored.cost_usd,
+                    stored.created_at,
+                ],
+            )
+            .map_err(|e| e.to_string())?;
+
+            Ok::<(), String>(())
+        })?;
+
+        Ok(stored)
+    }
+
+    /// Usage records for a task, oldest first
+    pub fn list_task_usage(&self, task_id: &str) -> Result<Vec<UsageRecord>, String> {
+        self.db.with_conn(|conn| {
+            let mut stmt = conn
+                .prepare(
+                    "SELECT id, task_id, agent_id, project_id, provider, model, input_tokens,
+                            output_tokens, cache_creation_tokens, cache_read_tokens, cost_usd, created_at
+                     FROM usage_records WHERE task_id = ?1 ORDER BY created_at",
+                )
+                .map_err(|e| e.to_string())?;
+
+            let records = stmt
+                .query_map(params![task_id], |row| {
+                    Ok(UsageRecord {
+                        id: row.get(0)?,
+                        task_id: row.get(1)?,
+                        agent_id: row.get(2)?,
+                        project_id: row.get(3)?,
+                        provider: row.get(4)?,
+                        model: row.get(5)?,
+                        usage: Usage {
+                            input_tokens: row.get::<_, i64>(6)? as u64,
+                            output_tokens: row.get::<_, i64>(7)? as u64,
+                            cache_creation_input_tokens: row.get::<_, i64>(8)? as u64,
+                            cache_read_input_tokens: row.get::<_, i64>(9)? as u64,
+                        },
+                        cost_usd: row.get(10)?,
+                        created_at: row.get(11)?,
+                    })
+                })
+                .map_err(|e| e.to_string())?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| e.to_string())?;
+
+            Ok(records)
+        })
+    }
+
+    /// Aggregate usage by the requested dimension
+    pub fn report(&self, query: &UsageQuery) -> Result<Vec<UsageSummary>, String> {
+        let (key, label, join) = query.group_by.sql();
+        let sql = format!(
+            "SELECT {key} AS key, {label} AS label, COUNT(*),
+                    SUM(u.input_tokens), SUM(u.output_tokens),
+                    SUM(u.cache_creation_tokens), SUM(u.cache_read_tokens), SUM(u.cost_usd)
+             FROM usage_records u {join}
+             WHERE u.created_at >= ?1 AND u.created_at < ?2
+             GROUP BY key
+             ORDER BY {order}",
+            order = match query.group_by {
+                ReportGroup::Day | ReportGroup::Week => "key",
+                _ => "SUM(u.cost_usd) DESC, key",
+            },
+        );
+
+        self.db.with_conn(|conn| {
+            let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+
+            let summaries = stmt
+                .query_map(
+                    params![query.from.unwrap_or(0), query.to.unwrap_or(i64::MAX)],
+                    |row| {
+                        Ok(UsageSummary {
+                            key: row.get(0)?,
+                            label: row.get(1)?,
+                            calls: row.get(2)?,
+                            input_tokens: row.get(3)?,
+                            output_tokens: row.get(4)?,
+                            cache_creation_tokens: row.get(5)?,
+                            cache_read_tokens: row.get(6)?,
+                            cost_usd: row.get(7)?,
+                        })
+                    },
+                )
+                .map_err(|e| e.to_string())?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| e.to_string())?;
+
+            Ok(summaries)
+        })
+    }
+
+    /// Render a report as CSV with a header row
+    pub fn export_csv(&self, query: &UsageQuery) -> Result<String, String> {
+        let mut csv = String::from(
+            "key,label,calls,input_tokens,output_tokens,cache_creation_tokens,cache_read_tokens,cost_usd\n",
+        );
+
+        for row in self.report(query)? {
+            csv.push_str(&format!(
+                "{},{},{},{},{},{},{},{:.6}\n",
+                csv_field(&row.key),
+                csv_field(&row.label),
+                row.calls,
+                row.input_tokens,
+                row.output_tokens,
+                row.cache_creation_tokens,
+                row.cache_read_tokens,
+                row.cost_usd,
+            ));
+        }
+
+        Ok(csv)
+    }
+}
+
+/// Quote a CSV field if it contains a separator, quote or newline
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn usage(input: u64, output: u64) -> Usage {
+        Usage {
+            input_tokens: input,
+            output_tokens: output,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_pricing_prefix_match() {
+        let dir = tempdir().unwrap();
+        let manager = UsageManager::new(Database::new(dir.path().to_path_buf()).unwrap());
+
+        let pricing = manager.pricing_for("gpt-4o-mini-2024-07-18").unwrap().unwrap();
+        assert_eq!(pricing.model_id, "gpt-4o-mini");
+        assert_eq!(manager.pricing_for("claude-sonnet-4-5").unwrap().unwrap().output_per_mtok, 15.0);
+        assert!(manager.pricing_for("llama3.1").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_record_and_report() {
+        let dir = tempdir().unwrap();
+        let manager = UsageManager::new(Database::new(dir.path().to_path_buf()).unwrap());
+
+        let record = manager
+            .record(&NewUsageRecord {
+                provider: "anthropic",
+                model: "claude-sonnet-4-5",
+                usage: Usage {
+                    cache_read_input_tokens: 1_000_000,
+                    ..usage(1_000_000, 100_000)
+                },
+                ..Default::default()
+            })
+            .unwrap();
+        // 3.00 input + 1.50 output + 0.30 cache read
+        assert!((record.cost_usd - 4.8).abs() < 1e-9);
+
+        let local = manager
+            .record(&NewUsageRecord {
+                provider: "ollama",
+                model: "llama3.1",
+                usage: usage(500, 500),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(local.cost_usd, 0.0);
+
+        let query = UsageQuery {
+            group_by: ReportGroup::Model,
+            from: None,
+            to: None,
+        };
+        let report = manager.report(&query).unwrap();
+        assert_eq!(report.len(), 2);
+        assert_eq!(report[0].key, "claude-sonnet-4-5");
+        assert_eq!(report[1].input_tokens, 500);
+
+        let day = manager
+            .report(&UsageQuery {
+                group_by: ReportGroup::Day,
+                ..query.clone()
+            })
+            .unwrap();
+        assert_eq!(day.len(), 1);
+        assert_eq!(day[0].calls, 2);
+
+        let csv = manager.export_csv(&query).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[1].starts_with("claude-sonnet-4-5,claude-sonnet-4-5,1,1000000,100000,0,1000000,4.8"));
+    }
+
+    #[test]
+    fn test_csv_field() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+}
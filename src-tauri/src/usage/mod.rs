@@ -0,0 +1,9 @@
+//! Usage module for Claud.io
+//!
+//! Records token usage and cost for every LLM call and aggregates it into
+//! reports by day, week, agent, project and model.
+
+pub mod commands;
+mod manager;
+
+pub use manager::{NewUsageRecord, UsageManager};
@@ -15,9 +15,10 @@ const models: { id: AgentModel; name: string; description: string }[] = [
 ];
 
 const modes: { id: AgentMode; name: string; description: string }[] = [
-  { id: 'normal', name: 'Normal', description: 'Requires approval for all actions' },
-  { id: 'autonomous', name: 'Autonomous', description: 'Works independently on assigned tasks' },
-  { id: 'supervised', name: 'Supervised', description: 'Requires approval for destructive actions only' },
+  { id: 'suggest', name: 'Suggest only', description: 'Proposes a plan without running any tools' },
+  { id: 'normal', name: 'Normal', description: 'The default; same as supervised' },
+  { id: 'autonomous', name: 'Autonomous', description: 'Acts freely within its permission rules' },
+  { id: 'supervised', name: 'Supervised', description: 'Reads freely, asks before changing anything' },
 ];
 
 export function WizardStep1BasicInfo() {
@@ -68,6 +68,9 @@ export function SidebarAgentsSection({
       const result = await pullAgentsRepo();
       if (result.success) {
         console.log('Agents synced:', result.message);
+        if (result.refreshError) {
+          console.error('Failed to reload agent definitions:', result.refreshError);
+        }
       } else {
         console.error('Sync failed:', result.message);
       }
@@ -50,6 +50,8 @@ export interface GitSyncResult {
   previousCommit: string | null;
   currentCommit: string | null;
   filesChanged: number;
+  /** Set when the pull went through but the agent definitions failed to reload */
+  refreshError?: string | null;
 }
 
 // ============================================================================
@@ -12,7 +12,7 @@ import { invoke } from '@tauri-apps/api/core';
 export type WizardStep = 1 | 2 | 3 | 4;
 
 export type AgentModel = 'sonnet' | 'opus' | 'haiku';
-export type AgentMode = 'normal' | 'autonomous' | 'supervised';
+export type AgentMode = 'suggest' | 'normal' | 'autonomous' | 'supervised';
 
 export interface WizardFormData {
   name: string;
@@ -27,6 +27,11 @@ export interface WizardFormData {
     start: string;
     end: string;
   };
+  /** Imported agent definition, whose mode the agent inherits */
+  template?: {
+    id: string;
+    mode: string;
+  };
 }
 
 export interface WizardSlice {
@@ -178,6 +183,7 @@ export const createWizardSlice: StateCreator<
         state.wizardFormData.model = (agent.model as AgentModel) || 'sonnet';
         state.wizardFormData.mode = (agent.mode as AgentMode) || 'normal';
         state.wizardFormData.systemPrompt = agent.systemPrompt;
+        state.wizardFormData.template = { id: templateId, mode: agent.mode };
       });
     } catch (error) {
       console.error('Failed to import template:', error);
@@ -291,6 +297,9 @@ ${systemPrompt}
           tools: formData.tools,
           autoAssign: formData.autoAssign,
           maxConcurrentTasks: 1,
+          // Keep following the definition's mode unless it was changed here
+          autonomy: formData.template?.mode === formData.mode ? undefined : formData.mode,
+          definitionId: formData.template?.id,
           workingHours: formData.workingHours.enabled
             ? {
                 start: formData.workingHours.start,
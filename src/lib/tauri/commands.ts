@@ -5,8 +5,33 @@
 import { invoke } from '@tauri-apps/api/core';
 import type { ClaudeStateData } from '../store/types';
 import type { TerminalSession } from '@/types/terminal';
-import type { Project, ProjectFile, GitStatus } from '@/types/project';
-import type { Agent, Task, TaskLog, AgentConfig, AgentType } from '@/types/agent';
+import type {
+  Project,
+  ProjectFile,
+  ProjectSettings,
+  GitStatus,
+  Checkpoint,
+} from '@/types/project';
+import type {
+  Agent,
+  Task,
+  TaskLog,
+  AgentConfig,
+  AgentType,
+  SlaReport,
+  ImportFormat,
+  ImportReport,
+  TaskFilter,
+  BulkResult,
+  TaskPriority,
+  Board,
+  BoardColumn,
+  TaskComment,
+  TaskAttachment,
+  TaskArtifact,
+  ArtifactPreview,
+} from '@/types/agent';
+import type { EvalSuite, EvalTarget, EvalRun, EvalComparison } from '@/types/eval';
 import type { Carousel, CarouselSlide, CopyRequest, CopyResult } from '@/types/content';
 
 // ============================================================================
@@ -117,6 +142,13 @@ export async function projectRemove(projectId: string): Promise<void> {
   return invoke('project_remove', { projectId });
 }
 
+export async function projectUpdateSettings(
+  projectId: string,
+  settings: ProjectSettings
+): Promise<void> {
+  return invoke('project_update_settings', { projectId, settings });
+}
+
 export async function projectGetFileTree(projectId: string): Promise<ProjectFile> {
   return invoke('project_get_file_tree', { projectId });
 }
@@ -133,6 +165,26 @@ export async function projectGitStatus(projectId: string): Promise<GitStatus | n
   return invoke('project_git_status', { projectId });
 }
 
+export async function projectListCheckpoints(projectId: string): Promise<Checkpoint[]> {
+  return invoke('project_list_checkpoints', { projectId });
+}
+
+export async function projectCreateCheckpoint(projectId: string, label?: string): Promise<Checkpoint> {
+  return invoke('project_create_checkpoint', { projectId, label });
+}
+
+/** Restore a checkpoint; resolves to the checkpoint of the replaced state */
+export async function projectRestoreCheckpoint(
+  projectId: string,
+  checkpointId: string
+): Promise<Checkpoint> {
+  return invoke('project_restore_checkpoint', { projectId, checkpointId });
+}
+
+export async function projectDeleteCheckpoint(projectId: string, checkpointId: string): Promise<void> {
+  return invoke('project_delete_checkpoint', { projectId, checkpointId });
+}
+
 export async function projectGitCommit(
   projectId: string,
   message: string,
@@ -162,6 +214,14 @@ export async function agentUpdate(
   return invoke('agent_update', { agentId, updates });
 }
 
+export async function agentSetProjects(agentId: string, projectIds: string[]): Promise<Agent> {
+  return invoke('agent_set_projects', { agentId, projectIds });
+}
+
+export async function agentListForProject(projectId: string): Promise<Agent[]> {
+  return invoke('agent_list_for_project', { projectId });
+}
+
 export async function agentDelete(agentId: string): Promise<void> {
   return invoke('agent_delete', { agentId });
 }
@@ -205,6 +265,142 @@ export async function taskGet(taskId: string): Promise<Task> {
   return invoke('task_get', { taskId });
 }
 
+export async function taskBoard(): Promise<Board> {
+  return invoke('task_board');
+}
+
+export async function taskMove(
+  taskId: string,
+  columnId: string,
+  beforeTaskId?: string
+): Promise<Task> {
+  return invoke('task_move', { taskId, columnId, beforeTaskId });
+}
+
+export async function boardListColumns(): Promise<BoardColumn[]> {
+  return invoke('board_list_columns');
+}
+
+export async function boardSaveColumns(columns: BoardColumn[]): Promise<void> {
+  return invoke('board_save_columns', { columns });
+}
+
+export async function taskListComments(taskId: string): Promise<TaskComment[]> {
+  return invoke('task_list_comments', { taskId });
+}
+
+export async function taskAddComment(taskId: string, body: string): Promise<TaskComment> {
+  return invoke('task_add_comment', { taskId, body });
+}
+
+export async function taskUpdateComment(commentId: string, body: string): Promise<void> {
+  return invoke('task_update_comment', { commentId, body });
+}
+
+export async function taskDeleteComment(commentId: string): Promise<void> {
+  return invoke('task_delete_comment', { commentId });
+}
+
+export async function taskListAttachments(taskId: string): Promise<TaskAttachment[]> {
+  return invoke('task_list_attachments', { taskId });
+}
+
+/** Copy the file at `path` into app storage and attach it */
+export async function taskAddAttachment(taskId: string, path: string): Promise<TaskAttachment> {
+  return invoke('task_add_attachment', { taskId, path });
+}
+
+export async function taskDeleteAttachment(attachmentId: string): Promise<void> {
+  return invoke('task_delete_attachment', { attachmentId });
+}
+
+export async function taskListArtifacts(taskId: string): Promise<TaskArtifact[]> {
+  return invoke('task_list_artifacts', { taskId });
+}
+
+export async function taskPreviewArtifact(artifactId: string): Promise<ArtifactPreview> {
+  return invoke('task_preview_artifact', { artifactId });
+}
+
+/** Write the artifact's files to the project; resolves to the artifacts applied */
+export async function taskApplyArtifact(artifactId: string): Promise<TaskArtifact[]> {
+  return invoke('task_apply_artifact', { artifactId });
+}
+
+/** Restore the artifact's files to the start commit; resolves to the artifacts discarded */
+export async function taskDiscardArtifact(artifactId: string): Promise<TaskArtifact[]> {
+  return invoke('task_discard_artifact', { artifactId });
+}
+
+/** Export the task to a new folder in `destDir`; resolves to that folder */
+export async function taskExport(taskId: string, destDir: string): Promise<string> {
+  return invoke('task_export', { taskId, destDir });
+}
+
+export async function taskImport(
+  format: ImportFormat,
+  content: string,
+  dryRun = false
+): Promise<ImportReport> {
+  return invoke('task_import', { format, content, dryRun });
+}
+
+export async function taskBulkCancel(filter: TaskFilter): Promise<BulkResult> {
+  return invoke('task_bulk_cancel', { filter });
+}
+
+export async function taskBulkReassign(filter: TaskFilter, agentId?: string): Promise<BulkResult> {
+  return invoke('task_bulk_reassign', { filter, agentId });
+}
+
+export async function taskBulkSetPriority(
+  filter: TaskFilter,
+  priority: TaskPriority
+): Promise<BulkResult> {
+  return invoke('task_bulk_set_priority', { filter, priority });
+}
+
+export async function taskDeadlineReport(since?: number): Promise<SlaReport> {
+  return invoke('task_deadline_report', { since });
+}
+
+// ============================================================================
+// Eval Commands
+// ============================================================================
+
+export async function evalListSuites(): Promise<EvalSuite[]> {
+  return invoke('eval_list_suites');
+}
+
+export async function evalSaveSuite(suite: EvalSuite): Promise<EvalSuite> {
+  return invoke('eval_save_suite', { suite });
+}
+
+export async function evalDeleteSuite(suiteId: string): Promise<void> {
+  return invoke('eval_delete_suite', { suiteId });
+}
+
+/** Run a suite; resolves to the finished run with its results */
+export async function evalRun(suiteId: string, target: EvalTarget): Promise<EvalRun> {
+  return invoke('eval_run', { suiteId, target });
+}
+
+export async function evalListRuns(suiteId: string): Promise<EvalRun[]> {
+  return invoke('eval_list_runs', { suiteId });
+}
+
+export async function evalGetRun(runId: string): Promise<EvalRun> {
+  return invoke('eval_get_run', { runId });
+}
+
+export async function evalDeleteRun(runId: string): Promise<void> {
+  return invoke('eval_delete_run', { runId });
+}
+
+export async function evalCompare(baseRunId: string, headRunId: string): Promise<EvalComparison> {
+  return invoke('eval_compare', { baseRunId, headRunId });
+}
+
 // ============================================================================
 // Content Commands
 // ============================================================================
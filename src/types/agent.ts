@@ -29,9 +29,36 @@ export interface AgentConfig {
     end: string;    // "17:00"
     timezone: string;
   };
+  /** Projects the agent is assigned to; empty means any */
   allowedProjects?: string[];
+  /** Capability tags used to route tasks */
+  skills?: string[];
   tokenLimit?: number;
   dailyBudget?: number;
+  maxIterations?: number;
+  permissions?: {
+    allow: string[];
+    deny: string[];
+    ask: string[];
+  };
+  /** Overrides the level inherited from the agent definition */
+  autonomy?: 'suggest' | 'supervised' | 'autonomous';
+  /** Containment for shell commands (Linux) */
+  sandbox?: SandboxConfig;
+  /** Agent definition this agent was created from */
+  definitionId?: string;
+}
+
+export interface SandboxConfig {
+  /** `auto` contains commands where possible, `required` refuses otherwise */
+  mode: 'auto' | 'required' | 'off';
+  network: boolean;
+  memoryMb: number | null;
+  cpuSecs: number | null;
+  /** Cap on a command's wall-clock timeout */
+  timeLimitSecs: number | null;
+  /** Directories besides the project that stay writable */
+  writablePaths: string[];
 }
 
 export interface AgentStats {
@@ -59,9 +86,146 @@ export interface Task {
   logs: TaskLog[];
   dependsOn?: string[];
   blockedBy?: string[];
+  parentTaskId?: string | null;
+  depth?: number;
+  targetAgentType?: string | null;
+  /** Skills an agent needs to pick the task up; any one will do */
+  skills?: string[];
+  routing?: RoutingDecision | null;
+  /** When the task was flagged as at risk of missing its deadline */
+  deadlineWarnedAt?: number | null;
+  /** When the task was marked overdue */
+  overdueAt?: number | null;
+  /** Manual order within a board column, ascending */
+  boardRank?: number | null;
+  /** Board column the task was last moved to by hand */
+  boardColumn?: string | null;
+  startCommit?: string | null;
+}
+
+export interface TaskComment {
+  id: string;
+  taskId: string;
+  author: 'human' | 'agent';
+  agentId: string | null;
+  /** Markdown */
+  body: string;
+  createdAt: number;
+  updatedAt: number | null;
+}
+
+export interface TaskAttachment {
+  id: string;
+  taskId: string;
+  fileName: string;
+  mimeType: string;
+  size: number;
+  sha256: string;
+  createdAt: number;
+}
+
+export type ArtifactKind = 'diff' | 'file' | 'transcript';
+
+export interface TaskArtifact {
+  id: string;
+  taskId: string;
+  kind: ArtifactKind;
+  path: string | null;
+  change: 'added' | 'modified' | 'deleted' | null;
+  size: number;
+  binary: boolean;
+  status: 'pending' | 'applied' | 'discarded';
+  createdAt: number;
+  decidedAt: number | null;
 }
 
-export type TaskStatus = 'pending' | 'assigned' | 'running' | 'completed' | 'failed' | 'cancelled' | 'waiting';
+export interface ArtifactPreview {
+  artifact: TaskArtifact;
+  text: string | null;
+  patch: string | null;
+}
+
+export interface BoardColumn {
+  id: string;
+  name: string;
+  statuses: TaskStatus[];
+  /** Status given to tasks moved into the column; unset if they can't be */
+  dropStatus?: TaskStatus | null;
+  wipLimit?: number | null;
+}
+
+export interface Board {
+  lanes: { column: BoardColumn; tasks: Task[] }[];
+}
+
+/** Payload of the `agent:task-deadline` event */
+export interface DeadlineNotice {
+  taskId: string;
+  title: string;
+  alert: 'atRisk' | 'overdue';
+  deadline: number;
+  priority: TaskPriority;
+}
+
+export type ImportFormat = 'csv' | 'json' | 'markdown';
+
+export interface ImportReport {
+  dryRun: boolean;
+  /** Created tasks, or the ones that would be created */
+  tasks: Task[];
+  /** Nothing is created when any row has errors */
+  errors: { row: number; message: string }[];
+}
+
+/** Selects tasks for bulk operations; every set field must match */
+export interface TaskFilter {
+  taskIds?: string[];
+  statuses?: TaskStatus[];
+  agentId?: string;
+  projectId?: string;
+  priority?: TaskPriority;
+}
+
+export interface BulkResult {
+  updated: string[];
+  skipped: { taskId: string; reason: string }[];
+}
+
+export interface SlaRow {
+  id: string | null;
+  name: string;
+  due: number;
+  met: number;
+  missed: number;
+  open: number;
+  adherence: number | null;
+  avgLatenessSecs: number | null;
+}
+
+export interface SlaReport {
+  since: number | null;
+  generatedAt: number;
+  byAgent: SlaRow[];
+  byProject: SlaRow[];
+}
+
+export interface RoutingDecision {
+  agentId: string;
+  score: number;
+  explanation: string;
+  candidates: {
+    agentId: string;
+    name: string;
+    score: number;
+    fit: number;
+    load: number;
+    successRate: number;
+    reasons: string[];
+  }[];
+  routedAt: number;
+}
+
+export type TaskStatus = 'backlog' | 'pending' | 'assigned' | 'running' | 'completed' | 'failed' | 'cancelled' | 'waiting';
 export type TaskPriority = 'low' | 'normal' | 'high' | 'urgent';
 
 export interface TaskResult {
@@ -86,6 +250,15 @@ export interface TaskLog {
   metadata?: Record<string, unknown>;
 }
 
+export interface TaskMessage {
+  id: number;
+  taskId: string;
+  role: 'user' | 'assistant' | 'tool';
+  content: string;
+  metadata?: Record<string, unknown>;
+  createdAt: number;
+}
+
 export interface AgentState {
   agents: Agent[];
   tasks: Task[];
@@ -0,0 +1,112 @@
+/**
+ * Eval types for Claud.io
+ *
+ * Golden-task suites scored against agent config revisions.
+ */
+
+import type { AgentConfig } from './agent';
+
+export type EvalCheck =
+  | { type: 'exact'; expected: string; ignoreCase?: boolean }
+  | { type: 'regex'; pattern: string }
+  | { type: 'jsonSchema'; schema: Record<string, unknown> }
+  | {
+      type: 'llmGraded';
+      rubric: string;
+      /** Grader model; the run's model when unset */
+      model?: string | null;
+      passScore?: number;
+    };
+
+export interface EvalCase {
+  id: string;
+  name: string;
+  input: string;
+  checks: EvalCheck[];
+}
+
+/** Reply for mock models: the first rule whose regex matches the input wins */
+export interface MockRule {
+  pattern: string;
+  reply: string;
+}
+
+export interface EvalSuite {
+  id: string;
+  name: string;
+  description: string;
+  cases: EvalCase[];
+  mockResponses: MockRule[];
+  createdAt: number;
+  updatedAt: number;
+}
+
+/** What to run a suite with; `model: "mock"` runs offline */
+export interface EvalTarget {
+  agentId?: string | null;
+  config?: AgentConfig | null;
+  model?: string | null;
+}
+
+export interface CheckOutcome {
+  type: EvalCheck['type'];
+  passed: boolean;
+  score: number;
+  message: string | null;
+}
+
+export interface EvalResult {
+  caseId: string;
+  caseName: string;
+  output: string;
+  score: number;
+  passed: boolean;
+  checks: CheckOutcome[];
+  tokens: number;
+  durationMs: number;
+  error: string | null;
+}
+
+export interface EvalRun {
+  id: string;
+  suiteId: string;
+  agentId: string | null;
+  /** Short hash of the agent config */
+  revision: string;
+  config: AgentConfig;
+  model: string;
+  status: 'running' | 'completed' | 'failed';
+  score: number | null;
+  passed: number;
+  total: number;
+  error: string | null;
+  startedAt: number;
+  completedAt: number | null;
+  results: EvalResult[];
+}
+
+export interface CaseComparison {
+  caseId: string;
+  caseName: string;
+  baseScore: number | null;
+  headScore: number | null;
+  basePassed: boolean | null;
+  headPassed: boolean | null;
+  change: 'improved' | 'regressed' | 'unchanged' | 'added' | 'removed';
+}
+
+export interface EvalComparison {
+  suiteId: string;
+  baseRunId: string;
+  headRunId: string;
+  baseRevision: string;
+  headRevision: string;
+  baseScore: number;
+  headScore: number;
+  scoreDelta: number;
+  basePassed: number;
+  headPassed: number;
+  improved: number;
+  regressed: number;
+  cases: CaseComparison[];
+}
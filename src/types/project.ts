@@ -22,6 +22,19 @@ export interface ProjectSettings {
   autoCommit?: boolean;
   watchPatterns?: string[];
   ignorePatterns?: string[];
+  /** Most of the project's tasks that may run at once; git projects run one at a time */
+  maxConcurrentTasks?: number;
+  /** Checkpoint the work tree before each task; on unless false */
+  checkpointTasks?: boolean;
+}
+
+export interface Checkpoint {
+  id: string;
+  commit: string;
+  head: string | null;
+  label: string;
+  taskId: string | null;
+  createdAt: number;
 }
 
 export interface ProjectFile {
@@ -0,0 +1,44 @@
+/**
+ * Trigger types for Claud.io
+ *
+ * Rules that create tasks from app events.
+ */
+
+import type { TaskPriority } from './agent';
+
+export type TriggerEventKind = 'files_changed' | 'commit' | 'claude_state' | 'agents_pulled';
+
+export interface TriggerRule {
+  id: string;
+  name: string;
+  event: TriggerEventKind;
+  filter: TriggerFilter;
+  template: TaskTemplate;
+  /** Least time between two tasks from this rule */
+  debounceSecs: number;
+  enabled: boolean;
+  lastFiredAt?: number | null;
+  fireCount: number;
+  createdAt: number;
+}
+
+export interface TriggerFilter {
+  projectId?: string | null;
+  /** Glob one of the changed paths must match (files_changed) */
+  pattern?: string | null;
+  /** State Claude entered, e.g. "idle" (claude_state) */
+  state?: string | null;
+  /** State Claude left (claude_state) */
+  fromState?: string | null;
+}
+
+/** Text fields may use {{placeholders}} such as {{project}}, {{sha}} or {{paths}} */
+export interface TaskTemplate {
+  title: string;
+  description: string;
+  agentId?: string | null;
+  projectId?: string | null;
+  targetAgentType?: string | null;
+  priority?: TaskPriority | null;
+  skills?: string[];
+}